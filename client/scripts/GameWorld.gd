@@ -12,6 +12,9 @@ const CAMERA_ZOOM_STEP := 0.75
 const PLAYER_EYE_HEIGHT := 1.6
 const MAX_SIGNED_64: int = 9223372036854775807
 const MIN_FLOOR_Y := 0.5  # Safety floor to keep the player above terrain
+const LOOT_RANGE := 5.0  # Matches the server's looting distance
+const PARTY_INVITE_RANGE := 20.0
+const LOOT_METHODS := ["FreeForAll", "RoundRobin", "NeedBeforeGreed"]
 
 @onready var gravity_value: float = ProjectSettings.get_setting("physics/3d/default_gravity") * 1.2  # Increased by 20%
 @onready var camera: Camera3D = $Camera3D
@@ -62,6 +65,7 @@ func _physics_process(delta: float) -> void:
 		client_networking.poll()
 	_update_player_movement(delta)
 	_update_camera_position()
+	_update_cast_bar()
 
 func _input(event) -> void:
 	if input_manager:
@@ -92,6 +96,16 @@ func _load_session_modules() -> void:
 			input_manager.connect("action_pressed", Callable(self, "_on_action_pressed"))
 	if client_networking and not client_networking.is_connected("world_snapshot_received", Callable(self, "_on_world_snapshot_received")):
 		client_networking.connect("world_snapshot_received", Callable(self, "_on_world_snapshot_received"))
+	if client_networking and not client_networking.is_connected("entity_update_batch_received", Callable(self, "_on_entity_update_batch_received")):
+		client_networking.connect("entity_update_batch_received", Callable(self, "_on_entity_update_batch_received"))
+	if client_networking and not client_networking.is_connected("loot_received", Callable(self, "_on_loot_received")):
+		client_networking.connect("loot_received", Callable(self, "_on_loot_received"))
+	if client_networking and not client_networking.is_connected("shutdown_notice_received", Callable(self, "_on_shutdown_notice_received")):
+		client_networking.connect("shutdown_notice_received", Callable(self, "_on_shutdown_notice_received"))
+	if game_state_manager and not game_state_manager.is_connected("entity_effect_received", Callable(self, "_on_entity_effect_received")):
+		game_state_manager.connect("entity_effect_received", Callable(self, "_on_entity_effect_received"))
+	if game_state_manager and not game_state_manager.is_connected("combat_log_updated", Callable(self, "_on_combat_log_updated")):
+		game_state_manager.connect("combat_log_updated", Callable(self, "_on_combat_log_updated"))
 	if movement_system and not movement_system.is_connected("movement_intent_sent", Callable(self, "_on_movement_intent_sent")):
 		movement_system.connect("movement_intent_sent", Callable(self, "_on_movement_intent_sent"))
 
@@ -287,6 +301,101 @@ func _on_action_pressed(action_name: String) -> void:
 			_adjust_zoom(-CAMERA_ZOOM_STEP)
 		"scroll_down":
 			_adjust_zoom(CAMERA_ZOOM_STEP)
+		"release":
+			_request_release()
+		"loot":
+			_request_loot()
+		"roll_need":
+			_answer_loot_roll("need")
+		"roll_greed":
+			_answer_loot_roll("greed")
+		"party_invite":
+			_invite_nearest_player()
+		"party_accept":
+			_accept_party_invite()
+		"party_leave":
+			if client_networking:
+				client_networking.send_party_leave_request()
+		"party_loot_method":
+			_cycle_party_loot_method()
+
+func _request_release() -> void:
+	if not game_state_manager or not client_networking:
+		return
+	if game_state_manager.is_entity_dead(game_state_manager.player_entity_id):
+		client_networking.send_release_request()
+
+# Open the nearest corpse in reach; the server decides whether there is anything on it
+func _request_loot() -> void:
+	if not game_state_manager or not client_networking:
+		return
+	var player_id: int = game_state_manager.player_entity_id
+	if game_state_manager.is_entity_dead(player_id):
+		return
+	var origin: Vector3 = game_state_manager.get_entity_position(player_id)
+	var nearest_id := 0
+	var nearest_distance := LOOT_RANGE
+	for entity_data in game_state_manager.get_entities_in_range(origin, LOOT_RANGE):
+		var entity_id := _u64_to_int(entity_data.get("id", 0))
+		if entity_id == player_id or not game_state_manager.is_entity_dead(entity_id):
+			continue
+		var distance := origin.distance_to(game_state_manager.get_entity_position(entity_id))
+		if distance <= nearest_distance:
+			nearest_id = entity_id
+			nearest_distance = distance
+	if nearest_id != 0:
+		client_networking.send_loot_open_request(nearest_id)
+
+func _answer_loot_roll(choice: String) -> void:
+	if not game_state_manager or not client_networking:
+		return
+	var roll: Dictionary = game_state_manager.take_loot_roll()
+	if not roll.is_empty():
+		client_networking.send_loot_roll_request(roll.corpse_id, roll.slot, choice)
+
+# Invite the nearest other player in range; the server says why if it refuses
+func _invite_nearest_player() -> void:
+	if not game_state_manager or not client_networking:
+		return
+	var player_id: int = game_state_manager.player_entity_id
+	var origin: Vector3 = game_state_manager.get_entity_position(player_id)
+	var nearest_id := 0
+	var nearest_distance := PARTY_INVITE_RANGE
+	for entity_data in game_state_manager.get_entities_in_range(origin, PARTY_INVITE_RANGE):
+		var entity_id := _u64_to_int(entity_data.get("id", 0))
+		if entity_id == player_id or str(entity_data.get("entity_type", "")).to_lower() != "player":
+			continue
+		var distance := origin.distance_to(game_state_manager.get_entity_position(entity_id))
+		if distance <= nearest_distance:
+			nearest_id = entity_id
+			nearest_distance = distance
+	if nearest_id != 0:
+		client_networking.send_party_invite_request(nearest_id)
+
+func _accept_party_invite() -> void:
+	if not game_state_manager or not client_networking:
+		return
+	if not game_state_manager.take_party_invite().is_empty():
+		client_networking.send_party_invite_reply(true)
+
+# Leaders step the party through the loot methods, keeping the rarity threshold
+func _cycle_party_loot_method() -> void:
+	if not game_state_manager or not client_networking:
+		return
+	var party: Dictionary = game_state_manager.party
+	if party.party_id == 0:
+		return
+	var next: String = LOOT_METHODS[(LOOT_METHODS.find(party.method) + 1) % LOOT_METHODS.size()]
+	client_networking.send_party_loot_rules_request(next, party.threshold)
+
+# Loot everything: take the first item left until the corpse is bare or a take fails
+func _on_loot_received(response: Dictionary) -> void:
+	if not game_state_manager:
+		return
+	var items: Array = game_state_manager.apply_loot_response(response)
+	if not items.is_empty() and client_networking:
+		var corpse_id := _u64_to_int(response.get("corpse_id", 0))
+		client_networking.send_loot_take_request(corpse_id, int(items[0].get("slot", 0)))
 
 func _return_to_menu() -> void:
 	left_dragging = false
@@ -310,11 +419,22 @@ func _on_world_snapshot_received(snapshot: Dictionary) -> void:
 	_initial_snapshot_applied = true
 	if game_state_manager:
 		game_state_manager.apply_world_snapshot(snapshot)
+	if client_networking:
+		client_networking.send_snapshot_ack(_u64_to_int(snapshot.get("snapshot_id", 0)))
 	_sync_entity_proxies()
 	_apply_authoritative_player_rotation()
 	_apply_authoritative_player_position()
 	_log_player_entity(snapshot)
 	_update_hud_name()
+	_update_hud_resource()
+
+func _on_entity_update_batch_received(batch: Dictionary) -> void:
+	if game_state_manager:
+		if game_state_manager.apply_entity_update_batch(batch) and client_networking:
+			client_networking.send_snapshot_ack(_u64_to_int(batch.get("snapshot_id", 0)))
+	_sync_entity_proxies()
+	_apply_authoritative_player_position()
+	_update_hud_resource()
 
 func _apply_authoritative_player_position() -> void:
 	if not game_state_manager:
@@ -409,6 +529,20 @@ func _update_hud_name() -> void:
 		name = str(character_data.get("name"))
 	hud_label.text = name
 
+func _update_hud_resource() -> void:
+	var hud_label: Label = get_node_or_null("HUD/PlayerResource")
+	if not hud_label or not game_state_manager:
+		return
+	var resource: Dictionary = game_state_manager.get_entity_resource(game_state_manager.player_entity_id)
+	if resource.is_empty():
+		hud_label.text = ""
+		return
+	hud_label.text = "%s %d / %d" % [
+		str(resource.get("resource_type", "")).capitalize(),
+		int(resource.get("current", 0)),
+		int(resource.get("maximum", 0))
+	]
+
 func _apply_authoritative_player_rotation() -> void:
 	if _initial_rotation_applied:
 		return
@@ -473,7 +607,6 @@ func _spawn_or_update_proxy(entity_id: int, entity_data: Dictionary) -> void:
 
 		var label = Label3D.new()
 		label.name = "NameLabel"
-		label.text = _display_name_for_entity(entity_data)
 		label.billboard = BaseMaterial3D.BILLBOARD_ENABLED
 		label.transform.origin = Vector3(0, 2, 0)
 		label.outline_size = 2
@@ -497,9 +630,7 @@ func _spawn_or_update_proxy(entity_id: int, entity_data: Dictionary) -> void:
 			var target_rot: float = rot.get("y", proxy_node.rotation.y)
 			proxy_node.rotation.y = target_rot
 
-	var label_node: Label3D = proxy_node.get_node_or_null("NameLabel")
-	if label_node:
-		label_node.text = _display_name_for_entity(entity_data)
+	_update_proxy_label(entity_id, entity_data)
 
 	var avatar_node: Node3D = proxy_node.get_node_or_null("Avatar")
 	if avatar_node and avatar_node.has_method("set_remote_movement_state"):
@@ -508,6 +639,111 @@ func _spawn_or_update_proxy(entity_id: int, entity_data: Dictionary) -> void:
 		_log_remote_player(entity_id, entity_data)
 		_log_remote_player(entity_id, entity_data)
 
+func _update_proxy_label(entity_id: int, entity_data: Dictionary) -> void:
+	var proxy_node: Node3D = entity_proxies.get(entity_id, null)
+	if not is_instance_valid(proxy_node):
+		return
+	var label_node: Label3D = proxy_node.get_node_or_null("NameLabel")
+	if not label_node:
+		return
+	var text := _display_name_for_entity(entity_data)
+	if game_state_manager:
+		var names := PackedStringArray()
+		for status in game_state_manager.get_status_effects(entity_id):
+			var stacks: int = status.get("stacks", 1)
+			names.append(status.name if stacks <= 1 else "%s x%d" % [status.name, stacks])
+		if not names.is_empty():
+			text += "\n" + ", ".join(names)
+	label_node.text = text
+
+func _on_entity_effect_received(entity_id: int, effect: Dictionary) -> void:
+	if not game_state_manager:
+		return
+	if effect.has("StatusEffect") or effect.has("Respawn"):
+		_update_proxy_label(entity_id, game_state_manager.get_entity(entity_id))
+	if effect.has("DamageNumber"):
+		_show_floating_number(entity_id, effect.DamageNumber)
+	elif effect.has("LevelUp"):
+		_show_floating_number(entity_id, {"kind": "LevelUp", "is_critical": true})
+	if entity_id != game_state_manager.player_entity_id:
+		return
+	if effect.has("Death"):
+		_set_death_notice("You died. Press R to release.")
+	elif effect.has("Respawn"):
+		_set_death_notice("")
+		_snap_player_to_server_position()
+
+# Damage and healing float up from the entity and fade out
+func _show_floating_number(entity_id: int, hit: Dictionary) -> void:
+	var anchor: Node3D = player if entity_id == game_state_manager.player_entity_id else entity_proxies.get(entity_id, null)
+	if not is_instance_valid(anchor):
+		return
+	var label := Label3D.new()
+	label.billboard = BaseMaterial3D.BILLBOARD_ENABLED
+	label.no_depth_test = true
+	label.font_size = 48 if hit.get("is_critical", false) else 32
+	match str(hit.get("kind", "Damage")):
+		"Heal":
+			label.text = "+%d" % int(hit.get("amount", 0))
+			label.modulate = Color(0.4, 1.0, 0.4)
+		"Miss", "Dodge", "Parry":
+			label.text = str(hit.get("kind"))
+			label.modulate = Color(0.8, 0.8, 0.8)
+		"LevelUp":
+			label.text = "Level Up!"
+			label.modulate = Color(1.0, 0.8, 0.2)
+		_:
+			label.text = str(int(hit.get("amount", 0)))
+			label.modulate = Color(1.0, 0.9, 0.3) if hit.get("is_critical", false) else Color(1.0, 1.0, 1.0)
+	add_child(label)
+	label.global_position = anchor.global_position + Vector3(randf_range(-0.3, 0.3), 2.4, 0.0)
+	var tween := label.create_tween()
+	tween.set_parallel(true)
+	tween.tween_property(label, "global_position", label.global_position + Vector3.UP * 1.5, 1.2)
+	tween.tween_property(label, "modulate:a", 0.0, 1.2)
+	tween.chain().tween_callback(label.queue_free)
+
+func _on_shutdown_notice_received(notice: Dictionary) -> void:
+	if game_state_manager:
+		game_state_manager.log_notice(str(notice.get("message", "Server shutting down")))
+
+func _on_combat_log_updated(_line: String) -> void:
+	var log_label: Label = get_node_or_null("HUD/CombatLog")
+	if not log_label or not game_state_manager:
+		return
+	var lines: Array = game_state_manager.get_combat_log()
+	log_label.text = "\n".join(PackedStringArray(lines.slice(max(lines.size() - 8, 0))))
+
+func _update_cast_bar() -> void:
+	var cast_bar: Label = get_node_or_null("HUD/CastBar")
+	if not cast_bar or not game_state_manager:
+		return
+	var cast: Dictionary = game_state_manager.get_cast(game_state_manager.player_entity_id)
+	if cast.is_empty():
+		cast_bar.text = ""
+		return
+	# Channels drain from full; casts fill up
+	var fraction: float = 1.0 - cast.progress if cast.channel else cast.progress
+	var filled := int(round(fraction * 20.0))
+	var verb := "Channelling" if cast.channel else "Casting"
+	cast_bar.text = "%s [%s%s]" % [verb, "#".repeat(filled), "-".repeat(20 - filled)]
+
+func _set_death_notice(text: String) -> void:
+	var notice: Label = get_node_or_null("HUD/DeathNotice")
+	if notice:
+		notice.text = text
+
+# Released players reappear at the graveyard, far from where they fell
+func _snap_player_to_server_position() -> void:
+	var player_entity = game_state_manager.get_entity(game_state_manager.player_entity_id)
+	if not player_entity.has("position"):
+		return
+	var pos = player_entity.position
+	player.global_position = Vector3(pos.x, max(pos.y, MIN_FLOOR_Y), pos.z)
+	player.velocity = Vector3.ZERO
+	if movement_system:
+		movement_system.set_target_position(player.global_position)
+
 func _despawn_proxy(entity_id: int) -> void:
 	if not entity_proxies.has(entity_id):
 		return
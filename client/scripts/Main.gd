@@ -143,6 +143,8 @@ func _initialize_modules():
 		client_networking.connect("character_selected", Callable(self, "_on_character_selected"))
 	if client_networking and not client_networking.is_connected("world_snapshot_received", Callable(self, "_on_world_snapshot_received")):
 		client_networking.connect("world_snapshot_received", Callable(self, "_on_world_snapshot_received"))
+	if client_networking and not client_networking.is_connected("entity_update_batch_received", Callable(self, "_on_entity_update_batch_received")):
+		client_networking.connect("entity_update_batch_received", Callable(self, "_on_entity_update_batch_received"))
 
 	if ui_state_manager and not ui_state_manager.is_connected("state_changed", Callable(self, "_on_ui_state_changed")):
 		ui_state_manager.connect("state_changed", Callable(self, "_on_ui_state_changed"))
@@ -459,6 +461,8 @@ func _on_world_snapshot_received(snapshot: Dictionary):
 	network_debug.add_message("World snapshot sync: " + str(snapshot.get("zone_name", "unknown")))
 	if game_state_manager:
 		game_state_manager.apply_world_snapshot(snapshot)
+	if client_networking:
+		client_networking.send_snapshot_ack(_u64_to_int(snapshot.get("snapshot_id", 0)))
 	var tree = get_tree()
 	if tree:
 		tree.set_meta("latest_world_snapshot", snapshot.duplicate(true))
@@ -472,6 +476,15 @@ func _on_world_snapshot_received(snapshot: Dictionary):
 		# Fallback: if tree is not yet available, store on the SceneTree once ready
 		call_deferred("_cache_snapshot_later", snapshot.duplicate(true))
 
+func _on_entity_update_batch_received(batch: Dictionary):
+	# Keep the cached snapshot current until the game world takes over
+	if game_state_manager:
+		if game_state_manager.apply_entity_update_batch(batch) and client_networking:
+			client_networking.send_snapshot_ack(_u64_to_int(batch.get("snapshot_id", 0)))
+		var tree = get_tree()
+		if tree:
+			tree.set_meta("latest_world_snapshot", game_state_manager.last_world_snapshot.duplicate(true))
+
 func _cache_snapshot_later(snapshot: Dictionary):
 	var tree = get_tree()
 	if tree:
@@ -10,6 +10,8 @@ signal player_spawned(player_id: int)
 signal entity_updated(entity_id: int, entity_data: Dictionary)
 signal entity_removed(entity_id: int)
 signal zone_changed(zone_id: int)
+signal entity_effect_received(entity_id: int, effect: Dictionary)
+signal combat_log_updated(line: String)
 
 # Game state
 var current_zone_id: int = 1
@@ -19,7 +21,27 @@ var entities: Dictionary = {}  # entity_id -> entity_data
 var player_stats: Dictionary = {}
 var inventory: Array = []
 var equipment: Dictionary = {}
+var gold: int = 0
+# corpse_id -> items left on it, as returned by the last loot response
+var loot_windows: Dictionary = {}
+# Party loot rolls waiting for an answer, oldest first, as { "corpse_id", "slot", "item_id" }
+var loot_rolls: Array = []
+# Latest party invitation not answered yet, as { "inviter_id", "inviter_name" }, or empty
+var party_invite: Dictionary = {}
+# The player's party as last announced; party_id 0 when in none
+var party: Dictionary = {}
 var last_world_snapshot: Dictionary = {}
+# snapshot_id -> entities as of that snapshot, kept as delta baselines
+var snapshot_history: Dictionary = {}
+var last_applied_snapshot_id: int = 0
+# entity_id -> { effect_id -> { "name", "stacks", "expires_at" (msec ticks) } }
+var status_effects: Dictionary = {}
+# entity_id -> { "ability_id", "channel", "duration", "started_at" (msec ticks) }
+var casts: Dictionary = {}
+# Newest last, capped at MAX_COMBAT_LOG lines
+var combat_log: Array = []
+const MAX_COMBAT_LOG: int = 50
+const MAX_SNAPSHOT_HISTORY: int = 64
 const MAX_SIGNED_64: int = 9223372036854775807
 
 func _init():
@@ -38,7 +60,17 @@ func _reset_state():
 	}
 	inventory.clear()
 	equipment.clear()
+	gold = 0
+	loot_windows.clear()
+	loot_rolls.clear()
+	party_invite = {}
+	party = {"party_id": 0, "leader_id": 0, "members": [], "method": "NeedBeforeGreed", "threshold": "Uncommon"}
 	last_world_snapshot = {}
+	snapshot_history.clear()
+	last_applied_snapshot_id = 0
+	status_effects.clear()
+	casts.clear()
+	combat_log.clear()
 
 func set_player_entity(player_id: int):
 	player_entity_id = player_id
@@ -58,6 +90,8 @@ func update_entity(entity_id: int, entity_data: Dictionary):
 func remove_entity(entity_id: int):
 	if entities.has(entity_id):
 		entities.erase(entity_id)
+		status_effects.erase(entity_id)
+		loot_windows.erase(entity_id)
 		emit_signal("entity_removed", entity_id)
 
 func get_entity(entity_id: int) -> Dictionary:
@@ -125,6 +159,300 @@ func apply_world_snapshot(snapshot: Dictionary):
 			continue
 		add_entity(entity_id, entity_data)
 
+	# Re-applying the current state (e.g. a cached copy) keeps older baselines
+	var snapshot_id = _u64_to_int(snapshot.get("snapshot_id", 0))
+	if snapshot_id != last_applied_snapshot_id:
+		snapshot_history.clear()
+	snapshot_history[snapshot_id] = entities.duplicate(true)
+	last_applied_snapshot_id = snapshot_id
+
+# Apply a delta batch to the snapshot it was computed against. Returns true
+# when the batch was applied and should be acknowledged.
+func apply_entity_update_batch(batch: Dictionary) -> bool:
+	var snapshot_id = _u64_to_int(batch.get("snapshot_id", 0))
+	var baseline_id = _u64_to_int(batch.get("baseline_id", 0))
+	if snapshot_id <= last_applied_snapshot_id:
+		return false  # Stale or already applied
+	if not snapshot_history.has(baseline_id):
+		return false  # Baseline unknown; the server resends a full snapshot
+
+	var next_entities: Dictionary = snapshot_history[baseline_id].duplicate(true)
+	for entity_data in batch.get("spawned", []):
+		if typeof(entity_data) != TYPE_DICTIONARY:
+			continue
+		var entity_id = _u64_to_int(entity_data.get("id", 0))
+		if entity_id != 0:
+			next_entities[entity_id] = entity_data
+
+	for update in batch.get("updated", []):
+		if typeof(update) != TYPE_DICTIONARY:
+			continue
+		var entity_id = _u64_to_int(update.get("entity_id", 0))
+		if not next_entities.has(entity_id):
+			continue
+		var entity = next_entities[entity_id]
+		# Only fields that differ from the baseline are present
+		for field in ["position", "rotation", "state"]:
+			if update.get(field) != null:
+				entity[field] = update[field]
+
+	for despawned_id in batch.get("despawned", []):
+		next_entities.erase(_u64_to_int(despawned_id))
+
+	for entity_id in entities.keys():
+		if not next_entities.has(entity_id):
+			remove_entity(entity_id)
+	for entity_id in next_entities:
+		if entities.get(entity_id) != next_entities[entity_id]:
+			update_entity(entity_id, next_entities[entity_id].duplicate(true))
+
+	# Older snapshots can no longer be a baseline once a newer one is acknowledged
+	for old_id in snapshot_history.keys():
+		if old_id < baseline_id:
+			snapshot_history.erase(old_id)
+	snapshot_history[snapshot_id] = next_entities
+	while snapshot_history.size() > MAX_SNAPSHOT_HISTORY:
+		snapshot_history.erase(snapshot_history.keys().min())
+	last_applied_snapshot_id = snapshot_id
+
+	# Events ride along with the batch they were raised in
+	for update in batch.get("updated", []):
+		if typeof(update) != TYPE_DICTIONARY:
+			continue
+		var entity_id = _u64_to_int(update.get("entity_id", 0))
+		for effect in update.get("effects", []):
+			if typeof(effect) == TYPE_DICTIONARY:
+				_apply_entity_effect(entity_id, effect)
+			elif typeof(effect) == TYPE_STRING:
+				# Effects without data (Death, Respawn) arrive as bare names
+				_apply_entity_effect(entity_id, {effect: {}})
+
+	if not last_world_snapshot.is_empty():
+		last_world_snapshot["entities"] = entities.values()
+		last_world_snapshot["snapshot_id"] = snapshot_id
+	return true
+
+func _apply_entity_effect(entity_id: int, effect: Dictionary):
+	if effect.has("StatusEffect"):
+		var status: Dictionary = effect.StatusEffect
+		var effect_id = _u64_to_int(status.get("effect_id", 0))
+		var effects: Dictionary = status_effects.get(entity_id, {})
+		if str(status.get("change", "Applied")) == "Expired":
+			effects.erase(effect_id)
+		else:
+			effects[effect_id] = {
+				"name": str(status.get("effect_type", "")),
+				"stacks": int(status.get("stacks", 1)),
+				"expires_at": Time.get_ticks_msec() + int(float(status.get("duration", 0.0)) * 1000.0)
+			}
+		if effects.is_empty():
+			status_effects.erase(entity_id)
+		else:
+			status_effects[entity_id] = effects
+	elif effect.has("Respawn"):
+		# The server drops every status effect when an entity comes back
+		status_effects.erase(entity_id)
+	elif effect.has("Cast"):
+		_apply_cast(entity_id, effect.Cast)
+	elif effect.has("Experience"):
+		var earned: Dictionary = effect.Experience
+		player_stats["level"] = int(earned.get("level", 1))
+		player_stats["experience"] = _u64_to_int(earned.get("experience", 0))
+		player_stats["experience_to_next"] = _u64_to_int(earned.get("experience_to_next", 0))
+		_append_combat_log("You gain %d experience" % _u64_to_int(earned.get("amount", 0)))
+	elif effect.has("LevelUp"):
+		var level := int(effect.LevelUp.get("level", 1))
+		if entity_id == player_entity_id:
+			player_stats["level"] = level
+			_append_combat_log("You have reached level %d!" % level)
+		else:
+			_append_combat_log("%s has reached level %d" % [_entity_name(entity_id), level])
+	elif effect.has("LootRoll"):
+		var roll: Dictionary = effect.LootRoll
+		loot_rolls.append({
+			"corpse_id": _u64_to_int(roll.get("corpse_id", 0)),
+			"slot": int(roll.get("slot", 0)),
+			"item_id": int(roll.get("item_id", 0))
+		})
+		_append_combat_log("Roll for item %d: N for need, G for greed" % int(roll.get("item_id", 0)))
+	elif effect.has("LootRollResult"):
+		_apply_loot_roll_result(effect.LootRollResult)
+	elif effect.has("PartyInvite"):
+		var invite: Dictionary = effect.PartyInvite
+		party_invite = {
+			"inviter_id": _u64_to_int(invite.get("inviter_id", 0)),
+			"inviter_name": str(invite.get("inviter_name", ""))
+		}
+		_append_combat_log("%s invites you to a party: Y to accept" % party_invite.inviter_name)
+	elif effect.has("PartyChanged"):
+		_apply_party_changed(effect.PartyChanged)
+	elif effect.has("PartyRefused"):
+		_append_combat_log(str(effect.PartyRefused.get("reason", "")))
+	elif effect.has("DamageNumber") or effect.has("ActionRejected"):
+		_append_combat_log(_combat_log_line(entity_id, effect))
+	emit_signal("entity_effect_received", entity_id, effect)
+
+func _apply_loot_roll_result(result: Dictionary):
+	var corpse_id := _u64_to_int(result.get("corpse_id", 0))
+	var slot := int(result.get("slot", 0))
+	for index in range(loot_rolls.size() - 1, -1, -1):
+		if loot_rolls[index].corpse_id == corpse_id and loot_rolls[index].slot == slot:
+			loot_rolls.remove_at(index)
+	var item_id := int(result.get("item_id", 0))
+	var winner_id := _u64_to_int(result.get("winner_id", 0))
+	if winner_id == 0:
+		_append_combat_log("Everyone passed on item %d" % item_id)
+		return
+	var winner := "You" if winner_id == player_entity_id else _entity_name(winner_id)
+	_append_combat_log("%s won item %d (%s %d)" % [winner, item_id, str(result.get("choice", "")), int(result.get("roll", 0))])
+
+func _apply_party_changed(changed: Dictionary):
+	var was_in_party: bool = party.party_id != 0
+	var members: Array = []
+	for member in changed.get("members", []):
+		members.append({
+			"player_id": _u64_to_int(member.get("player_id", 0)),
+			"name": str(member.get("name", ""))
+		})
+	party = {
+		"party_id": _u64_to_int(changed.get("party_id", 0)),
+		"leader_id": _u64_to_int(changed.get("leader_id", 0)),
+		"members": members,
+		"method": str(changed.get("method", "NeedBeforeGreed")),
+		"threshold": str(changed.get("threshold", "Uncommon"))
+	}
+	if party.party_id == 0:
+		if was_in_party:
+			_append_combat_log("You are no longer in a party")
+		return
+	var names: Array = []
+	for member in members:
+		names.append(member.name)
+	_append_combat_log("Party: %s (loot %s, %s and up)" % [", ".join(names), party.method, party.threshold])
+
+# Pending party invitation, cleared once taken, or empty
+func take_party_invite() -> Dictionary:
+	var invite := party_invite
+	party_invite = {}
+	return invite
+
+# Oldest loot roll the player has not answered yet, or empty
+func take_loot_roll() -> Dictionary:
+	if loot_rolls.is_empty():
+		return {}
+	return loot_rolls.pop_front()
+
+func _apply_cast(entity_id: int, cast: Dictionary):
+	match str(cast.get("phase", "Started")):
+		"Started", "Progress":
+			# Anchor the start time so progress events correct any drift
+			var elapsed_msec := int(float(cast.get("elapsed", 0.0)) * 1000.0)
+			casts[entity_id] = {
+				"ability_id": int(cast.get("ability_id", 0)),
+				"channel": bool(cast.get("channel", false)),
+				"duration": float(cast.get("duration", 0.0)),
+				"started_at": Time.get_ticks_msec() - elapsed_msec
+			}
+		"Interrupted":
+			casts.erase(entity_id)
+			if entity_id == player_entity_id:
+				_append_combat_log("Cast interrupted: " + str(cast.get("reason", "")))
+		_:
+			casts.erase(entity_id)
+
+# Cast in progress on an entity, with "progress" from 0 to 1, or empty
+func get_cast(entity_id: int) -> Dictionary:
+	if not casts.has(entity_id):
+		return {}
+	var cast: Dictionary = casts[entity_id].duplicate()
+	var duration_msec: float = max(cast.duration * 1000.0, 1.0)
+	cast["progress"] = clamp(float(Time.get_ticks_msec() - cast.started_at) / duration_msec, 0.0, 1.0)
+	return cast
+
+func _combat_log_line(entity_id: int, effect: Dictionary) -> String:
+	if effect.has("ActionRejected"):
+		return "Can't do that: " + str(effect.ActionRejected.get("reason", ""))
+	var hit: Dictionary = effect.DamageNumber
+	var target := _entity_name(entity_id)
+	var source := _entity_name(_u64_to_int(hit.get("source_id", 0)))
+	var amount := int(hit.get("amount", 0))
+	var critical := " (critical)" if hit.get("is_critical", false) else ""
+	match str(hit.get("kind", "Damage")):
+		"Heal":
+			return "%s heals %s for %d%s" % [source, target, amount, critical]
+		"Miss":
+			return "%s misses %s" % [source, target]
+		"Dodge":
+			return "%s dodges %s's attack" % [target, source]
+		"Parry":
+			return "%s parries %s's attack" % [target, source]
+	var line := "%s hits %s for %d%s" % [source, target, amount, critical]
+	if hit.get("killing_blow", false):
+		line += ", killing it"
+	return line
+
+func _entity_name(entity_id: int) -> String:
+	var state = entities.get(entity_id, {}).get("state", {})
+	if typeof(state) == TYPE_DICTIONARY and state.has("display_name"):
+		return str(state.display_name)
+	return "Something"
+
+func _append_combat_log(line: String):
+	combat_log.append(line)
+	while combat_log.size() > MAX_COMBAT_LOG:
+		combat_log.pop_front()
+	emit_signal("combat_log_updated", line)
+
+# Server announcements share the combat log
+func log_notice(line: String):
+	_append_combat_log(line)
+
+func get_combat_log() -> Array:
+	return combat_log
+
+# Active status effects on an entity, as { "name", "stacks", "expires_at" }
+func get_status_effects(entity_id: int) -> Array:
+	return status_effects.get(entity_id, {}).values()
+
+func is_entity_dead(entity_id: int) -> bool:
+	var state = entities.get(entity_id, {}).get("state", {})
+	return typeof(state) == TYPE_DICTIONARY and str(state.get("movement_state", "")) == "Dead"
+
+# Class resource of an entity as { "resource_type", "current", "maximum" }, or empty
+func get_entity_resource(entity_id: int) -> Dictionary:
+	var state = entities.get(entity_id, {}).get("state", {})
+	if typeof(state) != TYPE_DICTIONARY or typeof(state.get("resource")) != TYPE_DICTIONARY:
+		return {}
+	return state.resource
+
+# Record what is left on a corpse and log what the player picked up
+func apply_loot_response(response: Dictionary) -> Array:
+	var corpse_id := _u64_to_int(response.get("corpse_id", 0))
+	if not response.get("success", false):
+		var message = response.get("error_message", null)
+		_append_combat_log("Cannot loot: " + (str(message) if message != null else "unknown reason"))
+		loot_windows.erase(corpse_id)
+		return []
+
+	var picked_up := int(response.get("gold", 0))
+	if picked_up > 0:
+		gold += picked_up
+		_append_combat_log("You loot %d gold" % picked_up)
+
+	var items: Array = response.get("items", [])
+	var remaining := {}
+	for entry in items:
+		remaining[int(entry.get("slot", 0))] = true
+	for entry in loot_windows.get(corpse_id, []):
+		if not remaining.has(int(entry.get("slot", 0))):
+			var item: Dictionary = entry.get("item", {})
+			_append_combat_log("You receive item %d x%d" % [int(item.get("definition_id", 0)), int(item.get("quantity", 1))])
+	if items.is_empty():
+		loot_windows.erase(corpse_id)
+	else:
+		loot_windows[corpse_id] = items
+	return items
+
 func update_inventory(items: Array):
 	inventory = items
 
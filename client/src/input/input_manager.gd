@@ -50,6 +50,22 @@ func process_input(event):
 					emit_signal("action_pressed", "escape")
 				KEY_TAB:
 					emit_signal("action_pressed", "tab")
+				KEY_R:
+					emit_signal("action_pressed", "release")
+				KEY_L:
+					emit_signal("action_pressed", "loot")
+				KEY_N:
+					emit_signal("action_pressed", "roll_need")
+				KEY_G:
+					emit_signal("action_pressed", "roll_greed")
+				KEY_P:
+					emit_signal("action_pressed", "party_invite")
+				KEY_Y:
+					emit_signal("action_pressed", "party_accept")
+				KEY_U:
+					emit_signal("action_pressed", "party_leave")
+				KEY_O:
+					emit_signal("action_pressed", "party_loot_method")
 				KEY_1, KEY_2, KEY_3, KEY_4, KEY_5:
 					var ability_slot = event.keycode - KEY_1 + 1
 					emit_signal("action_pressed", "ability_" + str(ability_slot))
@@ -16,6 +16,11 @@ signal character_list_received(characters: Array)
 signal character_created(character_data: Dictionary)
 signal character_selected(character_data: Dictionary)
 signal world_snapshot_received(snapshot: Dictionary)
+signal entity_update_batch_received(batch: Dictionary)
+signal session_resumed(resume_data: Dictionary)
+signal session_resume_failed(reason: String)
+signal loot_received(response: Dictionary)
+signal shutdown_notice_received(notice: Dictionary)
 
 # Connection state
 enum ConnectionState {
@@ -30,6 +35,9 @@ var websocket: WebSocketPeer = null
 var sequence_id: int = 0
 var session_id: String = ""
 var player_id: int = 0
+# Resume token survives disconnects so a dropped client can reclaim its character
+var resume_token: String = ""
+var _resume_on_connect: bool = false
 const MAX_SIGNED_64: int = 9223372036854775807
 
 # Message queues
@@ -87,6 +95,9 @@ func poll():
 				print("DEBUG: Emitting connected signal")
 				emit_signal("connected")
 				_send_handshake()
+				if _resume_on_connect:
+					_resume_on_connect = false
+					_send_resume_request()
 		WebSocketPeer.STATE_CLOSED:
 			if connection_state != ConnectionState.DISCONNECTED:
 				connection_state = ConnectionState.DISCONNECTED
@@ -126,6 +137,75 @@ func send_message(payload: Dictionary) -> Error:
 
 	return OK
 
+# Tell the server which snapshot we hold so it can delta against it
+func send_snapshot_ack(snapshot_id: int) -> Error:
+	return send_message({
+		"SnapshotAck": {
+			"snapshot_id": snapshot_id
+		}
+	})
+
+# Come back at the zone's graveyard after dying
+func send_release_request() -> Error:
+	return send_message({
+		"ReleaseRequest": {}
+	})
+
+# Open a corpse: picks up its gold and lists the items left on it
+func send_loot_open_request(corpse_id: int) -> Error:
+	return send_message({
+		"LootOpenRequest": {
+			"corpse_id": corpse_id
+		}
+	})
+
+# Take the item in one loot slot of a corpse
+func send_loot_take_request(corpse_id: int, slot: int) -> Error:
+	return send_message({
+		"LootTakeRequest": {
+			"corpse_id": corpse_id,
+			"slot": slot
+		}
+	})
+
+# Answer a party loot roll: choice is "need", "greed" or "pass"
+func send_loot_roll_request(corpse_id: int, slot: int, choice: String) -> Error:
+	return send_message({
+		"LootRollRequest": {
+			"corpse_id": corpse_id,
+			"slot": slot,
+			"choice": choice
+		}
+	})
+
+func send_party_invite_request(player_id: int) -> Error:
+	return send_message({
+		"PartyInviteRequest": {
+			"player_id": player_id
+		}
+	})
+
+func send_party_invite_reply(accept: bool) -> Error:
+	return send_message({
+		"PartyInviteReply": {
+			"accept": accept
+		}
+	})
+
+func send_party_leave_request() -> Error:
+	return send_message({
+		"PartyLeaveRequest": {}
+	})
+
+# method is a LootMethod name (e.g. "RoundRobin"), threshold an ItemRarity name
+func send_party_loot_rules_request(method: String, threshold: String) -> Error:
+	return send_message({
+		"PartyLootRulesRequest": {
+			"method": method,
+			"threshold": threshold
+		}
+	})
+
 func _send_handshake():
 	var handshake = {
 		"HandshakeRequest": {
@@ -136,6 +216,23 @@ func _send_handshake():
 	}
 	send_message(handshake)
 
+# Reconnect after a dropped connection and reclaim the link-dead session
+func reconnect_and_resume(url: String) -> Error:
+	if resume_token == "":
+		return ERR_UNAVAILABLE
+	var error = connect_to_server(url)
+	if error == OK:
+		_resume_on_connect = true
+	return error
+
+func _send_resume_request():
+	var request = {
+		"SessionResumeRequest": {
+			"session_token": resume_token
+		}
+	}
+	send_message(request)
+
 func _process_incoming_message(message: Dictionary):
 	emit_signal("message_received", message)
 
@@ -155,8 +252,21 @@ func _process_incoming_message(message: Dictionary):
 			_handle_character_select_response(payload.CharacterSelectResponse)
 		elif payload.has("WorldSnapshot"):
 			_handle_world_snapshot(payload.WorldSnapshot)
+		elif payload.has("EntityUpdateBatch"):
+			_handle_entity_update_batch(payload.EntityUpdateBatch)
+		elif payload.has("LootResponse"):
+			emit_signal("loot_received", payload.LootResponse)
+		elif payload.has("SessionResumeResponse"):
+			_handle_session_resume_response(payload.SessionResumeResponse)
+		elif payload.has("Ping"):
+			_handle_ping(payload.Ping)
 		elif payload.has("Pong"):
 			_handle_pong(payload.Pong)
+		elif payload.has("ShutdownNotice"):
+			# Only a warning: the server keeps the connection open until it stops
+			emit_signal("shutdown_notice_received", payload.ShutdownNotice)
+		elif payload.has("Disconnect"):
+			_handle_disconnect(payload.Disconnect)
 		elif payload.has("Error"):
 			_handle_error(payload.Error)
 
@@ -168,6 +278,19 @@ func _handle_handshake_response(response: Dictionary):
 		connection_state = ConnectionState.ERROR
 		emit_signal("connection_error", "Handshake rejected")
 
+# Server heartbeat; an unanswered ping eventually times the session out
+func _handle_ping(ping: Dictionary):
+	send_message({
+		"Pong": {
+			"timestamp": ping.get("timestamp", 0)
+		}
+	})
+
+func _handle_disconnect(disconnect: Dictionary):
+	var reason = str(disconnect.get("reason", "Unknown"))
+	push_warning("Disconnected by server (" + reason + "): " + disconnect.get("message", ""))
+	emit_signal("connection_error", disconnect.get("message", "Disconnected by server"))
+
 func _handle_pong(pong: Dictionary):
 	# Calculate ping time if needed
 	pass
@@ -175,11 +298,24 @@ func _handle_pong(pong: Dictionary):
 func _handle_auth_response(response: Dictionary):
 	if response.success:
 		session_id = response.get("session_token", "")
+		resume_token = session_id
 		player_id = _u64_to_int(response.get("player_id", 0))
 		emit_signal("auth_successful", response)
 	else:
 		emit_signal("auth_failed", response.get("message", "Authentication failed"))
 
+func _handle_session_resume_response(response: Dictionary):
+	if response.success:
+		var token = response.get("session_token")
+		if token != null:
+			resume_token = token
+		session_id = resume_token
+		player_id = _u64_to_int(response.get("player_id", 0))
+		emit_signal("session_resumed", response)
+	else:
+		resume_token = ""
+		emit_signal("session_resume_failed", response.get("message", "Session resume failed"))
+
 func _handle_character_list_response(response: Dictionary):
 	var characters = response.get("characters", [])
 	emit_signal("character_list_received", characters)
@@ -200,6 +336,9 @@ func _handle_character_select_response(response: Dictionary):
 func _handle_world_snapshot(snapshot: Dictionary):
 	emit_signal("world_snapshot_received", snapshot)
 
+func _handle_entity_update_batch(batch: Dictionary):
+	emit_signal("entity_update_batch_received", batch)
+
 func _handle_error(error: Dictionary):
 	push_error("Server error: " + error.get("message", "Unknown error"))
 	emit_signal("connection_error", error.get("message", "Unknown error"))
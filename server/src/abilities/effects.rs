@@ -0,0 +1,79 @@
+//! Status effect definitions
+//!
+//! Effects live next to the abilities that apply them in `abilities.json`.
+
+use crate::entities::StatModifiers;
+use serde::{Deserialize, Serialize};
+
+pub type EffectId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    Buff,
+    Debuff,
+}
+
+/// What happens when an effect is applied to an entity that already has it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StackRule {
+    /// Restart the duration
+    #[default]
+    Refresh,
+    /// Add a stack, up to `max_stacks`, and restart the duration
+    Stack,
+    /// Leave the running effect alone
+    Ignore,
+}
+
+/// A buff, debuff, damage-over-time or heal-over-time effect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectDefinition {
+    pub id: EffectId,
+    pub name: String,
+    pub kind: EffectKind,
+    /// Seconds the effect lasts
+    pub duration: f32,
+    #[serde(default)]
+    pub stacking: StackRule,
+    #[serde(default = "default_max_stacks")]
+    pub max_stacks: u32,
+    /// Seconds between periodic ticks; 0 means the effect never ticks
+    #[serde(default)]
+    pub tick_interval: f32,
+    /// Damage per tick and stack
+    #[serde(default)]
+    pub tick_damage: u32,
+    /// Healing per tick and stack
+    #[serde(default)]
+    pub tick_heal: u32,
+    /// Stat changes per stack while the effect is active
+    #[serde(default)]
+    pub modifiers: StatModifiers,
+    /// Stop the entity acting and break its casts while active
+    #[serde(default)]
+    pub stun: bool,
+}
+
+fn default_max_stacks() -> u32 {
+    1
+}
+
+impl EffectDefinition {
+    pub(super) fn validate(&self) -> Result<(), String> {
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            return Err("duration must be positive".to_string());
+        }
+        if !self.tick_interval.is_finite() || self.tick_interval < 0.0 {
+            return Err("tick_interval must be a non-negative number".to_string());
+        }
+        if self.max_stacks == 0 {
+            return Err("max_stacks must be at least 1".to_string());
+        }
+        if self.tick_interval == 0.0 && (self.tick_damage > 0 || self.tick_heal > 0) {
+            return Err("periodic damage or healing needs a tick_interval".to_string());
+        }
+        Ok(())
+    }
+}
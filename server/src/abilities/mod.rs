@@ -0,0 +1,309 @@
+//! Data-driven ability and status effect definitions
+//!
+//! Abilities and the effects they apply are described in
+//! `content/abilities.json` rather than in code.
+//! The bundled copy is compiled into the server; `ABILITIES_PATH` points the
+//! server at a different file so content can change without a rebuild.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub mod effects;
+
+#[cfg(test)]
+mod tests;
+
+pub use effects::*;
+
+pub type AbilityId = u32;
+
+const BUNDLED_ABILITIES: &str = include_str!("../../../content/abilities.json");
+/// Format version of `abilities.json` this server understands
+const CONTENT_VERSION: u32 = 1;
+
+/// Who an ability may be aimed at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetType {
+    /// Any other entity the caster can attack
+    Enemy,
+    /// An entity that is not hostile to the caster, including the caster
+    Friendly,
+    /// Always the caster; the requested target is ignored
+    Caster,
+}
+
+/// Shape of the area an ability lands on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AreaShape {
+    /// A circle around a point on the ground within the ability's range
+    GroundCircle,
+    /// A cone spreading from the caster in the direction it faces
+    Cone,
+}
+
+/// Area an ability hits instead of a single target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AreaDefinition {
+    pub shape: AreaShape,
+    /// Circle radius, or how far a cone reaches
+    pub radius: f32,
+    /// Full width of a cone in degrees
+    #[serde(default)]
+    pub angle: f32,
+    /// Most targets hit, nearest to the centre of the area first
+    pub max_targets: u32,
+}
+
+/// Linear scaling formula: `base + attack_power * attack_power_scale`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Formula {
+    #[serde(default)]
+    pub base: f32,
+    #[serde(default)]
+    pub attack_power_scale: f32,
+}
+
+impl Formula {
+    pub fn evaluate(&self, attack_power: u32) -> u32 {
+        (self.base + attack_power as f32 * self.attack_power_scale)
+            .max(0.0)
+            .round() as u32
+    }
+}
+
+/// A single ability as defined in content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbilityDefinition {
+    pub id: AbilityId,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub target: TargetType,
+    /// Maximum distance to the target in world units
+    #[serde(default)]
+    pub range: f32,
+    /// Class resource spent on use
+    #[serde(default)]
+    pub resource_cost: u32,
+    /// Seconds before the ability can be used again
+    #[serde(default)]
+    pub cooldown: f32,
+    /// Seconds spent casting before the ability resolves; 0 is instant
+    #[serde(default)]
+    pub cast_time: f32,
+    /// Seconds the ability is channelled for; 0 means it is not channelled
+    #[serde(default)]
+    pub channel_time: f32,
+    /// Times a channel resolves its damage, healing and effects, evenly spaced
+    #[serde(default)]
+    pub channel_ticks: u32,
+    /// Damage before the target's mitigation
+    #[serde(default)]
+    pub damage: Option<Formula>,
+    #[serde(default)]
+    pub heal: Option<Formula>,
+    /// Status effects applied to the target
+    #[serde(default)]
+    pub effects: Vec<EffectId>,
+    /// Raise the caster to the top of the target's threat table
+    #[serde(default)]
+    pub taunt: bool,
+    /// Cancel whatever the target is casting or channelling
+    #[serde(default)]
+    pub interrupts: bool,
+    /// Hit every valid target in an area rather than the requested target
+    #[serde(default)]
+    pub area: Option<AreaDefinition>,
+}
+
+impl AbilityDefinition {
+    fn validate(&self) -> Result<(), AbilityError> {
+        let invalid = |reason: &str| AbilityError::Invalid {
+            id: self.id,
+            reason: reason.to_string(),
+        };
+
+        for (name, value) in [
+            ("range", self.range),
+            ("cooldown", self.cooldown),
+            ("cast_time", self.cast_time),
+            ("channel_time", self.channel_time),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(invalid(&format!("{} must be a non-negative number", name)));
+            }
+        }
+        if self.damage.is_none()
+            && self.heal.is_none()
+            && self.effects.is_empty()
+            && !self.taunt
+            && !self.interrupts
+        {
+            return Err(invalid(
+                "ability has no damage, heal, effects, taunt or interrupt",
+            ));
+        }
+        if self.channel_time > 0.0 {
+            if self.cast_time > 0.0 {
+                return Err(invalid("ability cannot both cast and channel"));
+            }
+            if self.channel_ticks == 0 {
+                return Err(invalid("channelled ability needs channel_ticks"));
+            }
+        }
+        if let Some(area) = &self.area {
+            if self.target == TargetType::Caster {
+                return Err(invalid("area ability cannot target the caster"));
+            }
+            if !area.radius.is_finite() || area.radius <= 0.0 {
+                return Err(invalid("area radius must be a positive number"));
+            }
+            if area.max_targets == 0 {
+                return Err(invalid("area must allow at least one target"));
+            }
+            if area.shape == AreaShape::Cone && !(area.angle > 0.0 && area.angle <= 360.0) {
+                return Err(invalid("cone angle must be in (0, 360] degrees"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether using the ability takes time rather than resolving at once
+    pub fn is_timed(&self) -> bool {
+        self.cast_time > 0.0 || self.is_channelled()
+    }
+
+    pub fn is_channelled(&self) -> bool {
+        self.channel_time > 0.0
+    }
+
+    /// Seconds between channel ticks
+    pub fn channel_interval(&self) -> f32 {
+        if self.is_channelled() {
+            self.channel_time / self.channel_ticks as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Errors raised while loading ability content
+#[derive(Debug, thiserror::Error)]
+pub enum AbilityError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("malformed ability data: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("unsupported abilities file version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("ability {0} is defined more than once")]
+    DuplicateId(AbilityId),
+
+    #[error("ability {id} is invalid: {reason}")]
+    Invalid { id: AbilityId, reason: String },
+
+    #[error("effect {0} is defined more than once")]
+    DuplicateEffectId(EffectId),
+
+    #[error("effect {id} is invalid: {reason}")]
+    InvalidEffect { id: EffectId, reason: String },
+}
+
+/// On-disk layout of `abilities.json`
+#[derive(Debug, Deserialize)]
+struct AbilityFile {
+    version: u32,
+    abilities: Vec<AbilityDefinition>,
+    #[serde(default)]
+    effects: Vec<EffectDefinition>,
+}
+
+/// All known abilities and status effects, keyed by ID
+#[derive(Debug, Clone, Default)]
+pub struct AbilityRegistry {
+    abilities: HashMap<AbilityId, AbilityDefinition>,
+    effects: HashMap<EffectId, EffectDefinition>,
+}
+
+impl AbilityRegistry {
+    /// The abilities shipped with the server
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_ABILITIES).expect("bundled abilities.json is valid")
+    }
+
+    /// Load abilities from a content file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AbilityError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|source| AbilityError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// `ABILITIES_PATH` if set, otherwise the bundled abilities
+    pub fn from_env() -> Result<Self, AbilityError> {
+        match std::env::var("ABILITIES_PATH") {
+            Ok(path) if !path.is_empty() => Self::load(path),
+            _ => Ok(Self::bundled()),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, AbilityError> {
+        let file: AbilityFile = serde_json::from_str(json)?;
+        if file.version != CONTENT_VERSION {
+            return Err(AbilityError::UnsupportedVersion(file.version));
+        }
+
+        let mut effects = HashMap::with_capacity(file.effects.len());
+        for effect in file.effects {
+            effect
+                .validate()
+                .map_err(|reason| AbilityError::InvalidEffect {
+                    id: effect.id,
+                    reason,
+                })?;
+            if effects.contains_key(&effect.id) {
+                return Err(AbilityError::DuplicateEffectId(effect.id));
+            }
+            effects.insert(effect.id, effect);
+        }
+
+        let mut abilities = HashMap::with_capacity(file.abilities.len());
+        for ability in file.abilities {
+            ability.validate()?;
+            if let Some(unknown) = ability.effects.iter().find(|id| !effects.contains_key(id)) {
+                return Err(AbilityError::Invalid {
+                    id: ability.id,
+                    reason: format!("applies unknown effect {}", unknown),
+                });
+            }
+            if abilities.contains_key(&ability.id) {
+                return Err(AbilityError::DuplicateId(ability.id));
+            }
+            abilities.insert(ability.id, ability);
+        }
+        Ok(Self { abilities, effects })
+    }
+
+    pub fn get(&self, id: AbilityId) -> Option<&AbilityDefinition> {
+        self.abilities.get(&id)
+    }
+
+    pub fn effect(&self, id: EffectId) -> Option<&EffectDefinition> {
+        self.effects.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.abilities.len()
+    }
+}
@@ -0,0 +1,93 @@
+use crate::abilities::{AbilityError, AbilityRegistry, TargetType};
+
+#[test]
+fn test_bundled_abilities_load() {
+    let registry = AbilityRegistry::bundled();
+    for id in [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 100] {
+        assert!(registry.get(id).is_some(), "ability {} missing", id);
+    }
+
+    let mend = registry.get(3).unwrap();
+    assert_eq!(mend.target, TargetType::Friendly);
+    assert!(mend.heal.is_some());
+    assert!(mend.damage.is_none());
+    for effect_id in &mend.effects {
+        assert!(registry.effect(*effect_id).is_some());
+    }
+}
+
+#[test]
+fn test_duplicate_ability_ids_are_rejected() {
+    let json = r#"{
+        "version": 1,
+        "abilities": [
+            { "id": 7, "name": "A", "target": "enemy", "damage": { "base": 1 } },
+            { "id": 7, "name": "B", "target": "enemy", "damage": { "base": 2 } }
+        ]
+    }"#;
+    assert!(matches!(
+        AbilityRegistry::from_json(json),
+        Err(AbilityError::DuplicateId(7))
+    ));
+}
+
+#[test]
+fn test_invalid_abilities_are_rejected() {
+    let negative_cooldown = r#"{
+        "version": 1,
+        "abilities": [
+            { "id": 1, "name": "A", "target": "enemy", "cooldown": -1, "damage": { "base": 1 } }
+        ]
+    }"#;
+    assert!(matches!(
+        AbilityRegistry::from_json(negative_cooldown),
+        Err(AbilityError::Invalid { id: 1, .. })
+    ));
+
+    let does_nothing = r#"{
+        "version": 1,
+        "abilities": [{ "id": 2, "name": "B", "target": "caster" }]
+    }"#;
+    assert!(matches!(
+        AbilityRegistry::from_json(does_nothing),
+        Err(AbilityError::Invalid { id: 2, .. })
+    ));
+
+    let unknown_effect = r#"{
+        "version": 1,
+        "abilities": [{ "id": 3, "name": "C", "target": "enemy", "effects": [42] }]
+    }"#;
+    assert!(matches!(
+        AbilityRegistry::from_json(unknown_effect),
+        Err(AbilityError::Invalid { id: 3, .. })
+    ));
+
+    let tickless_channel = r#"{
+        "version": 1,
+        "abilities": [
+            { "id": 4, "name": "D", "target": "enemy", "channel_time": 3, "damage": { "base": 1 } }
+        ]
+    }"#;
+    assert!(matches!(
+        AbilityRegistry::from_json(tickless_channel),
+        Err(AbilityError::Invalid { id: 4, .. })
+    ));
+
+    let flat_cone = r#"{
+        "version": 1,
+        "abilities": [{
+            "id": 5, "name": "E", "target": "enemy", "damage": { "base": 1 },
+            "area": { "shape": "cone", "radius": 4, "max_targets": 3 }
+        }]
+    }"#;
+    assert!(matches!(
+        AbilityRegistry::from_json(flat_cone),
+        Err(AbilityError::Invalid { id: 5, .. })
+    ));
+
+    let future_version = r#"{ "version": 99, "abilities": [] }"#;
+    assert!(matches!(
+        AbilityRegistry::from_json(future_version),
+        Err(AbilityError::UnsupportedVersion(99))
+    ));
+}
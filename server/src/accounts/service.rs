@@ -12,6 +12,9 @@ use uuid::Uuid;
 use crate::accounts::{AccountError, AccountResult};
 use crate::db::models::{Account, Character};
 
+/// `progression.progression_type` of a character's level and experience
+const CHARACTER_LEVEL_PROGRESSION: &str = "character_level";
+
 /// Account service for managing user accounts and authentication
 #[derive(Clone)]
 pub struct AccountService {
@@ -292,6 +295,75 @@ impl AccountService {
         Ok(())
     }
 
+    pub async fn update_character_resource(
+        &self,
+        character_id: Uuid,
+        resource_value: i32,
+    ) -> AccountResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE characters 
+            SET resource_value = LEAST($1, max_resource), updated_at = NOW()
+            WHERE id = $2
+            "#,
+        )
+        .bind(resource_value)
+        .bind(character_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Save a character's level and experience to its row and its `progression` entry
+    pub async fn update_character_progression(
+        &self,
+        character_id: Uuid,
+        level: i32,
+        experience: i64,
+        experience_to_next: i64,
+    ) -> AccountResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE characters
+            SET level = $1, experience = $2, updated_at = NOW()
+            WHERE id = $3
+            "#,
+        )
+        .bind(level)
+        .bind(experience)
+        .bind(character_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO progression (
+                character_id, progression_type, current_value, max_value,
+                level, experience_to_next_level
+            )
+            VALUES ($1, $2, $3, $4, $5, $4)
+            ON CONFLICT (character_id, progression_type) DO UPDATE
+            SET current_value = EXCLUDED.current_value,
+                max_value = EXCLUDED.max_value,
+                level = EXCLUDED.level,
+                experience_to_next_level = EXCLUDED.experience_to_next_level
+            "#,
+        )
+        .bind(character_id)
+        .bind(CHARACTER_LEVEL_PROGRESSION)
+        .bind(experience)
+        .bind(experience_to_next)
+        .bind(level)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
     // Private helper methods
 
     fn validate_username(&self, username: &str) -> AccountResult<()> {
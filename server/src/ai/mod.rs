@@ -0,0 +1,168 @@
+//! Data-driven mob AI profiles
+//!
+//! How far a mob notices players, how far it can be pulled from home, where
+//! it patrols, when it flees and which abilities it uses are described in
+//! `content/ai_profiles.json` rather than in code. Mobs refer to a profile by
+//! name. The bundled copy is compiled into the server; `AI_PROFILES_PATH`
+//! points the server at a different file.
+
+use crate::abilities::AbilityId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+const BUNDLED_AI_PROFILES: &str = include_str!("../../../content/ai_profiles.json");
+/// Format version of `ai_profiles.json` this server understands
+const CONTENT_VERSION: u32 = 1;
+
+/// Behaviour parameters shared by every mob using the profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Distance at which a hostile player is noticed
+    pub aggro_range: f32,
+    /// Distance from home past which the mob gives up and returns
+    pub leash_range: f32,
+    /// Patrol route as (x, z) offsets from home; empty means the mob stays put
+    #[serde(default)]
+    pub patrol: Vec<(f32, f32)>,
+    /// Seconds spent idle before setting off on patrol
+    #[serde(default)]
+    pub idle_time: f32,
+    /// Health fraction at or below which the mob flees once per fight; 0 never flees
+    #[serde(default)]
+    pub flee_health_fraction: f32,
+    /// Seconds spent fleeing before turning to fight again
+    #[serde(default)]
+    pub flee_duration: f32,
+    /// Abilities in priority order, used whenever off cooldown and in range
+    #[serde(default)]
+    pub abilities: Vec<AbilityId>,
+}
+
+impl AiProfile {
+    fn validate(&self) -> Result<(), AiProfileError> {
+        let invalid = |reason: &str| AiProfileError::Invalid {
+            name: self.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        for (field, value) in [
+            ("aggro_range", self.aggro_range),
+            ("leash_range", self.leash_range),
+            ("idle_time", self.idle_time),
+            ("flee_duration", self.flee_duration),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(invalid(&format!("{} must be a non-negative number", field)));
+            }
+        }
+        if self.leash_range < self.aggro_range {
+            return Err(invalid("leash_range must be at least aggro_range"));
+        }
+        if !(0.0..1.0).contains(&self.flee_health_fraction) {
+            return Err(invalid("flee_health_fraction must be in [0, 1)"));
+        }
+        if self.flee_health_fraction > 0.0 && self.flee_duration == 0.0 {
+            return Err(invalid("fleeing needs a flee_duration"));
+        }
+        if self
+            .patrol
+            .iter()
+            .any(|(x, z)| !x.is_finite() || !z.is_finite())
+        {
+            return Err(invalid("patrol offsets must be finite"));
+        }
+        Ok(())
+    }
+}
+
+/// Errors raised while loading AI profiles
+#[derive(Debug, thiserror::Error)]
+pub enum AiProfileError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("malformed AI profile data: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("unsupported AI profiles file version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("AI profile '{0}' is defined more than once")]
+    DuplicateName(String),
+
+    #[error("AI profile '{name}' is invalid: {reason}")]
+    Invalid { name: String, reason: String },
+}
+
+/// On-disk layout of `ai_profiles.json`
+#[derive(Debug, Deserialize)]
+struct AiProfileFile {
+    version: u32,
+    profiles: Vec<AiProfile>,
+}
+
+/// All known AI profiles, keyed by name
+#[derive(Debug, Clone, Default)]
+pub struct AiProfileRegistry {
+    profiles: HashMap<String, AiProfile>,
+}
+
+impl AiProfileRegistry {
+    /// The profiles shipped with the server
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_AI_PROFILES).expect("bundled ai_profiles.json is valid")
+    }
+
+    /// Load profiles from a content file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AiProfileError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|source| AiProfileError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// `AI_PROFILES_PATH` if set, otherwise the bundled profiles
+    pub fn from_env() -> Result<Self, AiProfileError> {
+        match std::env::var("AI_PROFILES_PATH") {
+            Ok(path) if !path.is_empty() => Self::load(path),
+            _ => Ok(Self::bundled()),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, AiProfileError> {
+        let file: AiProfileFile = serde_json::from_str(json)?;
+        if file.version != CONTENT_VERSION {
+            return Err(AiProfileError::UnsupportedVersion(file.version));
+        }
+
+        let mut profiles = HashMap::with_capacity(file.profiles.len());
+        for profile in file.profiles {
+            profile.validate()?;
+            if profiles.contains_key(&profile.name) {
+                return Err(AiProfileError::DuplicateName(profile.name));
+            }
+            profiles.insert(profile.name.clone(), profile);
+        }
+        Ok(Self { profiles })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AiProfile> {
+        self.profiles.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.profiles.len()
+    }
+}
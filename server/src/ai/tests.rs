@@ -0,0 +1,54 @@
+use crate::ai::{AiProfileError, AiProfileRegistry};
+
+#[test]
+fn test_bundled_ai_profiles_load() {
+    let registry = AiProfileRegistry::bundled();
+    for name in ["brute", "skirmisher", "prowler"] {
+        assert!(registry.get(name).is_some(), "profile {} missing", name);
+    }
+
+    let skirmisher = registry.get("skirmisher").unwrap();
+    assert!(!skirmisher.patrol.is_empty());
+    assert!(skirmisher.flee_health_fraction > 0.0);
+}
+
+#[test]
+fn test_invalid_ai_profiles_are_rejected() {
+    let duplicate = r#"{
+        "version": 1,
+        "profiles": [
+            { "name": "a", "aggro_range": 5, "leash_range": 10 },
+            { "name": "a", "aggro_range": 6, "leash_range": 10 }
+        ]
+    }"#;
+    assert!(matches!(
+        AiProfileRegistry::from_json(duplicate),
+        Err(AiProfileError::DuplicateName(name)) if name == "a"
+    ));
+
+    let short_leash = r#"{
+        "version": 1,
+        "profiles": [{ "name": "b", "aggro_range": 10, "leash_range": 5 }]
+    }"#;
+    assert!(matches!(
+        AiProfileRegistry::from_json(short_leash),
+        Err(AiProfileError::Invalid { name, .. }) if name == "b"
+    ));
+
+    let endless_flee = r#"{
+        "version": 1,
+        "profiles": [
+            { "name": "c", "aggro_range": 5, "leash_range": 10, "flee_health_fraction": 0.3 }
+        ]
+    }"#;
+    assert!(matches!(
+        AiProfileRegistry::from_json(endless_flee),
+        Err(AiProfileError::Invalid { name, .. }) if name == "c"
+    ));
+
+    let future_version = r#"{ "version": 99, "profiles": [] }"#;
+    assert!(matches!(
+        AiProfileRegistry::from_json(future_version),
+        Err(AiProfileError::UnsupportedVersion(99))
+    ));
+}
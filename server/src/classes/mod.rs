@@ -0,0 +1,202 @@
+//! Data-driven character classes
+//!
+//! Each class's starting stats, how they grow per level and the abilities it
+//! learns along the way are described in `content/classes.json` rather than
+//! in code. The bundled copy is compiled
+//! into the server; `CLASSES_PATH` points the server at a different file.
+
+use crate::abilities::AbilityId;
+use crate::entities::StatBlock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+const BUNDLED_CLASSES: &str = include_str!("../../../content/classes.json");
+/// Format version of `classes.json` this server understands
+const CONTENT_VERSION: u32 = 1;
+
+/// A set of class stats, either at level 1 or gained per level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClassStats {
+    pub health: f32,
+    pub attack_power: f32,
+    pub defense: f32,
+    pub agility: f32,
+    /// Bonus critical strike chance (0.0 - 1.0)
+    pub critical_chance: f32,
+    /// Unarmed attacks per second
+    pub attack_speed: f32,
+}
+
+/// An ability a class learns on reaching `level`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClassAbility {
+    pub id: AbilityId,
+    pub level: u32,
+}
+
+/// A single class as defined in content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Stats at level 1
+    pub base: ClassStats,
+    /// Stats gained with every level after the first
+    #[serde(default)]
+    pub per_level: ClassStats,
+    /// Abilities learned as the class levels
+    #[serde(default)]
+    pub abilities: Vec<ClassAbility>,
+}
+
+impl ClassDefinition {
+    fn validate(&self) -> Result<(), ClassError> {
+        let invalid = |reason: &str| ClassError::Invalid {
+            name: self.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        for stats in [&self.base, &self.per_level] {
+            for (field, value) in [
+                ("health", stats.health),
+                ("attack_power", stats.attack_power),
+                ("defense", stats.defense),
+                ("agility", stats.agility),
+                ("critical_chance", stats.critical_chance),
+                ("attack_speed", stats.attack_speed),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    return Err(invalid(&format!("{} must be a non-negative number", field)));
+                }
+            }
+        }
+        if self.base.health < 1.0 {
+            return Err(invalid("base health must be at least 1"));
+        }
+        if self.base.attack_speed <= 0.0 {
+            return Err(invalid("base attack_speed must be positive"));
+        }
+        if self.abilities.iter().any(|ability| ability.level == 0) {
+            return Err(invalid("abilities are learned from level 1"));
+        }
+        Ok(())
+    }
+
+    /// Stats of a character of this class at `level`, before gear and buffs
+    pub fn stats_at(&self, level: u32) -> StatBlock {
+        let levels = level.max(1) as f32 - 1.0;
+        let grown = |base: f32, per_level: f32| (base + per_level * levels).round() as u32;
+        StatBlock {
+            max_health: grown(self.base.health, self.per_level.health).max(1),
+            attack_power: grown(self.base.attack_power, self.per_level.attack_power),
+            defense: grown(self.base.defense, self.per_level.defense),
+            agility: grown(self.base.agility, self.per_level.agility),
+            critical_chance: self.base.critical_chance + self.per_level.critical_chance * levels,
+            haste: 0.0,
+            attack_speed: self.base.attack_speed + self.per_level.attack_speed * levels,
+            movement_speed: 1.0,
+        }
+    }
+
+    /// Abilities a character of this class knows at `level`
+    pub fn abilities_at(&self, level: u32) -> Vec<AbilityId> {
+        self.abilities
+            .iter()
+            .filter(|ability| ability.level <= level.max(1))
+            .map(|ability| ability.id)
+            .collect()
+    }
+}
+
+/// Errors raised while loading class content
+#[derive(Debug, thiserror::Error)]
+pub enum ClassError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("malformed class data: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("unsupported classes file version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("class '{0}' is defined more than once")]
+    DuplicateName(String),
+
+    #[error("class '{name}' is invalid: {reason}")]
+    Invalid { name: String, reason: String },
+}
+
+/// On-disk layout of `classes.json`
+#[derive(Debug, Deserialize)]
+struct ClassFile {
+    version: u32,
+    classes: Vec<ClassDefinition>,
+}
+
+/// All known classes, keyed by lowercase name
+#[derive(Debug, Clone, Default)]
+pub struct ClassRegistry {
+    classes: HashMap<String, ClassDefinition>,
+}
+
+impl ClassRegistry {
+    /// The classes shipped with the server
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_CLASSES).expect("bundled classes.json is valid")
+    }
+
+    /// Load classes from a content file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ClassError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|source| ClassError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// `CLASSES_PATH` if set, otherwise the bundled classes
+    pub fn from_env() -> Result<Self, ClassError> {
+        match std::env::var("CLASSES_PATH") {
+            Ok(path) if !path.is_empty() => Self::load(path),
+            _ => Ok(Self::bundled()),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ClassError> {
+        let file: ClassFile = serde_json::from_str(json)?;
+        if file.version != CONTENT_VERSION {
+            return Err(ClassError::UnsupportedVersion(file.version));
+        }
+
+        let mut classes = HashMap::with_capacity(file.classes.len());
+        for class in file.classes {
+            class.validate()?;
+            let key = class.name.to_lowercase();
+            if classes.contains_key(&key) {
+                return Err(ClassError::DuplicateName(class.name));
+            }
+            classes.insert(key, class);
+        }
+        Ok(Self { classes })
+    }
+
+    /// Look a class up by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&ClassDefinition> {
+        self.classes.get(&name.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+}
@@ -0,0 +1,84 @@
+use crate::classes::{ClassError, ClassRegistry};
+
+#[test]
+fn test_bundled_classes_load_and_grow_with_level() {
+    let registry = ClassRegistry::bundled();
+    for name in ["warrior", "mage", "rogue"] {
+        assert!(registry.get(name).is_some(), "class {} missing", name);
+    }
+    assert!(registry.get("Warrior").is_some(), "lookups ignore case");
+
+    let warrior = registry.get("warrior").unwrap();
+    let novice = warrior.stats_at(1);
+    let veteran = warrior.stats_at(10);
+    assert_eq!(novice.max_health, 120);
+    assert!(veteran.max_health > novice.max_health);
+    assert!(veteran.attack_power > novice.attack_power);
+    assert!(veteran.defense > novice.defense);
+    assert_eq!(warrior.stats_at(0), novice, "level 0 is treated as level 1");
+
+    assert_eq!(warrior.abilities_at(1), vec![1, 10]);
+    assert_eq!(warrior.abilities_at(0), vec![1, 10]);
+    assert_eq!(warrior.abilities_at(5), vec![1, 10, 4, 9]);
+    let mage = registry.get("mage").unwrap();
+    assert!(
+        !mage.abilities_at(60).contains(&1),
+        "mages never learn Strike"
+    );
+}
+
+#[test]
+fn test_invalid_classes_are_rejected() {
+    let duplicate = r#"{
+        "version": 1,
+        "classes": [
+            { "name": "a", "base": { "health": 10, "attack_speed": 1 } },
+            { "name": "A", "base": { "health": 20, "attack_speed": 1 } }
+        ]
+    }"#;
+    assert!(matches!(
+        ClassRegistry::from_json(duplicate),
+        Err(ClassError::DuplicateName(name)) if name == "A"
+    ));
+
+    let frozen = r#"{
+        "version": 1,
+        "classes": [{ "name": "b", "base": { "health": 10 } }]
+    }"#;
+    assert!(matches!(
+        ClassRegistry::from_json(frozen),
+        Err(ClassError::Invalid { name, .. }) if name == "b"
+    ));
+
+    let shrinking = r#"{
+        "version": 1,
+        "classes": [{
+            "name": "c",
+            "base": { "health": 10, "attack_speed": 1 },
+            "per_level": { "health": -5 }
+        }]
+    }"#;
+    assert!(matches!(
+        ClassRegistry::from_json(shrinking),
+        Err(ClassError::Invalid { name, .. }) if name == "c"
+    ));
+
+    let unlearnable = r#"{
+        "version": 1,
+        "classes": [{
+            "name": "d",
+            "base": { "health": 10, "attack_speed": 1 },
+            "abilities": [{ "id": 1, "level": 0 }]
+        }]
+    }"#;
+    assert!(matches!(
+        ClassRegistry::from_json(unlearnable),
+        Err(ClassError::Invalid { name, .. }) if name == "d"
+    ));
+
+    let future_version = r#"{ "version": 99, "classes": [] }"#;
+    assert!(matches!(
+        ClassRegistry::from_json(future_version),
+        Err(ClassError::UnsupportedVersion(99))
+    ));
+}
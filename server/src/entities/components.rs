@@ -35,6 +35,68 @@ pub struct Health {
     pub current: u32,
     pub maximum: u32,
     pub regeneration_rate: f32, // HP per second
+    /// Regeneration owed but not yet a whole point, carried between ticks
+    #[serde(default)]
+    pub regen_accumulator: f32,
+}
+
+/// Class resource kinds; each fills and drains by its own rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceType {
+    Mana,
+    Rage,
+    Energy,
+}
+
+impl ResourceType {
+    /// Parse the name stored in `characters.resource_type`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "mana" => Some(Self::Mana),
+            "rage" => Some(Self::Rage),
+            "energy" => Some(Self::Energy),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mana => "mana",
+            Self::Rage => "rage",
+            Self::Energy => "energy",
+        }
+    }
+}
+
+/// Class resource component spent by abilities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resource {
+    pub resource_type: ResourceType,
+    pub current: f32,
+    pub maximum: u32,
+    pub last_combat_time: f64, // Timestamp the entity last dealt or took damage
+}
+
+impl Resource {
+    pub fn new(resource_type: ResourceType, current: u32, maximum: u32) -> Self {
+        Self {
+            resource_type,
+            current: current.min(maximum) as f32,
+            maximum,
+            last_combat_time: f64::NEG_INFINITY, // Never in combat
+        }
+    }
+
+    /// Whole points available to spend
+    pub fn value(&self) -> u32 {
+        self.current.max(0.0) as u32
+    }
+
+    /// Add (or remove, if negative) points, clamped to the valid range
+    pub fn adjust(&mut self, amount: f32) {
+        self.current = (self.current + amount).clamp(0.0, self.maximum as f32);
+    }
 }
 
 /// Combat component for attack/defense stats
@@ -45,6 +107,9 @@ pub struct Combat {
     pub attack_range: f32,
     pub attack_speed: f32,     // Attacks per second
     pub last_attack_time: f64, // Timestamp of last attack
+    pub level: u32,            // Level used for hit and critical strike chances
+    pub agility: u32,          // Raises dodge and critical strike chance
+    pub critical_chance: f32,  // Bonus critical strike chance (0.0 - 1.0)
 }
 
 /// Ability component for entity abilities
@@ -54,6 +119,88 @@ pub struct Abilities {
     pub cooldowns: HashMap<u32, f64>, // Ability ID -> cooldown end time
 }
 
+/// Fractional stat changes, e.g. 0.1 is +10% and -0.3 is -30%.
+/// Critical strike chance and haste are added as is, so 0.05 is 5 more points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatModifiers {
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub movement_speed: f32,
+    pub regeneration: f32,
+    pub attack_power: f32,
+    pub defense: f32,
+    pub max_health: f32,
+    pub critical_chance: f32,
+    pub haste: f32,
+}
+
+impl StatModifiers {
+    /// Turn a fractional change into a multiplier that never goes negative
+    pub fn multiplier(change: f32) -> f32 {
+        (1.0 + change).max(0.0)
+    }
+
+    fn add_scaled(&mut self, other: &StatModifiers, times: f32) {
+        self.damage_dealt += other.damage_dealt * times;
+        self.damage_taken += other.damage_taken * times;
+        self.movement_speed += other.movement_speed * times;
+        self.regeneration += other.regeneration * times;
+        self.attack_power += other.attack_power * times;
+        self.defense += other.defense * times;
+        self.max_health += other.max_health * times;
+        self.critical_chance += other.critical_chance * times;
+        self.haste += other.haste * times;
+    }
+}
+
+/// A status effect currently on an entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveEffect {
+    pub effect_id: u32,
+    pub source_id: EntityId,
+    pub stacks: u32,
+    pub expires_at: f64,          // World time the effect wears off
+    pub next_tick_at: f64,        // World time of the next periodic tick
+    pub modifiers: StatModifiers, // Per stack
+    pub stun: bool,               // Prevents acting and breaks casts
+}
+
+/// Status effect component: buffs, debuffs and periodic effects
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusEffects {
+    pub active: Vec<ActiveEffect>,
+}
+
+impl StatusEffects {
+    /// Combined modifiers of every active effect and stack
+    pub fn modifiers(&self) -> StatModifiers {
+        let mut total = StatModifiers::default();
+        for effect in &self.active {
+            total.add_scaled(&effect.modifiers, effect.stacks as f32);
+        }
+        total
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.active.iter().any(|effect| effect.stun)
+    }
+}
+
+/// A cast or channel in progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Casting {
+    pub ability_id: u32,
+    pub target_id: EntityId,
+    pub target_position: Option<(f32, f32, f32)>, // Ground point of an area ability
+    pub started_at: f64,
+    pub finishes_at: f64,
+    pub channel: bool,
+    pub tick_interval: f64,    // Seconds between channel ticks; 0 for casts
+    pub next_tick_at: f64,     // World time of the next channel tick
+    pub next_progress_at: f64, // World time of the next progress event
+}
+
 /// AI component for NPC/mob behavior
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AiState {
@@ -79,10 +226,54 @@ pub enum AiState {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ai {
     pub state: AiState,
+    pub profile: String, // Name of the AI profile in content
     pub aggro_range: f32,
     pub leash_range: f32,
     pub home_position: (f32, f32, f32),
     pub last_state_change: f64,
+    pub has_fled: bool, // Fled already this fight
+}
+
+/// Threat each attacker has built against a mob; the mob attacks the top of the table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Threat {
+    pub table: HashMap<EntityId, f32>, // Attacker ID -> threat
+    pub target_id: Option<EntityId>,   // Entity currently being attacked
+    pub tagged_by: Option<EntityId>,   // First attacker to damage the mob; owns its loot
+    #[serde(default)]
+    pub last_attacker: Option<EntityId>, // Latest attacker to damage the mob; the killer once it dies
+}
+
+impl Threat {
+    pub fn add(&mut self, source_id: EntityId, amount: f32) {
+        *self.table.entry(source_id).or_insert(0.0) += amount;
+    }
+
+    pub fn get(&self, source_id: EntityId) -> f32 {
+        self.table.get(&source_id).copied().unwrap_or(0.0)
+    }
+
+    /// Highest threat on the table, ties broken by the lower entity ID
+    pub fn top(&self) -> Option<(EntityId, f32)> {
+        self.table
+            .iter()
+            .map(|(id, threat)| (*id, *threat))
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+        self.target_id = None;
+        self.tagged_by = None;
+        self.last_attacker = None;
+    }
+}
+
+/// Where and how soon a mob returns after its corpse despawns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Respawn {
+    pub home_position: (f32, f32, f32),
+    pub respawn_delay: f64, // Seconds between despawn and respawn
 }
 
 /// Faction component for social relationships
@@ -100,29 +291,26 @@ pub struct Social {
     pub reputation: HashMap<Faction, i32>, // Faction -> reputation value
 }
 
-/// Inventory component for items
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Inventory {
-    pub items: HashMap<u32, u32>, // Item ID -> quantity
-    pub max_slots: u32,
-}
-
-/// Equipment component for equipped items
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
-pub enum EquipmentSlot {
-    Head,
-    Chest,
-    Legs,
-    Feet,
-    MainHand,
-    OffHand,
-    Accessory1,
-    Accessory2,
+/// Final combat, health and movement stats produced by the stat pipeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatBlock {
+    pub max_health: u32,
+    pub attack_power: u32,
+    pub defense: u32,
+    pub agility: u32,
+    pub critical_chance: f32, // Bonus on top of the attack table's base chance
+    pub haste: f32,           // Fractional attack speed bonus
+    pub attack_speed: f32,    // Attacks per second, haste included
+    pub movement_speed: f32,  // Multiplier on the entity's base maximum speed
 }
 
+/// Stat component: the inputs of the stat pipeline and its last result
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Equipment {
-    pub equipped_items: HashMap<EquipmentSlot, u32>, // Slot -> Item ID
+pub struct Stats {
+    pub class: Option<String>, // Classed entities take base stats from class and level
+    pub base: StatBlock,       // Base stats of classless entities such as mobs
+    pub base_max_speed: f32,   // Maximum movement speed before modifiers
+    pub derived: Option<StatBlock>, // Last result written to Health, Combat and Movement
 }
 
 /// Level and progression component
@@ -160,4 +348,15 @@ pub struct NetworkSync {
     pub last_sync_time: f64,
     pub sync_interval: f64,        // How often to sync this entity
     pub visible_to: Vec<EntityId>, // Which players can see this entity
+    pub viewer_intervals: HashMap<EntityId, f64>, // Player ID -> seconds between syncs to them
+}
+
+impl NetworkSync {
+    /// How often `viewer_id` needs this entity refreshed
+    pub fn interval_for(&self, viewer_id: EntityId) -> f64 {
+        self.viewer_intervals
+            .get(&viewer_id)
+            .copied()
+            .unwrap_or(self.sync_interval)
+    }
 }
@@ -4,6 +4,8 @@
 //! and the Entity struct that composes components.
 
 use crate::entities::components::*;
+use crate::equipment::Equipment;
+use crate::inventory::Inventory;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -30,8 +32,15 @@ pub struct Entity {
     // Optional components
     pub movement: Option<Movement>,
     pub combat: Option<Combat>,
+    pub resource: Option<Resource>,
     pub abilities: Option<Abilities>,
+    pub status_effects: Option<StatusEffects>,
+    pub casting: Option<Casting>,
     pub ai: Option<Ai>,
+    pub threat: Option<Threat>,
+    pub respawn: Option<Respawn>,
+    pub loot_table: Option<u32>, // Loot table rolled when the entity dies
+    pub stats: Option<Stats>,
     pub social: Option<Social>,
     pub inventory: Option<Inventory>,
     pub equipment: Option<Equipment>,
@@ -58,6 +67,7 @@ impl Entity {
                 current: 100,
                 maximum: 100,
                 regeneration_rate: 1.0,
+                regen_accumulator: 0.0,
             }),
             movement: Some(Movement {
                 velocity_x: 0.0,
@@ -72,24 +82,29 @@ impl Entity {
                 defense: 5,
                 attack_range: 2.0,
                 attack_speed: 1.0,
-                last_attack_time: 0.0,
+                last_attack_time: f64::NEG_INFINITY, // Never attacked
+                level: 1,
+                agility: 10,
+                critical_chance: 0.0,
             }),
+            resource: None, // Set from the character's class on spawn
             abilities: Some(Abilities {
-                ability_ids: vec![1, 2, 3], // Basic abilities
+                ability_ids: Vec::new(), // Learned from the character's class on spawn
                 cooldowns: HashMap::new(),
             }),
+            status_effects: Some(StatusEffects::default()),
+            casting: None,
             ai: None, // Players don't have AI
+            threat: None,
+            respawn: None, // Players release instead
+            loot_table: None,
+            stats: None,
             social: Some(Social {
                 faction: Faction::Player,
                 reputation: HashMap::new(),
             }),
-            inventory: Some(Inventory {
-                items: HashMap::new(),
-                max_slots: 20,
-            }),
-            equipment: Some(Equipment {
-                equipped_items: HashMap::new(),
-            }),
+            inventory: Some(Inventory::new(id, 20)),
+            equipment: Some(Equipment::new(id)),
             progression: Some(Progression {
                 level: 1,
                 experience: 0,
@@ -108,8 +123,10 @@ impl Entity {
                 last_sync_time: 0.0,
                 sync_interval: 0.1, // Sync 10 times per second
                 visible_to: Vec::new(),
+                viewer_intervals: HashMap::new(),
             }),
         }
+        .with_base_stats()
     }
 
     /// Create a new mob entity
@@ -131,6 +148,7 @@ impl Entity {
                 current: base_health,
                 maximum: base_health,
                 regeneration_rate: 0.5,
+                regen_accumulator: 0.0,
             }),
             movement: Some(Movement {
                 velocity_x: 0.0,
@@ -145,19 +163,34 @@ impl Entity {
                 defense: base_attack / 2,
                 attack_range: 1.5,
                 attack_speed: 0.8,
-                last_attack_time: 0.0,
+                last_attack_time: f64::NEG_INFINITY, // Never attacked
+                level,
+                agility: level * 2,
+                critical_chance: 0.0,
             }),
+            resource: None, // Mob abilities are free
             abilities: Some(Abilities {
                 ability_ids: vec![100], // Basic mob attack
                 cooldowns: HashMap::new(),
             }),
+            status_effects: Some(StatusEffects::default()),
+            casting: None,
             ai: Some(Ai {
                 state: AiState::Idle,
+                profile: "brute".to_string(),
                 aggro_range: 8.0,
                 leash_range: 25.0,
                 home_position: (0.0, 0.0, 0.0),
                 last_state_change: 0.0,
+                has_fled: false,
+            }),
+            threat: Some(Threat::default()),
+            respawn: Some(Respawn {
+                home_position: (0.0, 0.0, 0.0),
+                respawn_delay: 30.0 + level as f64 * 15.0,
             }),
+            loot_table: None, // Set by whoever places the mob
+            stats: None,
             social: Some(Social {
                 faction: Faction::Hostile,
                 reputation: HashMap::new(),
@@ -175,8 +208,35 @@ impl Entity {
                 last_sync_time: 0.0,
                 sync_interval: 0.2, // Sync 5 times per second for mobs
                 visible_to: Vec::new(),
+                viewer_intervals: HashMap::new(),
             }),
         }
+        .with_base_stats()
+    }
+
+    /// Use the entity's starting health, combat and movement numbers as the
+    /// base of its stat pipeline
+    fn with_base_stats(mut self) -> Self {
+        if let (Some(health), Some(combat), Some(movement)) =
+            (&self.health, &self.combat, &self.movement)
+        {
+            self.stats = Some(Stats {
+                class: None,
+                base: StatBlock {
+                    max_health: health.maximum,
+                    attack_power: combat.attack_power,
+                    defense: combat.defense,
+                    agility: combat.agility,
+                    critical_chance: combat.critical_chance,
+                    haste: 0.0,
+                    attack_speed: combat.attack_speed,
+                    movement_speed: 1.0,
+                },
+                base_max_speed: movement.max_speed,
+                derived: None,
+            });
+        }
+        self
     }
 
     /// Create a new NPC entity
@@ -195,19 +255,24 @@ impl Entity {
                 current: 1,
                 maximum: 1,
                 regeneration_rate: 0.0, // NPCs don't regenerate
+                regen_accumulator: 0.0,
             }),
-            movement: None,  // NPCs don't move
-            combat: None,    // NPCs don't fight
-            abilities: None, // NPCs don't have abilities
-            ai: None,        // NPCs don't have AI
+            movement: None,       // NPCs don't move
+            combat: None,         // NPCs don't fight
+            resource: None,       // NPCs don't cast
+            abilities: None,      // NPCs don't have abilities
+            status_effects: None, // NPCs can't be buffed or debuffed
+            casting: None,
+            ai: None, // NPCs don't have AI
+            threat: None,
+            respawn: None, // NPCs don't die
+            loot_table: None,
+            stats: None,
             social: Some(Social {
                 faction: Faction::Friendly,
                 reputation: HashMap::new(),
             }),
-            inventory: Some(Inventory {
-                items: HashMap::new(), // Can be populated with vendor items
-                max_slots: 50,
-            }),
+            inventory: Some(Inventory::new(id, 50)), // Can be populated with vendor items
             equipment: None,
             progression: None,
             quest_state: None,
@@ -220,6 +285,7 @@ impl Entity {
                 last_sync_time: 0.0,
                 sync_interval: 1.0, // Sync once per second for static NPCs
                 visible_to: Vec::new(),
+                viewer_intervals: HashMap::new(),
             }),
         }
     }
@@ -239,8 +305,15 @@ impl Entity {
             health: None,   // World objects may or may not have health
             movement: None, // World objects don't move
             combat: None,   // World objects don't fight
+            resource: None,
             abilities: None,
+            status_effects: None,
+            casting: None,
             ai: None,
+            threat: None,
+            respawn: None,
+            loot_table: None,
+            stats: None,
             social: Some(Social {
                 faction: Faction::Neutral,
                 reputation: HashMap::new(),
@@ -258,6 +331,7 @@ impl Entity {
                 last_sync_time: 0.0,
                 sync_interval: 2.0, // Sync every 2 seconds for static objects
                 visible_to: Vec::new(),
+                viewer_intervals: HashMap::new(),
             }),
         }
     }
@@ -267,6 +341,22 @@ impl Entity {
         self.health.as_ref().map_or(true, |h| h.current > 0)
     }
 
+    /// Combined stat modifiers from active status effects
+    pub fn stat_modifiers(&self) -> StatModifiers {
+        self.status_effects
+            .as_ref()
+            .map(StatusEffects::modifiers)
+            .unwrap_or_default()
+    }
+
+    /// Movement speed multiplier from gear and status effects, as last derived
+    pub fn movement_speed_multiplier(&self) -> f32 {
+        self.stats
+            .as_ref()
+            .and_then(|stats| stats.derived)
+            .map_or(1.0, |derived| derived.movement_speed)
+    }
+
     /// Check if entity can move
     pub fn can_move(&self) -> bool {
         self.movement.is_some() && self.is_alive()
@@ -277,6 +367,13 @@ impl Entity {
         self.combat.is_some() && self.is_alive()
     }
 
+    /// Whether a status effect currently stuns the entity
+    pub fn is_stunned(&self) -> bool {
+        self.status_effects
+            .as_ref()
+            .is_some_and(StatusEffects::is_stunned)
+    }
+
     /// Get distance to another entity
     pub fn distance_to(&self, other: &Entity) -> f32 {
         if let (Some(pos1), Some(pos2)) = (&self.position, &other.position) {
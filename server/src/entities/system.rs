@@ -5,6 +5,7 @@
 
 use crate::entities::components::*;
 use crate::entities::{Entity, EntityId, EntityType};
+use crate::items::ItemInstance;
 use std::collections::HashMap;
 
 /// Manages all entities in the game world
@@ -15,9 +16,14 @@ pub struct EntityManager {
 
 impl EntityManager {
     pub fn new() -> Self {
+        Self::with_first_id(1)
+    }
+
+    /// Create a manager whose generated IDs start at `first_id`
+    pub fn with_first_id(first_id: EntityId) -> Self {
         Self {
             entities: HashMap::new(),
-            next_id: 1,
+            next_id: first_id,
         }
     }
 
@@ -53,6 +59,11 @@ impl EntityManager {
         self.entities.values().collect()
     }
 
+    /// Get mutable references to all entities
+    pub fn entities_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.entities.values_mut()
+    }
+
     /// Get entities by type
     pub fn get_entities_by_type(&self, entity_type: EntityType) -> Vec<&Entity> {
         self.entities
@@ -101,22 +112,25 @@ impl EntityManager {
             }
         }
 
-        // Update AI separately to avoid borrow issues
-        // TODO: Re-enable AI updates after fixing compilation
-        // for entity_id in entity_ids {
-        //     if let Some(entity) = self.entities.get_mut(&entity_id) {
-        //         self.update_ai(entity, delta_time);
-        //     }
-        // }
+        // Mob AI runs in `simulation::AiSystem`, which needs the clock and combat
     }
 
     /// Update basic entity properties (health, movement)
     fn update_entity_basic(entity: &mut Entity, delta_time: f64) {
         // Update health regeneration
+        let modifiers = entity.stat_modifiers();
         if let Some(health) = &mut entity.health {
-            if health.current < health.maximum {
-                let regen_amount = (health.regeneration_rate * delta_time as f32) as u32;
+            // The dead stay dead until they respawn or release
+            if health.current > 0 && health.current < health.maximum {
+                let regeneration_rate =
+                    health.regeneration_rate * StatModifiers::multiplier(modifiers.regeneration);
+                // A tick is worth well under a point, so keep the fraction for the next one
+                health.regen_accumulator += regeneration_rate * delta_time as f32;
+                let regen_amount = health.regen_accumulator as u32;
+                health.regen_accumulator -= regen_amount as f32;
                 health.current = (health.current + regen_amount).min(health.maximum);
+            } else {
+                health.regen_accumulator = 0.0;
             }
         }
 
@@ -133,11 +147,6 @@ impl EntityManager {
         }
     }
 
-    // /// Update AI behavior for an entity
-    // fn update_ai(&self, entity: &mut Entity, delta_time: f64) {
-    //     // TODO: Implement AI updates
-    // }
-
     /// Create a test player entity
     pub fn create_test_player(&mut self, name: String) -> EntityId {
         let id = self.generate_id();
@@ -152,8 +161,16 @@ impl EntityManager {
         id
     }
 
-    /// Create a test mob entity
-    pub fn create_test_mob(&mut self, name: String, x: f32, z: f32, level: u32) -> EntityId {
+    /// Create a test mob entity driven by the named AI profile
+    pub fn create_test_mob(
+        &mut self,
+        name: String,
+        x: f32,
+        z: f32,
+        level: u32,
+        ai_profile: &str,
+        loot_table: Option<u32>,
+    ) -> EntityId {
         let id = self.generate_id();
         let mut mob = Entity::new_mob(id, name, level);
         mob.position = Some(Position {
@@ -164,7 +181,12 @@ impl EntityManager {
         });
         if let Some(ai) = &mut mob.ai {
             ai.home_position = (x, 0.0, z);
+            ai.profile = ai_profile.to_string();
+        }
+        if let Some(respawn) = &mut mob.respawn {
+            respawn.home_position = (x, 0.0, z);
         }
+        mob.loot_table = loot_table;
         self.add_entity(mob);
         id
     }
@@ -181,8 +203,8 @@ impl EntityManager {
         });
         // Add some items to inventory for selling
         if let Some(inventory) = &mut npc.inventory {
-            inventory.items.insert(1, 10); // Item ID 1, quantity 10
-            inventory.items.insert(2, 5); // Item ID 2, quantity 5
+            inventory.slots.insert(0, ItemInstance::new(1, 10)); // Item ID 1, quantity 10
+            inventory.slots.insert(1, ItemInstance::new(2, 5)); // Item ID 2, quantity 5
         }
         self.add_entity(npc);
         id
@@ -14,6 +14,7 @@ pub struct Inventory {
     pub slots: HashMap<SlotId, ItemInstance>,
     pub max_slots: u32,
     pub owner_id: EntityId,
+    pub gold: u64,
 }
 
 impl Inventory {
@@ -22,6 +23,7 @@ impl Inventory {
             slots: HashMap::new(),
             max_slots,
             owner_id,
+            gold: 0,
         }
     }
 
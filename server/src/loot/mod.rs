@@ -1,11 +1,14 @@
 //! Loot system for managing item drops and rewards
 
 use crate::entities::EntityId;
-use crate::items::{ItemId, ItemInstance};
+use crate::items::{ItemId, ItemInstance, ItemRarity};
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+#[cfg(test)]
+mod tests;
+
 /// Loot table entry defining an item drop
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LootEntry {
@@ -92,7 +95,6 @@ impl LootCondition {
 /// Context for loot generation
 #[derive(Debug, Clone)]
 pub struct LootContext {
-    pub player_id: EntityId,
     pub player_level: u32,
     pub player_class: String,
     pub active_quests: Vec<u32>,
@@ -102,9 +104,8 @@ pub struct LootContext {
 }
 
 impl LootContext {
-    pub fn new(player_id: EntityId, player_level: u32, player_class: String) -> Self {
+    pub fn new(player_level: u32, player_class: String) -> Self {
         Self {
-            player_id,
             player_level,
             player_class,
             active_quests: Vec::new(),
@@ -171,8 +172,7 @@ impl LootTable {
     }
 
     /// Generate loot from this table
-    pub fn generate_loot(&self, context: &LootContext) -> Vec<LootDrop> {
-        let mut rng = rand::thread_rng();
+    pub fn generate_loot(&self, rng: &mut impl Rng, context: &LootContext) -> Vec<LootDrop> {
         let mut drops = Vec::new();
 
         // Add guaranteed drops
@@ -182,8 +182,8 @@ impl LootTable {
 
         // Process loot entries
         for entry in &self.entries {
-            if entry.should_drop(&mut rng, context) {
-                let quantity = entry.generate_quantity(&mut rng);
+            if entry.should_drop(rng, context) {
+                let quantity = entry.generate_quantity(rng);
                 drops.push(LootDrop::Item(ItemInstance::new(entry.item_id, quantity)));
             }
         }
@@ -212,6 +212,241 @@ pub enum LootDrop {
     Experience(u32),
 }
 
+/// How a party shares out the loot of the mobs it tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LootMethod {
+    /// Any member may take anything
+    FreeForAll,
+    /// Members take turns to own a whole corpse
+    RoundRobin,
+    /// Items at or above the threshold are rolled for; the rest go round robin
+    NeedBeforeGreed,
+}
+
+/// A party's loot settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LootRules {
+    pub method: LootMethod,
+    pub threshold: ItemRarity, // Lowest rarity rolled for under need before greed
+}
+
+impl Default for LootRules {
+    fn default() -> Self {
+        Self {
+            method: LootMethod::NeedBeforeGreed,
+            threshold: ItemRarity::Uncommon,
+        }
+    }
+}
+
+impl LootRules {
+    /// Whether an item of `rarity` is rolled for rather than handed out
+    pub fn is_rolled(&self, rarity: ItemRarity) -> bool {
+        self.method == LootMethod::NeedBeforeGreed && rarity as u8 >= self.threshold as u8
+    }
+}
+
+/// A player's answer to a loot roll
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollChoice {
+    Need,
+    Greed,
+    Pass,
+}
+
+/// Who won a loot roll, with what and how high
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RollResult {
+    pub winner_id: EntityId,
+    pub choice: RollChoice,
+    pub roll: u32, // 1 to 100
+}
+
+/// A need/greed/pass roll for one item
+#[derive(Debug, Clone)]
+pub struct LootRoll {
+    pub eligible: Vec<EntityId>,
+    pub choices: HashMap<EntityId, RollChoice>,
+    pub ends_at: f64, // World time players who have not chosen are taken to pass
+}
+
+impl LootRoll {
+    pub fn new(eligible: Vec<EntityId>, ends_at: f64) -> Self {
+        Self {
+            eligible,
+            choices: HashMap::new(),
+            ends_at,
+        }
+    }
+
+    /// Record a player's choice; each eligible player chooses once
+    pub fn choose(&mut self, player_id: EntityId, choice: RollChoice) -> Result<(), String> {
+        if !self.eligible.contains(&player_id) {
+            return Err("You are not rolling for that item".to_string());
+        }
+        if self.choices.contains_key(&player_id) {
+            return Err("You have already rolled for that item".to_string());
+        }
+        self.choices.insert(player_id, choice);
+        Ok(())
+    }
+
+    /// Whether every eligible player has chosen or time is up
+    pub fn is_finished(&self, now: f64) -> bool {
+        now >= self.ends_at || self.eligible.iter().all(|id| self.choices.contains_key(id))
+    }
+
+    /// Roll for everyone who chose need, or for the greedy if nobody did.
+    /// Ties go to the first roller in ID order; `None` when everyone passed.
+    pub fn resolve(&self, rng: &mut impl Rng) -> Option<RollResult> {
+        let mut rollers: Vec<EntityId> = self.choices.keys().copied().collect();
+        rollers.sort_unstable();
+        [RollChoice::Need, RollChoice::Greed]
+            .into_iter()
+            .find_map(|choice| {
+                let mut best: Option<RollResult> = None;
+                for &winner_id in &rollers {
+                    if self.choices[&winner_id] != choice {
+                        continue;
+                    }
+                    let roll = rng.gen_range(1..=100);
+                    if best.map_or(true, |best| roll > best.roll) {
+                        best = Some(RollResult {
+                            winner_id,
+                            choice,
+                            roll,
+                        });
+                    }
+                }
+                best
+            })
+    }
+}
+
+/// An item on a corpse and who may take it
+#[derive(Debug, Clone)]
+pub struct CorpseItem {
+    pub item: ItemInstance,
+    /// Only this looter may take the item until the corpse's loot goes free
+    pub owner: Option<EntityId>,
+    /// Roll still deciding the owner; nobody may take the item meanwhile
+    pub roll: Option<LootRoll>,
+}
+
+/// Loot waiting on a corpse until it is taken or the corpse despawns
+#[derive(Debug, Clone, Default)]
+pub struct CorpseLoot {
+    /// Items by loot slot; taking one leaves its slot empty so the rest keep their numbers
+    pub items: Vec<Option<CorpseItem>>,
+    pub gold: u32,
+    /// Players allowed to loot the corpse: its tagger, or the tagger's party
+    pub looters: Vec<EntityId>,
+    /// World time from which anyone may loot what is left
+    pub free_at: f64,
+}
+
+impl CorpseLoot {
+    /// Lay out rolled drops on a corpse, free for any of its looters to take.
+    /// Experience is paid for the kill itself, so experience drops are not
+    /// left on the corpse.
+    pub fn new(drops: Vec<LootDrop>, looters: Vec<EntityId>, free_at: f64) -> Self {
+        let mut loot = Self {
+            looters,
+            free_at,
+            ..Self::default()
+        };
+        for drop in drops {
+            match drop {
+                LootDrop::Item(item) => loot.items.push(Some(CorpseItem {
+                    item,
+                    owner: None,
+                    roll: None,
+                })),
+                LootDrop::Gold(amount) => loot.gold += amount,
+                LootDrop::Experience(_) => {}
+            }
+        }
+        loot
+    }
+
+    /// Share the items out by a party's rules. Under round robin every item
+    /// goes to `turn`; under need before greed items at or above the threshold
+    /// are rolled for by all looters until `roll_ends_at` and the rest go to `turn`.
+    pub fn share_out(
+        &mut self,
+        rules: &LootRules,
+        turn: EntityId,
+        rarity_of: impl Fn(&ItemInstance) -> ItemRarity,
+        roll_ends_at: f64,
+    ) {
+        if rules.method == LootMethod::FreeForAll {
+            return;
+        }
+        for corpse_item in self.items.iter_mut().flatten() {
+            if rules.is_rolled(rarity_of(&corpse_item.item)) {
+                corpse_item.roll = Some(LootRoll::new(self.looters.clone(), roll_ends_at));
+            } else {
+                corpse_item.owner = Some(turn);
+            }
+        }
+    }
+
+    /// Whether unclaimed loot has been left long enough for anyone to take
+    pub fn is_free(&self, now: f64) -> bool {
+        now >= self.free_at
+    }
+
+    pub fn can_open(&self, player_id: EntityId, now: f64) -> bool {
+        self.is_free(now) || self.looters.contains(&player_id)
+    }
+
+    /// Why `player_id` may not take the item in `slot` right now, if they may not
+    pub fn check_take(&self, player_id: EntityId, slot: u32, now: f64) -> Result<(), String> {
+        let corpse_item = self
+            .items
+            .get(slot as usize)
+            .and_then(Option::as_ref)
+            .ok_or("That item is no longer there")?;
+        if corpse_item.roll.is_some() {
+            return Err("That item is still being rolled for".to_string());
+        }
+        if !self.is_free(now) && corpse_item.owner.is_some_and(|owner| owner != player_id) {
+            return Err("That item belongs to someone else".to_string());
+        }
+        Ok(())
+    }
+
+    /// Items `player_id` may take right now, with their loot slots
+    pub fn takeable_items(
+        &self,
+        player_id: EntityId,
+        now: f64,
+    ) -> impl Iterator<Item = (u32, &ItemInstance)> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, corpse_item)| Some((slot as u32, corpse_item.as_ref()?)))
+            .filter(move |(slot, _)| self.check_take(player_id, *slot, now).is_ok())
+            .map(|(slot, corpse_item)| (slot, &corpse_item.item))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gold == 0 && self.items.iter().all(Option::is_none)
+    }
+
+    pub fn take_gold(&mut self) -> u32 {
+        std::mem::take(&mut self.gold)
+    }
+
+    pub fn take_item(&mut self, slot: u32) -> Option<ItemInstance> {
+        self.items
+            .get_mut(slot as usize)?
+            .take()
+            .map(|corpse_item| corpse_item.item)
+    }
+}
+
 /// Loot system for managing loot tables and generation
 pub struct LootSystem {
     tables: HashMap<u32, LootTable>,
@@ -232,12 +467,6 @@ impl LootSystem {
         self.tables.get(&id)
     }
 
-    pub fn generate_loot(&self, table_id: u32, context: &LootContext) -> Option<Vec<LootDrop>> {
-        self.tables
-            .get(&table_id)
-            .map(|table| table.generate_loot(context))
-    }
-
     /// Load default loot tables
     pub fn load_defaults(&mut self) {
         // Goblin loot table
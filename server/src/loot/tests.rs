@@ -0,0 +1,144 @@
+use crate::items::{ItemInstance, ItemRarity};
+use crate::loot::{CorpseLoot, LootDrop, LootMethod, LootRoll, LootRules, RollChoice};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Item 1 is common and item 2 rare; everything else counts as uncommon
+fn rarity_of(item: &ItemInstance) -> ItemRarity {
+    match item.definition_id {
+        1 => ItemRarity::Common,
+        2 => ItemRarity::Rare,
+        _ => ItemRarity::Uncommon,
+    }
+}
+
+fn corpse(looters: Vec<u64>) -> CorpseLoot {
+    CorpseLoot::new(
+        vec![
+            LootDrop::Item(ItemInstance::new(1, 1)),
+            LootDrop::Item(ItemInstance::new(2, 1)),
+            LootDrop::Gold(12),
+            LootDrop::Experience(50),
+        ],
+        looters,
+        20.0,
+    )
+}
+
+#[test]
+fn test_rules_roll_only_at_or_above_the_threshold() {
+    let rules = LootRules::default();
+    assert!(!rules.is_rolled(ItemRarity::Common));
+    assert!(rules.is_rolled(ItemRarity::Uncommon));
+    assert!(rules.is_rolled(ItemRarity::Legendary));
+
+    let rare = LootRules {
+        threshold: ItemRarity::Rare,
+        ..rules
+    };
+    assert!(!rare.is_rolled(ItemRarity::Uncommon));
+    assert!(rare.is_rolled(ItemRarity::Epic));
+
+    let round_robin = LootRules {
+        method: LootMethod::RoundRobin,
+        ..rules
+    };
+    assert!(!round_robin.is_rolled(ItemRarity::Legendary));
+}
+
+#[test]
+fn test_need_beats_greed_and_everyone_passing_leaves_no_winner() {
+    let mut roll = LootRoll::new(vec![1, 2, 3], 10.0);
+    assert!(!roll.is_finished(0.0));
+    roll.choose(1, RollChoice::Greed).unwrap();
+    roll.choose(2, RollChoice::Need).unwrap();
+    assert_eq!(
+        roll.choose(2, RollChoice::Greed),
+        Err("You have already rolled for that item".to_string())
+    );
+    assert_eq!(
+        roll.choose(4, RollChoice::Need),
+        Err("You are not rolling for that item".to_string())
+    );
+    assert!(!roll.is_finished(5.0));
+    assert!(roll.is_finished(10.0), "time up counts the rest as passing");
+    roll.choose(3, RollChoice::Pass).unwrap();
+    assert!(roll.is_finished(5.0));
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..20 {
+        let result = roll.resolve(&mut rng).unwrap();
+        assert_eq!(result.winner_id, 2);
+        assert_eq!(result.choice, RollChoice::Need);
+        assert!((1..=100).contains(&result.roll));
+    }
+
+    let mut passed = LootRoll::new(vec![1, 2], 10.0);
+    passed.choose(1, RollChoice::Pass).unwrap();
+    assert_eq!(passed.resolve(&mut rng), None);
+}
+
+#[test]
+fn test_shared_out_items_wait_for_their_owner_until_the_loot_goes_free() {
+    let mut loot = corpse(vec![1, 2]);
+    assert_eq!(loot.items.len(), 2, "experience is not left on the corpse");
+    assert_eq!(loot.gold, 12);
+    assert!(loot.can_open(2, 0.0));
+    assert!(!loot.can_open(3, 0.0));
+    assert!(loot.can_open(3, 20.0));
+
+    // Need before greed: the common item goes to whoever's turn it is, the rare one is rolled for
+    loot.share_out(&LootRules::default(), 2, rarity_of, 15.0);
+    let common = loot.items[0].as_ref().unwrap();
+    assert_eq!(common.owner, Some(2));
+    assert!(common.roll.is_none());
+    let rare = loot.items[1].as_ref().unwrap();
+    assert_eq!(rare.roll.as_ref().unwrap().eligible, vec![1, 2]);
+
+    assert_eq!(loot.check_take(2, 0, 0.0), Ok(()));
+    assert_eq!(
+        loot.check_take(1, 0, 0.0),
+        Err("That item belongs to someone else".to_string())
+    );
+    assert_eq!(
+        loot.check_take(2, 1, 0.0),
+        Err("That item is still being rolled for".to_string())
+    );
+    let takeable: Vec<u32> = loot.takeable_items(1, 0.0).map(|(slot, _)| slot).collect();
+    assert!(takeable.is_empty());
+
+    // Unclaimed items are anyone's once the timer runs out
+    assert_eq!(loot.check_take(3, 0, 20.0), Ok(()));
+    assert_eq!(loot.take_item(0).map(|item| item.definition_id), Some(1));
+    assert_eq!(
+        loot.check_take(2, 0, 0.0),
+        Err("That item is no longer there".to_string())
+    );
+    loot.items[1] = None;
+    assert!(!loot.is_empty());
+    assert_eq!(loot.take_gold(), 12);
+    assert!(loot.is_empty());
+}
+
+#[test]
+fn test_round_robin_and_free_for_all_share_out() {
+    let mut round_robin = corpse(vec![1, 2]);
+    let rules = LootRules {
+        method: LootMethod::RoundRobin,
+        threshold: ItemRarity::Common,
+    };
+    round_robin.share_out(&rules, 1, rarity_of, 15.0);
+    assert!(round_robin
+        .items
+        .iter()
+        .flatten()
+        .all(|corpse_item| corpse_item.owner == Some(1) && corpse_item.roll.is_none()));
+
+    let mut free_for_all = corpse(vec![1, 2]);
+    let rules = LootRules {
+        method: LootMethod::FreeForAll,
+        ..rules
+    };
+    free_for_all.share_out(&rules, 1, rarity_of, 15.0);
+    assert_eq!(free_for_all.takeable_items(2, 0.0).count(), 2);
+}
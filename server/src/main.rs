@@ -1,4 +1,7 @@
+mod abilities;
 mod accounts;
+mod ai;
+mod classes;
 mod db;
 mod entities;
 mod equipment;
@@ -6,11 +9,14 @@ mod inventory;
 mod items;
 mod loot;
 mod network;
+mod party;
+mod progression;
 mod simulation;
 mod world;
 
-use crate::network::messages::Envelope;
-use crate::simulation::tick_loop::build_world_snapshot;
+use crate::network::codec::WireFormat;
+use crate::network::messages::{Disconnect, DisconnectReason, Envelope, Payload};
+use crate::network::outbound::OutboundError;
 use axum::{
     extract::{State, WebSocketUpgrade},
     http::StatusCode,
@@ -31,35 +37,96 @@ struct AppState {
     session_store: network::SessionStore,
     world_state: std::sync::Arc<tokio::sync::RwLock<world::WorldState>>,
     account_service: accounts::AccountService,
+    /// Feature bits this server is willing to negotiate in handshakes
+    wire_features: u32,
+    session_tokens: network::session_token::SessionTokenSigner,
+    /// How long a dropped player stays link-dead in the world awaiting resume
+    resume_grace: Duration,
+    rate_limits: network::rate_limit::RateLimitConfig,
+    outbound: network::outbound::OutboundConfig,
+    heartbeat: network::heartbeat::HeartbeatConfig,
 }
 
 async fn persist_active_positions(state: &AppState) {
-    let sessions = state.session_store.get_active_sessions().await;
-    let world = state.world_state.read().await;
+    // Link-dead characters stay in the world and keep changing until they are reaped
+    let sessions = state.session_store.get_sessions_in_world().await;
+    // Copy everything out first so the tick loop is not held up by the database
+    let saves: Vec<_> = {
+        let world = state.world_state.read().await;
+        sessions
+            .iter()
+            .filter_map(|session| match (session.player_id, session.character_id) {
+                (Some(player_id), Some(character_id)) => Some((
+                    session.id,
+                    character_id,
+                    world.get_player_pose(player_id),
+                    world.get_player_resource(player_id),
+                    world.get_player_progression(player_id),
+                )),
+                _ => None,
+            })
+            .collect()
+    };
 
-    for session in sessions {
-        if let (Some(player_id), Some(character_id)) = (session.player_id, session.character_id) {
-            if let Some((x, y, z, rot)) = world.get_player_pose(player_id) {
-                if let Err(e) = state
-                    .account_service
-                    .update_character_position(
-                        character_id,
-                        x as f64,
-                        y as f64,
-                        z as f64,
-                        rot as f64,
-                    )
-                    .await
-                {
-                    warn!("Periodic save failed for session {}: {:?}", session.id, e);
-                } else {
-                    info!(
-                        "Periodic save for character {} (session {}): ({:.2}, {:.2}, {:.2}) rot {:.2}",
-                        character_id, session.id, x, y, z, rot
-                    );
-                }
+    for (session_id, character_id, pose, resource, progression) in saves {
+        if let Some((x, y, z, rot)) = pose {
+            if let Err(e) = state
+                .account_service
+                .update_character_position(character_id, x as f64, y as f64, z as f64, rot as f64)
+                .await
+            {
+                warn!("Periodic save failed for session {}: {:?}", session_id, e);
+            } else {
+                info!(
+                    "Periodic save for character {} (session {}): ({:.2}, {:.2}, {:.2}) rot {:.2}",
+                    character_id, session_id, x, y, z, rot
+                );
             }
         }
+        if let Some(resource) = resource {
+            persist_resource(state, character_id, resource).await;
+        }
+        if let Some(progression) = progression {
+            persist_progression(state, character_id, &progression).await;
+        }
+    }
+}
+
+/// Save a player's class resource back to its character row
+async fn persist_resource(state: &AppState, character_id: Uuid, resource: u32) {
+    if let Err(e) = state
+        .account_service
+        .update_character_resource(character_id, resource.min(i32::MAX as u32) as i32)
+        .await
+    {
+        warn!(
+            "Failed to persist resource for character {}: {:?}",
+            character_id, e
+        );
+    }
+}
+
+/// Save a player's level and experience back to its character
+async fn persist_progression(
+    state: &AppState,
+    character_id: Uuid,
+    progression: &entities::Progression,
+) {
+    let clamp = |value: u64| value.min(i64::MAX as u64) as i64;
+    if let Err(e) = state
+        .account_service
+        .update_character_progression(
+            character_id,
+            progression.level.min(i32::MAX as u32) as i32,
+            clamp(progression.experience),
+            clamp(progression.experience_to_next),
+        )
+        .await
+    {
+        warn!(
+            "Failed to persist progression for character {}: {:?}",
+            character_id, e
+        );
     }
 }
 
@@ -170,8 +237,39 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Database connectivity verified");
 
-    // Create world state
-    let world_state = std::sync::Arc::new(tokio::sync::RwLock::new(world::WorldState::new()));
+    // Load content and create world state
+    let abilities = abilities::AbilityRegistry::from_env()
+        .map_err(|e| anyhow::anyhow!("Failed to load abilities: {}", e))?;
+    info!("Loaded {} abilities", abilities.len());
+    let ai_profiles = ai::AiProfileRegistry::from_env()
+        .map_err(|e| anyhow::anyhow!("Failed to load AI profiles: {}", e))?;
+    info!("Loaded {} AI profiles", ai_profiles.len());
+    let classes = classes::ClassRegistry::from_env()
+        .map_err(|e| anyhow::anyhow!("Failed to load classes: {}", e))?;
+    info!("Loaded {} classes", classes.len());
+    let experience = progression::ExperienceTable::from_env()
+        .map_err(|e| anyhow::anyhow!("Failed to load experience curve: {}", e))?;
+    info!(
+        "Loaded experience curve up to level {}",
+        experience.max_level()
+    );
+    let mut world = world::WorldState::new();
+    world.set_abilities(abilities);
+    world.set_ai_profiles(ai_profiles);
+    world.set_classes(classes);
+    world.set_experience(experience);
+    world.set_death_config(simulation::DeathConfig::from_env());
+    world.set_loot_config(simulation::LootConfig::from_env());
+    world.set_attack_table(simulation::AttackTableConfig::from_env());
+    // COMBAT_RNG_SEED fixes the attack table rolls so a session's fights can be replayed
+    if let Some(seed) = std::env::var("COMBAT_RNG_SEED")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+    {
+        info!("Combat rolls seeded with {}", seed);
+        world.seed_combat_rng(seed);
+    }
+    let world_state = std::sync::Arc::new(tokio::sync::RwLock::new(world));
     info!(
         "World state initialized with {} zones",
         world_state.read().await.zone_count()
@@ -180,25 +278,64 @@ async fn main() -> anyhow::Result<()> {
     // Create application state
     let session_store = network::SessionStore::new();
     let account_service = accounts::AccountService::new(db_pool.clone());
+    // WIRE_FORMAT=json disables binary frames, keeping every session on JSON for debugging
+    let wire_features = match std::env::var("WIRE_FORMAT") {
+        Ok(format) if format.eq_ignore_ascii_case("json") => {
+            info!("WIRE_FORMAT=json; binary Protobuf frames disabled");
+            0
+        }
+        _ => network::codec::SUPPORTED_FEATURES,
+    };
+
+    let session_tokens = match std::env::var("SESSION_TOKEN_SECRET") {
+        Ok(secret) if !secret.is_empty() => network::session_token::SessionTokenSigner::new(
+            secret.into_bytes(),
+            network::session_token::DEFAULT_TOKEN_TTL,
+        ),
+        _ => {
+            info!("SESSION_TOKEN_SECRET not set; resume tokens will not survive a restart");
+            network::session_token::SessionTokenSigner::with_random_secret(
+                network::session_token::DEFAULT_TOKEN_TTL,
+            )
+        }
+    };
+    let resume_grace = Duration::from_secs(
+        std::env::var("RESUME_GRACE_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .unwrap_or(60),
+    );
+
     let state = AppState {
         db_pool,
         session_store,
         world_state: world_state.clone(),
         account_service,
+        wire_features,
+        session_tokens,
+        resume_grace,
+        rate_limits: network::rate_limit::RateLimitConfig::from_env(),
+        outbound: network::outbound::OutboundConfig::from_env(),
+        heartbeat: network::heartbeat::HeartbeatConfig::from_env(),
     };
 
-    // Start simulation loop in background
+    // Start simulation loop in background; it stops once shutdown flips to true
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
     let simulation_world_state = world_state.clone();
     let simulation_session_store = state.session_store.clone();
-    tokio::spawn(async move {
-        let mut simulation_loop =
-            simulation::SimulationLoop::new(simulation_world_state, simulation_session_store);
+    let simulation_task = tokio::spawn(async move {
+        let mut simulation_loop = simulation::SimulationLoop::new(
+            simulation_world_state,
+            simulation_session_store,
+            simulation::interest::InterestConfig::from_env(),
+        )
+        .with_shutdown(shutdown_rx);
         simulation_loop.run().await;
     });
 
     // Periodically persist active player positions
     let state_for_persist = state.clone();
-    tokio::spawn(async move {
+    let persist_task = tokio::spawn(async move {
         let mut ticker = interval(Duration::from_secs(5));
         loop {
             ticker.tick().await;
@@ -206,12 +343,29 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    // Log out link-dead players whose resume window has passed
+    let state_for_reaper = state.clone();
+    let reaper_task = tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(1));
+        loop {
+            ticker.tick().await;
+            let expired = state_for_reaper
+                .session_store
+                .take_expired_link_dead(state_for_reaper.resume_grace)
+                .await;
+            for session in expired {
+                info!("Resume window expired for link-dead session {}", session.id);
+                logout_session(&state_for_reaper, &session).await;
+            }
+        }
+    });
+
     // Build our application with routes
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/health/db", get(database_health_check))
         .route("/ws", get(ws_handler))
-        .with_state(state);
+        .with_state(state.clone());
 
     // Run the server
     let server_host = std::env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
@@ -228,11 +382,142 @@ async fn main() -> anyhow::Result<()> {
     info!("OpenMMO server listening on {}", addr_str);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    let (stop_accepting_tx, stop_accepting_rx) = tokio::sync::oneshot::channel::<()>();
+    let mut server_task = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = stop_accepting_rx.await;
+            })
+            .await
+    });
+
+    tokio::select! {
+        _ = shutdown_signal() => {}
+        result = &mut server_task => {
+            result??;
+            return Ok(());
+        }
+    }
+
+    info!("Shutdown requested; no longer accepting connections");
+    let _ = stop_accepting_tx.send(());
+
+    let shutdown = network::shutdown::ShutdownConfig::from_env();
+    for remaining in (1..=shutdown.countdown).rev() {
+        if shutdown.is_warning_due(remaining) {
+            info!("Shutting down in {} seconds", remaining);
+            network::shutdown::warn_sessions(&state.session_store, remaining).await;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    // Freeze the world before saving it
+    let _ = shutdown_tx.send(true);
+    if let Err(e) = simulation_task.await {
+        warn!("Simulation task ended abnormally: {:?}", e);
+    }
+    persist_task.abort();
+    reaper_task.abort();
+
+    // Disconnect everyone, including link-dead players, and flush their characters
+    let sessions = network::shutdown::disconnect_all(&state.session_store).await;
+    info!("Flushing {} sessions before exit", sessions.len());
+    for session in &sessions {
+        logout_session(&state, session).await;
+    }
+
+    if tokio::time::timeout(Duration::from_secs(5), server_task)
+        .await
+        .is_err()
+    {
+        warn!("HTTP server did not stop in time");
+    }
+    state.db_pool.close().await;
+    info!("Shutdown complete");
 
     Ok(())
 }
 
+/// Resolves on Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+fn disconnect_envelope(reason: DisconnectReason, message: String) -> Envelope {
+    Envelope {
+        sequence_id: 0,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        payload: Payload::Disconnect(Disconnect { reason, message }),
+    }
+}
+
+/// Answer a loot request with what is left on the corpse, or why it was refused
+fn loot_response(
+    sequence_id: u32,
+    corpse_id: u64,
+    result: Result<simulation::looting_system::LootWindow, String>,
+) -> Envelope {
+    let response = match result {
+        Ok(window) => network::messages::LootResponse {
+            corpse_id,
+            success: true,
+            error_message: None,
+            gold: window.gold,
+            items: window
+                .items
+                .iter()
+                .map(|(slot, item)| network::messages::LootSlot {
+                    slot: *slot,
+                    item: item.into(),
+                })
+                .collect(),
+        },
+        Err(message) => network::messages::LootResponse {
+            corpse_id,
+            success: false,
+            error_message: Some(message),
+            gold: 0,
+            items: Vec::new(),
+        },
+    };
+    Envelope {
+        sequence_id,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        payload: Payload::LootResponse(response),
+    }
+}
+
 async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
     Ok(Json(json!({
         "status": "healthy",
@@ -268,352 +553,617 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
 
     info!("New WebSocket connection established");
 
-    // Create a session for this connection
-    let session_id = state.session_store.create_session().await;
+    // Create a session for this connection; a resume may swap it for an older one
+    let mut session_id = state.session_store.create_session().await;
     info!("Created session: {}", session_id);
 
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
-    let (outgoing_tx, mut outgoing_rx) = tokio::sync::mpsc::unbounded_channel::<Envelope>();
+    let (outgoing_tx, mut outgoing_rx) = network::outbound::channel(state.outbound.clone());
     state
         .session_store
         .set_sender(&session_id, Some(outgoing_tx.clone()))
         .await;
 
     let send_task = tokio::spawn(async move {
+        // Frames start as JSON; the handshake response decides what follows it.
+        let mut wire_format = WireFormat::Json;
         while let Some(envelope) = outgoing_rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&envelope) {
-                if ws_sender.send(Message::Text(json)).await.is_err() {
-                    break;
+            match network::codec::encode(&envelope, wire_format) {
+                Ok(frame) => {
+                    if ws_sender.send(frame).await.is_err() {
+                        break;
+                    }
                 }
+                Err(e) => error!("Failed to encode outgoing envelope: {}", e),
             }
+            wire_format = wire_format.after_send(&envelope);
         }
+        let _ = ws_sender.close().await;
     });
 
-    // Send handshake response
-    let handshake_response = Envelope {
-        sequence_id: 1,
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64,
-        payload: Payload::HandshakeResponse(HandshakeResponse {
-            accepted: true,
-            server_version: "0.1.0".to_string(),
-            protocol_version: "1.0".to_string(),
-            server_features: 0,
-            message: "Welcome to OpenMMO!".to_string(),
-        }),
-    };
+    let mut handshake_complete = false;
+    // Clean closes, kicks and clients too slow to keep up skip the link-dead grace period
+    let mut logout_now = false;
+    // Why sending to the client failed, if that is what ended the loop
+    let mut send_failure = None;
+    let mut rate_limiter = network::rate_limit::SessionRateLimiter::new(state.rate_limits.clone());
+    let mut liveness = network::heartbeat::Liveness::new(&state.heartbeat);
+    let mut heartbeat = interval(state.heartbeat.interval);
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    heartbeat.tick().await;
+
+    // Handle incoming messages until the client leaves or falls too far behind
+    loop {
+        let msg = tokio::select! {
+            msg = ws_receiver.next() => match msg {
+                Some(Ok(msg)) => msg,
+                _ => break,
+            },
+            _ = outgoing_tx.lagged() => {
+                send_failure = Some(OutboundError::Lagging);
+                break;
+            }
+            _ = heartbeat.tick() => {
+                if liveness.is_timed_out() {
+                    info!("Session {} timed out after inactivity", session_id);
+                    let disconnect = disconnect_envelope(
+                        DisconnectReason::Timeout,
+                        "No activity from client".to_string(),
+                    );
+                    let _ = send_session_envelope(&state, &session_id, disconnect).await;
+                    logout_now = true;
+                    break;
+                }
 
-    if !send_session_envelope(&state, &session_id, handshake_response).await {
-        return;
-    }
+                if handshake_complete {
+                    let timestamp = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64;
+                    let ping = Envelope {
+                        sequence_id: 0,
+                        timestamp,
+                        payload: Payload::Ping(Ping { timestamp }),
+                    };
+                    if let Err(err) = send_session_envelope(&state, &session_id, ping).await {
+                        send_failure = Some(err);
+                        break;
+                    }
+                }
+                continue;
+            }
+        };
+        liveness.record_activity();
 
-    // Handle incoming messages
-    while let Some(Ok(msg)) = ws_receiver.next().await {
-        match msg {
+        let envelope = match msg {
             Message::Text(text) => {
                 info!("Received message: {}", text);
 
-                // Try to parse as Envelope
-                if let Ok(envelope) = serde_json::from_str::<Envelope>(&text) {
-                    match &envelope.payload {
-                        Payload::Ping(ping) => {
-                            // Respond with pong
-                            let pong_response = Envelope {
-                                sequence_id: envelope.sequence_id,
-                                timestamp: SystemTime::now()
-                                    .duration_since(UNIX_EPOCH)
-                                    .unwrap()
-                                    .as_millis() as u64,
-                                payload: Payload::Pong(Pong {
-                                    timestamp: ping.timestamp,
-                                }),
-                            };
+                match network::codec::decode_json(&text) {
+                    Ok(envelope) => envelope,
+                    Err(e) => {
+                        error!("Failed to parse message: {} ({})", text, e);
+                        continue;
+                    }
+                }
+            }
+            Message::Binary(bytes) => match network::codec::decode_protobuf(&bytes) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    error!(
+                        "Failed to parse binary message ({} bytes): {}",
+                        bytes.len(),
+                        e
+                    );
+                    continue;
+                }
+            },
+            Message::Close(_) => {
+                info!("WebSocket connection closed for session: {}", session_id);
+                logout_now = true;
+                break;
+            }
+            _ => continue,
+        };
 
-                            if !send_session_envelope(&state, &session_id, pong_response).await {
-                                break;
+        let category = network::rate_limit::MessageCategory::of(&envelope.payload);
+        match rate_limiter.check(category) {
+            network::rate_limit::RateDecision::Allowed => {}
+            network::rate_limit::RateDecision::Limited => {
+                warn!(
+                    "Session {} exceeded its {:?} rate limit; dropping message",
+                    session_id, category
+                );
+                let error_response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::Error(Error {
+                        code: ErrorCode::RateLimited,
+                        message: "Too many requests; slow down".to_string(),
+                        details: std::collections::HashMap::from([(
+                            "category".to_string(),
+                            format!("{:?}", category),
+                        )]),
+                    }),
+                };
+
+                if let Err(err) = send_session_envelope(&state, &session_id, error_response).await {
+                    send_failure = Some(err);
+                    break;
+                }
+                continue;
+            }
+            network::rate_limit::RateDecision::Disconnect => {
+                warn!(
+                    "Disconnecting session {} for repeatedly exceeding rate limits",
+                    session_id
+                );
+                let disconnect = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::Disconnect(Disconnect {
+                        reason: DisconnectReason::Kicked,
+                        message: "Rate limit exceeded".to_string(),
+                    }),
+                };
+
+                let _ = send_session_envelope(&state, &session_id, disconnect).await;
+                logout_now = true;
+                break;
+            }
+        }
+
+        // Everything except keepalives waits for a successful handshake
+        if !handshake_complete
+            && !matches!(
+                envelope.payload,
+                Payload::HandshakeRequest(_) | Payload::Ping(_) | Payload::Pong(_)
+            )
+        {
+            warn!(
+                "Session {} sent a message before completing the handshake",
+                session_id
+            );
+            let error_response = Envelope {
+                sequence_id: envelope.sequence_id,
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as u64,
+                payload: Payload::Error(Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: "Handshake required before other messages".to_string(),
+                    details: std::collections::HashMap::new(),
+                }),
+            };
+
+            if let Err(err) = send_session_envelope(&state, &session_id, error_response).await {
+                send_failure = Some(err);
+                break;
+            }
+            continue;
+        }
+
+        match &envelope.payload {
+            Payload::Ping(ping) => {
+                // Respond with pong
+                let pong_response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::Pong(Pong {
+                        timestamp: ping.timestamp,
+                    }),
+                };
+
+                if let Err(err) = send_session_envelope(&state, &session_id, pong_response).await {
+                    send_failure = Some(err);
+                    break;
+                }
+            }
+            Payload::Pong(_) => {
+                // Heartbeat reply; receiving it already counted as activity
+            }
+            Payload::SnapshotAck(ack) => {
+                state
+                    .session_store
+                    .acknowledge_snapshot(&session_id, ack.snapshot_id)
+                    .await;
+            }
+            Payload::MovementIntent(movement) => {
+                // Queue movement intent for processing
+                if let Some(session) = state.session_store.get_session(&session_id).await {
+                    let intent = network::MovementIntent {
+                        player_id: session.player_id.unwrap_or(0),
+                        target_x: movement.target_position.x,
+                        target_y: movement.target_position.y,
+                        target_z: movement.target_position.z,
+                        speed_modifier: movement.speed_modifier,
+                        stop_movement: movement.stop_movement,
+                        rotation_y: movement.rotation_y,
+                    };
+
+                    {
+                        let mut world = state.world_state.write().await;
+                        world.queue_movement_intent(intent);
+                    }
+                }
+            }
+            Payload::CombatAction(combat) => {
+                // Queue combat action for processing
+                if let Some(session) = state.session_store.get_session(&session_id).await {
+                    let action = match combat.action_type {
+                        network::messages::ActionType::AutoAttack => {
+                            crate::simulation::CombatAction::AutoAttack {
+                                target_id: combat.target_entity_id,
                             }
                         }
-                        Payload::MovementIntent(movement) => {
-                            // Queue movement intent for processing
-                            if let Some(session) =
-                                state.session_store.get_session(&session_id).await
-                            {
-                                let intent = network::MovementIntent {
-                                    player_id: session.player_id.unwrap_or(0),
-                                    target_x: movement.target_position.x,
-                                    target_y: movement.target_position.y,
-                                    target_z: movement.target_position.z,
-                                    speed_modifier: movement.speed_modifier,
-                                    stop_movement: movement.stop_movement,
-                                    rotation_y: movement.rotation_y,
-                                };
-
-                                {
-                                    let mut world = state.world_state.write().await;
-                                    world.queue_movement_intent(intent);
-                                }
+                        network::messages::ActionType::Ability => {
+                            crate::simulation::CombatAction::Ability {
+                                ability_id: combat.ability_id,
+                                target_id: combat.target_entity_id,
+                                target_position: combat
+                                    .target_position
+                                    .as_ref()
+                                    .map(|position| (position.x, position.y, position.z)),
                             }
                         }
-                        Payload::CombatAction(combat) => {
-                            // Queue combat action for processing
-                            if let Some(session) =
-                                state.session_store.get_session(&session_id).await
-                            {
-                                let action = match combat.action_type {
-                                    network::messages::ActionType::AutoAttack => {
-                                        crate::simulation::CombatAction::AutoAttack {
-                                            target_id: combat.target_entity_id,
-                                        }
-                                    }
-                                    network::messages::ActionType::Ability => {
-                                        crate::simulation::CombatAction::Ability {
-                                            ability_id: combat.ability_id,
-                                            target_id: combat.target_entity_id,
-                                        }
-                                    }
-                                };
+                    };
 
-                                {
-                                    let mut world = state.world_state.write().await;
-                                    world.queue_combat_action(
-                                        session.player_id.unwrap_or(0),
-                                        action,
-                                    );
-                                }
-                            }
+                    {
+                        let mut world = state.world_state.write().await;
+                        world.queue_combat_action(session.player_id.unwrap_or(0), action);
+                    }
+                }
+            }
+            Payload::ReleaseRequest(_) => {
+                if let Some(player_id) = state
+                    .session_store
+                    .get_session(&session_id)
+                    .await
+                    .and_then(|session| session.player_id)
+                {
+                    let mut world = state.world_state.write().await;
+                    if let Err(e) = simulation::DeathSystem::release(&mut world, player_id) {
+                        warn!("Release refused for player {}: {}", player_id, e);
+                    }
+                }
+            }
+            Payload::LootOpenRequest(request) => {
+                if let Some(player_id) = state
+                    .session_store
+                    .get_session(&session_id)
+                    .await
+                    .and_then(|session| session.player_id)
+                {
+                    let result = {
+                        let mut world = state.world_state.write().await;
+                        simulation::LootingSystem::open(&mut world, player_id, request.corpse_id)
+                    };
+                    let response = loot_response(envelope.sequence_id, request.corpse_id, result);
+                    if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                        send_failure = Some(err);
+                        break;
+                    }
+                }
+            }
+            Payload::LootTakeRequest(request) => {
+                if let Some(player_id) = state
+                    .session_store
+                    .get_session(&session_id)
+                    .await
+                    .and_then(|session| session.player_id)
+                {
+                    let result = {
+                        let mut world = state.world_state.write().await;
+                        simulation::LootingSystem::take(
+                            &mut world,
+                            player_id,
+                            request.corpse_id,
+                            request.slot,
+                        )
+                    };
+                    let response = loot_response(envelope.sequence_id, request.corpse_id, result);
+                    if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                        send_failure = Some(err);
+                        break;
+                    }
+                }
+            }
+            Payload::LootRollRequest(request) => {
+                if let Some(player_id) = state
+                    .session_store
+                    .get_session(&session_id)
+                    .await
+                    .and_then(|session| session.player_id)
+                {
+                    let mut world = state.world_state.write().await;
+                    if let Err(e) = simulation::LootingSystem::roll(
+                        &mut world,
+                        player_id,
+                        request.corpse_id,
+                        request.slot,
+                        request.choice,
+                    ) {
+                        warn!("Loot roll refused for player {}: {}", player_id, e);
+                    }
+                }
+            }
+            Payload::PartyInviteRequest(request) => {
+                if let Some(player_id) = session_player_id(&state, &session_id).await {
+                    let mut world = state.world_state.write().await;
+                    let result =
+                        simulation::PartySystem::invite(&mut world, player_id, request.player_id);
+                    refuse_party_request(&mut world, player_id, "Party invite", result);
+                }
+            }
+            Payload::PartyInviteReply(reply) => {
+                if let Some(player_id) = session_player_id(&state, &session_id).await {
+                    let mut world = state.world_state.write().await;
+                    let result =
+                        simulation::PartySystem::reply(&mut world, player_id, reply.accept);
+                    refuse_party_request(&mut world, player_id, "Party invite reply", result);
+                }
+            }
+            Payload::PartyLeaveRequest(_) => {
+                if let Some(player_id) = session_player_id(&state, &session_id).await {
+                    let mut world = state.world_state.write().await;
+                    let result = simulation::PartySystem::leave(&mut world, player_id);
+                    refuse_party_request(&mut world, player_id, "Party leave", result);
+                }
+            }
+            Payload::PartyLootRulesRequest(request) => {
+                if let Some(player_id) = session_player_id(&state, &session_id).await {
+                    let rules = loot::LootRules {
+                        method: request.method,
+                        threshold: request.threshold,
+                    };
+                    let mut world = state.world_state.write().await;
+                    let result =
+                        simulation::PartySystem::set_loot_rules(&mut world, player_id, rules);
+                    refuse_party_request(&mut world, player_id, "Party loot rules", result);
+                }
+            }
+            Payload::AuthRequest(auth) => {
+                // Handle authentication request
+                let auth_result = if auth.character_name.is_some() {
+                    // Treat presence of character name as login attempt
+                    state
+                        .account_service
+                        .authenticate(&auth.username, &auth.password_hash)
+                        .await
+                } else {
+                    // Registration flow: try auth, then auto-register if needed
+                    match state
+                        .account_service
+                        .authenticate(&auth.username, &auth.password_hash)
+                        .await
+                    {
+                        Ok(account) => Ok(account),
+                        Err(_) => {
+                            state
+                                .account_service
+                                .register(
+                                    auth.username.clone(),
+                                    format!("{}@openmmo.local", auth.username),
+                                    auth.password_hash.clone(),
+                                )
+                                .await
                         }
-                        Payload::AuthRequest(auth) => {
-                            // Handle authentication request
-                            let auth_result = if auth.character_name.is_some() {
-                                // Treat presence of character name as login attempt
-                                state
-                                    .account_service
-                                    .authenticate(&auth.username, &auth.password_hash)
-                                    .await
-                            } else {
-                                // Registration flow: try auth, then auto-register if needed
-                                match state
-                                    .account_service
-                                    .authenticate(&auth.username, &auth.password_hash)
-                                    .await
-                                {
-                                    Ok(account) => Ok(account),
-                                    Err(_) => {
-                                        state
-                                            .account_service
-                                            .register(
-                                                auth.username.clone(),
-                                                format!("{}@openmmo.local", auth.username),
-                                                auth.password_hash.clone(),
-                                            )
-                                            .await
-                                    }
-                                }
-                            };
+                    }
+                };
 
-                            let auth_response = match auth_result {
-                                Ok(account) => {
-                                    let player_id_u64 = match state
-                                        .session_store
-                                        .allocate_player_id(&session_id)
-                                        .await
-                                    {
-                                        Some(id) => id,
-                                        None => {
-                                            error!(
-                                                "Failed to allocate synthetic player id for session {}",
-                                                session_id
-                                            );
-                                            let response = network::messages::AuthResponse {
-                                                success: false,
-                                                session_token: None,
-                                                message: "Internal server error".to_string(),
-                                                player_id: None,
-                                                character_id: None,
-                                            };
-
-                                            let envelope = Envelope {
-                                                sequence_id: envelope.sequence_id,
-                                                timestamp: SystemTime::now()
-                                                    .duration_since(UNIX_EPOCH)
-                                                    .unwrap()
-                                                    .as_millis()
-                                                    as u64,
-                                                payload: Payload::AuthResponse(response),
-                                            };
-
-                                            if !send_session_envelope(&state, &session_id, envelope)
-                                                .await
-                                            {
-                                                break;
-                                            }
-
-                                            continue;
-                                        }
+                let auth_response = match auth_result {
+                    Ok(account) => {
+                        let player_id_u64 =
+                            match state.session_store.allocate_player_id(&session_id).await {
+                                Some(id) => id,
+                                None => {
+                                    error!(
+                                        "Failed to allocate synthetic player id for session {}",
+                                        session_id
+                                    );
+                                    let response = network::messages::AuthResponse {
+                                        success: false,
+                                        session_token: None,
+                                        message: "Internal server error".to_string(),
+                                        player_id: None,
+                                        character_id: None,
                                     };
-                                    state
-                                        .session_store
-                                        .authenticate_session(
-                                            &session_id,
-                                            account.id,
-                                            player_id_u64,
-                                            None,
-                                        )
-                                        .await;
 
-                                    network::messages::AuthResponse {
-                                        success: true,
-                                        session_token: Some(session_id.to_string()),
-                                        message: "Authentication successful".to_string(),
-                                        player_id: Some(player_id_u64),
-                                        character_id: None,
+                                    let envelope = Envelope {
+                                        sequence_id: envelope.sequence_id,
+                                        timestamp: SystemTime::now()
+                                            .duration_since(UNIX_EPOCH)
+                                            .unwrap()
+                                            .as_millis()
+                                            as u64,
+                                        payload: Payload::AuthResponse(response),
+                                    };
+
+                                    if let Err(err) =
+                                        send_session_envelope(&state, &session_id, envelope).await
+                                    {
+                                        send_failure = Some(err);
+                                        break;
                                     }
-                                }
-                                Err(e) => network::messages::AuthResponse {
-                                    success: false,
-                                    session_token: None,
-                                    message: format!("Authentication failed: {:?}", e),
-                                    player_id: None,
-                                    character_id: None,
-                                },
-                            };
 
-                            let response = Envelope {
-                                sequence_id: envelope.sequence_id,
-                                timestamp: SystemTime::now()
-                                    .duration_since(UNIX_EPOCH)
-                                    .unwrap()
-                                    .as_millis() as u64,
-                                payload: Payload::AuthResponse(auth_response),
+                                    continue;
+                                }
                             };
-
-                            if !send_session_envelope(&state, &session_id, response).await {
-                                break;
-                            }
+                        state
+                            .session_store
+                            .authenticate_session(&session_id, account.id, player_id_u64, None)
+                            .await;
+
+                        network::messages::AuthResponse {
+                            success: true,
+                            session_token: Some(state.session_tokens.issue(session_id, account.id)),
+                            message: "Authentication successful".to_string(),
+                            player_id: Some(player_id_u64),
+                            character_id: None,
                         }
-                        Payload::CharacterCreateRequest(create_req) => {
-                            // Ensure session exists
-                            let session = if let Some(s) =
-                                state.session_store.get_session(&session_id).await
-                            {
-                                s
-                            } else {
-                                let error_response = network::messages::CharacterCreateResponse {
-                                    success: false,
-                                    character: None,
-                                    error_message: Some("Session not found".to_string()),
-                                };
+                    }
+                    Err(e) => network::messages::AuthResponse {
+                        success: false,
+                        session_token: None,
+                        message: format!("Authentication failed: {:?}", e),
+                        player_id: None,
+                        character_id: None,
+                    },
+                };
 
-                                let response = Envelope {
-                                    sequence_id: envelope.sequence_id,
-                                    timestamp: SystemTime::now()
-                                        .duration_since(UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_millis()
-                                        as u64,
-                                    payload: Payload::CharacterCreateResponse(error_response),
-                                };
-
-                                if !send_session_envelope(&state, &session_id, response).await {
-                                    break;
-                                }
-                                continue;
-                            };
+                let response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::AuthResponse(auth_response),
+                };
 
-                            let account_id = if let Some(id) = session.account_id {
-                                id
-                            } else {
-                                let error_response = network::messages::CharacterCreateResponse {
-                                    success: false,
-                                    character: None,
-                                    error_message: Some("Not authenticated".to_string()),
-                                };
+                if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                    send_failure = Some(err);
+                    break;
+                }
+            }
+            Payload::CharacterCreateRequest(create_req) => {
+                // Ensure session exists
+                let session = if let Some(s) = state.session_store.get_session(&session_id).await {
+                    s
+                } else {
+                    let error_response = network::messages::CharacterCreateResponse {
+                        success: false,
+                        character: None,
+                        error_message: Some("Session not found".to_string()),
+                    };
+
+                    let response = Envelope {
+                        sequence_id: envelope.sequence_id,
+                        timestamp: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap()
+                            .as_millis() as u64,
+                        payload: Payload::CharacterCreateResponse(error_response),
+                    };
+
+                    if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                        send_failure = Some(err);
+                        break;
+                    }
+                    continue;
+                };
 
-                                let response = Envelope {
-                                    sequence_id: envelope.sequence_id,
-                                    timestamp: SystemTime::now()
-                                        .duration_since(UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_millis()
-                                        as u64,
-                                    payload: Payload::CharacterCreateResponse(error_response),
-                                };
+                let account_id = if let Some(id) = session.account_id {
+                    id
+                } else {
+                    let error_response = network::messages::CharacterCreateResponse {
+                        success: false,
+                        character: None,
+                        error_message: Some("Not authenticated".to_string()),
+                    };
+
+                    let response = Envelope {
+                        sequence_id: envelope.sequence_id,
+                        timestamp: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap()
+                            .as_millis() as u64,
+                        payload: Payload::CharacterCreateResponse(error_response),
+                    };
+
+                    if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                        send_failure = Some(err);
+                        break;
+                    }
+                    continue;
+                };
 
-                                if !send_session_envelope(&state, &session_id, response).await {
-                                    break;
+                let create_result = state
+                    .account_service
+                    .create_character(
+                        account_id,
+                        create_req.name.clone(),
+                        create_req.class.clone(),
+                    )
+                    .await;
+
+                let create_response = match create_result {
+                    Ok(character) => match state
+                        .session_store
+                        .map_character_id(&session_id, character.id)
+                        .await
+                    {
+                        Some(synthetic_id) => {
+                            match build_character_info(
+                                &character,
+                                synthetic_id,
+                                character.is_online,
+                            ) {
+                                Ok(info) => network::messages::CharacterCreateResponse {
+                                    success: true,
+                                    character: Some(info),
+                                    error_message: None,
+                                },
+                                Err(err) => {
+                                    error!(
+                                        "Invalid character data for session {}: {}",
+                                        session_id, err
+                                    );
+                                    network::messages::CharacterCreateResponse {
+                                        success: false,
+                                        character: None,
+                                        error_message: Some("Invalid character data".to_string()),
+                                    }
                                 }
-                                continue;
-                            };
+                            }
+                        }
+                        None => {
+                            error!("Failed to map character id for session {}", session_id);
+                            network::messages::CharacterCreateResponse {
+                                success: false,
+                                character: None,
+                                error_message: Some("Internal server error".to_string()),
+                            }
+                        }
+                    },
+                    Err(e) => network::messages::CharacterCreateResponse {
+                        success: false,
+                        character: None,
+                        error_message: Some(format!("Character creation failed: {:?}", e)),
+                    },
+                };
 
-                            let create_result = state
-                                .account_service
-                                .create_character(
-                                    account_id,
-                                    create_req.name.clone(),
-                                    create_req.class.clone(),
-                                )
-                                .await;
+                let response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::CharacterCreateResponse(create_response),
+                };
 
-                            let create_response = match create_result {
-                                Ok(character) => match state
-                                    .session_store
-                                    .map_character_id(&session_id, character.id)
-                                    .await
-                                {
-                                    Some(synthetic_id) => {
-                                        match build_character_info(
-                                            &character,
-                                            synthetic_id,
-                                            character.is_online,
-                                        ) {
-                                            Ok(info) => {
-                                                network::messages::CharacterCreateResponse {
-                                                    success: true,
-                                                    character: Some(info),
-                                                    error_message: None,
-                                                }
-                                            }
-                                            Err(err) => {
-                                                error!(
-                                                    "Invalid character data for session {}: {}",
-                                                    session_id, err
-                                                );
-                                                network::messages::CharacterCreateResponse {
-                                                    success: false,
-                                                    character: None,
-                                                    error_message: Some(
-                                                        "Invalid character data".to_string(),
-                                                    ),
-                                                }
-                                            }
-                                        }
-                                    }
-                                    None => {
-                                        error!(
-                                            "Failed to map character id for session {}",
-                                            session_id
-                                        );
-                                        network::messages::CharacterCreateResponse {
-                                            success: false,
-                                            character: None,
-                                            error_message: Some(
-                                                "Internal server error".to_string(),
-                                            ),
-                                        }
-                                    }
-                                },
-                                Err(e) => network::messages::CharacterCreateResponse {
-                                    success: false,
-                                    character: None,
-                                    error_message: Some(format!(
-                                        "Character creation failed: {:?}",
-                                        e
-                                    )),
-                                },
-                            };
+                if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                    send_failure = Some(err);
+                    break;
+                }
+            }
+            Payload::CharacterListRequest(_req) => {
+                let account_id = match state.session_store.get_session(&session_id).await {
+                    Some(session) => match session.account_id {
+                        Some(id) => id,
+                        None => {
+                            let error_response =
+                                network::messages::CharacterListResponse { characters: vec![] };
 
                             let response = Envelope {
                                 sequence_id: envelope.sequence_id,
@@ -621,105 +1171,101 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                                     .duration_since(UNIX_EPOCH)
                                     .unwrap()
                                     .as_millis() as u64,
-                                payload: Payload::CharacterCreateResponse(create_response),
+                                payload: Payload::CharacterListResponse(error_response),
                             };
 
-                            if !send_session_envelope(&state, &session_id, response).await {
+                            if let Err(err) =
+                                send_session_envelope(&state, &session_id, response).await
+                            {
+                                send_failure = Some(err);
                                 break;
                             }
+                            continue;
                         }
-                        Payload::CharacterListRequest(_req) => {
-                            let account_id = match state
+                    },
+                    None => {
+                        let error_response =
+                            network::messages::CharacterListResponse { characters: vec![] };
+
+                        let response = Envelope {
+                            sequence_id: envelope.sequence_id,
+                            timestamp: SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap()
+                                .as_millis() as u64,
+                            payload: Payload::CharacterListResponse(error_response),
+                        };
+
+                        if let Err(err) = send_session_envelope(&state, &session_id, response).await
+                        {
+                            send_failure = Some(err);
+                            break;
+                        }
+                        continue;
+                    }
+                };
+
+                let characters_result = state.account_service.get_characters(account_id).await;
+
+                let character_list_response = match characters_result {
+                    Ok(characters) => {
+                        let mut infos = Vec::with_capacity(characters.len());
+                        for character in characters {
+                            match state
                                 .session_store
-                                .get_session(&session_id)
+                                .map_character_id(&session_id, character.id)
                                 .await
                             {
-                                Some(session) => match session.account_id {
-                                    Some(id) => id,
-                                    None => {
-                                        let error_response =
-                                            network::messages::CharacterListResponse {
-                                                characters: vec![],
-                                            };
-
-                                        let response = Envelope {
-                                            sequence_id: envelope.sequence_id,
-                                            timestamp: SystemTime::now()
-                                                .duration_since(UNIX_EPOCH)
-                                                .unwrap()
-                                                .as_millis()
-                                                as u64,
-                                            payload: Payload::CharacterListResponse(error_response),
-                                        };
-
-                                        if !send_session_envelope(&state, &session_id, response)
-                                            .await
-                                        {
-                                            break;
-                                        }
-                                        continue;
+                                Some(synthetic_id) => {
+                                    match build_character_info(
+                                        &character,
+                                        synthetic_id,
+                                        character.is_online,
+                                    ) {
+                                        Ok(info) => infos.push(info),
+                                        Err(err) => error!(
+                                            "Invalid character data for session {}: {}",
+                                            session_id, err
+                                        ),
                                     }
-                                },
+                                }
                                 None => {
-                                    let error_response = network::messages::CharacterListResponse {
-                                        characters: vec![],
-                                    };
-
-                                    let response = Envelope {
-                                        sequence_id: envelope.sequence_id,
-                                        timestamp: SystemTime::now()
-                                            .duration_since(UNIX_EPOCH)
-                                            .unwrap()
-                                            .as_millis()
-                                            as u64,
-                                        payload: Payload::CharacterListResponse(error_response),
-                                    };
-
-                                    if !send_session_envelope(&state, &session_id, response).await {
-                                        break;
-                                    }
-                                    continue;
+                                    error!("Failed to map character id for session {}", session_id)
                                 }
-                            };
+                            }
+                        }
 
-                            let characters_result =
-                                state.account_service.get_characters(account_id).await;
-
-                            let character_list_response = match characters_result {
-                                Ok(characters) => {
-                                    let mut infos = Vec::with_capacity(characters.len());
-                                    for character in characters {
-                                        match state
-                                            .session_store
-                                            .map_character_id(&session_id, character.id)
-                                            .await
-                                        {
-                                            Some(synthetic_id) => {
-                                                match build_character_info(
-                                                    &character,
-                                                    synthetic_id,
-                                                    character.is_online,
-                                                ) {
-                                                    Ok(info) => infos.push(info),
-                                                    Err(err) => error!(
-                                                        "Invalid character data for session {}: {}",
-                                                        session_id, err
-                                                    ),
-                                                }
-                                            }
-                                            None => error!(
-                                                "Failed to map character id for session {}",
-                                                session_id
-                                            ),
-                                        }
-                                    }
+                        network::messages::CharacterListResponse { characters: infos }
+                    }
+                    Err(e) => {
+                        error!("Failed to get characters: {:?}", e);
+                        network::messages::CharacterListResponse { characters: vec![] }
+                    }
+                };
 
-                                    network::messages::CharacterListResponse { characters: infos }
-                                }
-                                Err(e) => {
-                                    error!("Failed to get characters: {:?}", e);
-                                    network::messages::CharacterListResponse { characters: vec![] }
-                                }
+                let response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::CharacterListResponse(character_list_response),
+                };
+
+                if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                    send_failure = Some(err);
+                    break;
+                }
+            }
+            Payload::CharacterSelectRequest(select_req) => {
+                let account_id = match state.session_store.get_session(&session_id).await {
+                    Some(session) => match session.account_id {
+                        Some(id) => id,
+                        None => {
+                            let error_response = network::messages::CharacterSelectResponse {
+                                success: false,
+                                character: None,
+                                error_message: Some("Not authenticated".to_string()),
                             };
 
                             let response = Envelope {
@@ -728,333 +1274,467 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                                     .duration_since(UNIX_EPOCH)
                                     .unwrap()
                                     .as_millis() as u64,
-                                payload: Payload::CharacterListResponse(character_list_response),
+                                payload: Payload::CharacterSelectResponse(error_response),
                             };
 
-                            if !send_session_envelope(&state, &session_id, response).await {
+                            if let Err(err) =
+                                send_session_envelope(&state, &session_id, response).await
+                            {
+                                send_failure = Some(err);
                                 break;
                             }
+                            continue;
                         }
-                        Payload::CharacterSelectRequest(select_req) => {
-                            let account_id = match state
-                                .session_store
-                                .get_session(&session_id)
-                                .await
-                            {
-                                Some(session) => match session.account_id {
-                                    Some(id) => id,
-                                    None => {
-                                        let error_response =
-                                            network::messages::CharacterSelectResponse {
-                                                success: false,
-                                                character: None,
-                                                error_message: Some(
-                                                    "Not authenticated".to_string(),
-                                                ),
-                                            };
-
-                                        let response = Envelope {
-                                            sequence_id: envelope.sequence_id,
-                                            timestamp: SystemTime::now()
-                                                .duration_since(UNIX_EPOCH)
-                                                .unwrap()
-                                                .as_millis()
-                                                as u64,
-                                            payload: Payload::CharacterSelectResponse(
-                                                error_response,
+                    },
+                    None => {
+                        let error_response = network::messages::CharacterSelectResponse {
+                            success: false,
+                            character: None,
+                            error_message: Some("Session not found".to_string()),
+                        };
+
+                        let response = Envelope {
+                            sequence_id: envelope.sequence_id,
+                            timestamp: SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap()
+                                .as_millis() as u64,
+                            payload: Payload::CharacterSelectResponse(error_response),
+                        };
+
+                        if let Err(err) = send_session_envelope(&state, &session_id, response).await
+                        {
+                            send_failure = Some(err);
+                            break;
+                        }
+                        continue;
+                    }
+                };
+
+                let target_character_uuid = match state
+                    .session_store
+                    .resolve_character_id(&session_id, select_req.character_id)
+                    .await
+                {
+                    Some(uuid) => uuid,
+                    None => {
+                        let error_response = network::messages::CharacterSelectResponse {
+                            success: false,
+                            character: None,
+                            error_message: Some("Unknown character selection".to_string()),
+                        };
+
+                        let response = Envelope {
+                            sequence_id: envelope.sequence_id,
+                            timestamp: SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap()
+                                .as_millis() as u64,
+                            payload: Payload::CharacterSelectResponse(error_response),
+                        };
+
+                        if let Err(err) = send_session_envelope(&state, &session_id, response).await
+                        {
+                            send_failure = Some(err);
+                            break;
+                        }
+                        continue;
+                    }
+                };
+
+                // A link-dead session still holding this character is logged out first
+                if let Some(stale) = state
+                    .session_store
+                    .take_link_dead_for_character(target_character_uuid)
+                    .await
+                {
+                    info!(
+                        "Replacing link-dead session {} for character {}",
+                        stale.id, target_character_uuid
+                    );
+                    logout_session(&state, &stale).await;
+                }
+
+                let characters_result = state.account_service.get_characters(account_id).await;
+
+                // Set once the player entity exists; the session is only bound to it
+                // after the response goes out so replication's join snapshot follows it
+                let mut spawned_player: Option<(u64, Uuid)> = None;
+
+                let character_select_response = match characters_result {
+                    Ok(characters) => {
+                        let selected = characters
+                            .into_iter()
+                            .find(|c| c.id == target_character_uuid);
+
+                        match selected {
+                            Some(character) => {
+                                let snapshot_character = character.clone();
+
+                                let spawn_pose = (
+                                    snapshot_character.position_x as f32,
+                                    snapshot_character.position_y as f32,
+                                    snapshot_character.position_z as f32,
+                                    snapshot_character.rotation as f32,
+                                );
+
+                                let entity_id = {
+                                    let mut world = state.world_state.write().await;
+                                    // Clear any stale copies of this character by name
+                                    world.remove_player_by_name(&snapshot_character.name);
+                                    info!(
+                                        "Spawning character {} in zone {} at ({:.2}, {:.2}, {:.2}) rot {:.2}",
+                                        snapshot_character.id,
+                                        snapshot_character.zone_id,
+                                        spawn_pose.0,
+                                        spawn_pose.1,
+                                        spawn_pose.2,
+                                        spawn_pose.3
+                                    );
+                                    let entity_id = world
+                                        .spawn_player_entity(
+                                            &snapshot_character.name,
+                                            &snapshot_character.zone_id,
+                                            (spawn_pose.0, spawn_pose.1, spawn_pose.2),
+                                            spawn_pose.3,
+                                            (
+                                                snapshot_character.health,
+                                                snapshot_character.max_health,
                                             ),
-                                        };
+                                            (
+                                                &snapshot_character.resource_type,
+                                                snapshot_character.resource_value,
+                                                snapshot_character.max_resource,
+                                            ),
+                                        )
+                                        .unwrap_or_else(|_| {
+                                            world
+                                                .spawn_player_entity(
+                                                    &snapshot_character.name,
+                                                    "1",
+                                                    (spawn_pose.0, spawn_pose.1, spawn_pose.2),
+                                                    spawn_pose.3,
+                                                    (
+                                                        snapshot_character.health,
+                                                        snapshot_character.max_health,
+                                                    ),
+                                                    (
+                                                        &snapshot_character.resource_type,
+                                                        snapshot_character.resource_value,
+                                                        snapshot_character.max_resource,
+                                                    ),
+                                                )
+                                                .expect("Failed to spawn player entity")
+                                        });
+                                    world.set_player_class(
+                                        entity_id,
+                                        &snapshot_character.class,
+                                        snapshot_character.level.max(1) as u32,
+                                    );
+                                    world.set_player_experience(
+                                        entity_id,
+                                        snapshot_character.experience.max(0) as u64,
+                                    );
+                                    entity_id
+                                };
 
-                                        if !send_session_envelope(&state, &session_id, response)
-                                            .await
-                                        {
-                                            break;
-                                        }
-                                        continue;
-                                    }
-                                },
-                                None => {
-                                    let error_response =
-                                        network::messages::CharacterSelectResponse {
-                                            success: false,
-                                            character: None,
-                                            error_message: Some("Session not found".to_string()),
-                                        };
+                                spawned_player = Some((entity_id, character.id));
 
-                                    let response = Envelope {
-                                        sequence_id: envelope.sequence_id,
-                                        timestamp: SystemTime::now()
-                                            .duration_since(UNIX_EPOCH)
-                                            .unwrap()
-                                            .as_millis()
-                                            as u64,
-                                        payload: Payload::CharacterSelectResponse(error_response),
-                                    };
+                                // Persist spawn pose immediately so re-joins use latest position
+                                if let Err(e) = state
+                                    .account_service
+                                    .update_character_position(
+                                        character.id,
+                                        spawn_pose.0 as f64,
+                                        spawn_pose.1 as f64,
+                                        spawn_pose.2 as f64,
+                                        spawn_pose.3 as f64,
+                                    )
+                                    .await
+                                {
+                                    warn!(
+                                        "Failed to persist spawn pose for character {}: {:?}",
+                                        character.id, e
+                                    );
+                                }
 
-                                    if !send_session_envelope(&state, &session_id, response).await {
-                                        break;
-                                    }
-                                    continue;
+                                if let Err(e) = state
+                                    .account_service
+                                    .set_character_online(character.id, true)
+                                    .await
+                                {
+                                    error!(
+                                        "Failed to mark character online for session {}: {:?}",
+                                        session_id, e
+                                    );
                                 }
-                            };
 
-                            let target_character_uuid = match state
-                                .session_store
-                                .resolve_character_id(&session_id, select_req.character_id)
-                                .await
-                            {
-                                Some(uuid) => uuid,
-                                None => {
-                                    let error_response =
+                                match build_character_info(
+                                    &character,
+                                    select_req.character_id,
+                                    true,
+                                ) {
+                                    Ok(info) => network::messages::CharacterSelectResponse {
+                                        success: true,
+                                        character: Some(info),
+                                        error_message: None,
+                                    },
+                                    Err(err) => {
+                                        error!(
+                                            "Invalid character data for session {}: {}",
+                                            session_id, err
+                                        );
                                         network::messages::CharacterSelectResponse {
                                             success: false,
                                             character: None,
                                             error_message: Some(
-                                                "Unknown character selection".to_string(),
+                                                "Invalid character data".to_string(),
                                             ),
-                                        };
-
-                                    let response = Envelope {
-                                        sequence_id: envelope.sequence_id,
-                                        timestamp: SystemTime::now()
-                                            .duration_since(UNIX_EPOCH)
-                                            .unwrap()
-                                            .as_millis()
-                                            as u64,
-                                        payload: Payload::CharacterSelectResponse(error_response),
-                                    };
-
-                                    if !send_session_envelope(&state, &session_id, response).await {
-                                        break;
-                                    }
-                                    continue;
-                                }
-                            };
-
-                            let characters_result =
-                                state.account_service.get_characters(account_id).await;
-
-                            let mut snapshot_to_send: Option<network::messages::WorldSnapshot> =
-                                None;
-
-                            let character_select_response = match characters_result {
-                                Ok(characters) => {
-                                    let selected = characters
-                                        .into_iter()
-                                        .find(|c| c.id == target_character_uuid);
-
-                                    match selected {
-                                        Some(character) => {
-                                            let snapshot_character = character.clone();
-
-                                            let spawn_pose = (
-                                                snapshot_character.position_x as f32,
-                                                snapshot_character.position_y as f32,
-                                                snapshot_character.position_z as f32,
-                                                snapshot_character.rotation as f32,
-                                            );
-
-                                            let entity_id = {
-                                                let mut world = state.world_state.write().await;
-                                                // Clear any stale copies of this character by name
-                                                world.remove_player_by_name(
-                                                    &snapshot_character.name,
-                                                );
-                                                info!(
-                                                    "Spawning character {} in zone {} at ({:.2}, {:.2}, {:.2}) rot {:.2}",
-                                                    snapshot_character.id,
-                                                    snapshot_character.zone_id,
-                                                    spawn_pose.0,
-                                                    spawn_pose.1,
-                                                    spawn_pose.2,
-                                                    spawn_pose.3
-                                                );
-                                                world
-                                                    .spawn_player_entity(
-                                                        &snapshot_character.name,
-                                                        &snapshot_character.zone_id,
-                                                        (spawn_pose.0, spawn_pose.1, spawn_pose.2),
-                                                        spawn_pose.3,
-                                                        (
-                                                            snapshot_character.health,
-                                                            snapshot_character.max_health,
-                                                        ),
-                                                    )
-                                                    .unwrap_or_else(|_| {
-                                                        world
-                                                            .spawn_player_entity(
-                                                                &snapshot_character.name,
-                                                                "1",
-                                                                (
-                                                                    spawn_pose.0,
-                                                                    spawn_pose.1,
-                                                                    spawn_pose.2,
-                                                                ),
-                                                                spawn_pose.3,
-                                                                (
-                                                                    snapshot_character.health,
-                                                                    snapshot_character.max_health,
-                                                                ),
-                                                            )
-                                                            .expect("Failed to spawn player entity")
-                                                    })
-                                            };
-
-                                            state
-                                                .session_store
-                                                .authenticate_session(
-                                                    &session_id,
-                                                    account_id,
-                                                    entity_id,
-                                                    Some(character.id),
-                                                )
-                                                .await;
-
-                                            // Persist spawn pose immediately so re-joins use latest position
-                                            if let Err(e) = state
-                                                .account_service
-                                                .update_character_position(
-                                                    character.id,
-                                                    spawn_pose.0 as f64,
-                                                    spawn_pose.1 as f64,
-                                                    spawn_pose.2 as f64,
-                                                    spawn_pose.3 as f64,
-                                                )
-                                                .await
-                                            {
-                                                warn!(
-                                                    "Failed to persist spawn pose for character {}: {:?}",
-                                                    character.id, e
-                                                );
-                                            }
-
-                                            if let Err(e) = state
-                                                .account_service
-                                                .set_character_online(character.id, true)
-                                                .await
-                                            {
-                                                error!(
-                                                    "Failed to mark character online for session {}: {:?}",
-                                                    session_id, e
-                                                );
-                                            }
-
-                                            snapshot_to_send = {
-                                                let world = state.world_state.read().await;
-                                                if let Some(session) = state
-                                                    .session_store
-                                                    .get_session(&session_id)
-                                                    .await
-                                                {
-                                                    build_world_snapshot(&world, &session)
-                                                } else {
-                                                    None
-                                                }
-                                            };
-
-                                            match build_character_info(
-                                                &character,
-                                                select_req.character_id,
-                                                true,
-                                            ) {
-                                                Ok(info) => {
-                                                    network::messages::CharacterSelectResponse {
-                                                        success: true,
-                                                        character: Some(info),
-                                                        error_message: None,
-                                                    }
-                                                }
-                                                Err(err) => {
-                                                    error!(
-                                                        "Invalid character data for session {}: {}",
-                                                        session_id, err
-                                                    );
-                                                    network::messages::CharacterSelectResponse {
-                                                        success: false,
-                                                        character: None,
-                                                        error_message: Some(
-                                                            "Invalid character data".to_string(),
-                                                        ),
-                                                    }
-                                                }
-                                            }
                                         }
-                                        None => network::messages::CharacterSelectResponse {
-                                            success: false,
-                                            character: None,
-                                            error_message: Some("Character not found".to_string()),
-                                        },
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Failed to get characters for selection: {:?}", e);
-                                    network::messages::CharacterSelectResponse {
-                                        success: false,
-                                        character: None,
-                                        error_message: Some(
-                                            "Failed to retrieve characters".to_string(),
-                                        ),
                                     }
                                 }
-                            };
+                            }
+                            None => network::messages::CharacterSelectResponse {
+                                success: false,
+                                character: None,
+                                error_message: Some("Character not found".to_string()),
+                            },
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to get characters for selection: {:?}", e);
+                        network::messages::CharacterSelectResponse {
+                            success: false,
+                            character: None,
+                            error_message: Some("Failed to retrieve characters".to_string()),
+                        }
+                    }
+                };
 
-                            let response = Envelope {
-                                sequence_id: envelope.sequence_id,
-                                timestamp: SystemTime::now()
-                                    .duration_since(UNIX_EPOCH)
-                                    .unwrap()
-                                    .as_millis() as u64,
-                                payload: Payload::CharacterSelectResponse(
-                                    character_select_response,
-                                ),
-                            };
+                let response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::CharacterSelectResponse(character_select_response),
+                };
 
-                            if !send_session_envelope(&state, &session_id, response).await {
-                                break;
-                            }
+                let sent = send_session_envelope(&state, &session_id, response).await;
+
+                // Bind even if the send failed so session cleanup removes the entity
+                if let Some((entity_id, character_id)) = spawned_player {
+                    state
+                        .session_store
+                        .authenticate_session(
+                            &session_id,
+                            account_id,
+                            entity_id,
+                            Some(character_id),
+                        )
+                        .await;
+                }
 
-                            if let Some(snapshot) = snapshot_to_send {
-                                let snapshot_envelope = Envelope {
-                                    sequence_id: envelope.sequence_id.wrapping_add(1),
-                                    timestamp: SystemTime::now()
-                                        .duration_since(UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_millis()
-                                        as u64,
-                                    payload: Payload::WorldSnapshot(snapshot),
-                                };
+                if let Err(err) = sent {
+                    send_failure = Some(err);
+                    break;
+                }
+            }
+            Payload::SessionResumeRequest(resume) => {
+                let result = match state.session_tokens.verify(&resume.session_token) {
+                    Ok(claims) => state
+                        .session_store
+                        .resume_session(&claims, &session_id)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
 
-                                if !send_session_envelope(&state, &session_id, snapshot_envelope)
-                                    .await
-                                {
-                                    break;
-                                }
-                            }
-                        }
-                        Payload::HandshakeRequest(_) => {
-                            // Already handled handshake
+                let resume_response = match result {
+                    Ok(session) => {
+                        info!(
+                            "Session {} resumed by connection that opened as {}",
+                            session.id, session_id
+                        );
+                        session_id = session.id;
+
+                        network::messages::SessionResumeResponse {
+                            success: true,
+                            session_token: session.account_id.map(|account_id| {
+                                state.session_tokens.issue(session.id, account_id)
+                            }),
+                            message: "Session resumed".to_string(),
+                            player_id: session.player_id,
+                            character_id: session
+                                .character_id
+                                .and_then(|id| session.reverse_character_map.get(&id).copied()),
                         }
-                        _ => {
-                            info!("Received unhandled message type");
+                    }
+                    Err(reason) => {
+                        warn!("Session resume failed for {}: {}", session_id, reason);
+                        network::messages::SessionResumeResponse {
+                            success: false,
+                            session_token: None,
+                            message: reason,
+                            player_id: None,
+                            character_id: None,
                         }
                     }
-                } else {
-                    error!("Failed to parse message: {}", text);
+                };
+
+                let response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::SessionResumeResponse(resume_response),
+                };
+
+                if let Err(err) = send_session_envelope(&state, &session_id, response).await {
+                    send_failure = Some(err);
+                    break;
                 }
             }
-            Message::Close(_) => {
-                info!("WebSocket connection closed for session: {}", session_id);
-                break;
+            Payload::HandshakeRequest(handshake) => {
+                if handshake_complete {
+                    warn!("Session {} sent a second handshake", session_id);
+                    let error_response = Envelope {
+                        sequence_id: envelope.sequence_id,
+                        timestamp: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap()
+                            .as_millis() as u64,
+                        payload: Payload::Error(Error {
+                            code: ErrorCode::InvalidRequest,
+                            message: "Handshake already completed".to_string(),
+                            details: std::collections::HashMap::new(),
+                        }),
+                    };
+
+                    if let Err(err) =
+                        send_session_envelope(&state, &session_id, error_response).await
+                    {
+                        send_failure = Some(err);
+                        break;
+                    }
+                    continue;
+                }
+
+                if let Err(e) = network::handshake::validate(handshake) {
+                    warn!(
+                        "Rejecting handshake from session {} (client {}, protocol {}): {}",
+                        session_id, handshake.client_version, handshake.protocol_version, e
+                    );
+
+                    let timestamp = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64;
+                    let details = std::collections::HashMap::from([
+                        (
+                            "server_version".to_string(),
+                            network::handshake::SERVER_VERSION.to_string(),
+                        ),
+                        (
+                            "protocol_version".to_string(),
+                            network::handshake::PROTOCOL_VERSION.to_string(),
+                        ),
+                        (
+                            "min_protocol_version".to_string(),
+                            network::handshake::MIN_PROTOCOL_VERSION.to_string(),
+                        ),
+                        (
+                            "min_client_version".to_string(),
+                            network::handshake::MIN_CLIENT_VERSION.to_string(),
+                        ),
+                    ]);
+                    let error_response = Envelope {
+                        sequence_id: envelope.sequence_id,
+                        timestamp,
+                        payload: Payload::Error(Error {
+                            code: ErrorCode::ProtocolMismatch,
+                            message: e.to_string(),
+                            details,
+                        }),
+                    };
+                    let disconnect = Envelope {
+                        sequence_id: envelope.sequence_id,
+                        timestamp,
+                        payload: Payload::Disconnect(Disconnect {
+                            reason: DisconnectReason::ProtocolMismatch,
+                            message: "Please update your client".to_string(),
+                        }),
+                    };
+
+                    // Cleanup below flushes both before the socket closes
+                    let _ = send_session_envelope(&state, &session_id, error_response).await;
+                    let _ = send_session_envelope(&state, &session_id, disconnect).await;
+                    break;
+                }
+
+                let server_features = network::codec::negotiate_features(
+                    handshake.supported_features,
+                    state.wire_features,
+                );
+                info!(
+                    "Handshake from session {}: client {} protocol {}, features {:#x} -> {:#x}",
+                    session_id,
+                    handshake.client_version,
+                    handshake.protocol_version,
+                    handshake.supported_features,
+                    server_features
+                );
+
+                let handshake_response = Envelope {
+                    sequence_id: envelope.sequence_id,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                    payload: Payload::HandshakeResponse(HandshakeResponse {
+                        accepted: true,
+                        server_version: network::handshake::SERVER_VERSION.to_string(),
+                        protocol_version: network::handshake::PROTOCOL_VERSION.to_string(),
+                        server_features,
+                        message: "Welcome to OpenMMO!".to_string(),
+                    }),
+                };
+
+                if let Err(err) =
+                    send_session_envelope(&state, &session_id, handshake_response).await
+                {
+                    send_failure = Some(err);
+                    break;
+                }
+                handshake_complete = true;
+            }
+            _ => {
+                info!("Received unhandled message type");
             }
-            _ => {}
         }
     }
 
-    let session_snapshot = state.session_store.get_session(&session_id).await;
+    if let Some(OutboundError::Lagging) = send_failure {
+        warn!(
+            "Session {} is not keeping up with outbound traffic; disconnecting",
+            session_id
+        );
+        outgoing_tx.close_with(disconnect_envelope(
+            DisconnectReason::SlowConnection,
+            "Connection too slow to keep up".to_string(),
+        ));
+        // Resuming would only land the client back in the same backlog
+        logout_now = true;
+    }
 
-    state.session_store.set_sender(&session_id, None).await;
+    let owns_session = state
+        .session_store
+        .detach_sender(&session_id, &outgoing_tx)
+        .await;
     drop(outgoing_tx);
     info!("Waiting for send task to finish for {}", session_id);
+    let send_abort = send_task.abort_handle();
     match tokio::time::timeout(Duration::from_secs(2), send_task).await {
         Ok(join_res) => {
             if let Err(e) = join_res {
@@ -1068,118 +1748,177 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                 "Send task did not finish in time for {}, aborting",
                 session_id
             );
+            send_abort.abort();
         }
     }
 
-    info!("Beginning session cleanup for {}", session_id);
-
-    if let Some(session) = &session_snapshot {
+    if !owns_session {
         info!(
-            "Cleanup state for session {}: player_id {:?}, character_id {:?}",
-            session_id, session.player_id, session.character_id
+            "Session {} now belongs to another connection or was already closed",
+            session_id
         );
+        return;
+    }
 
-        if let (Some(player_id), Some(character_id)) = (session.player_id, session.character_id) {
-            let pose_and_name = {
-                let world = state.world_state.read().await;
-                (
-                    world.get_player_pose(player_id),
-                    world.get_player_name(player_id),
-                )
-            };
+    // A dropped (not closed) connection keeps its character in the world for a while
+    let in_world_player = state
+        .session_store
+        .get_session(&session_id)
+        .await
+        .filter(|session| session.character_id.is_some())
+        .and_then(|session| session.player_id);
+    if let Some(player_id) = in_world_player {
+        if !logout_now && !state.resume_grace.is_zero() {
+            state.session_store.mark_link_dead(&session_id).await;
+            state.world_state.write().await.halt_player(player_id);
+            info!(
+                "Session {} is link-dead; resumable for {}s",
+                session_id,
+                state.resume_grace.as_secs()
+            );
+            return;
+        }
+    }
 
-            if let Some((x, y, z, rot)) = pose_and_name.0 {
-                info!(
-                    "Persisting pose for session {} character {}: ({:.2}, {:.2}, {:.2}) rot {:.2}",
-                    session_id, character_id, x, y, z, rot
-                );
+    match state.session_store.remove_session(&session_id).await {
+        Some(session) => logout_session(&state, &session).await,
+        None => warn!(
+            "No session found during cleanup for {} (session already removed?)",
+            session_id
+        ),
+    }
+}
 
-                if let Err(e) = state
-                    .account_service
-                    .update_character_position(
-                        character_id,
-                        x as f64,
-                        y as f64,
-                        z as f64,
-                        rot as f64,
-                    )
-                    .await
-                {
-                    warn!(
-                        "Failed to persist character position for session {}: {:?}",
-                        session_id, e
-                    );
-                } else {
-                    info!(
-                        "Saved character {} position for session {}: ({:.2}, {:.2}, {:.2}) rot {:.2}",
-                        character_id, session_id, x, y, z, rot
-                    );
-                }
-            } else {
-                let diagnostics = {
-                    let world = state.world_state.read().await;
-                    let zone_id = world.get_player_zone_id(player_id);
-                    let has_entity = zone_id
-                        .and_then(|zid| {
-                            world
-                                .get_zone(zid)
-                                .and_then(|zone| zone.entities.get_entity(player_id))
-                        })
-                        .is_some();
-                    (zone_id, has_entity)
-                };
+/// Persist a departing player's character and remove it from the world.
+/// The session must already have been taken out of the session store.
+async fn logout_session(state: &AppState, session: &network::Session) {
+    let session_id = session.id;
+    info!("Beginning session cleanup for {}", session_id);
+    info!(
+        "Cleanup state for session {}: player_id {:?}, character_id {:?}",
+        session_id, session.player_id, session.character_id
+    );
 
-                warn!(
-                    "No pose available to save for session {} (player_id {:?}), zone {:?}, entity_exists {}",
-                    session_id, session.player_id, diagnostics.0, diagnostics.1
-                );
-            }
+    if let (Some(player_id), Some(character_id)) = (session.player_id, session.character_id) {
+        let pose_and_name = {
+            let world = state.world_state.read().await;
+            (
+                world.get_player_pose(player_id),
+                world.get_player_name(player_id),
+                world.get_player_resource(player_id),
+                world.get_player_progression(player_id),
+            )
+        };
+
+        if let Some((x, y, z, rot)) = pose_and_name.0 {
+            info!(
+                "Persisting pose for session {} character {}: ({:.2}, {:.2}, {:.2}) rot {:.2}",
+                session_id, character_id, x, y, z, rot
+            );
 
             if let Err(e) = state
                 .account_service
-                .set_character_online(character_id, false)
+                .update_character_position(character_id, x as f64, y as f64, z as f64, rot as f64)
                 .await
             {
                 warn!(
-                    "Failed to mark character offline for session {}: {:?}",
+                    "Failed to persist character position for session {}: {:?}",
                     session_id, e
                 );
-            }
-
-            let mut world = state.world_state.write().await;
-            world.remove_player(player_id);
-            // Also clear any duplicate stale entries by name
-            if let Some(name) = pose_and_name.1 {
-                world.remove_player_by_name(&name);
+            } else {
+                info!(
+                    "Saved character {} position for session {}: ({:.2}, {:.2}, {:.2}) rot {:.2}",
+                    character_id, session_id, x, y, z, rot
+                );
             }
         } else {
+            let diagnostics = {
+                let world = state.world_state.read().await;
+                let zone_id = world.get_player_zone_id(player_id);
+                let has_entity = zone_id
+                    .and_then(|zid| {
+                        world
+                            .get_zone(zid)
+                            .and_then(|zone| zone.entities.get_entity(player_id))
+                    })
+                    .is_some();
+                (zone_id, has_entity)
+            };
+
+            warn!(
+                "No pose available to save for session {} (player_id {:?}), zone {:?}, entity_exists {}",
+                session_id, session.player_id, diagnostics.0, diagnostics.1
+            );
+        }
+
+        if let Some(resource) = pose_and_name.2 {
+            persist_resource(state, character_id, resource).await;
+        }
+        if let Some(progression) = &pose_and_name.3 {
+            persist_progression(state, character_id, progression).await;
+        }
+
+        if let Err(e) = state
+            .account_service
+            .set_character_online(character_id, false)
+            .await
+        {
             warn!(
-                "Session {} missing player_id or character_id during cleanup (player_id {:?}, character_id {:?})",
-                session_id, session.player_id, session.character_id
+                "Failed to mark character offline for session {}: {:?}",
+                session_id, e
             );
         }
+
+        let mut world = state.world_state.write().await;
+        world.remove_player(player_id);
+        // Also clear any duplicate stale entries by name
+        if let Some(name) = pose_and_name.1 {
+            world.remove_player_by_name(&name);
+        }
     } else {
         warn!(
-            "No session snapshot found during cleanup for {} (session already removed?)",
-            session_id
+            "Session {} missing player_id or character_id during cleanup (player_id {:?}, character_id {:?})",
+            session_id, session.player_id, session.character_id
         );
     }
 
-    state.session_store.remove_session(&session_id).await;
     info!("Session cleaned up: {}", session_id);
 }
 
-async fn send_session_envelope(state: &AppState, session_id: &Uuid, envelope: Envelope) -> bool {
-    match state
+async fn send_session_envelope(
+    state: &AppState,
+    session_id: &Uuid,
+    envelope: Envelope,
+) -> Result<(), OutboundError> {
+    let result = state
         .session_store
         .send_envelope(session_id, envelope)
+        .await;
+    if let Err(err) = &result {
+        error!("Failed to send envelope to {}: {:?}", session_id, err);
+    }
+    result
+}
+
+/// The player a session has in the world, if any
+async fn session_player_id(state: &AppState, session_id: &Uuid) -> Option<entities::EntityId> {
+    state
+        .session_store
+        .get_session(session_id)
         .await
-    {
-        Ok(_) => true,
-        Err(err) => {
-            error!("Failed to send envelope to {}: {:?}", session_id, err);
-            false
-        }
+        .and_then(|session| session.player_id)
+}
+
+/// Tell the player why a party request failed
+fn refuse_party_request(
+    world: &mut world::WorldState,
+    player_id: entities::EntityId,
+    request: &str,
+    result: Result<(), party::PartyError>,
+) {
+    if let Err(e) = result {
+        warn!("{} refused for player {}: {}", request, player_id, e);
+        simulation::PartySystem::refuse(world, player_id, &e);
     }
 }
 
@@ -0,0 +1,84 @@
+//! Wire encoding for envelopes
+//!
+//! Every connection starts out speaking JSON text frames. If both sides
+//! advertise [`FEATURE_BINARY_PROTOBUF`] during the handshake, frames sent after
+//! the `HandshakeResponse` switch to binary Protobuf. Inbound frames are decoded
+//! by frame type, so a client may always fall back to JSON text for debugging.
+
+use crate::network::messages::{Envelope, Payload};
+use crate::network::proto::{self, ProtoConversionError};
+use axum::extract::ws::Message;
+use prost::Message as _;
+
+/// Binary Protobuf frames after the handshake
+pub const FEATURE_BINARY_PROTOBUF: u32 = 1 << 0;
+
+/// Every feature bit this server build understands
+pub const SUPPORTED_FEATURES: u32 = FEATURE_BINARY_PROTOBUF;
+
+/// Encoding used for frames on a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    Json,
+    Protobuf,
+}
+
+impl WireFormat {
+    /// Pick the encoding implied by a negotiated feature set
+    pub fn from_features(features: u32) -> Self {
+        if features & FEATURE_BINARY_PROTOBUF != 0 {
+            WireFormat::Protobuf
+        } else {
+            WireFormat::Json
+        }
+    }
+
+    /// Format that applies to frames sent after this envelope
+    pub fn after_send(self, envelope: &Envelope) -> Self {
+        match &envelope.payload {
+            Payload::HandshakeResponse(response) if response.accepted => {
+                Self::from_features(response.server_features)
+            }
+            _ => self,
+        }
+    }
+}
+
+/// Codec errors
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError {
+    #[error("invalid JSON envelope: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid Protobuf envelope: {0}")]
+    Protobuf(#[from] prost::DecodeError),
+
+    #[error("unsupported Protobuf envelope: {0}")]
+    Conversion(#[from] ProtoConversionError),
+}
+
+/// Intersect the client's advertised features with what the server enables
+pub fn negotiate_features(client_features: u32, server_features: u32) -> u32 {
+    client_features & server_features & SUPPORTED_FEATURES
+}
+
+/// Encode an envelope as a WebSocket frame in the given format
+pub fn encode(envelope: &Envelope, format: WireFormat) -> Result<Message, CodecError> {
+    match format {
+        WireFormat::Json => Ok(Message::Text(serde_json::to_string(envelope)?)),
+        WireFormat::Protobuf => Ok(Message::Binary(
+            proto::Envelope::from(envelope).encode_to_vec(),
+        )),
+    }
+}
+
+/// Decode a JSON text frame
+pub fn decode_json(text: &str) -> Result<Envelope, CodecError> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Decode a binary Protobuf frame
+pub fn decode_protobuf(bytes: &[u8]) -> Result<Envelope, CodecError> {
+    let envelope = proto::Envelope::decode(bytes)?;
+    Ok(Envelope::try_from(envelope)?)
+}
@@ -0,0 +1,95 @@
+//! Protocol version negotiation
+//!
+//! A connection must open with a `HandshakeRequest`. The client's protocol
+//! version has to fall inside the range this server speaks, and its build must
+//! be at least [`MIN_CLIENT_VERSION`]; otherwise the connection is refused with
+//! `ErrorCode::ProtocolMismatch` followed by a `Disconnect`.
+
+use crate::network::messages::HandshakeRequest;
+use std::cmp::Ordering;
+
+/// Server build reported in handshake responses
+pub const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Protocol version this server speaks (newest accepted)
+pub const PROTOCOL_VERSION: &str = "1.0";
+
+/// Oldest protocol version still accepted
+pub const MIN_PROTOCOL_VERSION: &str = "1.0";
+
+/// Oldest client build allowed to connect
+pub const MIN_CLIENT_VERSION: &str = "0.1.0";
+
+/// Reasons a handshake is refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HandshakeError {
+    #[error("malformed {field} '{value}'")]
+    MalformedVersion { field: &'static str, value: String },
+
+    #[error(
+        "protocol version {client} is not supported (server accepts {} to {})",
+        MIN_PROTOCOL_VERSION,
+        PROTOCOL_VERSION
+    )]
+    UnsupportedProtocol { client: String },
+
+    #[error("client version {client} is too old (minimum {})", MIN_CLIENT_VERSION)]
+    ClientTooOld { client: String },
+}
+
+/// Check a client's handshake against the supported version range
+pub fn validate(request: &HandshakeRequest) -> Result<(), HandshakeError> {
+    let protocol = parse_version(&request.protocol_version).ok_or_else(|| {
+        HandshakeError::MalformedVersion {
+            field: "protocol_version",
+            value: request.protocol_version.clone(),
+        }
+    })?;
+    let client =
+        parse_version(&request.client_version).ok_or_else(|| HandshakeError::MalformedVersion {
+            field: "client_version",
+            value: request.client_version.clone(),
+        })?;
+
+    let min_protocol = parse_version(MIN_PROTOCOL_VERSION).expect("valid MIN_PROTOCOL_VERSION");
+    let max_protocol = parse_version(PROTOCOL_VERSION).expect("valid PROTOCOL_VERSION");
+    if compare_versions(&protocol, &min_protocol) == Ordering::Less
+        || compare_versions(&protocol, &max_protocol) == Ordering::Greater
+    {
+        return Err(HandshakeError::UnsupportedProtocol {
+            client: request.protocol_version.clone(),
+        });
+    }
+
+    let min_client = parse_version(MIN_CLIENT_VERSION).expect("valid MIN_CLIENT_VERSION");
+    if compare_versions(&client, &min_client) == Ordering::Less {
+        return Err(HandshakeError::ClientTooOld {
+            client: request.client_version.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Parse a dotted numeric version such as "1.0" or "0.1.0"
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    let version = version.trim();
+    if version.is_empty() {
+        return None;
+    }
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Compare versions component-wise, treating missing components as zero
+fn compare_versions(a: &[u32], b: &[u32]) -> Ordering {
+    let len = a.len().max(b.len());
+    for i in 0..len {
+        let left = a.get(i).copied().unwrap_or(0);
+        let right = b.get(i).copied().unwrap_or(0);
+        match left.cmp(&right) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    Ordering::Equal
+}
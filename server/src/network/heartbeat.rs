@@ -0,0 +1,80 @@
+//! Server-driven heartbeats
+//!
+//! Every `interval` the server pings each handshaken connection. Any inbound
+//! frame, including the client's `Pong`, counts as activity; a connection
+//! silent for longer than `timeout` is disconnected with
+//! `DisconnectReason::Timeout` and logged out.
+
+use std::time::{Duration, Instant};
+
+/// Heartbeat tuning
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    /// How often the server pings a connected client
+    pub interval: Duration,
+    /// Silence after which a client is considered gone
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    /// Read `HEARTBEAT_INTERVAL_SECS` / `IDLE_TIMEOUT_SECS`, falling back to defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let interval = std::env::var("HEARTBEAT_INTERVAL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|secs: &f64| *secs > 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .unwrap_or(defaults.interval);
+        let timeout = std::env::var("IDLE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|secs: &f64| *secs > 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .unwrap_or(defaults.timeout);
+
+        Self {
+            interval,
+            // A timeout shorter than the ping interval would drop healthy clients
+            timeout: timeout.max(interval),
+        }
+    }
+}
+
+/// Last-seen bookkeeping for one connection
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    timeout: Duration,
+    last_activity: Instant,
+}
+
+impl Liveness {
+    pub fn new(config: &HeartbeatConfig) -> Self {
+        Self {
+            timeout: config.timeout,
+            last_activity: Instant::now(),
+        }
+    }
+
+    /// Note that the client sent something
+    pub fn record_activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn is_timed_out(&self) -> bool {
+        self.is_timed_out_at(Instant::now())
+    }
+
+    pub(crate) fn is_timed_out_at(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_activity) > self.timeout
+    }
+}
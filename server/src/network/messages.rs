@@ -1,6 +1,8 @@
 // Manual implementation of Protobuf message types
 // This will be replaced by generated code once protoc is available
 
+use crate::items::ItemRarity;
+use crate::loot::{LootMethod, RollChoice};
 use serde::{Deserialize, Serialize};
 
 /// Message envelope for all communications
@@ -42,6 +44,20 @@ pub enum Payload {
     EquipmentResponse(EquipmentResponse),
     ItemEquipRequest(ItemEquipRequest),
     ItemEquipResponse(ItemEquipResponse),
+    EntityUpdateBatch(EntityUpdateBatch),
+    SessionResumeRequest(SessionResumeRequest),
+    SessionResumeResponse(SessionResumeResponse),
+    SnapshotAck(SnapshotAck),
+    ReleaseRequest(ReleaseRequest),
+    LootOpenRequest(LootOpenRequest),
+    LootTakeRequest(LootTakeRequest),
+    LootResponse(LootResponse),
+    LootRollRequest(LootRollRequest),
+    ShutdownNotice(ShutdownNotice),
+    PartyInviteRequest(PartyInviteRequest),
+    PartyInviteReply(PartyInviteReply),
+    PartyLeaveRequest(PartyLeaveRequest),
+    PartyLootRulesRequest(PartyLootRulesRequest),
 }
 
 /// Handshake messages
@@ -78,6 +94,21 @@ pub struct AuthResponse {
     pub character_id: Option<u64>,
 }
 
+/// Reclaim a link-dead session after reconnecting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResumeRequest {
+    pub session_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResumeResponse {
+    pub success: bool,
+    pub session_token: Option<String>, // Fresh token on success
+    pub message: String,
+    pub player_id: Option<u64>,
+    pub character_id: Option<u64>,
+}
+
 /// Ping/Pong for connection health
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ping {
@@ -116,6 +147,13 @@ pub struct Disconnect {
     pub message: String,
 }
 
+/// Warning that the server is about to shut down; the connection stays open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownNotice {
+    pub seconds_remaining: u32,
+    pub message: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DisconnectReason {
     Unknown = 0,
@@ -124,6 +162,8 @@ pub enum DisconnectReason {
     Timeout = 3,
     Kicked = 4,
     Banned = 5,
+    ProtocolMismatch = 6,
+    SlowConnection = 7,
 }
 
 /// Basic world snapshot for initial state sync
@@ -133,10 +173,13 @@ pub struct WorldSnapshot {
     pub entities: Vec<Entity>,
     pub player_entity_id: u64,
     pub zone_name: String,
+    /// Simulation tick the state was taken on
+    #[serde(default)]
+    pub server_tick: u64,
 }
 
 /// Basic entity representation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Entity {
     pub id: u64,
     pub entity_type: String,
@@ -146,7 +189,7 @@ pub struct Entity {
 }
 
 /// 3D vector for positions/rotations
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
@@ -154,14 +197,25 @@ pub struct Vector3 {
 }
 
 /// Entity state information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EntityState {
     pub movement_state: MovementState,
     pub health_percent: f32,
     pub display_name: String,
+    /// Class resource, if the entity has one
+    #[serde(default)]
+    pub resource: Option<ResourceState>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Current and maximum class resource (mana, rage or energy)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResourceState {
+    pub resource_type: String,
+    pub current: u32,
+    pub maximum: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum MovementState {
     Idle = 0,
     Walking = 1,
@@ -184,6 +238,9 @@ pub struct CombatAction {
     pub action_type: ActionType,
     pub target_entity_id: u64,
     pub ability_id: u32,
+    /// Ground point for abilities aimed at an area rather than an entity
+    #[serde(default)]
+    pub target_position: Option<Vector3>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -202,13 +259,238 @@ pub struct EntityUpdate {
     pub effects: Vec<EntityEffect>,
 }
 
+/// Changes to the entities a client can see, relative to the snapshot
+/// `baseline_id`. Applying it to that baseline yields snapshot `snapshot_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityUpdateBatch {
+    pub snapshot_id: u64,
+    pub baseline_id: u64,
+    pub spawned: Vec<Entity>,
+    pub updated: Vec<EntityUpdate>,
+    pub despawned: Vec<u64>,
+    /// Simulation tick the state was taken on
+    #[serde(default)]
+    pub server_tick: u64,
+}
+
+/// Client confirmation that it holds the state of a snapshot or batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotAck {
+    pub snapshot_id: u64,
+}
+
+/// A dead player asks to come back at the zone's graveyard
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReleaseRequest {}
+
+/// Open a corpse: picks up its gold and lists the items left on it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootOpenRequest {
+    pub corpse_id: u64,
+}
+
+/// Take the item in one loot slot of a corpse into the inventory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootTakeRequest {
+    pub corpse_id: u64,
+    pub slot: u32,
+}
+
+/// What is left on a corpse after an open or take request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootResponse {
+    pub corpse_id: u64,
+    pub success: bool,
+    pub error_message: Option<String>,
+    pub gold: u32, // Gold picked up by this request
+    pub items: Vec<LootSlot>,
+}
+
+/// Need, greed or pass on an item up for a loot roll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootRollRequest {
+    pub corpse_id: u64,
+    pub slot: u32,
+    pub choice: RollChoice,
+}
+
+/// An item on a corpse and the loot slot to take it from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootSlot {
+    pub slot: u32,
+    pub item: ItemInstance,
+}
+
+/// Invite a player into your party, founding one if you are not in a party
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyInviteRequest {
+    pub player_id: u64,
+}
+
+/// Accept or decline the party invitation you were last sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyInviteReply {
+    pub accept: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PartyLeaveRequest {}
+
+/// Change how your party shares loot; only its leader may
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyLootRulesRequest {
+    pub method: LootMethod,
+    pub threshold: ItemRarity, // Lowest rarity rolled for under need before greed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PartyMember {
+    pub player_id: u64,
+    pub name: String,
+}
+
 /// Visual effects for entity updates
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EntityEffect {
-    DamageNumber { amount: u32, is_critical: bool },
-    StatusEffect { effect_type: String, duration: f32 },
+    /// Damage or healing that landed on the entity, or an attack that missed it.
+    /// `ability_id` is 0 for auto-attacks and periodic effects.
+    DamageNumber {
+        amount: u32,
+        is_critical: bool,
+        #[serde(default)]
+        source_id: u64,
+        #[serde(default)]
+        ability_id: u32,
+        #[serde(default)]
+        kind: CombatEventKind,
+        #[serde(default)]
+        killing_blow: bool,
+    },
+    /// A status effect was applied to, refreshed on or expired from the entity.
+    /// `effect_type` is the effect's display name and `duration` the seconds left.
+    StatusEffect {
+        effect_type: String,
+        duration: f32,
+        #[serde(default)]
+        effect_id: u32,
+        #[serde(default)]
+        stacks: u32,
+        #[serde(default)]
+        change: StatusEffectChange,
+    },
     Death,
     Respawn,
+    /// The entity's own combat action was refused; only its player is told
+    ActionRejected {
+        ability_id: u32,
+        target_id: u64,
+        reason: String,
+    },
+    /// The entity started, progressed, finished or lost a cast or channel.
+    /// `duration` is the full cast or channel time, `elapsed` the seconds done so
+    /// far and `reason` why an interrupted cast stopped.
+    Cast {
+        ability_id: u32,
+        target_id: u64,
+        phase: CastPhase,
+        duration: f32,
+        elapsed: f32,
+        #[serde(default)]
+        channel: bool,
+        #[serde(default)]
+        reason: String,
+    },
+    /// The entity's player earned experience from killing `source_id`; only
+    /// its player is told. `experience` is the progress into `level`.
+    Experience {
+        amount: u64,
+        source_id: u64,
+        level: u32,
+        experience: u64,
+        experience_to_next: u64,
+    },
+    /// The entity reached a new level
+    LevelUp {
+        level: u32,
+    },
+    /// An item on a corpse is up for a need/greed/pass roll lasting `duration`
+    /// seconds; only the entity's player is told
+    LootRoll {
+        corpse_id: u64,
+        slot: u32,
+        item_id: u32,
+        duration: f32,
+    },
+    /// A loot roll the entity's player took part in is over; `winner_id` is 0
+    /// when everyone passed
+    LootRollResult {
+        corpse_id: u64,
+        slot: u32,
+        item_id: u32,
+        winner_id: u64,
+        choice: RollChoice,
+        roll: u32,
+    },
+    PartyInvite {
+        inviter_id: u64,
+        inviter_name: String,
+    },
+    /// The player's party as it now stands; party_id 0 when they are in none
+    PartyChanged {
+        party_id: u64,
+        leader_id: u64,
+        members: Vec<PartyMember>,
+        method: LootMethod,
+        threshold: ItemRarity,
+    },
+    PartyRefused {
+        reason: String,
+    },
+}
+
+impl EntityEffect {
+    /// Events meant only for the player controlling the entity
+    pub fn is_private(&self) -> bool {
+        matches!(
+            self,
+            EntityEffect::ActionRejected { .. }
+                | EntityEffect::Experience { .. }
+                | EntityEffect::LootRoll { .. }
+                | EntityEffect::LootRollResult { .. }
+                | EntityEffect::PartyInvite { .. }
+                | EntityEffect::PartyChanged { .. }
+                | EntityEffect::PartyRefused { .. }
+        )
+    }
+}
+
+/// What a `DamageNumber` reports
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum CombatEventKind {
+    #[default]
+    Damage = 0,
+    Heal = 1,
+    Miss = 2,
+    Dodge = 3,
+    Parry = 4,
+}
+
+/// Stage of a cast reported by `EntityEffect::Cast`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum CastPhase {
+    #[default]
+    Started = 0,
+    Progress = 1,
+    Finished = 2,
+    Interrupted = 3,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum StatusEffectChange {
+    #[default]
+    Applied = 0,
+    Refreshed = 1,
+    Expired = 2,
 }
 
 /// Character management messages
@@ -319,6 +601,20 @@ pub struct ItemDurability {
     pub maximum: u32,
 }
 
+impl From<&crate::items::ItemInstance> for ItemInstance {
+    fn from(item: &crate::items::ItemInstance) -> Self {
+        Self {
+            definition_id: item.definition_id,
+            quantity: item.quantity,
+            is_bound: item.is_bound,
+            durability: item.durability.as_ref().map(|d| ItemDurability {
+                current: d.current,
+                maximum: d.maximum,
+            }),
+        }
+    }
+}
+
 /// Move item between inventory slots
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemMoveRequest {
@@ -1,9 +1,21 @@
+pub mod codec;
+pub mod handshake;
+pub mod heartbeat;
 pub mod messages;
+pub mod outbound;
+pub mod proto;
+pub mod rate_limit;
+pub mod session_token;
+pub mod shutdown;
+
+#[cfg(test)]
+mod tests;
 
 use crate::network::messages::Envelope;
+use crate::network::outbound::{OutboundError, OutboundSender};
+use crate::network::session_token::ResumeClaims;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::mpsc::{error::SendError, UnboundedSender};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -21,7 +33,24 @@ pub struct Session {
     pub character_id_map: HashMap<u64, Uuid>,
     pub reverse_character_map: HashMap<Uuid, u64>,
     pub next_character_numeric_id: u64,
-    pub sender: Option<UnboundedSender<Envelope>>,
+    pub sender: Option<OutboundSender>,
+    /// Set while the connection is gone but the character is kept in the world
+    pub link_dead_since: Option<std::time::Instant>,
+    /// Newest replication snapshot the client has acknowledged
+    pub acked_snapshot_id: Option<u64>,
+}
+
+/// Reasons a session resume is refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ResumeError {
+    #[error("no resumable session for this token")]
+    NotFound,
+
+    #[error("session is still connected")]
+    StillConnected,
+
+    #[error("this connection already has a character in the world")]
+    AlreadyInWorld,
 }
 
 /// Movement intent from a client
@@ -62,6 +91,8 @@ impl SessionStore {
             reverse_character_map: HashMap::new(),
             next_character_numeric_id: 1,
             sender: None,
+            link_dead_since: None,
+            acked_snapshot_id: None,
         };
 
         let mut sessions = self.sessions.write().await;
@@ -79,9 +110,9 @@ impl SessionStore {
         sessions.insert(session.id, session);
     }
 
-    pub async fn remove_session(&self, session_id: &Uuid) {
+    pub async fn remove_session(&self, session_id: &Uuid) -> Option<Session> {
         let mut sessions = self.sessions.write().await;
-        sessions.remove(session_id);
+        sessions.remove(session_id)
     }
 
     pub async fn authenticate_session(
@@ -100,14 +131,128 @@ impl SessionStore {
         }
     }
 
-    pub async fn set_sender(&self, session_id: &Uuid, sender: Option<UnboundedSender<Envelope>>) {
+    pub async fn set_sender(&self, session_id: &Uuid, sender: Option<OutboundSender>) {
         let mut sessions = self.sessions.write().await;
         if let Some(session) = sessions.get_mut(session_id) {
             session.sender = sender;
         }
     }
 
-    pub async fn get_sender(&self, session_id: &Uuid) -> Option<UnboundedSender<Envelope>> {
+    /// Clear the session's sender if it still belongs to this connection.
+    /// Returns false when another connection has taken the session over.
+    pub async fn detach_sender(&self, session_id: &Uuid, sender: &OutboundSender) -> bool {
+        let mut sessions = self.sessions.write().await;
+        match sessions.get_mut(session_id) {
+            Some(session)
+                if session
+                    .sender
+                    .as_ref()
+                    .is_some_and(|current| current.same_channel(sender)) =>
+            {
+                session.sender = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record a client's snapshot acknowledgement; stale acks are ignored
+    pub async fn acknowledge_snapshot(&self, session_id: &Uuid, snapshot_id: u64) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            if session
+                .acked_snapshot_id
+                .map_or(true, |acked| snapshot_id > acked)
+            {
+                session.acked_snapshot_id = Some(snapshot_id);
+            }
+        }
+    }
+
+    /// Keep a disconnected session around so it can be resumed
+    pub async fn mark_link_dead(&self, session_id: &Uuid) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.link_dead_since = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Hand a link-dead session over to the connection that owns `new_session_id`.
+    /// The new connection's own session is discarded and its sender moves across.
+    pub async fn resume_session(
+        &self,
+        claims: &ResumeClaims,
+        new_session_id: &Uuid,
+    ) -> Result<Session, ResumeError> {
+        let mut sessions = self.sessions.write().await;
+
+        let target = sessions
+            .get(&claims.session_id)
+            .filter(|session| session.account_id == Some(claims.account_id))
+            .ok_or(ResumeError::NotFound)?;
+        if target.link_dead_since.is_none() {
+            return Err(ResumeError::StillConnected);
+        }
+        // Its character would be orphaned with no session left to log it out
+        if sessions
+            .get(new_session_id)
+            .is_some_and(|session| session.character_id.is_some())
+        {
+            return Err(ResumeError::AlreadyInWorld);
+        }
+
+        let sender = sessions
+            .remove(new_session_id)
+            .and_then(|session| session.sender);
+        let target = sessions
+            .get_mut(&claims.session_id)
+            .ok_or(ResumeError::NotFound)?;
+        target.sender = sender;
+        target.link_dead_since = None;
+        target.connected_at = std::time::Instant::now();
+        // Replication starts the resumed connection over, snapshot ids included
+        target.acked_snapshot_id = None;
+        Ok(target.clone())
+    }
+
+    /// Remove and return every session, connected or link-dead
+    pub async fn take_all_sessions(&self) -> Vec<Session> {
+        let mut sessions = self.sessions.write().await;
+        sessions.drain().map(|(_, session)| session).collect()
+    }
+
+    /// Remove and return link-dead sessions whose grace window has run out
+    pub async fn take_expired_link_dead(&self, grace: std::time::Duration) -> Vec<Session> {
+        let mut sessions = self.sessions.write().await;
+        let expired: Vec<Uuid> = sessions
+            .values()
+            .filter(|session| {
+                session
+                    .link_dead_since
+                    .is_some_and(|since| since.elapsed() >= grace)
+            })
+            .map(|session| session.id)
+            .collect();
+
+        expired
+            .iter()
+            .filter_map(|session_id| sessions.remove(session_id))
+            .collect()
+    }
+
+    /// Remove and return a link-dead session holding this character, if any
+    pub async fn take_link_dead_for_character(&self, character_id: Uuid) -> Option<Session> {
+        let mut sessions = self.sessions.write().await;
+        let session_id = sessions
+            .values()
+            .find(|session| {
+                session.link_dead_since.is_some() && session.character_id == Some(character_id)
+            })
+            .map(|session| session.id)?;
+        sessions.remove(&session_id)
+    }
+
+    pub async fn get_sender(&self, session_id: &Uuid) -> Option<OutboundSender> {
         let sessions = self.sessions.read().await;
         sessions
             .get(session_id)
@@ -118,11 +263,10 @@ impl SessionStore {
         &self,
         session_id: &Uuid,
         envelope: Envelope,
-    ) -> Result<(), SendError<Envelope>> {
-        if let Some(sender) = self.get_sender(session_id).await {
-            sender.send(envelope)
-        } else {
-            Err(SendError(envelope))
+    ) -> Result<(), OutboundError> {
+        match self.get_sender(session_id).await {
+            Some(sender) => sender.send(envelope),
+            None => Err(OutboundError::Closed),
         }
     }
 
@@ -169,6 +313,16 @@ impl SessionStore {
             .cloned()
             .collect()
     }
+
+    /// Sessions with a character in the world, connected or link-dead
+    pub async fn get_sessions_in_world(&self) -> Vec<Session> {
+        let sessions = self.sessions.read().await;
+        sessions
+            .values()
+            .filter(|s| s.player_id.is_some() && s.character_id.is_some())
+            .cloned()
+            .collect()
+    }
 }
 
 impl Session {
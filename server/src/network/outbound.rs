@@ -0,0 +1,359 @@
+//! Bounded per-session outbound queues
+//!
+//! Replication state (`WorldSnapshot` / `EntityUpdateBatch`) is never queued
+//! more than once: a newer state message replaces or is folded into the one
+//! still waiting, so a slow reader gets the latest world rather than a backlog
+//! of stale frames. Events carried in a superseded batch are moved into its
+//! replacement. Everything else — responses, errors, chat — is reliable and kept
+//! in order. A client whose queue grows past `max_queued` entries or whose
+//! oldest undelivered message is older than `max_lag` is flagged as lagging so
+//! its connection can be dropped.
+
+use crate::network::messages::{
+    Entity, EntityUpdate, EntityUpdateBatch, Envelope, Payload, WorldSnapshot,
+};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Limits for one session's outbound queue
+#[derive(Debug, Clone)]
+pub struct OutboundConfig {
+    /// Undelivered messages allowed before the client counts as lagging
+    pub max_queued: usize,
+    /// How long the oldest undelivered message may wait
+    pub max_lag: Duration,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        Self {
+            max_queued: 256,
+            max_lag: Duration::from_secs(5),
+        }
+    }
+}
+
+impl OutboundConfig {
+    /// Read `OUTBOUND_QUEUE_LIMIT` / `OUTBOUND_MAX_LAG_SECS`, falling back to defaults
+    pub fn from_env() -> Self {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    /// Build the config from `lookup`; limits that are not positive fall back to defaults
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let max_queued = lookup("OUTBOUND_QUEUE_LIMIT")
+            .and_then(|value| value.parse().ok())
+            .filter(|limit: &usize| *limit > 0)
+            .unwrap_or(defaults.max_queued);
+        let max_lag = lookup("OUTBOUND_MAX_LAG_SECS")
+            .and_then(|value| value.parse().ok())
+            .filter(|secs: &f64| *secs > 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .unwrap_or(defaults.max_lag);
+
+        Self {
+            max_queued,
+            max_lag,
+        }
+    }
+}
+
+/// Why a message could not be queued
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OutboundError {
+    #[error("outbound queue is closed")]
+    Closed,
+
+    #[error("client is too far behind")]
+    Lagging,
+}
+
+#[derive(Debug)]
+struct Queue {
+    /// Undelivered envelopes with the time they (or the state they replaced) were queued
+    entries: VecDeque<(Instant, Envelope)>,
+    senders: usize,
+    closed: bool,
+    lagging: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    config: OutboundConfig,
+    queue: Mutex<Queue>,
+    /// Wakes the receiver when an entry arrives or the queue closes
+    available: Notify,
+    /// Wakes `lagged` waiters
+    lag: Notify,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Create a bounded outbound queue
+pub fn channel(config: OutboundConfig) -> (OutboundSender, OutboundReceiver) {
+    let shared = Arc::new(Shared {
+        config,
+        queue: Mutex::new(Queue {
+            entries: VecDeque::new(),
+            senders: 1,
+            closed: false,
+            lagging: false,
+        }),
+        available: Notify::new(),
+        lag: Notify::new(),
+    });
+
+    (
+        OutboundSender {
+            shared: shared.clone(),
+        },
+        OutboundReceiver { shared },
+    )
+}
+
+/// Producer half of a session's outbound queue
+#[derive(Debug)]
+pub struct OutboundSender {
+    shared: Arc<Shared>,
+}
+
+impl OutboundSender {
+    /// Queue an envelope, merging replication state into any still pending
+    pub fn send(&self, envelope: Envelope) -> Result<(), OutboundError> {
+        let mut queue = self.shared.lock();
+        if queue.closed {
+            return Err(OutboundError::Closed);
+        }
+        if queue.lagging {
+            return Err(OutboundError::Lagging);
+        }
+
+        let now = Instant::now();
+        let pending_state = if is_state(&envelope.payload) {
+            queue
+                .entries
+                .iter()
+                .position(|(_, queued)| is_state(&queued.payload))
+        } else {
+            None
+        };
+        let pending = pending_state.and_then(|index| Some((index, queue.entries.remove(index)?)));
+        match pending {
+            // Keep the stale entry's place and timestamp: the client has been behind since then
+            Some((index, (queued_at, stale))) => queue
+                .entries
+                .insert(index, (queued_at, merge(stale, envelope))),
+            None => queue.entries.push_back((now, envelope)),
+        }
+
+        let oldest = queue.entries.front().map(|(queued_at, _)| *queued_at);
+        let lagging = queue.entries.len() > self.shared.config.max_queued
+            || oldest.is_some_and(|queued_at| {
+                now.duration_since(queued_at) > self.shared.config.max_lag
+            });
+        drop(queue);
+
+        self.shared.available.notify_one();
+        if lagging {
+            self.shared.lock().lagging = true;
+            self.shared.lag.notify_waiters();
+            return Err(OutboundError::Lagging);
+        }
+        Ok(())
+    }
+
+    /// Discard everything undelivered, queue a final envelope and refuse further sends
+    pub fn close_with(&self, envelope: Envelope) {
+        let mut queue = self.shared.lock();
+        queue.entries.clear();
+        queue.entries.push_back((Instant::now(), envelope));
+        queue.closed = true;
+        drop(queue);
+        self.shared.available.notify_one();
+    }
+
+    /// Resolves once the client has fallen too far behind
+    pub async fn lagged(&self) {
+        loop {
+            let notified = self.shared.lag.notified();
+            if self.shared.lock().lagging {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn is_lagging(&self) -> bool {
+        self.shared.lock().lagging
+    }
+
+    pub fn same_channel(&self, other: &OutboundSender) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+impl Clone for OutboundSender {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for OutboundSender {
+    fn drop(&mut self) {
+        let mut queue = self.shared.lock();
+        queue.senders -= 1;
+        let last = queue.senders == 0;
+        drop(queue);
+        if last {
+            self.shared.available.notify_one();
+        }
+    }
+}
+
+/// Consumer half, drained by the connection's writer task
+#[derive(Debug)]
+pub struct OutboundReceiver {
+    shared: Arc<Shared>,
+}
+
+impl OutboundReceiver {
+    /// Next envelope to write, or `None` once the queue is closed or every
+    /// sender is gone and nothing is left to deliver
+    pub async fn recv(&mut self) -> Option<Envelope> {
+        loop {
+            {
+                let mut queue = self.shared.lock();
+                if let Some((_, envelope)) = queue.entries.pop_front() {
+                    return Some(envelope);
+                }
+                if queue.closed || queue.senders == 0 {
+                    return None;
+                }
+            }
+            self.shared.available.notified().await;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.shared.lock().entries.len()
+    }
+}
+
+/// Replication state, where only the newest view of the world matters
+fn is_state(payload: &Payload) -> bool {
+    matches!(
+        payload,
+        Payload::WorldSnapshot(_) | Payload::EntityUpdateBatch(_)
+    )
+}
+
+/// Fold a newer state envelope into one the client has not received yet
+fn merge(stale: Envelope, newer: Envelope) -> Envelope {
+    let payload = match (stale.payload, newer.payload) {
+        // The batch is a delta against the snapshot still waiting to go out
+        (Payload::WorldSnapshot(snapshot), Payload::EntityUpdateBatch(batch))
+            if batch.baseline_id == snapshot.snapshot_id =>
+        {
+            Payload::WorldSnapshot(apply_batch(snapshot, batch))
+        }
+        // Batches are deltas against an acknowledged baseline, so the newest
+        // one supersedes any undelivered predecessor; only its events carry over
+        (Payload::EntityUpdateBatch(stale), Payload::EntityUpdateBatch(mut batch)) => {
+            carry_effects(stale, &mut batch);
+            Payload::EntityUpdateBatch(batch)
+        }
+        // A full snapshot supersedes everything
+        (_, payload) => payload,
+    };
+
+    Envelope { payload, ..newer }
+}
+
+/// Apply a delta to a snapshot that has not been delivered yet
+fn apply_batch(mut snapshot: WorldSnapshot, batch: EntityUpdateBatch) -> WorldSnapshot {
+    let despawned: HashSet<u64> = batch.despawned.iter().copied().collect();
+    snapshot
+        .entities
+        .retain(|entity| !despawned.contains(&entity.id));
+
+    for spawned in batch.spawned {
+        upsert(&mut snapshot.entities, spawned);
+    }
+    for update in &batch.updated {
+        if let Some(entity) = snapshot
+            .entities
+            .iter_mut()
+            .find(|entity| entity.id == update.entity_id)
+        {
+            apply_update(entity, update);
+        }
+    }
+
+    snapshot.snapshot_id = batch.snapshot_id;
+    snapshot.server_tick = batch.server_tick;
+    snapshot
+}
+
+/// Move the events of an undelivered batch into the one replacing it, oldest first
+fn carry_effects(stale: EntityUpdateBatch, batch: &mut EntityUpdateBatch) {
+    for update in stale.updated.into_iter().rev() {
+        if update.effects.is_empty() {
+            continue;
+        }
+        match batch
+            .updated
+            .iter_mut()
+            .find(|newer| newer.entity_id == update.entity_id)
+        {
+            Some(newer) => {
+                newer.effects.splice(0..0, update.effects);
+            }
+            None => batch.updated.insert(
+                0,
+                EntityUpdate {
+                    entity_id: update.entity_id,
+                    position: None,
+                    rotation: None,
+                    state: None,
+                    effects: update.effects,
+                },
+            ),
+        }
+    }
+}
+
+fn upsert(entities: &mut Vec<Entity>, entity: Entity) {
+    match entities
+        .iter_mut()
+        .find(|existing| existing.id == entity.id)
+    {
+        Some(existing) => *existing = entity,
+        None => entities.push(entity),
+    }
+}
+
+fn apply_update(entity: &mut Entity, update: &EntityUpdate) {
+    if let Some(position) = &update.position {
+        entity.position = position.clone();
+    }
+    if let Some(rotation) = &update.rotation {
+        entity.rotation = rotation.clone();
+    }
+    if let Some(state) = &update.state {
+        entity.state = state.clone();
+    }
+}
@@ -0,0 +1,1004 @@
+//! Protobuf wire types for `proto/messages.proto`
+//!
+//! The `openmmo` package is compiled by prost-build in `build.rs`; this module
+//! maps the generated types onto the server's own message types.
+
+use crate::items;
+use crate::loot;
+use crate::network::messages;
+
+#[allow(dead_code, clippy::all)]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/openmmo.rs"));
+}
+
+pub use generated::*;
+
+/// Errors raised while mapping a decoded Protobuf envelope onto server types
+#[derive(Debug, thiserror::Error)]
+pub enum ProtoConversionError {
+    #[error("envelope has no payload")]
+    MissingPayload,
+
+    #[error("required field {0} is missing")]
+    MissingField(&'static str),
+
+    #[error("unknown value {value} for enum {field}")]
+    UnknownEnum { field: &'static str, value: i32 },
+
+    #[error("invalid effect data: {0}")]
+    InvalidEffectData(#[from] serde_json::Error),
+}
+
+type ConversionResult<T> = Result<T, ProtoConversionError>;
+
+fn required<T>(value: Option<T>, field: &'static str) -> ConversionResult<T> {
+    value.ok_or(ProtoConversionError::MissingField(field))
+}
+
+/// Proto3 strings and ids have no presence; empty and zero stand for absent
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+fn non_zero(value: u64) -> Option<u64> {
+    (value != 0).then_some(value)
+}
+
+impl From<&messages::Envelope> for Envelope {
+    fn from(envelope: &messages::Envelope) -> Self {
+        Self {
+            sequence_id: envelope.sequence_id,
+            timestamp: envelope.timestamp,
+            payload: Some(envelope::Payload::from(&envelope.payload)),
+        }
+    }
+}
+
+impl TryFrom<Envelope> for messages::Envelope {
+    type Error = ProtoConversionError;
+
+    fn try_from(envelope: Envelope) -> ConversionResult<Self> {
+        let payload = envelope
+            .payload
+            .ok_or(ProtoConversionError::MissingPayload)?;
+
+        Ok(Self {
+            sequence_id: envelope.sequence_id,
+            timestamp: envelope.timestamp,
+            payload: messages::Payload::try_from(payload)?,
+        })
+    }
+}
+
+impl From<&messages::Payload> for envelope::Payload {
+    fn from(payload: &messages::Payload) -> Self {
+        use envelope::Payload as P;
+        use messages::Payload as M;
+
+        match payload {
+            M::HandshakeRequest(m) => P::HandshakeRequest(HandshakeRequest {
+                client_version: m.client_version.clone(),
+                protocol_version: m.protocol_version.clone(),
+                supported_features: m.supported_features,
+            }),
+            M::HandshakeResponse(m) => P::HandshakeResponse(HandshakeResponse {
+                accepted: m.accepted,
+                server_version: m.server_version.clone(),
+                protocol_version: m.protocol_version.clone(),
+                server_features: m.server_features,
+                message: m.message.clone(),
+            }),
+            M::AuthRequest(m) => P::AuthRequest(AuthRequest {
+                username: m.username.clone(),
+                password_hash: m.password_hash.clone(),
+                character_name: m.character_name.clone().unwrap_or_default(),
+            }),
+            M::AuthResponse(m) => P::AuthResponse(AuthResponse {
+                success: m.success,
+                session_token: m.session_token.clone().unwrap_or_default(),
+                message: m.message.clone(),
+                player_id: m.player_id.unwrap_or_default(),
+                character_id: m.character_id.unwrap_or_default(),
+            }),
+            M::Ping(m) => P::Ping(Ping {
+                timestamp: m.timestamp,
+            }),
+            M::Pong(m) => P::Pong(Pong {
+                timestamp: m.timestamp,
+            }),
+            M::Error(m) => P::Error(Error {
+                code: error::ErrorCode::from(&m.code) as i32,
+                message: m.message.clone(),
+                details: m.details.clone(),
+            }),
+            M::Disconnect(m) => P::Disconnect(Disconnect {
+                reason: disconnect::DisconnectReason::from(&m.reason) as i32,
+                message: m.message.clone(),
+            }),
+            M::WorldSnapshot(m) => P::WorldSnapshot(WorldSnapshot::from(m)),
+            M::MovementIntent(m) => P::MovementIntent(MovementIntent {
+                target_position: Some(Vector3::from(&m.target_position)),
+                speed_modifier: m.speed_modifier,
+                stop_movement: m.stop_movement,
+                rotation_y: m.rotation_y,
+            }),
+            M::CombatAction(m) => P::CombatAction(CombatAction {
+                action_type: combat_action::ActionType::from(&m.action_type) as i32,
+                target_entity_id: m.target_entity_id,
+                ability_id: m.ability_id,
+                target_position: m.target_position.as_ref().map(Vector3::from),
+            }),
+            M::EntityUpdate(m) => P::EntityUpdate(EntityUpdate::from(m)),
+            M::CharacterListRequest(_) => P::CharacterListRequest(CharacterListRequest {}),
+            M::CharacterListResponse(m) => P::CharacterListResponse(CharacterListResponse {
+                characters: m.characters.iter().map(CharacterInfo::from).collect(),
+            }),
+            M::CharacterCreateRequest(m) => P::CharacterCreateRequest(CharacterCreateRequest {
+                name: m.name.clone(),
+                class: m.class.clone(),
+            }),
+            M::CharacterCreateResponse(m) => P::CharacterCreateResponse(CharacterCreateResponse {
+                success: m.success,
+                character: m.character.as_ref().map(CharacterInfo::from),
+                error_message: m.error_message.clone().unwrap_or_default(),
+            }),
+            M::CharacterSelectRequest(m) => P::CharacterSelectRequest(CharacterSelectRequest {
+                character_id: m.character_id,
+            }),
+            M::CharacterSelectResponse(m) => P::CharacterSelectResponse(CharacterSelectResponse {
+                success: m.success,
+                character: m.character.as_ref().map(CharacterInfo::from),
+                error_message: m.error_message.clone().unwrap_or_default(),
+            }),
+            M::CharacterDeleteRequest(m) => P::CharacterDeleteRequest(CharacterDeleteRequest {
+                character_id: m.character_id,
+            }),
+            M::CharacterDeleteResponse(m) => P::CharacterDeleteResponse(CharacterDeleteResponse {
+                success: m.success,
+                error_message: m.error_message.clone().unwrap_or_default(),
+            }),
+            M::InventoryRequest(_) => P::InventoryRequest(InventoryRequest {}),
+            M::InventoryResponse(m) => P::InventoryResponse(InventoryResponse {
+                slots: m
+                    .slots
+                    .iter()
+                    .map(|slot| InventorySlot {
+                        slot_id: slot.slot_id,
+                        item: Some(ItemInstance::from(&slot.item)),
+                    })
+                    .collect(),
+                max_slots: m.max_slots,
+            }),
+            M::ItemMoveRequest(m) => P::ItemMoveRequest(ItemMoveRequest {
+                from_slot: m.from_slot,
+                to_slot: m.to_slot,
+            }),
+            M::ItemMoveResponse(m) => P::ItemMoveResponse(ItemMoveResponse {
+                success: m.success,
+                error_message: m.error_message.clone().unwrap_or_default(),
+            }),
+            M::EquipmentRequest(_) => P::EquipmentRequest(EquipmentRequest {}),
+            M::EquipmentResponse(m) => P::EquipmentResponse(EquipmentResponse {
+                slots: m
+                    .slots
+                    .iter()
+                    .map(|slot| EquipmentSlot {
+                        slot_type: slot.slot_type,
+                        item: Some(ItemInstance::from(&slot.item)),
+                    })
+                    .collect(),
+            }),
+            M::ItemEquipRequest(m) => P::ItemEquipRequest(ItemEquipRequest {
+                inventory_slot: m.inventory_slot,
+                equipment_slot: m.equipment_slot,
+                unequip: m.unequip,
+            }),
+            M::ItemEquipResponse(m) => P::ItemEquipResponse(ItemEquipResponse {
+                success: m.success,
+                error_message: m.error_message.clone().unwrap_or_default(),
+            }),
+            M::EntityUpdateBatch(m) => P::EntityUpdateBatch(EntityUpdateBatch::from(m)),
+            M::SessionResumeRequest(m) => P::SessionResumeRequest(SessionResumeRequest {
+                session_token: m.session_token.clone(),
+            }),
+            M::SessionResumeResponse(m) => P::SessionResumeResponse(SessionResumeResponse {
+                success: m.success,
+                session_token: m.session_token.clone(),
+                message: m.message.clone(),
+                player_id: m.player_id,
+                character_id: m.character_id,
+            }),
+            M::SnapshotAck(m) => P::SnapshotAck(SnapshotAck {
+                snapshot_id: m.snapshot_id,
+            }),
+            M::ReleaseRequest(_) => P::ReleaseRequest(ReleaseRequest {}),
+            M::LootOpenRequest(m) => P::LootOpenRequest(LootOpenRequest {
+                corpse_id: m.corpse_id,
+            }),
+            M::LootTakeRequest(m) => P::LootTakeRequest(LootTakeRequest {
+                corpse_id: m.corpse_id,
+                slot: m.slot,
+            }),
+            M::LootResponse(m) => P::LootResponse(LootResponse {
+                corpse_id: m.corpse_id,
+                success: m.success,
+                error_message: m.error_message.clone(),
+                gold: m.gold,
+                items: m
+                    .items
+                    .iter()
+                    .map(|slot| LootSlot {
+                        slot: slot.slot,
+                        item: Some(ItemInstance::from(&slot.item)),
+                    })
+                    .collect(),
+            }),
+            M::LootRollRequest(m) => P::LootRollRequest(LootRollRequest {
+                corpse_id: m.corpse_id,
+                slot: m.slot,
+                choice: RollChoice::from(&m.choice) as i32,
+            }),
+            M::ShutdownNotice(m) => P::ShutdownNotice(ShutdownNotice {
+                seconds_remaining: m.seconds_remaining,
+                message: m.message.clone(),
+            }),
+            M::PartyInviteRequest(m) => P::PartyInviteRequest(PartyInviteRequest {
+                player_id: m.player_id,
+            }),
+            M::PartyInviteReply(m) => P::PartyInviteReply(PartyInviteReply { accept: m.accept }),
+            M::PartyLeaveRequest(_) => P::PartyLeaveRequest(PartyLeaveRequest {}),
+            M::PartyLootRulesRequest(m) => P::PartyLootRulesRequest(PartyLootRulesRequest {
+                method: LootMethod::from(&m.method) as i32,
+                threshold: ItemRarity::from(&m.threshold) as i32,
+            }),
+        }
+    }
+}
+
+impl TryFrom<envelope::Payload> for messages::Payload {
+    type Error = ProtoConversionError;
+
+    fn try_from(payload: envelope::Payload) -> ConversionResult<Self> {
+        use envelope::Payload as P;
+        use messages::Payload as M;
+
+        Ok(match payload {
+            P::HandshakeRequest(m) => M::HandshakeRequest(messages::HandshakeRequest {
+                client_version: m.client_version,
+                protocol_version: m.protocol_version,
+                supported_features: m.supported_features,
+            }),
+            P::HandshakeResponse(m) => M::HandshakeResponse(messages::HandshakeResponse {
+                accepted: m.accepted,
+                server_version: m.server_version,
+                protocol_version: m.protocol_version,
+                server_features: m.server_features,
+                message: m.message,
+            }),
+            P::AuthRequest(m) => M::AuthRequest(messages::AuthRequest {
+                username: m.username,
+                password_hash: m.password_hash,
+                character_name: non_empty(m.character_name),
+            }),
+            P::AuthResponse(m) => M::AuthResponse(messages::AuthResponse {
+                success: m.success,
+                session_token: non_empty(m.session_token),
+                message: m.message,
+                player_id: non_zero(m.player_id),
+                character_id: non_zero(m.character_id),
+            }),
+            P::Ping(m) => M::Ping(messages::Ping {
+                timestamp: m.timestamp,
+            }),
+            P::Pong(m) => M::Pong(messages::Pong {
+                timestamp: m.timestamp,
+            }),
+            P::Error(m) => M::Error(messages::Error {
+                code: error_code_from_wire(m.code)?,
+                message: m.message,
+                details: m.details,
+            }),
+            P::Disconnect(m) => M::Disconnect(messages::Disconnect {
+                reason: disconnect_reason_from_wire(m.reason)?,
+                message: m.message,
+            }),
+            P::WorldSnapshot(m) => M::WorldSnapshot(messages::WorldSnapshot::try_from(m)?),
+            P::MovementIntent(m) => M::MovementIntent(messages::MovementIntent {
+                target_position: required(m.target_position, "target_position")?.into(),
+                speed_modifier: m.speed_modifier,
+                stop_movement: m.stop_movement,
+                rotation_y: m.rotation_y,
+            }),
+            P::CombatAction(m) => M::CombatAction(messages::CombatAction {
+                action_type: action_type_from_wire(m.action_type)?,
+                target_entity_id: m.target_entity_id,
+                ability_id: m.ability_id,
+                target_position: m.target_position.map(Into::into),
+            }),
+            P::EntityUpdate(m) => M::EntityUpdate(messages::EntityUpdate::try_from(m)?),
+            P::CharacterListRequest(_) => {
+                M::CharacterListRequest(messages::CharacterListRequest { request: true })
+            }
+            P::CharacterListResponse(m) => {
+                M::CharacterListResponse(messages::CharacterListResponse {
+                    characters: m.characters.into_iter().map(Into::into).collect(),
+                })
+            }
+            P::CharacterCreateRequest(m) => {
+                M::CharacterCreateRequest(messages::CharacterCreateRequest {
+                    name: m.name,
+                    class: m.class,
+                })
+            }
+            P::CharacterCreateResponse(m) => {
+                M::CharacterCreateResponse(messages::CharacterCreateResponse {
+                    success: m.success,
+                    character: m.character.map(Into::into),
+                    error_message: non_empty(m.error_message),
+                })
+            }
+            P::CharacterSelectRequest(m) => {
+                M::CharacterSelectRequest(messages::CharacterSelectRequest {
+                    character_id: m.character_id,
+                })
+            }
+            P::CharacterSelectResponse(m) => {
+                M::CharacterSelectResponse(messages::CharacterSelectResponse {
+                    success: m.success,
+                    character: m.character.map(Into::into),
+                    error_message: non_empty(m.error_message),
+                })
+            }
+            P::CharacterDeleteRequest(m) => {
+                M::CharacterDeleteRequest(messages::CharacterDeleteRequest {
+                    character_id: m.character_id,
+                })
+            }
+            P::CharacterDeleteResponse(m) => {
+                M::CharacterDeleteResponse(messages::CharacterDeleteResponse {
+                    success: m.success,
+                    error_message: non_empty(m.error_message),
+                })
+            }
+            P::InventoryRequest(_) => M::InventoryRequest(messages::InventoryRequest),
+            P::InventoryResponse(m) => M::InventoryResponse(messages::InventoryResponse {
+                slots: m
+                    .slots
+                    .into_iter()
+                    .map(|slot| {
+                        Ok(messages::InventorySlot {
+                            slot_id: slot.slot_id,
+                            item: required(slot.item, "item")?.into(),
+                        })
+                    })
+                    .collect::<ConversionResult<_>>()?,
+                max_slots: m.max_slots,
+            }),
+            P::ItemMoveRequest(m) => M::ItemMoveRequest(messages::ItemMoveRequest {
+                from_slot: m.from_slot,
+                to_slot: m.to_slot,
+            }),
+            P::ItemMoveResponse(m) => M::ItemMoveResponse(messages::ItemMoveResponse {
+                success: m.success,
+                error_message: non_empty(m.error_message),
+            }),
+            P::EquipmentRequest(_) => M::EquipmentRequest(messages::EquipmentRequest),
+            P::EquipmentResponse(m) => M::EquipmentResponse(messages::EquipmentResponse {
+                slots: m
+                    .slots
+                    .into_iter()
+                    .map(|slot| {
+                        Ok(messages::EquipmentSlot {
+                            slot_type: slot.slot_type,
+                            item: required(slot.item, "item")?.into(),
+                        })
+                    })
+                    .collect::<ConversionResult<_>>()?,
+            }),
+            P::ItemEquipRequest(m) => M::ItemEquipRequest(messages::ItemEquipRequest {
+                inventory_slot: m.inventory_slot,
+                equipment_slot: m.equipment_slot,
+                unequip: m.unequip,
+            }),
+            P::ItemEquipResponse(m) => M::ItemEquipResponse(messages::ItemEquipResponse {
+                success: m.success,
+                error_message: non_empty(m.error_message),
+            }),
+            P::EntityUpdateBatch(m) => {
+                M::EntityUpdateBatch(messages::EntityUpdateBatch::try_from(m)?)
+            }
+            P::SessionResumeRequest(m) => M::SessionResumeRequest(messages::SessionResumeRequest {
+                session_token: m.session_token,
+            }),
+            P::SessionResumeResponse(m) => {
+                M::SessionResumeResponse(messages::SessionResumeResponse {
+                    success: m.success,
+                    session_token: m.session_token,
+                    message: m.message,
+                    player_id: m.player_id,
+                    character_id: m.character_id,
+                })
+            }
+            P::SnapshotAck(m) => M::SnapshotAck(messages::SnapshotAck {
+                snapshot_id: m.snapshot_id,
+            }),
+            P::ReleaseRequest(_) => M::ReleaseRequest(messages::ReleaseRequest {}),
+            P::LootOpenRequest(m) => M::LootOpenRequest(messages::LootOpenRequest {
+                corpse_id: m.corpse_id,
+            }),
+            P::LootTakeRequest(m) => M::LootTakeRequest(messages::LootTakeRequest {
+                corpse_id: m.corpse_id,
+                slot: m.slot,
+            }),
+            P::LootResponse(m) => M::LootResponse(messages::LootResponse {
+                corpse_id: m.corpse_id,
+                success: m.success,
+                error_message: m.error_message,
+                gold: m.gold,
+                items: m
+                    .items
+                    .into_iter()
+                    .map(|slot| {
+                        Ok(messages::LootSlot {
+                            slot: slot.slot,
+                            item: required(slot.item, "item")?.into(),
+                        })
+                    })
+                    .collect::<ConversionResult<_>>()?,
+            }),
+            P::LootRollRequest(m) => M::LootRollRequest(messages::LootRollRequest {
+                corpse_id: m.corpse_id,
+                slot: m.slot,
+                choice: roll_choice_from_wire(m.choice)?,
+            }),
+            P::ShutdownNotice(m) => M::ShutdownNotice(messages::ShutdownNotice {
+                seconds_remaining: m.seconds_remaining,
+                message: m.message,
+            }),
+            P::PartyInviteRequest(m) => M::PartyInviteRequest(messages::PartyInviteRequest {
+                player_id: m.player_id,
+            }),
+            P::PartyInviteReply(m) => {
+                M::PartyInviteReply(messages::PartyInviteReply { accept: m.accept })
+            }
+            P::PartyLeaveRequest(_) => M::PartyLeaveRequest(messages::PartyLeaveRequest {}),
+            P::PartyLootRulesRequest(m) => {
+                M::PartyLootRulesRequest(messages::PartyLootRulesRequest {
+                    method: loot_method_from_wire(m.method)?,
+                    threshold: item_rarity_from_wire(m.threshold)?,
+                })
+            }
+        })
+    }
+}
+
+impl From<&messages::Vector3> for Vector3 {
+    fn from(v: &messages::Vector3) -> Self {
+        Self {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<Vector3> for messages::Vector3 {
+    fn from(v: Vector3) -> Self {
+        Self {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<&messages::EntityState> for EntityState {
+    fn from(state: &messages::EntityState) -> Self {
+        Self {
+            movement_state: entity_state::MovementState::from(&state.movement_state) as i32,
+            health_percent: state.health_percent,
+            display_name: state.display_name.clone(),
+            resource: state.resource.as_ref().map(ResourceState::from),
+        }
+    }
+}
+
+impl From<&messages::ResourceState> for ResourceState {
+    fn from(resource: &messages::ResourceState) -> Self {
+        Self {
+            resource_type: resource.resource_type.clone(),
+            current: resource.current,
+            maximum: resource.maximum,
+        }
+    }
+}
+
+impl From<ResourceState> for messages::ResourceState {
+    fn from(resource: ResourceState) -> Self {
+        Self {
+            resource_type: resource.resource_type,
+            current: resource.current,
+            maximum: resource.maximum,
+        }
+    }
+}
+
+impl TryFrom<EntityState> for messages::EntityState {
+    type Error = ProtoConversionError;
+
+    fn try_from(state: EntityState) -> ConversionResult<Self> {
+        Ok(Self {
+            movement_state: movement_state_from_wire(state.movement_state)?,
+            health_percent: state.health_percent,
+            display_name: state.display_name,
+            resource: state.resource.map(messages::ResourceState::from),
+        })
+    }
+}
+
+impl From<&messages::Entity> for Entity {
+    fn from(entity: &messages::Entity) -> Self {
+        Self {
+            id: entity.id,
+            entity_type: entity.entity_type.clone(),
+            position: Some(Vector3::from(&entity.position)),
+            rotation: Some(Vector3::from(&entity.rotation)),
+            state: Some(EntityState::from(&entity.state)),
+        }
+    }
+}
+
+impl TryFrom<Entity> for messages::Entity {
+    type Error = ProtoConversionError;
+
+    fn try_from(entity: Entity) -> ConversionResult<Self> {
+        Ok(Self {
+            id: entity.id,
+            entity_type: entity.entity_type,
+            position: required(entity.position, "position")?.into(),
+            rotation: required(entity.rotation, "rotation")?.into(),
+            state: required(entity.state, "state")?.try_into()?,
+        })
+    }
+}
+
+impl From<&messages::WorldSnapshot> for WorldSnapshot {
+    fn from(snapshot: &messages::WorldSnapshot) -> Self {
+        Self {
+            snapshot_id: snapshot.snapshot_id,
+            entities: snapshot.entities.iter().map(Entity::from).collect(),
+            player_entity_id: snapshot.player_entity_id,
+            zone_name: snapshot.zone_name.clone(),
+            server_tick: snapshot.server_tick,
+        }
+    }
+}
+
+impl TryFrom<WorldSnapshot> for messages::WorldSnapshot {
+    type Error = ProtoConversionError;
+
+    fn try_from(snapshot: WorldSnapshot) -> ConversionResult<Self> {
+        Ok(Self {
+            snapshot_id: snapshot.snapshot_id,
+            entities: snapshot
+                .entities
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<ConversionResult<_>>()?,
+            player_entity_id: snapshot.player_entity_id,
+            zone_name: snapshot.zone_name,
+            server_tick: snapshot.server_tick,
+        })
+    }
+}
+
+impl From<&messages::EntityUpdate> for EntityUpdate {
+    fn from(update: &messages::EntityUpdate) -> Self {
+        Self {
+            entity_id: update.entity_id,
+            position: update.position.as_ref().map(Vector3::from),
+            rotation: update.rotation.as_ref().map(Vector3::from),
+            state: update.state.as_ref().map(EntityState::from),
+            effects: update.effects.iter().map(EntityEffect::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<EntityUpdate> for messages::EntityUpdate {
+    type Error = ProtoConversionError;
+
+    fn try_from(update: EntityUpdate) -> ConversionResult<Self> {
+        Ok(Self {
+            entity_id: update.entity_id,
+            position: update.position.map(Into::into),
+            rotation: update.rotation.map(Into::into),
+            state: update.state.map(TryInto::try_into).transpose()?,
+            effects: update
+                .effects
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<ConversionResult<_>>()?,
+        })
+    }
+}
+
+impl From<&messages::EntityUpdateBatch> for EntityUpdateBatch {
+    fn from(batch: &messages::EntityUpdateBatch) -> Self {
+        Self {
+            snapshot_id: batch.snapshot_id,
+            baseline_id: batch.baseline_id,
+            spawned: batch.spawned.iter().map(Entity::from).collect(),
+            updated: batch.updated.iter().map(EntityUpdate::from).collect(),
+            despawned: batch.despawned.clone(),
+            server_tick: batch.server_tick,
+        }
+    }
+}
+
+impl TryFrom<EntityUpdateBatch> for messages::EntityUpdateBatch {
+    type Error = ProtoConversionError;
+
+    fn try_from(batch: EntityUpdateBatch) -> ConversionResult<Self> {
+        Ok(Self {
+            snapshot_id: batch.snapshot_id,
+            baseline_id: batch.baseline_id,
+            spawned: batch
+                .spawned
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<ConversionResult<_>>()?,
+            updated: batch
+                .updated
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<ConversionResult<_>>()?,
+            despawned: batch.despawned,
+            server_tick: batch.server_tick,
+        })
+    }
+}
+
+impl From<&messages::EntityEffect> for EntityEffect {
+    fn from(effect: &messages::EntityEffect) -> Self {
+        use entity_effect::EffectType;
+
+        let (effect_type, effect_data) = match effect {
+            messages::EntityEffect::DamageNumber { .. } => {
+                (EffectType::DamageNumber, effect_data_json(effect))
+            }
+            messages::EntityEffect::StatusEffect { .. } => {
+                (EffectType::StatusEffect, effect_data_json(effect))
+            }
+            messages::EntityEffect::Death => (EffectType::Death, String::new()),
+            messages::EntityEffect::Respawn => (EffectType::Respawn, String::new()),
+            messages::EntityEffect::ActionRejected { .. } => {
+                (EffectType::ActionRejected, effect_data_json(effect))
+            }
+            messages::EntityEffect::Cast { .. } => (EffectType::Cast, effect_data_json(effect)),
+            messages::EntityEffect::Experience { .. } => {
+                (EffectType::Experience, effect_data_json(effect))
+            }
+            messages::EntityEffect::LevelUp { .. } => {
+                (EffectType::LevelUp, effect_data_json(effect))
+            }
+            messages::EntityEffect::LootRoll { .. } => {
+                (EffectType::LootRoll, effect_data_json(effect))
+            }
+            messages::EntityEffect::LootRollResult { .. } => {
+                (EffectType::LootRollResult, effect_data_json(effect))
+            }
+            messages::EntityEffect::PartyInvite { .. } => {
+                (EffectType::PartyInvite, effect_data_json(effect))
+            }
+            messages::EntityEffect::PartyChanged { .. } => {
+                (EffectType::PartyChanged, effect_data_json(effect))
+            }
+            messages::EntityEffect::PartyRefused { .. } => {
+                (EffectType::PartyRefused, effect_data_json(effect))
+            }
+        };
+
+        Self {
+            effect_type: effect_type as i32,
+            effect_data,
+        }
+    }
+}
+
+impl TryFrom<EntityEffect> for messages::EntityEffect {
+    type Error = ProtoConversionError;
+
+    fn try_from(effect: EntityEffect) -> ConversionResult<Self> {
+        use entity_effect::EffectType;
+
+        let effect_type = EffectType::try_from(effect.effect_type).map_err(|_| {
+            ProtoConversionError::UnknownEnum {
+                field: "EntityEffect.effect_type",
+                value: effect.effect_type,
+            }
+        })?;
+
+        Ok(match effect_type {
+            EffectType::Death => messages::EntityEffect::Death,
+            EffectType::Respawn => messages::EntityEffect::Respawn,
+            EffectType::DamageNumber
+            | EffectType::StatusEffect
+            | EffectType::ActionRejected
+            | EffectType::Cast
+            | EffectType::Experience
+            | EffectType::LevelUp
+            | EffectType::LootRoll
+            | EffectType::LootRollResult
+            | EffectType::PartyInvite
+            | EffectType::PartyChanged
+            | EffectType::PartyRefused => serde_json::from_str(&effect.effect_data)?,
+        })
+    }
+}
+
+/// Effect payloads ride in `effect_data` using the same externally tagged JSON
+/// layout the text protocol uses, so clients can share one decoder.
+fn effect_data_json(effect: &messages::EntityEffect) -> String {
+    serde_json::to_string(effect).unwrap_or_default()
+}
+
+impl From<&messages::CharacterInfo> for CharacterInfo {
+    fn from(info: &messages::CharacterInfo) -> Self {
+        Self {
+            id: info.id,
+            name: info.name.clone(),
+            class: info.class.clone(),
+            level: info.level,
+            experience: info.experience,
+            zone_id: info.zone_id.clone(),
+            health: info.health,
+            max_health: info.max_health,
+            resource_type: info.resource_type.clone(),
+            resource_value: info.resource_value,
+            max_resource: info.max_resource,
+            is_online: info.is_online,
+        }
+    }
+}
+
+impl From<CharacterInfo> for messages::CharacterInfo {
+    fn from(info: CharacterInfo) -> Self {
+        Self {
+            id: info.id,
+            name: info.name,
+            class: info.class,
+            level: info.level,
+            experience: info.experience,
+            zone_id: info.zone_id,
+            health: info.health,
+            max_health: info.max_health,
+            resource_type: info.resource_type,
+            resource_value: info.resource_value,
+            max_resource: info.max_resource,
+            is_online: info.is_online,
+        }
+    }
+}
+
+impl From<&messages::ItemInstance> for ItemInstance {
+    fn from(item: &messages::ItemInstance) -> Self {
+        Self {
+            definition_id: item.definition_id,
+            quantity: item.quantity,
+            is_bound: item.is_bound,
+            durability: item.durability.as_ref().map(|d| ItemDurability {
+                current: d.current,
+                maximum: d.maximum,
+            }),
+        }
+    }
+}
+
+impl From<ItemInstance> for messages::ItemInstance {
+    fn from(item: ItemInstance) -> Self {
+        Self {
+            definition_id: item.definition_id,
+            quantity: item.quantity,
+            is_bound: item.is_bound,
+            durability: item.durability.map(|d| messages::ItemDurability {
+                current: d.current,
+                maximum: d.maximum,
+            }),
+        }
+    }
+}
+
+impl From<&messages::ErrorCode> for error::ErrorCode {
+    fn from(code: &messages::ErrorCode) -> Self {
+        match code {
+            messages::ErrorCode::UnknownError => Self::UnknownError,
+            messages::ErrorCode::InvalidRequest => Self::InvalidRequest,
+            messages::ErrorCode::AuthenticationFailed => Self::AuthenticationFailed,
+            messages::ErrorCode::SessionExpired => Self::SessionExpired,
+            messages::ErrorCode::CharacterNotFound => Self::CharacterNotFound,
+            messages::ErrorCode::ServerFull => Self::ServerFull,
+            messages::ErrorCode::ProtocolMismatch => Self::ProtocolMismatch,
+            messages::ErrorCode::RateLimited => Self::RateLimited,
+        }
+    }
+}
+
+fn error_code_from_wire(value: i32) -> ConversionResult<messages::ErrorCode> {
+    use error::ErrorCode;
+
+    let code = ErrorCode::try_from(value).map_err(|_| ProtoConversionError::UnknownEnum {
+        field: "Error.code",
+        value,
+    })?;
+
+    Ok(match code {
+        ErrorCode::UnknownError => messages::ErrorCode::UnknownError,
+        ErrorCode::InvalidRequest => messages::ErrorCode::InvalidRequest,
+        ErrorCode::AuthenticationFailed => messages::ErrorCode::AuthenticationFailed,
+        ErrorCode::SessionExpired => messages::ErrorCode::SessionExpired,
+        ErrorCode::CharacterNotFound => messages::ErrorCode::CharacterNotFound,
+        ErrorCode::ServerFull => messages::ErrorCode::ServerFull,
+        ErrorCode::ProtocolMismatch => messages::ErrorCode::ProtocolMismatch,
+        ErrorCode::RateLimited => messages::ErrorCode::RateLimited,
+    })
+}
+
+impl From<&messages::DisconnectReason> for disconnect::DisconnectReason {
+    fn from(reason: &messages::DisconnectReason) -> Self {
+        match reason {
+            messages::DisconnectReason::Unknown => Self::Unknown,
+            messages::DisconnectReason::ClientRequest => Self::ClientRequest,
+            messages::DisconnectReason::ServerShutdown => Self::ServerShutdown,
+            messages::DisconnectReason::Timeout => Self::Timeout,
+            messages::DisconnectReason::Kicked => Self::Kicked,
+            messages::DisconnectReason::Banned => Self::Banned,
+            messages::DisconnectReason::ProtocolMismatch => Self::ProtocolMismatch,
+            messages::DisconnectReason::SlowConnection => Self::SlowConnection,
+        }
+    }
+}
+
+fn disconnect_reason_from_wire(value: i32) -> ConversionResult<messages::DisconnectReason> {
+    use disconnect::DisconnectReason;
+
+    let reason =
+        DisconnectReason::try_from(value).map_err(|_| ProtoConversionError::UnknownEnum {
+            field: "Disconnect.reason",
+            value,
+        })?;
+
+    Ok(match reason {
+        DisconnectReason::Unknown => messages::DisconnectReason::Unknown,
+        DisconnectReason::ClientRequest => messages::DisconnectReason::ClientRequest,
+        DisconnectReason::ServerShutdown => messages::DisconnectReason::ServerShutdown,
+        DisconnectReason::Timeout => messages::DisconnectReason::Timeout,
+        DisconnectReason::Kicked => messages::DisconnectReason::Kicked,
+        DisconnectReason::Banned => messages::DisconnectReason::Banned,
+        DisconnectReason::ProtocolMismatch => messages::DisconnectReason::ProtocolMismatch,
+        DisconnectReason::SlowConnection => messages::DisconnectReason::SlowConnection,
+    })
+}
+
+impl From<&messages::MovementState> for entity_state::MovementState {
+    fn from(state: &messages::MovementState) -> Self {
+        match state {
+            messages::MovementState::Idle => Self::Idle,
+            messages::MovementState::Walking => Self::Walking,
+            messages::MovementState::Running => Self::Running,
+            messages::MovementState::Dead => Self::Dead,
+        }
+    }
+}
+
+fn movement_state_from_wire(value: i32) -> ConversionResult<messages::MovementState> {
+    use entity_state::MovementState;
+
+    let state = MovementState::try_from(value).map_err(|_| ProtoConversionError::UnknownEnum {
+        field: "EntityState.movement_state",
+        value,
+    })?;
+
+    Ok(match state {
+        MovementState::Idle => messages::MovementState::Idle,
+        MovementState::Walking => messages::MovementState::Walking,
+        MovementState::Running => messages::MovementState::Running,
+        MovementState::Dead => messages::MovementState::Dead,
+    })
+}
+
+impl From<&messages::ActionType> for combat_action::ActionType {
+    fn from(action: &messages::ActionType) -> Self {
+        match action {
+            messages::ActionType::AutoAttack => Self::AutoAttack,
+            messages::ActionType::Ability => Self::Ability,
+        }
+    }
+}
+
+fn action_type_from_wire(value: i32) -> ConversionResult<messages::ActionType> {
+    use combat_action::ActionType;
+
+    let action = ActionType::try_from(value).map_err(|_| ProtoConversionError::UnknownEnum {
+        field: "CombatAction.action_type",
+        value,
+    })?;
+
+    Ok(match action {
+        ActionType::AutoAttack => messages::ActionType::AutoAttack,
+        ActionType::Ability => messages::ActionType::Ability,
+    })
+}
+
+impl From<&loot::RollChoice> for RollChoice {
+    fn from(choice: &loot::RollChoice) -> Self {
+        match choice {
+            loot::RollChoice::Need => Self::Need,
+            loot::RollChoice::Greed => Self::Greed,
+            loot::RollChoice::Pass => Self::Pass,
+        }
+    }
+}
+
+fn roll_choice_from_wire(value: i32) -> ConversionResult<loot::RollChoice> {
+    let choice = RollChoice::try_from(value).map_err(|_| ProtoConversionError::UnknownEnum {
+        field: "LootRollRequest.choice",
+        value,
+    })?;
+
+    Ok(match choice {
+        RollChoice::Need => loot::RollChoice::Need,
+        RollChoice::Greed => loot::RollChoice::Greed,
+        RollChoice::Pass => loot::RollChoice::Pass,
+    })
+}
+
+impl From<&loot::LootMethod> for LootMethod {
+    fn from(method: &loot::LootMethod) -> Self {
+        match method {
+            loot::LootMethod::FreeForAll => Self::FreeForAll,
+            loot::LootMethod::RoundRobin => Self::RoundRobin,
+            loot::LootMethod::NeedBeforeGreed => Self::NeedBeforeGreed,
+        }
+    }
+}
+
+fn loot_method_from_wire(value: i32) -> ConversionResult<loot::LootMethod> {
+    let method = LootMethod::try_from(value).map_err(|_| ProtoConversionError::UnknownEnum {
+        field: "PartyLootRulesRequest.method",
+        value,
+    })?;
+
+    Ok(match method {
+        LootMethod::FreeForAll => loot::LootMethod::FreeForAll,
+        LootMethod::RoundRobin => loot::LootMethod::RoundRobin,
+        LootMethod::NeedBeforeGreed => loot::LootMethod::NeedBeforeGreed,
+    })
+}
+
+impl From<&items::ItemRarity> for ItemRarity {
+    fn from(rarity: &items::ItemRarity) -> Self {
+        match rarity {
+            items::ItemRarity::Common => Self::Common,
+            items::ItemRarity::Uncommon => Self::Uncommon,
+            items::ItemRarity::Rare => Self::Rare,
+            items::ItemRarity::Epic => Self::Epic,
+            items::ItemRarity::Legendary => Self::Legendary,
+        }
+    }
+}
+
+fn item_rarity_from_wire(value: i32) -> ConversionResult<items::ItemRarity> {
+    let rarity = ItemRarity::try_from(value).map_err(|_| ProtoConversionError::UnknownEnum {
+        field: "PartyLootRulesRequest.threshold",
+        value,
+    })?;
+
+    Ok(match rarity {
+        ItemRarity::Common => items::ItemRarity::Common,
+        ItemRarity::Uncommon => items::ItemRarity::Uncommon,
+        ItemRarity::Rare => items::ItemRarity::Rare,
+        ItemRarity::Epic => items::ItemRarity::Epic,
+        ItemRarity::Legendary => items::ItemRarity::Legendary,
+    })
+}
@@ -0,0 +1,242 @@
+//! Per-session inbound rate limiting
+//!
+//! Every inbound message is charged against a token bucket for its category.
+//! A message arriving at an empty bucket is rejected with
+//! `ErrorCode::RateLimited`; a session that racks up too many rejections in a
+//! short window is disconnected.
+
+use crate::network::messages::Payload;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Groups of inbound messages that share a budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageCategory {
+    Movement,
+    Combat,
+    CharacterManagement,
+    Acknowledgement,
+    General,
+}
+
+impl MessageCategory {
+    pub fn of(payload: &Payload) -> Self {
+        match payload {
+            Payload::MovementIntent(_) => MessageCategory::Movement,
+            Payload::CombatAction(_) | Payload::ReleaseRequest(_) => MessageCategory::Combat,
+            Payload::SnapshotAck(_) => MessageCategory::Acknowledgement,
+            Payload::AuthRequest(_)
+            | Payload::SessionResumeRequest(_)
+            | Payload::CharacterListRequest(_)
+            | Payload::CharacterCreateRequest(_)
+            | Payload::CharacterSelectRequest(_)
+            | Payload::CharacterDeleteRequest(_) => MessageCategory::CharacterManagement,
+            _ => MessageCategory::General,
+        }
+    }
+
+    fn env_key(self) -> &'static str {
+        match self {
+            MessageCategory::Movement => "RATE_LIMIT_MOVEMENT",
+            MessageCategory::Combat => "RATE_LIMIT_COMBAT",
+            MessageCategory::CharacterManagement => "RATE_LIMIT_CHARACTER",
+            MessageCategory::Acknowledgement => "RATE_LIMIT_ACK",
+            MessageCategory::General => "RATE_LIMIT_GENERAL",
+        }
+    }
+}
+
+/// Burst size and sustained rate for one category
+#[derive(Debug, Clone, Copy)]
+pub struct BucketConfig {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+}
+
+impl BucketConfig {
+    /// Parse `"<capacity>:<refill_per_sec>"`, e.g. `"120:75"`
+    fn parse(value: &str) -> Option<Self> {
+        let (capacity, refill) = value.split_once(':')?;
+        let capacity: f64 = capacity.trim().parse().ok()?;
+        let refill_per_sec: f64 = refill.trim().parse().ok()?;
+        (capacity > 0.0 && refill_per_sec >= 0.0).then_some(Self {
+            capacity,
+            refill_per_sec,
+        })
+    }
+}
+
+/// Rate limiter tuning
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub buckets: HashMap<MessageCategory, BucketConfig>,
+    /// Rejections tolerated within `violation_window` before disconnecting
+    pub max_violations: u32,
+    pub violation_window: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        // The client sends movement every physics frame while moving (~60/s)
+        // and acknowledges every replication tick (20/s)
+        let buckets = HashMap::from([
+            (
+                MessageCategory::Movement,
+                BucketConfig {
+                    capacity: 120.0,
+                    refill_per_sec: 75.0,
+                },
+            ),
+            (
+                MessageCategory::Combat,
+                BucketConfig {
+                    capacity: 10.0,
+                    refill_per_sec: 5.0,
+                },
+            ),
+            (
+                MessageCategory::CharacterManagement,
+                BucketConfig {
+                    capacity: 5.0,
+                    refill_per_sec: 1.0,
+                },
+            ),
+            (
+                MessageCategory::Acknowledgement,
+                BucketConfig {
+                    capacity: 60.0,
+                    refill_per_sec: 30.0,
+                },
+            ),
+            (
+                MessageCategory::General,
+                BucketConfig {
+                    capacity: 30.0,
+                    refill_per_sec: 10.0,
+                },
+            ),
+        ]);
+
+        Self {
+            buckets,
+            max_violations: 30,
+            violation_window: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Defaults overridden by `RATE_LIMIT_<CATEGORY>=capacity:per_sec` and
+    /// `RATE_LIMIT_MAX_VIOLATIONS`
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        for (category, bucket) in config.buckets.iter_mut() {
+            if let Some(parsed) = std::env::var(category.env_key())
+                .ok()
+                .and_then(|value| BucketConfig::parse(&value))
+            {
+                *bucket = parsed;
+            }
+        }
+        if let Some(max) = std::env::var("RATE_LIMIT_MAX_VIOLATIONS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.max_violations = max;
+        }
+        config
+    }
+}
+
+/// Outcome of charging a message against its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allowed,
+    Limited,
+    /// Limited, and the session has exceeded its violation allowance
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    config: BucketConfig,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: BucketConfig, now: Instant) -> Self {
+        Self {
+            config,
+            tokens: config.capacity,
+            last_refill: now,
+        }
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.config.refill_per_sec).min(self.config.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Buckets and violation count for a single session
+#[derive(Debug, Clone)]
+pub struct SessionRateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<MessageCategory, TokenBucket>,
+    violations: u32,
+    window_started: Instant,
+}
+
+impl SessionRateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: HashMap::new(),
+            violations: 0,
+            window_started: Instant::now(),
+        }
+    }
+
+    /// Charge one message in `category`
+    pub fn check(&mut self, category: MessageCategory) -> RateDecision {
+        self.check_at(category, Instant::now())
+    }
+
+    pub(crate) fn check_at(&mut self, category: MessageCategory, now: Instant) -> RateDecision {
+        let Some(&bucket_config) = self.config.buckets.get(&category) else {
+            return RateDecision::Allowed;
+        };
+        let bucket = self
+            .buckets
+            .entry(category)
+            .or_insert_with(|| TokenBucket::new(bucket_config, now));
+
+        if bucket.try_take(now) {
+            return RateDecision::Allowed;
+        }
+
+        if now.saturating_duration_since(self.window_started) > self.config.violation_window {
+            self.window_started = now;
+            self.violations = 0;
+        }
+        self.violations += 1;
+
+        if self.violations > self.config.max_violations {
+            RateDecision::Disconnect
+        } else {
+            RateDecision::Limited
+        }
+    }
+}
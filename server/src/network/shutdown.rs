@@ -0,0 +1,87 @@
+//! Graceful shutdown
+//!
+//! On Ctrl+C or SIGTERM the server stops accepting connections and counts
+//! down `countdown` seconds, sending every connected client a
+//! `ShutdownNotice` when the countdown starts and each second of the last
+//! five. The connections stay open meanwhile. Once the countdown ends, the
+//! world is frozen and every session is taken out of the store. Each
+//! connected client gets a final `Disconnect`, and every character is then
+//! logged out and saved.
+
+use crate::network::messages::{Disconnect, DisconnectReason, Envelope, Payload, ShutdownNotice};
+use crate::network::{Session, SessionStore};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds of the countdown, at the end, that each get a warning
+const FINAL_WARNINGS: u64 = 5;
+
+/// Shutdown tuning
+#[derive(Debug, Clone)]
+pub struct ShutdownConfig {
+    /// Seconds players are warned before the server saves and exits
+    pub countdown: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { countdown: 10 }
+    }
+}
+
+impl ShutdownConfig {
+    /// Read `SHUTDOWN_COUNTDOWN_SECS`, falling back to the default
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let countdown = std::env::var("SHUTDOWN_COUNTDOWN_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.countdown);
+
+        Self { countdown }
+    }
+
+    /// Whether clients are warned when `remaining` seconds are left
+    pub fn is_warning_due(&self, remaining: u64) -> bool {
+        remaining == self.countdown || (1..=FINAL_WARNINGS).contains(&remaining)
+    }
+}
+
+/// Warn every connected client that the server stops in `seconds_remaining`
+pub async fn warn_sessions(store: &SessionStore, seconds_remaining: u64) {
+    let notice = envelope(Payload::ShutdownNotice(ShutdownNotice {
+        seconds_remaining: seconds_remaining.min(u32::MAX as u64) as u32,
+        message: format!("Server shutting down in {} seconds", seconds_remaining),
+    }));
+    for session in store.get_active_sessions().await {
+        if let Some(sender) = &session.sender {
+            let _ = sender.send(notice.clone());
+        }
+    }
+}
+
+/// Take every session out of the store, connected or link-dead, and close
+/// each connection behind a final `Disconnect`. The sessions are returned
+/// for their characters to be logged out.
+pub async fn disconnect_all(store: &SessionStore) -> Vec<Session> {
+    let sessions = store.take_all_sessions().await;
+    for session in &sessions {
+        if let Some(sender) = &session.sender {
+            sender.close_with(envelope(Payload::Disconnect(Disconnect {
+                reason: DisconnectReason::ServerShutdown,
+                message: "Server is shutting down".to_string(),
+            })));
+        }
+    }
+    sessions
+}
+
+fn envelope(payload: Payload) -> Envelope {
+    Envelope {
+        sequence_id: 0,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        payload,
+    }
+}
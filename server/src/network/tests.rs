@@ -0,0 +1,1256 @@
+use crate::items::ItemRarity;
+use crate::loot::{LootMethod, RollChoice};
+use crate::network::codec::{self, WireFormat, FEATURE_BINARY_PROTOBUF, SUPPORTED_FEATURES};
+use crate::network::messages::*;
+use axum::extract::ws::Message;
+
+fn snapshot_envelope() -> Envelope {
+    Envelope {
+        sequence_id: 7,
+        timestamp: 1_700_000_000_000,
+        payload: Payload::WorldSnapshot(WorldSnapshot {
+            snapshot_id: 42,
+            entities: vec![Entity {
+                id: 3,
+                entity_type: "mob".to_string(),
+                position: Vector3 {
+                    x: 1.5,
+                    y: 0.0,
+                    z: -4.25,
+                },
+                rotation: Vector3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                },
+                state: EntityState {
+                    movement_state: MovementState::Walking,
+                    health_percent: 0.5,
+                    display_name: "Wolf".to_string(),
+                    resource: Some(ResourceState {
+                        resource_type: "rage".to_string(),
+                        current: 35,
+                        maximum: 100,
+                    }),
+                },
+            }],
+            player_entity_id: 1,
+            zone_name: "starter_zone".to_string(),
+            server_tick: 118,
+        }),
+    }
+}
+
+#[test]
+fn test_protobuf_roundtrip_preserves_snapshot() {
+    let frame = codec::encode(&snapshot_envelope(), WireFormat::Protobuf).unwrap();
+    let Message::Binary(bytes) = frame else {
+        panic!("expected a binary frame");
+    };
+
+    let decoded = codec::decode_protobuf(&bytes).unwrap();
+    assert_eq!(decoded.sequence_id, 7);
+    assert_eq!(decoded.timestamp, 1_700_000_000_000);
+    let Payload::WorldSnapshot(snapshot) = decoded.payload else {
+        panic!("expected a world snapshot");
+    };
+    assert_eq!(snapshot.snapshot_id, 42);
+    assert_eq!(snapshot.zone_name, "starter_zone");
+    assert_eq!(snapshot.entities.len(), 1);
+    let entity = &snapshot.entities[0];
+    assert_eq!(entity.position.z, -4.25);
+    assert_eq!(entity.state.display_name, "Wolf");
+    assert!(matches!(
+        entity.state.movement_state,
+        MovementState::Walking
+    ));
+}
+
+#[test]
+fn test_protobuf_roundtrip_preserves_effects_and_optionals() {
+    let envelope = Envelope {
+        sequence_id: 1,
+        timestamp: 0,
+        payload: Payload::EntityUpdate(EntityUpdate {
+            entity_id: 9,
+            position: None,
+            rotation: None,
+            state: None,
+            effects: vec![
+                EntityEffect::DamageNumber {
+                    amount: 12,
+                    is_critical: true,
+                    source_id: 4,
+                    ability_id: 1,
+                    kind: CombatEventKind::Damage,
+                    killing_blow: true,
+                },
+                EntityEffect::ActionRejected {
+                    ability_id: 2,
+                    target_id: 4,
+                    reason: "Not enough mana".to_string(),
+                },
+                EntityEffect::Death,
+                EntityEffect::LevelUp { level: 3 },
+            ],
+        }),
+    };
+
+    let Message::Binary(bytes) = codec::encode(&envelope, WireFormat::Protobuf).unwrap() else {
+        panic!("expected a binary frame");
+    };
+    let Payload::EntityUpdate(update) = codec::decode_protobuf(&bytes).unwrap().payload else {
+        panic!("expected an entity update");
+    };
+    assert_eq!(update.entity_id, 9);
+    assert!(update.position.is_none());
+    assert!(matches!(
+        update.effects[0],
+        EntityEffect::DamageNumber {
+            amount: 12,
+            is_critical: true,
+            source_id: 4,
+            ability_id: 1,
+            kind: CombatEventKind::Damage,
+            killing_blow: true,
+        }
+    ));
+    assert!(matches!(
+        &update.effects[1],
+        EntityEffect::ActionRejected { ability_id: 2, target_id: 4, reason }
+            if reason == "Not enough mana"
+    ));
+    assert!(matches!(update.effects[2], EntityEffect::Death));
+    assert!(matches!(
+        update.effects[3],
+        EntityEffect::LevelUp { level: 3 }
+    ));
+}
+
+#[test]
+fn test_combat_action_target_position_is_optional() {
+    let action = |target_position| Envelope {
+        sequence_id: 2,
+        timestamp: 0,
+        payload: Payload::CombatAction(CombatAction {
+            action_type: ActionType::Ability,
+            target_entity_id: 0,
+            ability_id: 8,
+            target_position,
+        }),
+    };
+    let roundtrip = |envelope: &Envelope| {
+        let Message::Binary(bytes) = codec::encode(envelope, WireFormat::Protobuf).unwrap() else {
+            panic!("expected a binary frame");
+        };
+        let Payload::CombatAction(action) = codec::decode_protobuf(&bytes).unwrap().payload else {
+            panic!("expected a combat action");
+        };
+        action
+    };
+
+    let aimed = roundtrip(&action(Some(Vector3 {
+        x: 20.0,
+        y: 0.0,
+        z: -3.5,
+    })));
+    let position = aimed.target_position.unwrap();
+    assert_eq!((position.x, position.z), (20.0, -3.5));
+    assert!(roundtrip(&action(None)).target_position.is_none());
+}
+
+#[test]
+fn test_loot_response_roundtrip_keeps_slots() {
+    let envelope = Envelope {
+        sequence_id: 3,
+        timestamp: 0,
+        payload: Payload::LootResponse(LootResponse {
+            corpse_id: 12,
+            success: true,
+            error_message: None,
+            gold: 9,
+            items: vec![LootSlot {
+                slot: 2,
+                item: ItemInstance {
+                    definition_id: 200,
+                    quantity: 3,
+                    is_bound: false,
+                    durability: None,
+                },
+            }],
+        }),
+    };
+
+    let Message::Binary(bytes) = codec::encode(&envelope, WireFormat::Protobuf).unwrap() else {
+        panic!("expected a binary frame");
+    };
+    let Payload::LootResponse(response) = codec::decode_protobuf(&bytes).unwrap().payload else {
+        panic!("expected a loot response");
+    };
+    assert_eq!((response.corpse_id, response.gold), (12, 9));
+    assert!(response.error_message.is_none());
+    assert_eq!(response.items.len(), 1);
+    assert_eq!(response.items[0].slot, 2);
+    assert_eq!(
+        (
+            response.items[0].item.definition_id,
+            response.items[0].item.quantity
+        ),
+        (200, 3)
+    );
+
+    let text = r#"{"sequence_id":4,"timestamp":0,"payload":{"LootTakeRequest":{"corpse_id":12,"slot":2}}}"#;
+    let Payload::LootTakeRequest(take) = codec::decode_json(text).unwrap().payload else {
+        panic!("expected a loot take request");
+    };
+    assert_eq!((take.corpse_id, take.slot), (12, 2));
+
+    let roll = Envelope {
+        sequence_id: 5,
+        timestamp: 0,
+        payload: Payload::LootRollRequest(LootRollRequest {
+            corpse_id: 12,
+            slot: 1,
+            choice: RollChoice::Greed,
+        }),
+    };
+    let Message::Binary(bytes) = codec::encode(&roll, WireFormat::Protobuf).unwrap() else {
+        panic!("expected a binary frame");
+    };
+    let Payload::LootRollRequest(roll) = codec::decode_protobuf(&bytes).unwrap().payload else {
+        panic!("expected a loot roll request");
+    };
+    assert_eq!(
+        (roll.corpse_id, roll.slot, roll.choice),
+        (12, 1, RollChoice::Greed)
+    );
+
+    let result = Envelope {
+        sequence_id: 6,
+        timestamp: 0,
+        payload: Payload::EntityUpdate(EntityUpdate {
+            entity_id: 7,
+            position: None,
+            rotation: None,
+            state: None,
+            effects: vec![EntityEffect::LootRollResult {
+                corpse_id: 12,
+                slot: 1,
+                item_id: 2,
+                winner_id: 7,
+                choice: RollChoice::Need,
+                roll: 88,
+            }],
+        }),
+    };
+    let Message::Binary(bytes) = codec::encode(&result, WireFormat::Protobuf).unwrap() else {
+        panic!("expected a binary frame");
+    };
+    let Payload::EntityUpdate(update) = codec::decode_protobuf(&bytes).unwrap().payload else {
+        panic!("expected an entity update");
+    };
+    assert!(matches!(
+        update.effects[0],
+        EntityEffect::LootRollResult {
+            corpse_id: 12,
+            slot: 1,
+            item_id: 2,
+            winner_id: 7,
+            choice: RollChoice::Need,
+            roll: 88,
+        }
+    ));
+}
+
+#[test]
+fn test_party_effects_roundtrip_through_binary_frames() {
+    let envelope = Envelope {
+        sequence_id: 3,
+        timestamp: 0,
+        payload: Payload::EntityUpdate(EntityUpdate {
+            entity_id: 8,
+            position: None,
+            rotation: None,
+            state: None,
+            effects: vec![
+                EntityEffect::PartyInvite {
+                    inviter_id: 7,
+                    inviter_name: "Aria".to_string(),
+                },
+                EntityEffect::PartyChanged {
+                    party_id: 1,
+                    leader_id: 7,
+                    members: vec![
+                        PartyMember {
+                            player_id: 7,
+                            name: "Aria".to_string(),
+                        },
+                        PartyMember {
+                            player_id: 8,
+                            name: "Bram".to_string(),
+                        },
+                    ],
+                    method: LootMethod::FreeForAll,
+                    threshold: ItemRarity::Rare,
+                },
+                EntityEffect::PartyRefused {
+                    reason: "That player is already in a party".to_string(),
+                },
+            ],
+        }),
+    };
+    let Message::Binary(bytes) = codec::encode(&envelope, WireFormat::Protobuf).unwrap() else {
+        panic!("expected a binary frame");
+    };
+    let decoded = codec::decode_protobuf(&bytes).unwrap();
+    assert_eq!(
+        serde_json::to_value(&decoded).unwrap(),
+        serde_json::to_value(&envelope).unwrap()
+    );
+}
+
+#[test]
+fn test_json_frames_still_decode() {
+    let text = r#"{"sequence_id":1,"timestamp":5,"payload":{"HandshakeRequest":{"client_version":"0.1.0","protocol_version":"1.0","supported_features":0}}}"#;
+    let envelope = codec::decode_json(text).unwrap();
+    assert!(matches!(envelope.payload, Payload::HandshakeRequest(_)));
+
+    let frame = codec::encode(&envelope, WireFormat::Json).unwrap();
+    assert!(matches!(frame, Message::Text(_)));
+}
+
+#[test]
+fn test_snapshot_ack_decodes_from_binary_frames() {
+    let envelope = Envelope {
+        sequence_id: 2,
+        timestamp: 0,
+        payload: Payload::SnapshotAck(SnapshotAck { snapshot_id: 42 }),
+    };
+    let Message::Binary(bytes) = codec::encode(&envelope, WireFormat::Protobuf).unwrap() else {
+        panic!("expected a binary frame");
+    };
+    let Payload::SnapshotAck(ack) = codec::decode_protobuf(&bytes).unwrap().payload else {
+        panic!("expected a snapshot ack");
+    };
+    assert_eq!(ack.snapshot_id, 42);
+}
+
+#[test]
+fn test_release_request_decodes_from_binary_frames() {
+    let envelope = Envelope {
+        sequence_id: 3,
+        timestamp: 0,
+        payload: Payload::ReleaseRequest(ReleaseRequest {}),
+    };
+    let Message::Binary(bytes) = codec::encode(&envelope, WireFormat::Protobuf).unwrap() else {
+        panic!("expected a binary frame");
+    };
+    let decoded = codec::decode_protobuf(&bytes).unwrap();
+    assert!(matches!(decoded.payload, Payload::ReleaseRequest(_)));
+}
+
+/// The oneof tag each payload travels under in `proto/messages.proto`. The
+/// match has no wildcard arm, so a new payload does not compile until it is
+/// given a tag here and a sample in `one_of_every_payload`.
+fn wire_tag(payload: &Payload) -> u32 {
+    match payload {
+        Payload::HandshakeRequest(_) => 3,
+        Payload::HandshakeResponse(_) => 4,
+        Payload::AuthRequest(_) => 5,
+        Payload::AuthResponse(_) => 6,
+        Payload::Ping(_) => 7,
+        Payload::Pong(_) => 8,
+        Payload::Error(_) => 9,
+        Payload::Disconnect(_) => 10,
+        Payload::WorldSnapshot(_) => 11,
+        Payload::MovementIntent(_) => 12,
+        Payload::CombatAction(_) => 13,
+        Payload::EntityUpdate(_) => 14,
+        Payload::CharacterListRequest(_) => 15,
+        Payload::CharacterListResponse(_) => 16,
+        Payload::CharacterCreateRequest(_) => 17,
+        Payload::CharacterCreateResponse(_) => 18,
+        Payload::CharacterSelectRequest(_) => 19,
+        Payload::CharacterSelectResponse(_) => 20,
+        Payload::CharacterDeleteRequest(_) => 21,
+        Payload::CharacterDeleteResponse(_) => 22,
+        Payload::InventoryRequest(_) => 23,
+        Payload::InventoryResponse(_) => 24,
+        Payload::ItemMoveRequest(_) => 25,
+        Payload::ItemMoveResponse(_) => 26,
+        Payload::EquipmentRequest(_) => 27,
+        Payload::EquipmentResponse(_) => 28,
+        Payload::ItemEquipRequest(_) => 29,
+        Payload::ItemEquipResponse(_) => 30,
+        Payload::EntityUpdateBatch(_) => 31,
+        Payload::SessionResumeRequest(_) => 32,
+        Payload::SessionResumeResponse(_) => 33,
+        Payload::SnapshotAck(_) => 34,
+        Payload::ReleaseRequest(_) => 35,
+        Payload::LootOpenRequest(_) => 36,
+        Payload::LootTakeRequest(_) => 37,
+        Payload::LootResponse(_) => 38,
+        Payload::LootRollRequest(_) => 39,
+        Payload::ShutdownNotice(_) => 40,
+        Payload::PartyInviteRequest(_) => 41,
+        Payload::PartyInviteReply(_) => 42,
+        Payload::PartyLeaveRequest(_) => 43,
+        Payload::PartyLootRulesRequest(_) => 44,
+    }
+}
+
+fn sample_item() -> ItemInstance {
+    ItemInstance {
+        definition_id: 4,
+        quantity: 2,
+        is_bound: true,
+        durability: Some(ItemDurability {
+            current: 30,
+            maximum: 40,
+        }),
+    }
+}
+
+fn sample_character() -> CharacterInfo {
+    CharacterInfo {
+        id: 11,
+        name: "Aria".to_string(),
+        class: "warrior".to_string(),
+        level: 3,
+        experience: 250,
+        zone_id: "starter_zone".to_string(),
+        health: 90,
+        max_health: 120,
+        resource_type: "rage".to_string(),
+        resource_value: 20,
+        max_resource: 100,
+        is_online: true,
+    }
+}
+
+fn one_of_every_payload() -> Vec<Payload> {
+    let Payload::WorldSnapshot(snapshot) = snapshot_envelope().payload else {
+        unreachable!();
+    };
+    let entity = snapshot.entities[0].clone();
+    vec![
+        Payload::HandshakeRequest(handshake("0.1.0", "1.0")),
+        Payload::HandshakeResponse(HandshakeResponse {
+            accepted: true,
+            server_version: "0.1.0".to_string(),
+            protocol_version: "1.0".to_string(),
+            server_features: FEATURE_BINARY_PROTOBUF,
+            message: "Welcome".to_string(),
+        }),
+        Payload::AuthRequest(AuthRequest {
+            username: "aria".to_string(),
+            password_hash: "hash".to_string(),
+            character_name: Some("Aria".to_string()),
+        }),
+        Payload::AuthResponse(AuthResponse {
+            success: true,
+            session_token: Some("token".to_string()),
+            message: "Authenticated".to_string(),
+            player_id: Some(1),
+            character_id: Some(11),
+        }),
+        Payload::Ping(Ping { timestamp: 5 }),
+        Payload::Pong(Pong { timestamp: 6 }),
+        Payload::Error(Error {
+            code: ErrorCode::RateLimited,
+            message: "Slow down".to_string(),
+            details: [("limit".to_string(), "20".to_string())].into(),
+        }),
+        Payload::Disconnect(Disconnect {
+            reason: DisconnectReason::SlowConnection,
+            message: "Too far behind".to_string(),
+        }),
+        Payload::WorldSnapshot(snapshot),
+        Payload::MovementIntent(MovementIntent {
+            target_position: entity.position.clone(),
+            speed_modifier: 1.5,
+            stop_movement: true,
+            rotation_y: 0.25,
+        }),
+        Payload::CombatAction(CombatAction {
+            action_type: ActionType::Ability,
+            target_entity_id: 3,
+            ability_id: 2,
+            target_position: Some(entity.position.clone()),
+        }),
+        Payload::EntityUpdate(EntityUpdate {
+            entity_id: 3,
+            position: Some(entity.position.clone()),
+            rotation: Some(entity.rotation.clone()),
+            state: Some(entity.state.clone()),
+            effects: vec![EntityEffect::LevelUp { level: 4 }],
+        }),
+        Payload::CharacterListRequest(CharacterListRequest { request: true }),
+        Payload::CharacterListResponse(CharacterListResponse {
+            characters: vec![sample_character()],
+        }),
+        Payload::CharacterCreateRequest(CharacterCreateRequest {
+            name: "Aria".to_string(),
+            class: "warrior".to_string(),
+        }),
+        Payload::CharacterCreateResponse(CharacterCreateResponse {
+            success: false,
+            character: None,
+            error_message: Some("Name taken".to_string()),
+        }),
+        Payload::CharacterSelectRequest(CharacterSelectRequest { character_id: 11 }),
+        Payload::CharacterSelectResponse(CharacterSelectResponse {
+            success: true,
+            character: Some(sample_character()),
+            error_message: None,
+        }),
+        Payload::CharacterDeleteRequest(CharacterDeleteRequest { character_id: 11 }),
+        Payload::CharacterDeleteResponse(CharacterDeleteResponse {
+            success: false,
+            error_message: Some("Not yours".to_string()),
+        }),
+        Payload::InventoryRequest(InventoryRequest),
+        Payload::InventoryResponse(InventoryResponse {
+            slots: vec![InventorySlot {
+                slot_id: 3,
+                item: sample_item(),
+            }],
+            max_slots: 20,
+        }),
+        Payload::ItemMoveRequest(ItemMoveRequest {
+            from_slot: 1,
+            to_slot: 2,
+        }),
+        Payload::ItemMoveResponse(ItemMoveResponse {
+            success: false,
+            error_message: Some("Slot is empty".to_string()),
+        }),
+        Payload::EquipmentRequest(EquipmentRequest),
+        Payload::EquipmentResponse(EquipmentResponse {
+            slots: vec![EquipmentSlot {
+                slot_type: 5,
+                item: sample_item(),
+            }],
+        }),
+        Payload::ItemEquipRequest(ItemEquipRequest {
+            inventory_slot: 3,
+            equipment_slot: 5,
+            unequip: true,
+        }),
+        Payload::ItemEquipResponse(ItemEquipResponse {
+            success: true,
+            error_message: None,
+        }),
+        Payload::EntityUpdateBatch(EntityUpdateBatch {
+            snapshot_id: 8,
+            baseline_id: 6,
+            spawned: vec![entity],
+            updated: vec![],
+            despawned: vec![9],
+            server_tick: 30,
+        }),
+        Payload::SessionResumeRequest(SessionResumeRequest {
+            session_token: "token".to_string(),
+        }),
+        Payload::SessionResumeResponse(SessionResumeResponse {
+            success: true,
+            session_token: Some("fresh".to_string()),
+            message: "Resumed".to_string(),
+            player_id: Some(1),
+            character_id: Some(11),
+        }),
+        Payload::SnapshotAck(SnapshotAck { snapshot_id: 42 }),
+        Payload::ReleaseRequest(ReleaseRequest {}),
+        Payload::LootOpenRequest(LootOpenRequest { corpse_id: 12 }),
+        Payload::LootTakeRequest(LootTakeRequest {
+            corpse_id: 12,
+            slot: 1,
+        }),
+        Payload::LootResponse(LootResponse {
+            corpse_id: 12,
+            success: true,
+            error_message: None,
+            gold: 7,
+            items: vec![LootSlot {
+                slot: 1,
+                item: sample_item(),
+            }],
+        }),
+        Payload::LootRollRequest(LootRollRequest {
+            corpse_id: 12,
+            slot: 1,
+            choice: RollChoice::Greed,
+        }),
+        Payload::ShutdownNotice(ShutdownNotice {
+            seconds_remaining: 5,
+            message: "Server shutting down in 5 seconds".to_string(),
+        }),
+        Payload::PartyInviteRequest(PartyInviteRequest { player_id: 8 }),
+        Payload::PartyInviteReply(PartyInviteReply { accept: true }),
+        Payload::PartyLeaveRequest(PartyLeaveRequest::default()),
+        Payload::PartyLootRulesRequest(PartyLootRulesRequest {
+            method: LootMethod::RoundRobin,
+            threshold: ItemRarity::Epic,
+        }),
+    ]
+}
+
+#[test]
+fn test_every_payload_roundtrips_through_binary_frames() {
+    let payloads = one_of_every_payload();
+    let mut tags: Vec<u32> = payloads.iter().map(wire_tag).collect();
+    tags.sort_unstable();
+    tags.dedup();
+    let expected: Vec<u32> = (3..3 + payloads.len() as u32).collect();
+    assert_eq!(tags, expected, "one sample per payload, tags without gaps");
+
+    for payload in payloads {
+        let tag = wire_tag(&payload);
+        let envelope = Envelope {
+            sequence_id: 1,
+            timestamp: 2,
+            payload,
+        };
+        let Message::Binary(bytes) = codec::encode(&envelope, WireFormat::Protobuf).unwrap() else {
+            panic!("expected a binary frame");
+        };
+        assert_eq!(
+            payload_tag(&bytes),
+            tag,
+            "tag {} went out under another",
+            tag
+        );
+        let decoded = codec::decode_protobuf(&bytes)
+            .unwrap_or_else(|err| panic!("tag {} failed to decode: {}", tag, err));
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&envelope).unwrap(),
+            "tag {} changed on the way through",
+            tag
+        );
+    }
+}
+
+/// The tag of the payload field in an encoded envelope, skipping the
+/// `sequence_id` and `timestamp` varints ahead of it
+fn payload_tag(mut bytes: &[u8]) -> u32 {
+    loop {
+        let (tag, _) = prost::encoding::decode_key(&mut bytes).unwrap();
+        if tag > 2 {
+            return tag;
+        }
+        prost::encoding::decode_varint(&mut bytes).unwrap();
+    }
+}
+
+#[test]
+fn test_feature_negotiation_switches_after_handshake() {
+    assert_eq!(codec::negotiate_features(0, SUPPORTED_FEATURES), 0);
+    assert_eq!(
+        codec::negotiate_features(FEATURE_BINARY_PROTOBUF | 1 << 31, SUPPORTED_FEATURES),
+        FEATURE_BINARY_PROTOBUF
+    );
+    assert_eq!(codec::negotiate_features(FEATURE_BINARY_PROTOBUF, 0), 0);
+
+    let response = |server_features| Envelope {
+        sequence_id: 1,
+        timestamp: 0,
+        payload: Payload::HandshakeResponse(HandshakeResponse {
+            accepted: true,
+            server_version: "0.1.0".to_string(),
+            protocol_version: "1.0".to_string(),
+            server_features,
+            message: String::new(),
+        }),
+    };
+
+    assert_eq!(
+        WireFormat::Json.after_send(&response(FEATURE_BINARY_PROTOBUF)),
+        WireFormat::Protobuf
+    );
+    assert_eq!(WireFormat::Json.after_send(&response(0)), WireFormat::Json);
+    assert_eq!(
+        WireFormat::Protobuf.after_send(&snapshot_envelope()),
+        WireFormat::Protobuf
+    );
+}
+
+fn handshake(client_version: &str, protocol_version: &str) -> HandshakeRequest {
+    HandshakeRequest {
+        client_version: client_version.to_string(),
+        protocol_version: protocol_version.to_string(),
+        supported_features: 0,
+    }
+}
+
+#[test]
+fn test_handshake_version_range() {
+    use crate::network::handshake::{validate, HandshakeError};
+
+    assert!(validate(&handshake("0.1.0", "1.0")).is_ok());
+    assert!(validate(&handshake("0.2", "1.0.0")).is_ok());
+
+    assert!(matches!(
+        validate(&handshake("0.1.0", "2.0")),
+        Err(HandshakeError::UnsupportedProtocol { .. })
+    ));
+    assert!(matches!(
+        validate(&handshake("0.1.0", "0.9")),
+        Err(HandshakeError::UnsupportedProtocol { .. })
+    ));
+    assert!(matches!(
+        validate(&handshake("0.0.9", "1.0")),
+        Err(HandshakeError::ClientTooOld { .. })
+    ));
+    assert!(matches!(
+        validate(&handshake("0.1.0", "")),
+        Err(HandshakeError::MalformedVersion {
+            field: "protocol_version",
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(&handshake("dev-build", "1.0")),
+        Err(HandshakeError::MalformedVersion {
+            field: "client_version",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_session_token_roundtrip_and_rejection() {
+    use crate::network::session_token::{SessionTokenSigner, TokenError};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    let signer = SessionTokenSigner::new(b"test-secret".to_vec(), Duration::from_secs(60));
+    let (session_id, account_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let token = signer.issue(session_id, account_id);
+
+    let claims = signer.verify(&token).unwrap();
+    assert_eq!(claims.session_id, session_id);
+    assert_eq!(claims.account_id, account_id);
+
+    assert_eq!(
+        signer.verify_at(&token, claims.expires_at),
+        Err(TokenError::Expired)
+    );
+
+    let other = SessionTokenSigner::new(b"other-secret".to_vec(), Duration::from_secs(60));
+    assert_eq!(other.verify(&token), Err(TokenError::BadSignature));
+
+    let (payload, signature) = token.split_once('.').unwrap();
+    let forged = format!("{}x.{}", payload, signature);
+    assert_eq!(signer.verify(&forged), Err(TokenError::BadSignature));
+    assert_eq!(signer.verify("not-a-token"), Err(TokenError::Malformed));
+}
+
+#[tokio::test]
+async fn test_resume_reclaims_link_dead_session() {
+    use crate::network::session_token::ResumeClaims;
+    use crate::network::{ResumeError, SessionStore};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    let store = SessionStore::new();
+    let account_id = Uuid::new_v4();
+    let original = store.create_session().await;
+    let (old_tx, _old_rx) = crate::network::outbound::channel(Default::default());
+    store.set_sender(&original, Some(old_tx.clone())).await;
+    store
+        .authenticate_session(&original, account_id, 7, Some(Uuid::new_v4()))
+        .await;
+
+    let claims = ResumeClaims {
+        session_id: original,
+        account_id,
+        expires_at: u64::MAX,
+    };
+
+    let reconnect = store.create_session().await;
+    let (new_tx, _new_rx) = crate::network::outbound::channel(Default::default());
+    store.set_sender(&reconnect, Some(new_tx.clone())).await;
+    assert_eq!(
+        store.resume_session(&claims, &reconnect).await.unwrap_err(),
+        ResumeError::StillConnected
+    );
+
+    assert!(store.detach_sender(&original, &old_tx).await);
+    store.mark_link_dead(&original).await;
+
+    let wrong_account = ResumeClaims {
+        account_id: Uuid::new_v4(),
+        ..claims.clone()
+    };
+    assert_eq!(
+        store
+            .resume_session(&wrong_account, &reconnect)
+            .await
+            .unwrap_err(),
+        ResumeError::NotFound
+    );
+
+    let resumed = store.resume_session(&claims, &reconnect).await.unwrap();
+    assert_eq!(resumed.id, original);
+    assert_eq!(resumed.player_id, Some(7));
+    assert!(resumed.link_dead_since.is_none());
+    assert!(store.get_session(&reconnect).await.is_none());
+    // The old connection no longer owns the session
+    assert!(!store.detach_sender(&original, &old_tx).await);
+    assert!(store.detach_sender(&original, &new_tx).await);
+
+    store.mark_link_dead(&original).await;
+    assert!(store
+        .take_expired_link_dead(Duration::from_secs(60))
+        .await
+        .is_empty());
+    let expired = store.take_expired_link_dead(Duration::ZERO).await;
+    assert_eq!(expired.len(), 1);
+    assert!(store.get_session(&original).await.is_none());
+}
+
+#[tokio::test]
+async fn test_resume_is_refused_once_the_connection_has_a_character() {
+    use crate::network::session_token::ResumeClaims;
+    use crate::network::{ResumeError, SessionStore};
+    use uuid::Uuid;
+
+    let store = SessionStore::new();
+    let account_id = Uuid::new_v4();
+    let original = store.create_session().await;
+    store
+        .authenticate_session(&original, account_id, 7, Some(Uuid::new_v4()))
+        .await;
+    store.mark_link_dead(&original).await;
+
+    // Logging in alone does not get in the way
+    let reconnect = store.create_session().await;
+    store
+        .authenticate_session(&reconnect, account_id, 8, None)
+        .await;
+    let claims = ResumeClaims {
+        session_id: original,
+        account_id,
+        expires_at: u64::MAX,
+    };
+    store.resume_session(&claims, &reconnect).await.unwrap();
+    store.mark_link_dead(&original).await;
+
+    // A connection that selected a character before asking to resume is refused
+    let reconnect = store.create_session().await;
+    let selected = Uuid::new_v4();
+    store
+        .authenticate_session(&reconnect, account_id, 8, Some(selected))
+        .await;
+    assert_eq!(
+        store.resume_session(&claims, &reconnect).await.unwrap_err(),
+        ResumeError::AlreadyInWorld
+    );
+    // Both characters still belong to a session that will log them out
+    let kept = store.get_session(&reconnect).await.unwrap();
+    assert_eq!(
+        (kept.player_id, kept.character_id),
+        (Some(8), Some(selected))
+    );
+    let original = store.get_session(&original).await.unwrap();
+    assert!(original.link_dead_since.is_some());
+}
+
+#[tokio::test]
+async fn test_resumed_session_accepts_acks_from_the_new_view() {
+    use crate::network::session_token::ResumeClaims;
+    use crate::network::SessionStore;
+    use uuid::Uuid;
+
+    let store = SessionStore::new();
+    let account_id = Uuid::new_v4();
+    let original = store.create_session().await;
+    store
+        .authenticate_session(&original, account_id, 7, Some(Uuid::new_v4()))
+        .await;
+    store.acknowledge_snapshot(&original, 40).await;
+    store.mark_link_dead(&original).await;
+
+    let reconnect = store.create_session().await;
+    let claims = ResumeClaims {
+        session_id: original,
+        account_id,
+        expires_at: u64::MAX,
+    };
+    let resumed = store.resume_session(&claims, &reconnect).await.unwrap();
+    assert_eq!(resumed.acked_snapshot_id, None);
+
+    // The new connection's snapshots are numbered from the start again
+    store.acknowledge_snapshot(&original, 1).await;
+    let session = store.get_session(&original).await.unwrap();
+    assert_eq!(session.acked_snapshot_id, Some(1));
+}
+
+#[tokio::test]
+async fn test_link_dead_characters_count_as_in_the_world() {
+    use crate::network::SessionStore;
+    use uuid::Uuid;
+
+    let store = SessionStore::new();
+    let (tx, _rx) = crate::network::outbound::channel(Default::default());
+    let connected = store.create_session().await;
+    store.set_sender(&connected, Some(tx.clone())).await;
+    store
+        .authenticate_session(&connected, Uuid::new_v4(), 7, Some(Uuid::new_v4()))
+        .await;
+    let link_dead = store.create_session().await;
+    store
+        .authenticate_session(&link_dead, Uuid::new_v4(), 8, Some(Uuid::new_v4()))
+        .await;
+    store.mark_link_dead(&link_dead).await;
+    // Still picking a character
+    let lobby = store.create_session().await;
+    store.set_sender(&lobby, Some(tx)).await;
+
+    let mut in_world: Vec<Uuid> = store
+        .get_sessions_in_world()
+        .await
+        .iter()
+        .map(|session| session.id)
+        .collect();
+    in_world.sort();
+    let mut expected = vec![connected, link_dead];
+    expected.sort();
+    assert_eq!(in_world, expected);
+    assert_eq!(store.get_active_sessions().await.len(), 2);
+}
+
+#[test]
+fn test_rate_limiter_buckets_and_disconnect() {
+    use crate::network::rate_limit::{
+        BucketConfig, MessageCategory, RateDecision, RateLimitConfig, SessionRateLimiter,
+    };
+    use std::time::{Duration, Instant};
+
+    let mut config = RateLimitConfig {
+        max_violations: 2,
+        ..RateLimitConfig::default()
+    };
+    config.buckets.insert(
+        MessageCategory::Combat,
+        BucketConfig {
+            capacity: 2.0,
+            refill_per_sec: 1.0,
+        },
+    );
+    let mut limiter = SessionRateLimiter::new(config);
+    let start = Instant::now();
+
+    assert_eq!(
+        limiter.check_at(MessageCategory::Combat, start),
+        RateDecision::Allowed
+    );
+    assert_eq!(
+        limiter.check_at(MessageCategory::Combat, start),
+        RateDecision::Allowed
+    );
+    assert_eq!(
+        limiter.check_at(MessageCategory::Combat, start),
+        RateDecision::Limited
+    );
+
+    // Other categories keep their own budget
+    assert_eq!(
+        limiter.check_at(MessageCategory::Movement, start),
+        RateDecision::Allowed
+    );
+
+    // Budget refills over time
+    let later = start + Duration::from_secs(1);
+    assert_eq!(
+        limiter.check_at(MessageCategory::Combat, later),
+        RateDecision::Allowed
+    );
+
+    assert_eq!(
+        limiter.check_at(MessageCategory::Combat, later),
+        RateDecision::Limited
+    );
+    assert_eq!(
+        limiter.check_at(MessageCategory::Combat, later),
+        RateDecision::Disconnect
+    );
+}
+
+fn batch_envelope(batch: EntityUpdateBatch) -> Envelope {
+    Envelope {
+        sequence_id: batch.snapshot_id as u32,
+        timestamp: 0,
+        payload: Payload::EntityUpdateBatch(batch),
+    }
+}
+
+fn pong_envelope(timestamp: u64) -> Envelope {
+    Envelope {
+        sequence_id: 0,
+        timestamp: 0,
+        payload: Payload::Pong(Pong { timestamp }),
+    }
+}
+
+#[tokio::test]
+async fn test_outbound_queue_coalesces_state_and_keeps_reliable() {
+    use crate::network::outbound::{self, OutboundConfig};
+
+    let (tx, mut rx) = outbound::channel(OutboundConfig::default());
+    let mut snapshot = snapshot_envelope();
+    if let Payload::WorldSnapshot(snapshot) = &mut snapshot.payload {
+        snapshot.entities[0].id = 1;
+    }
+    let mut spawned = match &snapshot.payload {
+        Payload::WorldSnapshot(snapshot) => snapshot.entities[0].clone(),
+        _ => unreachable!(),
+    };
+    spawned.id = 2;
+
+    tx.send(snapshot).unwrap();
+    tx.send(pong_envelope(1)).unwrap();
+    tx.send(batch_envelope(EntityUpdateBatch {
+        snapshot_id: 43,
+        baseline_id: 42,
+        spawned: vec![spawned],
+        updated: vec![EntityUpdate {
+            entity_id: 1,
+            position: Some(Vector3 {
+                x: 9.0,
+                y: 0.0,
+                z: 9.0,
+            }),
+            rotation: None,
+            state: None,
+            effects: Vec::new(),
+        }],
+        despawned: Vec::new(),
+        server_tick: 120,
+    }))
+    .unwrap();
+    tx.send(pong_envelope(2)).unwrap();
+    assert_eq!(rx.len(), 3);
+
+    // The snapshot keeps its place ahead of later messages and absorbed the delta
+    match rx.recv().await.unwrap().payload {
+        Payload::WorldSnapshot(snapshot) => {
+            assert_eq!(snapshot.snapshot_id, 43);
+            assert_eq!(snapshot.server_tick, 120);
+            let ids: Vec<u64> = snapshot.entities.iter().map(|entity| entity.id).collect();
+            assert_eq!(ids, vec![1, 2]);
+            assert_eq!(snapshot.entities[0].position.x, 9.0);
+        }
+        other => panic!("expected snapshot, got {:?}", other),
+    }
+    assert!(matches!(
+        rx.recv().await.unwrap().payload,
+        Payload::Pong(Pong { timestamp: 1 })
+    ));
+    assert!(matches!(
+        rx.recv().await.unwrap().payload,
+        Payload::Pong(Pong { timestamp: 2 })
+    ));
+
+    // A newer delta against the acknowledged baseline replaces a pending one
+    tx.send(batch_envelope(EntityUpdateBatch {
+        snapshot_id: 44,
+        baseline_id: 43,
+        spawned: Vec::new(),
+        updated: vec![EntityUpdate {
+            entity_id: 1,
+            position: None,
+            rotation: Some(Vector3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }),
+            state: None,
+            effects: vec![EntityEffect::Death],
+        }],
+        despawned: Vec::new(),
+        server_tick: 121,
+    }))
+    .unwrap();
+    tx.send(batch_envelope(EntityUpdateBatch {
+        snapshot_id: 45,
+        baseline_id: 43,
+        spawned: Vec::new(),
+        updated: vec![EntityUpdate {
+            entity_id: 1,
+            position: Some(Vector3 {
+                x: 1.0,
+                y: 0.0,
+                z: 1.0,
+            }),
+            rotation: None,
+            state: None,
+            effects: Vec::new(),
+        }],
+        despawned: vec![2],
+        server_tick: 122,
+    }))
+    .unwrap();
+    assert_eq!(rx.len(), 1);
+    match rx.recv().await.unwrap().payload {
+        Payload::EntityUpdateBatch(batch) => {
+            assert_eq!(batch.snapshot_id, 45);
+            assert_eq!(batch.updated.len(), 1);
+            assert!(batch.updated[0].position.is_some());
+            assert!(batch.updated[0].rotation.is_none());
+            // The superseded batch's events are still delivered
+            assert!(matches!(
+                batch.updated[0].effects[..],
+                [EntityEffect::Death]
+            ));
+            assert_eq!(batch.despawned, vec![2]);
+        }
+        other => panic!("expected batch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_outbound_limits_fall_back_unless_positive() {
+    use crate::network::outbound::OutboundConfig;
+    use std::time::Duration;
+
+    let config_with = |limit: &str, lag: &str| {
+        OutboundConfig::from_vars(|key| match key {
+            "OUTBOUND_QUEUE_LIMIT" => Some(limit.to_string()),
+            "OUTBOUND_MAX_LAG_SECS" => Some(lag.to_string()),
+            _ => None,
+        })
+    };
+    let defaults = OutboundConfig::default();
+
+    let config = config_with("64", "2.5");
+    assert_eq!(
+        (config.max_queued, config.max_lag),
+        (64, Duration::from_secs_f64(2.5))
+    );
+    for bad in ["0", "-1", "NaN", "lots"] {
+        let config = config_with(bad, bad);
+        assert_eq!(
+            (config.max_queued, config.max_lag),
+            (defaults.max_queued, defaults.max_lag),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_outbound_queue_flags_lagging_client() {
+    use crate::network::outbound::{self, OutboundConfig, OutboundError};
+    use std::time::Duration;
+
+    let (tx, mut rx) = outbound::channel(OutboundConfig {
+        max_queued: 3,
+        max_lag: Duration::from_secs(60),
+    });
+
+    for timestamp in 0..3 {
+        tx.send(pong_envelope(timestamp)).unwrap();
+    }
+    assert!(!tx.is_lagging());
+    assert_eq!(tx.send(pong_envelope(3)), Err(OutboundError::Lagging));
+    assert!(tx.is_lagging());
+    tokio::time::timeout(Duration::from_secs(1), tx.lagged())
+        .await
+        .expect("lagged resolves once flagged");
+
+    // Closing replaces the backlog with a single final message
+    tx.close_with(pong_envelope(99));
+    assert_eq!(tx.send(pong_envelope(100)), Err(OutboundError::Closed));
+    assert!(matches!(
+        rx.recv().await.unwrap().payload,
+        Payload::Pong(Pong { timestamp: 99 })
+    ));
+    assert!(rx.recv().await.is_none());
+}
+
+#[test]
+fn test_liveness_times_out_after_silence() {
+    use crate::network::heartbeat::{HeartbeatConfig, Liveness};
+    use std::time::{Duration, Instant};
+
+    let config = HeartbeatConfig {
+        interval: Duration::from_secs(1),
+        timeout: Duration::from_secs(3),
+    };
+    let mut liveness = Liveness::new(&config);
+    let now = Instant::now();
+
+    assert!(!liveness.is_timed_out_at(now + Duration::from_secs(2)));
+    assert!(liveness.is_timed_out_at(now + Duration::from_secs(4)));
+
+    liveness.record_activity();
+    assert!(!liveness.is_timed_out_at(Instant::now() + Duration::from_secs(2)));
+}
+
+#[test]
+fn test_shutdown_countdown_warns_at_start_and_for_the_last_seconds() {
+    use crate::network::shutdown::ShutdownConfig;
+
+    let config = ShutdownConfig { countdown: 10 };
+    let warned: Vec<u64> = (1..=config.countdown)
+        .rev()
+        .filter(|remaining| config.is_warning_due(*remaining))
+        .collect();
+    assert_eq!(warned, vec![10, 5, 4, 3, 2, 1]);
+
+    let short = ShutdownConfig { countdown: 3 };
+    assert!((1..=3).all(|remaining| short.is_warning_due(remaining)));
+}
+
+#[tokio::test]
+async fn test_shutdown_warns_then_disconnects_every_session_before_logout() {
+    use crate::network::outbound::OutboundError;
+    use crate::network::shutdown::{disconnect_all, warn_sessions};
+    use crate::network::SessionStore;
+    use uuid::Uuid;
+
+    let store = SessionStore::new();
+    let connected = store.create_session().await;
+    let (tx, mut rx) = crate::network::outbound::channel(Default::default());
+    store.set_sender(&connected, Some(tx.clone())).await;
+    store
+        .authenticate_session(&connected, Uuid::new_v4(), 7, Some(Uuid::new_v4()))
+        .await;
+    let link_dead = store.create_session().await;
+    store
+        .authenticate_session(&link_dead, Uuid::new_v4(), 8, Some(Uuid::new_v4()))
+        .await;
+    store.mark_link_dead(&link_dead).await;
+
+    // Warnings leave the connection open
+    warn_sessions(&store, 5).await;
+    let Payload::ShutdownNotice(notice) = rx.recv().await.unwrap().payload else {
+        panic!("expected a shutdown notice");
+    };
+    assert_eq!(notice.seconds_remaining, 5);
+    assert!(tx.send(pong_envelope(1)).is_ok());
+    rx.recv().await.unwrap();
+
+    // The final flush hands back every session, link-dead ones included, for
+    // logout only after each connection has its last message queued
+    let mut flushed: Vec<Option<u64>> = disconnect_all(&store)
+        .await
+        .iter()
+        .map(|session| session.player_id)
+        .collect();
+    flushed.sort_unstable();
+    assert_eq!(flushed, vec![Some(7), Some(8)]);
+    assert!(store.get_session(&connected).await.is_none());
+    assert!(store.get_session(&link_dead).await.is_none());
+    assert!(matches!(
+        rx.recv().await.unwrap().payload,
+        Payload::Disconnect(Disconnect {
+            reason: DisconnectReason::ServerShutdown,
+            ..
+        })
+    ));
+    assert!(rx.recv().await.is_none());
+    assert_eq!(tx.send(pong_envelope(2)), Err(OutboundError::Closed));
+}
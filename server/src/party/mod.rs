@@ -0,0 +1,231 @@
+//! Parties
+//!
+//! Players in a party share the loot of any mob one of them tags. Parties form
+//! by invitation: a player outside any party, or a party's leader, invites a
+//! player who is not in one, and the invitation founds or grows the party
+//! when it is accepted. The party leader picks how loot is shared out; a
+//! leader who leaves hands the party to the next member, and a party left
+//! with one member disbands.
+
+use crate::entities::EntityId;
+use crate::loot::LootRules;
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod tests;
+
+/// Unique identifier for parties
+pub type PartyId = u64;
+
+/// Most players a party can hold
+pub const MAX_PARTY_SIZE: usize = 5;
+
+/// Errors raised by party membership changes
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PartyError {
+    #[error("Already in a party")]
+    AlreadyInParty,
+
+    #[error("Not in a party")]
+    NotInParty,
+
+    #[error("Party not found")]
+    PartyNotFound,
+
+    #[error("Party is full")]
+    PartyFull,
+
+    #[error("Only the party leader can do that")]
+    NotLeader,
+
+    #[error("That player cannot be invited")]
+    InvalidInvitee,
+
+    #[error("That player is already in a party")]
+    InviteeInParty,
+
+    #[error("No party invitation to answer")]
+    NoInvite,
+}
+
+/// A group of players sharing loot
+#[derive(Debug, Clone)]
+pub struct Party {
+    pub id: PartyId,
+    pub leader: EntityId,
+    pub members: Vec<EntityId>, // In joining order, leader included
+    pub loot_rules: LootRules,
+    next_looter: usize, // Index into `members` of whose turn it is under round robin
+}
+
+impl Party {
+    /// The member whose turn it is among `eligible`, moving the turn on past them
+    pub fn next_looter(&mut self, eligible: &[EntityId]) -> Option<EntityId> {
+        let count = self.members.len();
+        (0..count)
+            .map(|offset| (self.next_looter + offset) % count)
+            .find(|index| eligible.contains(&self.members[*index]))
+            .map(|index| {
+                self.next_looter = (index + 1) % count;
+                self.members[index]
+            })
+    }
+}
+
+/// Every party in the world and who belongs to which
+#[derive(Debug, Default)]
+pub struct Parties {
+    parties: HashMap<PartyId, Party>,
+    membership: HashMap<EntityId, PartyId>, // Player ID -> party ID
+    invites: HashMap<EntityId, EntityId>,   // Invited player ID -> inviting player ID
+    next_id: PartyId,
+}
+
+impl Parties {
+    /// Start a party led by `leader`
+    pub fn create(&mut self, leader: EntityId) -> Result<PartyId, PartyError> {
+        if self.membership.contains_key(&leader) {
+            return Err(PartyError::AlreadyInParty);
+        }
+        self.next_id += 1;
+        let id = self.next_id;
+        self.parties.insert(
+            id,
+            Party {
+                id,
+                leader,
+                members: vec![leader],
+                loot_rules: LootRules::default(),
+                next_looter: 0,
+            },
+        );
+        self.membership.insert(leader, id);
+        Ok(id)
+    }
+
+    pub fn join(&mut self, party_id: PartyId, player_id: EntityId) -> Result<(), PartyError> {
+        if self.membership.contains_key(&player_id) {
+            return Err(PartyError::AlreadyInParty);
+        }
+        let party = self
+            .parties
+            .get_mut(&party_id)
+            .ok_or(PartyError::PartyNotFound)?;
+        if party.members.len() >= MAX_PARTY_SIZE {
+            return Err(PartyError::PartyFull);
+        }
+        party.members.push(player_id);
+        self.membership.insert(player_id, party_id);
+        Ok(())
+    }
+
+    /// Invite a player into the inviter's party, or into one the inviter will
+    /// found, replacing any invitation they already had
+    pub fn invite(&mut self, inviter: EntityId, invitee: EntityId) -> Result<(), PartyError> {
+        if inviter == invitee {
+            return Err(PartyError::InvalidInvitee);
+        }
+        if self.membership.contains_key(&invitee) {
+            return Err(PartyError::InviteeInParty);
+        }
+        if let Some(party) = self.party_of(inviter) {
+            if party.leader != inviter {
+                return Err(PartyError::NotLeader);
+            }
+            if party.members.len() >= MAX_PARTY_SIZE {
+                return Err(PartyError::PartyFull);
+            }
+        }
+        self.invites.insert(invitee, inviter);
+        Ok(())
+    }
+
+    /// Join the party of whoever invited the player, founding it if the
+    /// inviter is not in one yet
+    pub fn accept_invite(&mut self, player_id: EntityId) -> Result<PartyId, PartyError> {
+        let inviter = self
+            .invites
+            .remove(&player_id)
+            .ok_or(PartyError::NoInvite)?;
+        let party_id = match self.party_of(inviter) {
+            Some(party) if party.leader != inviter => return Err(PartyError::NotLeader),
+            Some(party) => party.id,
+            None => {
+                // Check before founding a party that would be left with one member
+                if self.membership.contains_key(&player_id) {
+                    return Err(PartyError::AlreadyInParty);
+                }
+                self.create(inviter)?
+            }
+        };
+        self.join(party_id, player_id)?;
+        Ok(party_id)
+    }
+
+    /// Turn an invitation down, returning who sent it
+    pub fn decline_invite(&mut self, player_id: EntityId) -> Option<EntityId> {
+        self.invites.remove(&player_id)
+    }
+
+    /// Take a player out of their party, returning the party they left. Any
+    /// invitation they sent or were sent goes with them.
+    pub fn leave(&mut self, player_id: EntityId) -> Option<PartyId> {
+        self.invites
+            .retain(|invitee, inviter| *invitee != player_id && *inviter != player_id);
+        let party_id = self.membership.remove(&player_id)?;
+        let party = self.parties.get_mut(&party_id)?;
+        if let Some(index) = party.members.iter().position(|id| *id == player_id) {
+            party.members.remove(index);
+            if index < party.next_looter {
+                party.next_looter -= 1;
+            }
+        }
+        if party.members.len() < 2 {
+            for member in &party.members {
+                self.membership.remove(member);
+            }
+            self.parties.remove(&party_id);
+            return Some(party_id);
+        }
+        party.next_looter %= party.members.len();
+        if party.leader == player_id {
+            party.leader = party.members[0];
+        }
+        Some(party_id)
+    }
+
+    /// Change how the party shares loot; only its leader may
+    pub fn set_loot_rules(
+        &mut self,
+        player_id: EntityId,
+        rules: LootRules,
+    ) -> Result<(), PartyError> {
+        let party = self
+            .membership
+            .get(&player_id)
+            .and_then(|party_id| self.parties.get_mut(party_id))
+            .ok_or(PartyError::NotInParty)?;
+        if party.leader != player_id {
+            return Err(PartyError::NotLeader);
+        }
+        party.loot_rules = rules;
+        Ok(())
+    }
+
+    pub fn get(&self, party_id: PartyId) -> Option<&Party> {
+        self.parties.get(&party_id)
+    }
+
+    /// The party a player belongs to
+    pub fn party_of(&self, player_id: EntityId) -> Option<&Party> {
+        self.membership
+            .get(&player_id)
+            .and_then(|party_id| self.parties.get(party_id))
+    }
+
+    pub fn party_of_mut(&mut self, player_id: EntityId) -> Option<&mut Party> {
+        self.membership
+            .get(&player_id)
+            .and_then(|party_id| self.parties.get_mut(party_id))
+    }
+}
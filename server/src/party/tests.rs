@@ -0,0 +1,116 @@
+use crate::items::ItemRarity;
+use crate::loot::{LootMethod, LootRules};
+use crate::party::{Parties, PartyError, MAX_PARTY_SIZE};
+
+#[test]
+fn test_parties_fill_up_and_hand_on_leadership() {
+    let mut parties = Parties::default();
+    let party_id = parties.create(1).unwrap();
+    assert_eq!(parties.create(1), Err(PartyError::AlreadyInParty));
+    assert_eq!(parties.join(99, 2), Err(PartyError::PartyNotFound));
+
+    for player_id in 2..=MAX_PARTY_SIZE as u64 {
+        parties.join(party_id, player_id).unwrap();
+    }
+    assert_eq!(parties.join(party_id, 1), Err(PartyError::AlreadyInParty));
+    assert_eq!(parties.join(party_id, 10), Err(PartyError::PartyFull));
+    assert_eq!(parties.party_of(3).map(|party| party.id), Some(party_id));
+    assert!(parties.party_of(10).is_none());
+
+    // A leaving leader passes the party to the longest-standing member
+    assert_eq!(parties.leave(1), Some(party_id));
+    let party = parties.get(party_id).unwrap();
+    assert_eq!(party.leader, 2);
+    assert_eq!(party.members, vec![2, 3, 4, 5]);
+    assert_eq!(parties.leave(1), None);
+
+    // Down to one member, the party disbands
+    for player_id in [3, 4, 5] {
+        parties.leave(player_id);
+    }
+    assert!(parties.get(party_id).is_none());
+    assert!(parties.party_of(2).is_none());
+    assert!(parties.create(2).is_ok());
+}
+
+#[test]
+fn test_only_the_leader_sets_loot_rules() {
+    let mut parties = Parties::default();
+    let party_id = parties.create(1).unwrap();
+    parties.join(party_id, 2).unwrap();
+    let rules = LootRules {
+        method: LootMethod::RoundRobin,
+        threshold: ItemRarity::Rare,
+    };
+
+    assert_eq!(parties.set_loot_rules(2, rules), Err(PartyError::NotLeader));
+    assert_eq!(
+        parties.set_loot_rules(3, rules),
+        Err(PartyError::NotInParty)
+    );
+    assert_eq!(
+        parties.get(party_id).unwrap().loot_rules,
+        LootRules::default()
+    );
+    parties.set_loot_rules(1, rules).unwrap();
+    assert_eq!(parties.get(party_id).unwrap().loot_rules, rules);
+}
+
+#[test]
+fn test_round_robin_turns_skip_members_who_are_not_there() {
+    let mut parties = Parties::default();
+    let party_id = parties.create(1).unwrap();
+    parties.join(party_id, 2).unwrap();
+    parties.join(party_id, 3).unwrap();
+    let party = parties.party_of_mut(1).unwrap();
+
+    assert_eq!(party.next_looter(&[1, 2, 3]), Some(1));
+    assert_eq!(party.next_looter(&[1, 2, 3]), Some(2));
+    assert_eq!(
+        party.next_looter(&[1, 2]),
+        Some(1),
+        "3 is away, so the turn wraps"
+    );
+    assert_eq!(party.next_looter(&[1, 2, 3]), Some(2));
+    assert_eq!(party.next_looter(&[]), None);
+
+    // Turns keep going from the same member when someone ahead of them leaves
+    parties.leave(1);
+    let party = parties.party_of_mut(2).unwrap();
+    assert_eq!(party.next_looter(&[2, 3]), Some(3));
+}
+
+#[test]
+fn test_invitations_found_and_grow_parties() {
+    let mut parties = Parties::default();
+    assert_eq!(parties.invite(1, 1), Err(PartyError::InvalidInvitee));
+    assert_eq!(parties.accept_invite(2), Err(PartyError::NoInvite));
+
+    // Accepting an invitation from someone outside any party founds one
+    parties.invite(1, 2).unwrap();
+    let party_id = parties.accept_invite(2).unwrap();
+    let party = parties.get(party_id).unwrap();
+    assert_eq!(party.leader, 1);
+    assert_eq!(party.members, vec![1, 2]);
+    assert_eq!(parties.accept_invite(2), Err(PartyError::NoInvite));
+
+    // Only the leader invites, and only players who are not in a party
+    assert_eq!(parties.invite(2, 3), Err(PartyError::NotLeader));
+    assert_eq!(parties.invite(1, 2), Err(PartyError::InviteeInParty));
+    parties.invite(1, 3).unwrap();
+    assert_eq!(parties.decline_invite(3), Some(1));
+    assert_eq!(parties.accept_invite(3), Err(PartyError::NoInvite));
+
+    // A full party takes no more invitations
+    for player_id in 3..=MAX_PARTY_SIZE as u64 {
+        parties.invite(1, player_id).unwrap();
+        assert_eq!(parties.accept_invite(player_id), Ok(party_id));
+    }
+    assert_eq!(parties.invite(1, 10), Err(PartyError::PartyFull));
+
+    // Invitations go with a player who leaves
+    parties.leave(5);
+    parties.invite(1, 10).unwrap();
+    parties.leave(1);
+    assert_eq!(parties.decline_invite(10), None);
+}
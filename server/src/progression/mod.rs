@@ -0,0 +1,143 @@
+//! Experience curve and kill rewards
+//!
+//! How much experience each level takes and how much a kill is worth are
+//! described in `content/experience.json` rather than in code. The bundled
+//! copy is compiled into the server; `EXPERIENCE_PATH` points the server at a
+//! different file.
+
+use serde::Deserialize;
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+const BUNDLED_EXPERIENCE: &str = include_str!("../../../content/experience.json");
+/// Format version of `experience.json` this server understands
+const CONTENT_VERSION: u32 = 1;
+
+/// Experience a kill is worth and how the level gap scales it
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct KillReward {
+    /// Experience for a level 0 victim
+    pub base: u64,
+    /// Experience added per victim level
+    pub per_level: u64,
+    /// Fractional bonus per level the victim has over the killer
+    pub bonus_per_level: f32,
+    /// Levels above the killer past which the bonus stops growing
+    pub max_bonus_levels: u32,
+    /// Levels below the killer at which a victim is worth nothing
+    pub grey_gap: u32,
+}
+
+/// Errors raised while loading experience content
+#[derive(Debug, thiserror::Error)]
+pub enum ExperienceError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("malformed experience data: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("unsupported experience file version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("invalid experience data: {0}")]
+    Invalid(String),
+}
+
+/// On-disk layout of `experience.json`
+#[derive(Debug, Deserialize)]
+struct ExperienceFile {
+    version: u32,
+    levels: Vec<u64>,
+    kill: KillReward,
+}
+
+/// The experience curve: entry `n` is what level `n + 1` takes to reach the next
+#[derive(Debug, Clone)]
+pub struct ExperienceTable {
+    levels: Vec<u64>,
+    kill: KillReward,
+}
+
+impl ExperienceTable {
+    /// The curve shipped with the server
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_EXPERIENCE).expect("bundled experience.json is valid")
+    }
+
+    /// Load the curve from a content file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ExperienceError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|source| ExperienceError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// `EXPERIENCE_PATH` if set, otherwise the bundled curve
+    pub fn from_env() -> Result<Self, ExperienceError> {
+        match std::env::var("EXPERIENCE_PATH") {
+            Ok(path) if !path.is_empty() => Self::load(path),
+            _ => Ok(Self::bundled()),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ExperienceError> {
+        let file: ExperienceFile = serde_json::from_str(json)?;
+        if file.version != CONTENT_VERSION {
+            return Err(ExperienceError::UnsupportedVersion(file.version));
+        }
+        if file.levels.contains(&0) {
+            return Err(ExperienceError::Invalid(
+                "every level must take some experience".to_string(),
+            ));
+        }
+        if !file.kill.bonus_per_level.is_finite() || file.kill.bonus_per_level < 0.0 {
+            return Err(ExperienceError::Invalid(
+                "kill bonus_per_level must be a non-negative number".to_string(),
+            ));
+        }
+        if file.kill.grey_gap == 0 {
+            return Err(ExperienceError::Invalid(
+                "kill grey_gap must be at least 1".to_string(),
+            ));
+        }
+        Ok(Self {
+            levels: file.levels,
+            kill: file.kill,
+        })
+    }
+
+    /// Highest level a character can reach
+    pub fn max_level(&self) -> u32 {
+        self.levels.len() as u32 + 1
+    }
+
+    /// Experience a character at `level` needs for the next one; 0 at the cap
+    pub fn experience_to_next(&self, level: u32) -> u64 {
+        let index = level.max(1) as usize - 1;
+        self.levels.get(index).copied().unwrap_or(0)
+    }
+
+    /// Experience a killer at `killer_level` earns for a victim at `victim_level`.
+    /// Tougher victims are worth up to `max_bonus_levels` steps more; weaker ones
+    /// are worth less until they are `grey_gap` levels down and worth nothing.
+    pub fn kill_experience(&self, killer_level: u32, victim_level: u32) -> u64 {
+        let reward = &self.kill;
+        let base = reward.base + reward.per_level * victim_level as u64;
+        let scale = if victim_level >= killer_level {
+            let levels_above = (victim_level - killer_level).min(reward.max_bonus_levels);
+            1.0 + levels_above as f32 * reward.bonus_per_level
+        } else {
+            let levels_below = killer_level - victim_level;
+            1.0 - (levels_below as f32 / reward.grey_gap as f32).min(1.0)
+        };
+        (base as f32 * scale).round() as u64
+    }
+}
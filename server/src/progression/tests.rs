@@ -0,0 +1,62 @@
+use crate::progression::{ExperienceError, ExperienceTable};
+
+#[test]
+fn test_bundled_curve_and_kill_rewards() {
+    let table = ExperienceTable::bundled();
+    assert_eq!(table.max_level(), 20);
+    assert_eq!(table.experience_to_next(1), 400);
+    assert!(table.experience_to_next(10) > table.experience_to_next(9));
+    assert_eq!(table.experience_to_next(table.max_level()), 0);
+
+    // Even fights pay the base reward; tougher victims pay more, up to a cap
+    let even = table.kill_experience(3, 3);
+    assert_eq!(even, 45 + 5 * 3);
+    assert!(table.kill_experience(3, 5) > table.kill_experience(5, 5));
+    let capped = (45 + 5 * 20) as f32 * 1.2;
+    assert_eq!(
+        table.kill_experience(1, 20),
+        capped.round() as u64,
+        "the bonus stops at four levels"
+    );
+
+    // Weaker victims pay less until they turn grey
+    assert!(table.kill_experience(5, 3) < table.kill_experience(3, 3));
+    assert_eq!(table.kill_experience(8, 3), 0);
+    assert_eq!(table.kill_experience(20, 1), 0);
+}
+
+#[test]
+fn test_invalid_experience_data_is_rejected() {
+    let free_level = r#"{
+        "version": 1,
+        "levels": [100, 0],
+        "kill": { "base": 1, "per_level": 1, "bonus_per_level": 0.1,
+                  "max_bonus_levels": 1, "grey_gap": 3 }
+    }"#;
+    assert!(matches!(
+        ExperienceTable::from_json(free_level),
+        Err(ExperienceError::Invalid(_))
+    ));
+
+    let no_grey = r#"{
+        "version": 1,
+        "levels": [100],
+        "kill": { "base": 1, "per_level": 1, "bonus_per_level": 0.1,
+                  "max_bonus_levels": 1, "grey_gap": 0 }
+    }"#;
+    assert!(matches!(
+        ExperienceTable::from_json(no_grey),
+        Err(ExperienceError::Invalid(_))
+    ));
+
+    let future_version = r#"{
+        "version": 2,
+        "levels": [],
+        "kill": { "base": 1, "per_level": 1, "bonus_per_level": 0.1,
+                  "max_bonus_levels": 1, "grey_gap": 3 }
+    }"#;
+    assert!(matches!(
+        ExperienceTable::from_json(future_version),
+        Err(ExperienceError::UnsupportedVersion(2))
+    ));
+}
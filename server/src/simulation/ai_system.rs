@@ -0,0 +1,418 @@
+//! Mob AI state machine
+//!
+//! Each mob follows its AI profile:
+//! - Idle mobs wait, then set off along their patrol route if they have one.
+//! - Idle and patrolling mobs aggro on the nearest hostile player within
+//!   aggro range.
+//! - Engaged mobs chase the target picked by `ThreatSystem` and attack it
+//!   through `CombatSystem` once in range, preferring their profile's
+//!   abilities over auto-attacks.
+//! - A mob at low health may flee from its target once per fight.
+//! - A mob pulled past its leash range walks home, where its health is reset.
+//!
+//! Movement is applied by setting velocity; positions advance in the next
+//! `WorldState::update`.
+
+use crate::abilities::AbilityRegistry;
+use crate::ai::{AiProfile, AiProfileRegistry};
+use crate::entities::{AiState, Entity, EntityId};
+use crate::simulation::threat_system::distance_from_home;
+use crate::simulation::{CombatAction, CombatSystem, ThreatSystem};
+use crate::world::{WorldState, Zone};
+use tracing::debug;
+
+/// Distance at which a mob counts as having reached a waypoint or home
+const ARRIVAL_RADIUS: f32 = 1.0;
+/// Patrol speed as a fraction of the mob's maximum speed
+const WALK_SPEED_FRACTION: f32 = 0.5;
+
+/// Where a mob moves this tick
+enum Steering {
+    Stop,
+    Toward((f32, f32, f32), f32),
+    Away((f32, f32, f32), f32),
+    Face((f32, f32, f32)),
+}
+
+/// What a mob decided to do this tick
+struct Decision {
+    state: Option<AiState>,
+    steering: Steering,
+    aggro: Option<EntityId>,
+    action: Option<CombatAction>,
+    /// Back home after leashing: restore health and forget the fight
+    reset: bool,
+}
+
+impl Decision {
+    fn steer(steering: Steering) -> Self {
+        Self {
+            state: None,
+            steering,
+            aggro: None,
+            action: None,
+            reset: false,
+        }
+    }
+
+    fn transition(state: AiState, steering: Steering) -> Self {
+        Self {
+            state: Some(state),
+            ..Self::steer(steering)
+        }
+    }
+}
+
+/// AI system, ticked once per simulation tick
+pub struct AiSystem;
+
+impl AiSystem {
+    /// Advance every living mob's state machine and carry out its attacks
+    pub fn update(world_state: &mut WorldState) {
+        let profiles = world_state.ai_profiles();
+        let abilities = world_state.abilities();
+        let now = world_state.clock().now();
+        let mut actions = Vec::new();
+
+        for zone in world_state.zones_mut() {
+            Self::update_zone(zone, &profiles, &abilities, now, &mut actions);
+        }
+
+        for (mob_id, action) in actions {
+            let result = CombatSystem::process_combat_action(world_state, mob_id, action);
+            if !result.success {
+                debug!(mob = mob_id, error = ?result.error_message, "Mob attack refused");
+            }
+        }
+    }
+
+    fn update_zone(
+        zone: &mut Zone,
+        profiles: &AiProfileRegistry,
+        abilities: &AbilityRegistry,
+        now: f64,
+        actions: &mut Vec<(EntityId, CombatAction)>,
+    ) {
+        let mob_ids: Vec<EntityId> = zone
+            .entities
+            .get_all_entities()
+            .into_iter()
+            .filter(|entity| entity.ai.is_some() && entity.is_alive())
+            .map(|entity| entity.id)
+            .collect();
+
+        for mob_id in mob_ids {
+            let Some(mob) = zone.entities.get_entity(mob_id) else {
+                continue;
+            };
+            let Some(profile) = mob.ai.as_ref().and_then(|ai| profiles.get(&ai.profile)) else {
+                continue;
+            };
+            let decision = Self::decide(zone, mob, profile, abilities, now);
+            if let Some(mob) = zone.entities.get_entity_mut(mob_id) {
+                Self::apply(mob, profile, decision, now, actions);
+            }
+        }
+    }
+
+    fn decide(
+        zone: &Zone,
+        mob: &Entity,
+        profile: &AiProfile,
+        abilities: &AbilityRegistry,
+        now: f64,
+    ) -> Decision {
+        let ai = mob.ai.as_ref().expect("mobs with AI are filtered");
+        let run_speed = speed(mob, 1.0);
+        if mob.casting.is_some() {
+            // Moving would break the cast
+            return Decision::steer(Steering::Stop);
+        }
+
+        match &ai.state {
+            AiState::Idle | AiState::Patrolling { .. } => {
+                if let Some(target_id) = Self::find_aggro(zone, mob, profile) {
+                    return Decision {
+                        aggro: Some(target_id),
+                        ..Decision::transition(AiState::Chasing { target_id }, Steering::Stop)
+                    };
+                }
+                Self::patrol(mob, profile, now)
+            }
+            AiState::Chasing { target_id } | AiState::Attacking { target_id } => {
+                let target_id = *target_id;
+                let Some(target) = zone
+                    .entities
+                    .get_entity(target_id)
+                    .filter(|target| target.is_alive())
+                else {
+                    // ThreatSystem drops the target and picks the next one
+                    return Decision::steer(Steering::Stop);
+                };
+                let target_position = position_of(target);
+
+                if Self::should_flee(mob, profile) {
+                    return Decision::transition(
+                        AiState::Fleeing { target_id },
+                        Steering::Away(target_position, run_speed),
+                    );
+                }
+
+                let attack_range = mob.combat.as_ref().map_or(0.0, |c| c.attack_range);
+                let in_range = mob.distance_to(target) <= attack_range;
+                match (&ai.state, in_range) {
+                    (AiState::Chasing { .. }, true) => Decision::transition(
+                        AiState::Attacking { target_id },
+                        Steering::Face(target_position),
+                    ),
+                    (AiState::Attacking { .. }, false) => Decision::transition(
+                        AiState::Chasing { target_id },
+                        Steering::Toward(target_position, run_speed),
+                    ),
+                    (AiState::Attacking { .. }, true) => Decision {
+                        action: Self::choose_action(mob, target, profile, abilities, now),
+                        ..Decision::steer(Steering::Face(target_position))
+                    },
+                    _ => Decision::steer(Steering::Toward(target_position, run_speed)),
+                }
+            }
+            AiState::Fleeing { target_id } => {
+                let target_id = *target_id;
+                if now - ai.last_state_change >= profile.flee_duration as f64 {
+                    return Decision::transition(AiState::Chasing { target_id }, Steering::Stop);
+                }
+                match zone.entities.get_entity(target_id) {
+                    Some(target) => Decision::steer(Steering::Away(position_of(target), run_speed)),
+                    None => Decision::steer(Steering::Stop),
+                }
+            }
+            AiState::Returning { home_position } => {
+                if distance_from_home(mob).is_some_and(|distance| distance <= ARRIVAL_RADIUS) {
+                    Decision {
+                        reset: true,
+                        ..Decision::transition(AiState::Idle, Steering::Stop)
+                    }
+                } else {
+                    Decision::steer(Steering::Toward(*home_position, run_speed))
+                }
+            }
+        }
+    }
+
+    /// Wait at home, then walk the profile's route from waypoint to waypoint
+    fn patrol(mob: &Entity, profile: &AiProfile, now: f64) -> Decision {
+        let ai = mob.ai.as_ref().expect("mobs with AI are filtered");
+        if profile.patrol.is_empty() {
+            return Decision::steer(Steering::Stop);
+        }
+
+        match &ai.state {
+            AiState::Patrolling {
+                waypoints,
+                current_waypoint,
+            } => {
+                let Some(waypoint) = waypoints.get(*current_waypoint).copied() else {
+                    return Decision::transition(AiState::Idle, Steering::Stop);
+                };
+                if distance(position_of(mob), waypoint) <= ARRIVAL_RADIUS {
+                    let next = (current_waypoint + 1) % waypoints.len();
+                    return Decision::transition(
+                        AiState::Patrolling {
+                            waypoints: waypoints.clone(),
+                            current_waypoint: next,
+                        },
+                        Steering::Toward(waypoints[next], speed(mob, WALK_SPEED_FRACTION)),
+                    );
+                }
+                Decision::steer(Steering::Toward(waypoint, speed(mob, WALK_SPEED_FRACTION)))
+            }
+            _ if now - ai.last_state_change >= profile.idle_time as f64 => {
+                let (x, y, z) = ai.home_position;
+                let waypoints: Vec<(f32, f32, f32)> = profile
+                    .patrol
+                    .iter()
+                    .map(|(dx, dz)| (x + dx, y, z + dz))
+                    .collect();
+                Decision::transition(
+                    AiState::Patrolling {
+                        waypoints,
+                        current_waypoint: 0,
+                    },
+                    Steering::Stop,
+                )
+            }
+            _ => Decision::steer(Steering::Stop),
+        }
+    }
+
+    /// Nearest living hostile player within aggro range
+    fn find_aggro(zone: &Zone, mob: &Entity, profile: &AiProfile) -> Option<EntityId> {
+        zone.entities
+            .get_players()
+            .into_iter()
+            .filter(|player| player.is_alive() && mob.is_hostile_toward(player))
+            .map(|player| (player.id, mob.distance_to(player)))
+            .filter(|(_, distance)| *distance <= profile.aggro_range)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(player_id, _)| player_id)
+    }
+
+    fn should_flee(mob: &Entity, profile: &AiProfile) -> bool {
+        let has_fled = mob.ai.as_ref().is_some_and(|ai| ai.has_fled);
+        let health_fraction = mob.health.as_ref().map_or(1.0, |health| {
+            health.current as f32 / health.maximum.max(1) as f32
+        });
+        profile.flee_health_fraction > 0.0
+            && !has_fled
+            && health_fraction <= profile.flee_health_fraction
+    }
+
+    /// First ready profile ability in range, else an auto-attack once the swing timer allows
+    fn choose_action(
+        mob: &Entity,
+        target: &Entity,
+        profile: &AiProfile,
+        abilities: &AbilityRegistry,
+        now: f64,
+    ) -> Option<CombatAction> {
+        let known = mob.abilities.as_ref();
+        let ability = profile.abilities.iter().find(|ability_id| {
+            let Some(definition) = abilities.get(**ability_id) else {
+                return false;
+            };
+            known.is_some_and(|known| {
+                known.ability_ids.contains(ability_id)
+                    && known
+                        .cooldowns
+                        .get(ability_id)
+                        .map_or(true, |ready_at| now >= *ready_at)
+            }) && mob.distance_to(target) <= definition.range
+        });
+        if let Some(ability_id) = ability {
+            return Some(CombatAction::Ability {
+                ability_id: *ability_id,
+                target_id: target.id,
+                target_position: None,
+            });
+        }
+
+        let combat = mob.combat.as_ref()?;
+        let swing_ready = now - combat.last_attack_time >= 1.0 / combat.attack_speed as f64;
+        swing_ready.then_some(CombatAction::AutoAttack {
+            target_id: target.id,
+        })
+    }
+
+    fn apply(
+        mob: &mut Entity,
+        profile: &AiProfile,
+        decision: Decision,
+        now: f64,
+        actions: &mut Vec<(EntityId, CombatAction)>,
+    ) {
+        if let Some(target_id) = decision.aggro {
+            ThreatSystem::aggro(mob, target_id);
+        }
+        if decision.reset {
+            if let Some(health) = mob.health.as_mut() {
+                health.current = health.maximum;
+            }
+            if let Some(status_effects) = mob.status_effects.as_mut() {
+                status_effects.active.clear();
+            }
+        }
+        if let Some(ai) = mob.ai.as_mut() {
+            // Ranges live on the component so the threat leash can read them
+            ai.aggro_range = profile.aggro_range;
+            ai.leash_range = profile.leash_range;
+            if decision.reset {
+                ai.has_fled = false;
+            }
+            if let Some(state) = decision.state {
+                if matches!(state, AiState::Fleeing { .. }) {
+                    ai.has_fled = true;
+                }
+                ai.state = state;
+                ai.last_state_change = now;
+            }
+        }
+        steer(mob, decision.steering);
+        if let Some(action) = decision.action {
+            actions.push((mob.id, action));
+        }
+    }
+}
+
+fn position_of(entity: &Entity) -> (f32, f32, f32) {
+    entity
+        .position
+        .as_ref()
+        .map_or((0.0, 0.0, 0.0), |position| {
+            (position.x, position.y, position.z)
+        })
+}
+
+fn distance(a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+    let (dx, dy, dz) = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+/// A fraction of the mob's maximum speed, which already includes its movement modifiers
+fn speed(mob: &Entity, fraction: f32) -> f32 {
+    let max_speed = mob.movement.as_ref().map_or(0.0, |m| m.max_speed);
+    max_speed * fraction
+}
+
+fn steer(mob: &mut Entity, steering: Steering) {
+    let from = position_of(mob);
+    let (direction, speed) = match steering {
+        Steering::Stop => ((0.0, 0.0, 0.0), 0.0),
+        Steering::Face(point) => {
+            face(mob, from, point);
+            ((0.0, 0.0, 0.0), 0.0)
+        }
+        Steering::Toward(point, speed) => {
+            face(mob, from, point);
+            (
+                (point.0 - from.0, point.1 - from.1, point.2 - from.2),
+                speed,
+            )
+        }
+        Steering::Away(point, speed) => {
+            face(mob, point, from);
+            (
+                (from.0 - point.0, from.1 - point.1, from.2 - point.2),
+                speed,
+            )
+        }
+    };
+
+    let length = distance((0.0, 0.0, 0.0), direction);
+    let Some(movement) = mob.movement.as_mut() else {
+        return;
+    };
+    if length <= f32::EPSILON || speed <= 0.0 {
+        movement.velocity_x = 0.0;
+        movement.velocity_y = 0.0;
+        movement.velocity_z = 0.0;
+        movement.speed = 0.0;
+        movement.is_moving = false;
+        return;
+    }
+    movement.velocity_x = direction.0 / length * speed;
+    movement.velocity_y = direction.1 / length * speed;
+    movement.velocity_z = direction.2 / length * speed;
+    movement.speed = speed;
+    movement.is_moving = true;
+}
+
+/// Turn the mob to look from `from` toward `to` (yaw, -Z forward)
+fn face(mob: &mut Entity, from: (f32, f32, f32), to: (f32, f32, f32)) {
+    let (dx, dz) = (to.0 - from.0, to.2 - from.2);
+    if dx.abs() <= f32::EPSILON && dz.abs() <= f32::EPSILON {
+        return;
+    }
+    if let Some(position) = mob.position.as_mut() {
+        position.rotation = (-dx).atan2(-dz);
+    }
+}
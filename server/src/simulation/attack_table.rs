@@ -0,0 +1,171 @@
+//! Hit, miss, dodge, parry and critical strike resolution
+//!
+//! Every harmful action rolls once on a table built from both sides' stats.
+//! Miss, dodge, parry and critical chances are stacked in that order and
+//! whatever is left is an ordinary hit, so high avoidance pushes crits off
+//! the table. Each level the defender has over the attacker makes it harder
+//! to hit and to crit; each level below makes it easier. Only melee attacks
+//! can be dodged or parried. Heals never miss but can crit. Rolls come from
+//! the world's combat RNG, which can be seeded to replay a fight.
+
+use crate::entities::Entity;
+use rand::Rng;
+
+/// Abilities with a range up to this are melee and can be dodged or parried
+pub const MELEE_RANGE: f32 = 5.0;
+/// Upper bound on any single chance on the table
+const MAX_CHANCE: f32 = 0.75;
+
+/// Attack table tuning
+#[derive(Debug, Clone, Copy)]
+pub struct AttackTableConfig {
+    /// Base chance for any harmful action to miss
+    pub miss_chance: f32,
+    /// Base chance for a melee attack to be dodged
+    pub dodge_chance: f32,
+    /// Base chance for a melee attack to be parried
+    pub parry_chance: f32,
+    /// Base critical strike chance, added to the attacker's own
+    pub critical_chance: f32,
+    /// Damage and healing multiplier of a critical strike
+    pub critical_multiplier: f32,
+    /// Chance shifted per level of difference between attacker and defender
+    pub level_step: f32,
+    /// Dodge chance per point of the defender's agility and critical strike
+    /// chance per point of the attacker's
+    pub agility_step: f32,
+}
+
+impl Default for AttackTableConfig {
+    fn default() -> Self {
+        Self {
+            miss_chance: 0.05,
+            dodge_chance: 0.05,
+            parry_chance: 0.05,
+            critical_chance: 0.05,
+            critical_multiplier: 1.5,
+            level_step: 0.01,
+            agility_step: 0.001,
+        }
+    }
+}
+
+impl AttackTableConfig {
+    /// Read `MISS_CHANCE` / `DODGE_CHANCE` / `PARRY_CHANCE` / `CRITICAL_CHANCE` /
+    /// `CRITICAL_MULTIPLIER` / `ATTACK_LEVEL_STEP` / `ATTACK_AGILITY_STEP`,
+    /// falling back to defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |key: &str, default: f32| -> f32 {
+            std::env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            miss_chance: var("MISS_CHANCE", defaults.miss_chance).clamp(0.0, MAX_CHANCE),
+            dodge_chance: var("DODGE_CHANCE", defaults.dodge_chance).clamp(0.0, MAX_CHANCE),
+            parry_chance: var("PARRY_CHANCE", defaults.parry_chance).clamp(0.0, MAX_CHANCE),
+            critical_chance: var("CRITICAL_CHANCE", defaults.critical_chance)
+                .clamp(0.0, MAX_CHANCE),
+            critical_multiplier: var("CRITICAL_MULTIPLIER", defaults.critical_multiplier).max(1.0),
+            level_step: var("ATTACK_LEVEL_STEP", defaults.level_step).max(0.0),
+            agility_step: var("ATTACK_AGILITY_STEP", defaults.agility_step).max(0.0),
+        }
+    }
+}
+
+/// Result of a roll on the attack table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttackOutcome {
+    Miss,
+    Dodge,
+    Parry,
+    Critical,
+    #[default]
+    Hit,
+}
+
+impl AttackOutcome {
+    /// Whether the action connected
+    pub fn lands(self) -> bool {
+        matches!(self, Self::Critical | Self::Hit)
+    }
+}
+
+/// Chance of each outcome for one action; the remainder is a hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttackTable {
+    pub miss: f32,
+    pub dodge: f32,
+    pub parry: f32,
+    pub critical: f32,
+}
+
+impl AttackTable {
+    /// Table for a harmful action by `attacker` against `defender`
+    pub fn attack(
+        config: &AttackTableConfig,
+        attacker: &Entity,
+        defender: &Entity,
+        melee: bool,
+    ) -> Self {
+        let level_gap = level(defender) as f32 - level(attacker) as f32;
+        let shift = level_gap * config.level_step;
+        let chance = |value: f32| value.clamp(0.0, MAX_CHANCE);
+        let melee_only = |value: f32| if melee { chance(value) } else { 0.0 };
+
+        Self {
+            miss: chance(config.miss_chance + shift),
+            dodge: melee_only(
+                config.dodge_chance + agility(defender) as f32 * config.agility_step + shift,
+            ),
+            parry: melee_only(config.parry_chance + shift),
+            critical: chance(critical_chance(config, attacker) - shift),
+        }
+    }
+
+    /// Table for a heal, which always lands
+    pub fn heal(config: &AttackTableConfig, healer: &Entity) -> Self {
+        Self {
+            miss: 0.0,
+            dodge: 0.0,
+            parry: 0.0,
+            critical: critical_chance(config, healer).clamp(0.0, MAX_CHANCE),
+        }
+    }
+
+    /// Roll once on the table
+    pub fn roll(&self, rng: &mut impl Rng) -> AttackOutcome {
+        let roll: f32 = rng.gen();
+        let mut ceiling = 0.0;
+        for (chance, outcome) in [
+            (self.miss, AttackOutcome::Miss),
+            (self.dodge, AttackOutcome::Dodge),
+            (self.parry, AttackOutcome::Parry),
+            (self.critical, AttackOutcome::Critical),
+        ] {
+            ceiling += chance;
+            if roll < ceiling {
+                return outcome;
+            }
+        }
+        AttackOutcome::Hit
+    }
+}
+
+fn level(entity: &Entity) -> u32 {
+    entity.combat.as_ref().map_or(1, |combat| combat.level)
+}
+
+fn agility(entity: &Entity) -> u32 {
+    entity.combat.as_ref().map_or(0, |combat| combat.agility)
+}
+
+fn critical_chance(config: &AttackTableConfig, entity: &Entity) -> f32 {
+    let own = entity.combat.as_ref().map_or(0.0, |combat| {
+        combat.critical_chance + combat.agility as f32 * config.agility_step
+    });
+    config.critical_chance + own
+}
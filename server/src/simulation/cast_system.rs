@@ -0,0 +1,196 @@
+//! Cast times and channelled abilities
+//!
+//! A cast ability resolves once its cast time has passed; a channelled ability
+//! resolves in evenly spaced ticks for as long as the channel lasts. Either is
+//! cancelled when the caster moves, is stunned, dies or is interrupted by an
+//! enemy ability. Every stage is raised as an `EntityEffect::Cast` event on the
+//! caster, with progress reported every `PROGRESS_INTERVAL` seconds.
+
+use crate::abilities::AbilityDefinition;
+use crate::entities::{Casting, Entity, EntityId};
+use crate::network::messages::{CastPhase, EntityEffect};
+use crate::simulation::combat_system::Stage;
+use crate::simulation::{CombatAction, CombatSystem};
+use crate::world::WorldState;
+
+/// Seconds between cast progress events
+const PROGRESS_INTERVAL: f64 = 0.5;
+/// Slack for channel ticks landing exactly on the end of the channel
+const TICK_EPSILON: f64 = 1e-6;
+
+/// Cast system, ticked once per simulation tick
+pub struct CastSystem;
+
+impl CastSystem {
+    /// Put the entity into the casting state, returning the start event
+    pub fn begin(
+        entity: &mut Entity,
+        definition: &AbilityDefinition,
+        target_id: EntityId,
+        target_position: Option<(f32, f32, f32)>,
+        now: f64,
+    ) -> EntityEffect {
+        let channel = definition.is_channelled();
+        let duration = if channel {
+            definition.channel_time
+        } else {
+            definition.cast_time
+        };
+        let tick_interval = definition.channel_interval() as f64;
+        let casting = Casting {
+            ability_id: definition.id,
+            target_id,
+            target_position,
+            started_at: now,
+            finishes_at: now + duration as f64,
+            channel,
+            tick_interval,
+            next_tick_at: now + tick_interval,
+            next_progress_at: now + PROGRESS_INTERVAL,
+        };
+        let event = cast_event(&casting, CastPhase::Started, now, String::new());
+        entity.casting = Some(casting);
+        event
+    }
+
+    /// Stop whatever the entity is casting, returning the interrupt event
+    pub fn cancel(entity: &mut Entity, now: f64, reason: &str) -> Option<EntityEffect> {
+        let casting = entity.casting.take()?;
+        Some(cast_event(
+            &casting,
+            CastPhase::Interrupted,
+            now,
+            reason.to_string(),
+        ))
+    }
+
+    /// Break casts that can no longer continue, resolve channel ticks and
+    /// finished casts, and report progress
+    pub fn update(world_state: &mut WorldState) {
+        let now = world_state.clock().now();
+        let casters: Vec<EntityId> = world_state
+            .get_all_zones()
+            .into_iter()
+            .flat_map(|zone| zone.entities.get_all_entities())
+            .filter(|entity| entity.casting.is_some())
+            .map(|entity| entity.id)
+            .collect();
+
+        for caster_id in casters {
+            Self::update_caster(world_state, caster_id, now);
+        }
+    }
+
+    fn update_caster(world_state: &mut WorldState, caster_id: EntityId, now: f64) {
+        let Some(caster) = caster_mut(world_state, caster_id) else {
+            return;
+        };
+        let reason = if !caster.is_alive() {
+            Some("Died")
+        } else if caster.is_stunned() {
+            Some("Stunned")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            if let Some(event) = Self::cancel(caster, now, reason) {
+                world_state.push_entity_effect(caster_id, event);
+            }
+            return;
+        }
+        let Some(mut casting) = caster.casting.clone() else {
+            return;
+        };
+        let action = CombatAction::Ability {
+            ability_id: casting.ability_id,
+            target_id: casting.target_id,
+            target_position: casting.target_position,
+        };
+
+        if casting.channel {
+            while casting.next_tick_at <= now
+                && casting.next_tick_at <= casting.finishes_at + TICK_EPSILON
+            {
+                let result = CombatSystem::resolve_action(
+                    world_state,
+                    caster_id,
+                    action.clone(),
+                    Stage::ChannelTick,
+                );
+                if !result.success {
+                    Self::fail(world_state, caster_id, now, result.error_message);
+                    return;
+                }
+                casting.next_tick_at += casting.tick_interval;
+            }
+        }
+
+        if now >= casting.finishes_at {
+            // Clear the cast before resolving so the caster is free to act again
+            if let Some(caster) = caster_mut(world_state, caster_id) {
+                caster.casting = None;
+            }
+            let (phase, reason) = if casting.channel {
+                (CastPhase::Finished, String::new())
+            } else {
+                let result = CombatSystem::resolve_action(
+                    world_state,
+                    caster_id,
+                    action,
+                    Stage::CastComplete,
+                );
+                match result.error_message.filter(|_| !result.success) {
+                    None => (CastPhase::Finished, String::new()),
+                    Some(reason) => (CastPhase::Interrupted, reason),
+                }
+            };
+            world_state.push_entity_effect(caster_id, cast_event(&casting, phase, now, reason));
+            return;
+        }
+
+        let progress = now >= casting.next_progress_at;
+        while casting.next_progress_at <= now {
+            casting.next_progress_at += PROGRESS_INTERVAL;
+        }
+        if let Some(caster) = caster_mut(world_state, caster_id) {
+            caster.casting = Some(casting.clone());
+        }
+        if progress {
+            world_state.push_entity_effect(
+                caster_id,
+                cast_event(&casting, CastPhase::Progress, now, String::new()),
+            );
+        }
+    }
+
+    /// End a channel whose tick could not resolve, e.g. because the target died
+    fn fail(world_state: &mut WorldState, caster_id: EntityId, now: f64, reason: Option<String>) {
+        let reason = reason.unwrap_or_default();
+        let event = caster_mut(world_state, caster_id)
+            .and_then(|caster| Self::cancel(caster, now, &reason));
+        if let Some(event) = event {
+            world_state.push_entity_effect(caster_id, event);
+        }
+    }
+}
+
+fn caster_mut(world_state: &mut WorldState, caster_id: EntityId) -> Option<&mut Entity> {
+    let zone_id = world_state.get_entity_zone_id(caster_id)?;
+    world_state
+        .get_zone_mut(zone_id)?
+        .entities
+        .get_entity_mut(caster_id)
+}
+
+fn cast_event(casting: &Casting, phase: CastPhase, now: f64, reason: String) -> EntityEffect {
+    let duration = casting.finishes_at - casting.started_at;
+    EntityEffect::Cast {
+        ability_id: casting.ability_id,
+        target_id: casting.target_id,
+        phase,
+        duration: duration as f32,
+        elapsed: (now - casting.started_at).clamp(0.0, duration) as f32,
+        channel: casting.channel,
+        reason,
+    }
+}
@@ -0,0 +1,35 @@
+//! Monotonic world clock
+//!
+//! The clock only moves when the simulation loop advances it, so every
+//! system reading it during a tick sees the same time and game timers
+//! (cooldowns, regeneration, AI) stay consistent with the tick rate rather
+//! than wall time.
+
+/// Ticks and simulated seconds since the world started
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WorldClock {
+    tick: u64,
+    elapsed: f64,
+}
+
+impl WorldClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward by one tick of `delta_time` seconds
+    pub fn advance(&mut self, delta_time: f64) {
+        self.tick += 1;
+        self.elapsed += delta_time.max(0.0);
+    }
+
+    /// Number of ticks simulated so far
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Simulated seconds since the world started
+    pub fn now(&self) -> f64 {
+        self.elapsed
+    }
+}
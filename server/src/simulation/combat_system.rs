@@ -2,9 +2,20 @@
 //!
 //! This module implements the combat mechanics including
 //! attack validation, damage calculation, and death handling.
+//! Each target is rolled on the attack table, so attacks can miss, be dodged
+//! or parried, or land as critical strikes.
+//! Area abilities land on every valid target in a ground circle or in a cone
+//! in front of the caster, up to the ability's target cap.
 
-use crate::entities::{Entity, EntityId};
-use crate::world::WorldState;
+use crate::abilities::{AbilityDefinition, AbilityRegistry, AreaDefinition, AreaShape, TargetType};
+use crate::entities::{Entity, EntityId, Position, StatModifiers};
+use crate::network::messages::{CombatEventKind, EntityEffect};
+use crate::simulation::attack_table::{AttackOutcome, AttackTable, MELEE_RANGE};
+use crate::simulation::{
+    AttackTableConfig, CastSystem, EffectsSystem, ResourceSystem, ThreatSystem,
+};
+use crate::world::{WorldState, Zone};
+use rand::rngs::StdRng;
 
 /// Combat action types
 #[derive(Debug, Clone)]
@@ -15,9 +26,22 @@ pub enum CombatAction {
     Ability {
         ability_id: u32,
         target_id: EntityId,
+        /// Ground point for area abilities; the target's position when absent
+        target_position: Option<(f32, f32, f32)>,
     },
 }
 
+/// How far along a timed ability an action is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// A fresh request; cast and channelled abilities start instead of resolving
+    Request,
+    /// A cast ran its full time and resolves now
+    CastComplete,
+    /// One tick of a channel whose cost and cooldown were paid when it started
+    ChannelTick,
+}
+
 /// Combat result after processing an action
 #[derive(Debug, Clone)]
 pub struct CombatResult {
@@ -27,97 +51,262 @@ pub struct CombatResult {
     pub error_message: Option<String>,
 }
 
+/// What an action did to one of its targets
+#[derive(Debug, Default)]
+struct Hit {
+    target_id: EntityId,
+    outcome: AttackOutcome,
+    damage: u32,
+    healing: u32,
+    killed: bool,
+    /// Status effect and interrupt events raised on the target
+    events: Vec<EntityEffect>,
+}
+
+impl CombatResult {
+    fn failure(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            damage_dealt: 0,
+            target_killed: false,
+            error_message: Some(message.into()),
+        }
+    }
+}
+
 /// Combat system for processing combat actions
 pub struct CombatSystem;
 
 impl CombatSystem {
-    /// Process a combat action
+    /// Process a combat action, raising combat events for the outcome.
+    /// A refused action is reported privately to the attacker.
     pub fn process_combat_action(
         world_state: &mut WorldState,
         attacker_id: EntityId,
         action: CombatAction,
     ) -> CombatResult {
+        let (ability_id, target_id) = match action {
+            CombatAction::AutoAttack { target_id } => (0, target_id),
+            CombatAction::Ability {
+                ability_id,
+                target_id,
+                ..
+            } => (ability_id, target_id),
+        };
+
+        let result = Self::resolve_action(world_state, attacker_id, action, Stage::Request);
+        if let Some(reason) = result.error_message.clone().filter(|_| !result.success) {
+            world_state.push_entity_effect(
+                attacker_id,
+                EntityEffect::ActionRejected {
+                    ability_id,
+                    target_id,
+                    reason,
+                },
+            );
+        }
+        result
+    }
+
+    /// Validate and carry out an action at the given stage, without reporting refusals
+    pub fn resolve_action(
+        world_state: &mut WorldState,
+        attacker_id: EntityId,
+        action: CombatAction,
+        stage: Stage,
+    ) -> CombatResult {
+        let now = world_state.clock().now();
+        let registry = world_state.abilities();
+        let attack_table = *world_state.attack_table();
+
+        // Resolve the ability definition up front; unknown IDs never reach the zone
+        let ability = match action {
+            CombatAction::AutoAttack { .. } => None,
+            CombatAction::Ability { ability_id, .. } => match registry.get(ability_id) {
+                Some(definition) => Some(definition),
+                None => return CombatResult::failure(format!("Unknown ability {}", ability_id)),
+            },
+        };
+
         // Get attacker's zone
-        let zone_id = match world_state.get_player_zone_id(attacker_id) {
+        let zone_id = match world_state.get_entity_zone_id(attacker_id) {
             Some(id) => id,
-            None => {
-                return CombatResult {
-                    success: false,
-                    damage_dealt: 0,
-                    target_killed: false,
-                    error_message: Some("Attacker not in any zone".to_string()),
-                }
-            }
+            None => return CombatResult::failure("Attacker not in any zone"),
         };
 
-        let zone = match world_state.get_zone_mut(zone_id) {
-            Some(z) => z,
-            None => {
-                return CombatResult {
-                    success: false,
-                    damage_dealt: 0,
-                    target_killed: false,
-                    error_message: Some("Zone not found".to_string()),
-                }
-            }
+        let (zone, rng) = match world_state.zone_and_combat_rng_mut(zone_id) {
+            Some(found) => found,
+            None => return CombatResult::failure("Zone not found"),
         };
 
         // Get attacker entity
         let attacker = match zone.entities.get_entity(attacker_id) {
             Some(e) => e,
-            None => {
-                return CombatResult {
-                    success: false,
-                    damage_dealt: 0,
-                    target_killed: false,
-                    error_message: Some("Attacker entity not found".to_string()),
-                }
-            }
+            None => return CombatResult::failure("Attacker entity not found"),
         };
 
         // Validate attacker can attack
         if !attacker.can_attack() {
-            return CombatResult {
-                success: false,
-                damage_dealt: 0,
-                target_killed: false,
-                error_message: Some("Attacker cannot attack".to_string()),
-            };
+            return CombatResult::failure("Attacker cannot attack");
         }
-
-        let target_id = match action {
-            CombatAction::AutoAttack { target_id } | CombatAction::Ability { target_id, .. } => {
-                target_id
+        if stage == Stage::Request {
+            if attacker.is_stunned() {
+                return CombatResult::failure("Stunned");
+            }
+            if attacker.casting.is_some() {
+                return CombatResult::failure("Already casting");
             }
+        }
+
+        let (requested_id, target_position) = match action {
+            CombatAction::AutoAttack { target_id } => (target_id, None),
+            CombatAction::Ability {
+                target_id,
+                target_position,
+                ..
+            } => (target_id, target_position),
+        };
+        let target_id = match ability {
+            Some(definition) if definition.target == TargetType::Caster => attacker_id,
+            _ => requested_id,
         };
+        let area = ability.and_then(|definition| definition.area);
 
-        // Get target entity
-        let target = match zone.entities.get_entity(target_id) {
-            Some(e) => e,
-            None => {
-                return CombatResult {
-                    success: false,
-                    damage_dealt: 0,
-                    target_killed: false,
-                    error_message: Some("Target entity not found".to_string()),
+        // Validate the action; area abilities need a point to land on instead of a target
+        let area_center = match (ability, area) {
+            (Some(definition), Some(area)) => {
+                if let Err(error) = Self::validate_use(attacker, definition, now, stage) {
+                    return CombatResult::failure(error);
+                }
+                match Self::area_center(
+                    zone,
+                    attacker,
+                    definition,
+                    &area,
+                    target_id,
+                    target_position,
+                ) {
+                    Ok(center) => Some(center),
+                    Err(error) => return CombatResult::failure(error),
                 }
             }
+            _ => {
+                let target = match zone.entities.get_entity(target_id) {
+                    Some(e) => e,
+                    None => return CombatResult::failure("Target entity not found"),
+                };
+                let validation = match ability {
+                    None => Self::validate_attack(attacker, target, now),
+                    Some(definition) => {
+                        Self::validate_ability(attacker, target, definition, now, stage)
+                    }
+                };
+                if let Err(error) = validation {
+                    return CombatResult::failure(error);
+                }
+                None
+            }
         };
 
-        // Validate attack
-        if let Err(error) = Self::validate_attack(attacker, target, &action) {
+        // Cast and channelled abilities take time; CastSystem resolves them later
+        if let Some(definition) =
+            ability.filter(|definition| stage == Stage::Request && definition.is_timed())
+        {
+            // A ground circle keeps landing where it was aimed; a cone follows the caster
+            let target_position = area_center
+                .filter(|_| area.is_some_and(|area| area.shape == AreaShape::GroundCircle));
+            let attacker = zone.entities.get_entity_mut(attacker_id).unwrap();
+            let started = CastSystem::begin(attacker, definition, target_id, target_position, now);
+            // Channels pay up front; casts pay when they complete
+            if definition.is_channelled() {
+                Self::start_cooldown(attacker, definition, now);
+            }
+            world_state.push_entity_effect(attacker_id, started);
             return CombatResult {
-                success: false,
+                success: true,
                 damage_dealt: 0,
                 target_killed: false,
-                error_message: Some(error),
+                error_message: None,
             };
         }
 
-        // Calculate and apply damage
-        let damage = Self::calculate_damage(attacker, target, &action);
-        let target_killed =
-            Self::apply_damage(zone.entities.get_entity_mut(target_id).unwrap(), damage);
+        let targets = match (ability, area, area_center) {
+            (Some(definition), Some(area), Some(center)) => {
+                Self::area_targets(zone, attacker_id, definition, &area, center)
+            }
+            _ => vec![target_id],
+        };
+        let hits: Vec<Hit> = targets
+            .into_iter()
+            .map(|target_id| {
+                let outcome = Self::roll(zone, &attack_table, rng, attacker_id, target_id, ability);
+                Self::hit(
+                    zone,
+                    &registry,
+                    &attack_table,
+                    attacker_id,
+                    target_id,
+                    ability,
+                    outcome,
+                    now,
+                )
+            })
+            .collect();
+        let damage: u32 = hits.iter().map(|hit| hit.damage).sum();
+        let target_killed = hits.iter().any(|hit| hit.killed);
+
+        // Start the attacker's swing timer or the ability's cooldown and pay its cost
+        if let Some(attacker) = zone.entities.get_entity_mut(attacker_id) {
+            if damage > 0 {
+                ResourceSystem::on_damage_dealt(attacker, damage, now);
+            }
+            match ability {
+                None => {
+                    if let Some(combat) = attacker.combat.as_mut() {
+                        combat.last_attack_time = now;
+                    }
+                }
+                Some(_) if stage == Stage::ChannelTick => {}
+                Some(definition) => Self::start_cooldown(attacker, definition, now),
+            }
+        }
+
+        let ability_id = ability.map_or(0, |definition| definition.id);
+        let combat_event = |amount, kind, is_critical, killing_blow| EntityEffect::DamageNumber {
+            amount,
+            is_critical,
+            source_id: attacker_id,
+            ability_id,
+            kind,
+            killing_blow,
+        };
+        for hit in hits {
+            let critical = hit.outcome == AttackOutcome::Critical;
+            let avoided = match hit.outcome {
+                AttackOutcome::Miss => Some(CombatEventKind::Miss),
+                AttackOutcome::Dodge => Some(CombatEventKind::Dodge),
+                AttackOutcome::Parry => Some(CombatEventKind::Parry),
+                AttackOutcome::Critical | AttackOutcome::Hit => None,
+            };
+            if let Some(kind) = avoided {
+                world_state.push_entity_effect(hit.target_id, combat_event(0, kind, false, false));
+            }
+            if hit.damage > 0 {
+                world_state.push_entity_effect(
+                    hit.target_id,
+                    combat_event(hit.damage, CombatEventKind::Damage, critical, hit.killed),
+                );
+            }
+            if hit.healing > 0 {
+                world_state.push_entity_effect(
+                    hit.target_id,
+                    combat_event(hit.healing, CombatEventKind::Heal, critical, false),
+                );
+            }
+            for event in hit.events {
+                world_state.push_entity_effect(hit.target_id, event);
+            }
+        }
 
         CombatResult {
             success: true,
@@ -127,44 +316,229 @@ impl CombatSystem {
         }
     }
 
-    /// Validate if an attack can be performed
-    fn validate_attack(
+    /// Roll the attack table for one target. Damage rolls the full table, with
+    /// dodge and parry for melee only; heals can only crit; anything else lands.
+    fn roll(
+        zone: &Zone,
+        config: &AttackTableConfig,
+        rng: &mut StdRng,
+        attacker_id: EntityId,
+        target_id: EntityId,
+        ability: Option<&AbilityDefinition>,
+    ) -> AttackOutcome {
+        let (Some(attacker), Some(target)) = (
+            zone.entities.get_entity(attacker_id),
+            zone.entities.get_entity(target_id),
+        ) else {
+            return AttackOutcome::Hit;
+        };
+        let table = match ability {
+            None => AttackTable::attack(config, attacker, target, true),
+            Some(definition) if definition.damage.is_some() => {
+                AttackTable::attack(config, attacker, target, definition.range <= MELEE_RANGE)
+            }
+            Some(definition) if definition.heal.is_some() => AttackTable::heal(config, attacker),
+            Some(_) => return AttackOutcome::Hit,
+        };
+        table.roll(rng)
+    }
+
+    /// Deal an action's damage, healing, threat and status effects to one target.
+    /// An attack that missed or was avoided does nothing.
+    #[allow(clippy::too_many_arguments)]
+    fn hit(
+        zone: &mut Zone,
+        registry: &AbilityRegistry,
+        config: &AttackTableConfig,
+        attacker_id: EntityId,
+        target_id: EntityId,
+        ability: Option<&AbilityDefinition>,
+        outcome: AttackOutcome,
+        now: f64,
+    ) -> Hit {
+        let missed = Hit {
+            target_id,
+            outcome,
+            ..Hit::default()
+        };
+        if !outcome.lands() {
+            return missed;
+        }
+        let (Some(attacker), Some(target)) = (
+            zone.entities.get_entity(attacker_id),
+            zone.entities.get_entity(target_id),
+        ) else {
+            return missed;
+        };
+
+        // Calculate damage and healing
+        let attack_power = attacker.combat.as_ref().map_or(0, |c| c.attack_power);
+        let (damage, healing) = match ability {
+            None => (Self::calculate_damage(attack_power, attacker, target), 0),
+            Some(definition) => (
+                definition.damage.map_or(0, |formula| {
+                    Self::calculate_damage(formula.evaluate(attack_power), attacker, target)
+                }),
+                definition
+                    .heal
+                    .map_or(0, |formula| formula.evaluate(attack_power)),
+            ),
+        };
+        let (damage, healing) = if outcome == AttackOutcome::Critical {
+            let critical =
+                |amount: u32| (amount as f32 * config.critical_multiplier).round() as u32;
+            (critical(damage), critical(healing))
+        } else {
+            (damage, healing)
+        };
+
+        let target = zone.entities.get_entity_mut(target_id).unwrap();
+        let killed = damage > 0 && Self::apply_damage(target, damage);
+        if damage > 0 {
+            ResourceSystem::on_damage_taken(target, damage, now);
+            ThreatSystem::on_damage(target, attacker_id, damage);
+        }
+        if ability.is_some_and(|definition| definition.taunt) && !killed {
+            ThreatSystem::taunt(target, attacker_id);
+        }
+        let interrupted = if ability.is_some_and(|definition| definition.interrupts) {
+            CastSystem::cancel(target, now, "Interrupted")
+        } else {
+            None
+        };
+        let healing = if healing > 0 {
+            Self::apply_healing(target, healing)
+        } else {
+            0
+        };
+
+        // Apply the ability's status effects to a surviving target
+        let mut events: Vec<EntityEffect> = interrupted.into_iter().collect();
+        if let Some(definition) = ability.filter(|_| !killed) {
+            for effect in definition
+                .effects
+                .iter()
+                .filter_map(|id| registry.effect(*id))
+            {
+                if let Some(event) = EffectsSystem::apply(target, effect, attacker_id, now) {
+                    events.push(event);
+                }
+            }
+        }
+
+        if healing > 0 {
+            ThreatSystem::on_healing(zone, attacker_id, target_id, healing);
+        }
+
+        Hit {
+            target_id,
+            outcome,
+            damage,
+            healing,
+            killed,
+            events,
+        }
+    }
+
+    /// Where an area ability is centred: the aimed ground point for circles, the caster for cones
+    fn area_center(
+        zone: &Zone,
         attacker: &Entity,
-        target: &Entity,
-        action: &CombatAction,
-    ) -> Result<(), String> {
+        ability: &AbilityDefinition,
+        area: &AreaDefinition,
+        target_id: EntityId,
+        target_position: Option<(f32, f32, f32)>,
+    ) -> Result<(f32, f32, f32), String> {
+        let origin = attacker
+            .position
+            .as_ref()
+            .map(|position| (position.x, position.y, position.z))
+            .ok_or_else(|| "Attacker has no position".to_string())?;
+        if area.shape == AreaShape::Cone {
+            return Ok(origin);
+        }
+
+        let center = target_position
+            .or_else(|| {
+                let position = zone.entities.get_entity(target_id)?.position.as_ref()?;
+                Some((position.x, position.y, position.z))
+            })
+            .ok_or_else(|| "No target position".to_string())?;
+        let distance = distance(origin, center);
+        if distance > ability.range {
+            return Err(format!(
+                "Target position is out of range ({} > {})",
+                distance, ability.range
+            ));
+        }
+        Ok(center)
+    }
+
+    /// Entities inside the area that the ability may affect, nearest the centre first,
+    /// capped at the area's target limit
+    fn area_targets(
+        zone: &Zone,
+        attacker_id: EntityId,
+        ability: &AbilityDefinition,
+        area: &AreaDefinition,
+        center: (f32, f32, f32),
+    ) -> Vec<EntityId> {
+        let Some(attacker) = zone.entities.get_entity(attacker_id) else {
+            return Vec::new();
+        };
+        let facing = attacker.position.as_ref().map_or(0.0, |p| p.rotation);
+        let half_angle = (area.angle * 0.5).to_radians();
+
+        let mut targets: Vec<(f32, EntityId)> = zone
+            .entities
+            .get_entities_in_range(&center, area.radius)
+            .into_iter()
+            .filter(|target| target.health.is_some() && target.is_alive())
+            .filter(|target| Self::area_affects(attacker, target, ability.target))
+            .filter_map(|target| {
+                let position = target.position.as_ref()?;
+                let in_shape = match area.shape {
+                    AreaShape::GroundCircle => true,
+                    AreaShape::Cone => within_cone(center, facing, half_angle, position),
+                };
+                in_shape.then(|| {
+                    let point = (position.x, position.y, position.z);
+                    (distance(center, point), target.id)
+                })
+            })
+            .collect();
+        targets.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        targets.truncate(area.max_targets as usize);
+        targets.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Whether an area ability of the given kind lands on `target`.
+    /// Harmful areas only hit entities hostile to or from the caster, and helpful
+    /// areas only entities on the caster's side.
+    fn area_affects(attacker: &Entity, target: &Entity, target_type: TargetType) -> bool {
+        let hostile = attacker.is_hostile_toward(target) || target.is_hostile_toward(attacker);
+        match target_type {
+            TargetType::Enemy => attacker.id != target.id && hostile,
+            TargetType::Friendly => !hostile,
+            TargetType::Caster => false,
+        }
+    }
+
+    /// Validate if an auto-attack can be performed at world time `now`
+    fn validate_attack(attacker: &Entity, target: &Entity, now: f64) -> Result<(), String> {
         // Check if target is alive
         if !target.is_alive() {
             return Err("Target is already dead".to_string());
         }
 
         // Check range
-        let attack_range = attacker.combat.as_ref().unwrap().attack_range;
-        let distance = attacker.distance_to(target);
-
-        if distance > attack_range {
-            return Err(format!(
-                "Target is out of range ({} > {})",
-                distance, attack_range
-            ));
-        }
+        let combat = attacker.combat.as_ref().unwrap();
+        Self::check_range(attacker, target, combat.attack_range)?;
 
         // Check attack cooldown
-        let combat = attacker.combat.as_ref().unwrap();
-        let current_time = 0.0; // TODO: Get actual current time
-        let time_since_last_attack = current_time - combat.last_attack_time;
-
-        match action {
-            CombatAction::AutoAttack { .. } => {
-                let attack_cooldown = 1.0 / combat.attack_speed;
-                if time_since_last_attack < attack_cooldown as f64 {
-                    return Err("Attack is on cooldown".to_string());
-                }
-            }
-            CombatAction::Ability { ability_id: _, .. } => {
-                // TODO: Check ability cooldowns
-                // For now, allow abilities
-            }
+        let attack_cooldown = 1.0 / combat.attack_speed as f64;
+        if now - combat.last_attack_time < attack_cooldown {
+            return Err("Attack is on cooldown".to_string());
         }
 
         // Check if target is valid (not attacking self, etc.)
@@ -175,26 +549,105 @@ impl CombatSystem {
         Ok(())
     }
 
-    /// Calculate damage for an attack
-    fn calculate_damage(attacker: &Entity, target: &Entity, action: &CombatAction) -> u32 {
-        let attacker_combat = attacker.combat.as_ref().unwrap();
-        let target_combat = target.combat.as_ref();
+    /// Start an ability's cooldown and pay its cost
+    fn start_cooldown(attacker: &mut Entity, definition: &AbilityDefinition, now: f64) {
+        if let Some(abilities) = attacker.abilities.as_mut() {
+            abilities
+                .cooldowns
+                .insert(definition.id, now + definition.cooldown as f64);
+        }
+        ResourceSystem::spend(attacker, definition.resource_cost);
+    }
+
+    /// Validate if an ability can be used on `target` at world time `now`
+    fn validate_ability(
+        attacker: &Entity,
+        target: &Entity,
+        ability: &AbilityDefinition,
+        now: f64,
+        stage: Stage,
+    ) -> Result<(), String> {
+        Self::validate_use(attacker, ability, now, stage)?;
 
-        let base_damage = match action {
-            CombatAction::AutoAttack { .. } => attacker_combat.attack_power,
-            CombatAction::Ability { ability_id: _, .. } => {
-                // TODO: Look up ability damage from data
-                // For now, use a simple formula
-                attacker_combat.attack_power * 2
+        if !target.is_alive() {
+            return Err("Target is already dead".to_string());
+        }
+
+        // The same sides `area_affects` picks for area abilities
+        let hostile = attacker.is_hostile_toward(target) || target.is_hostile_toward(attacker);
+        match ability.target {
+            TargetType::Enemy if attacker.id == target.id => {
+                return Err("Cannot attack self".to_string());
             }
-        };
+            TargetType::Enemy if !hostile => {
+                return Err("Target is not hostile".to_string());
+            }
+            TargetType::Friendly if hostile => {
+                return Err("Target is not friendly".to_string());
+            }
+            _ => {}
+        }
 
-        // Apply defense reduction
-        let defense = target_combat.map_or(0, |c| c.defense);
-        let damage_reduction = (defense as f32 * 0.5).min(base_damage as f32 * 0.75);
-        let final_damage = (base_damage as f32 - damage_reduction).max(1.0) as u32;
+        if attacker.id != target.id {
+            Self::check_range(attacker, target, ability.range)?;
+        }
 
-        final_damage
+        Ok(())
+    }
+
+    /// Validate that the attacker knows the ability, it is off cooldown and affordable.
+    /// Channel ticks were paid for when the channel started, so skip cooldown and cost.
+    fn validate_use(
+        attacker: &Entity,
+        ability: &AbilityDefinition,
+        now: f64,
+        stage: Stage,
+    ) -> Result<(), String> {
+        let abilities = attacker
+            .abilities
+            .as_ref()
+            .filter(|abilities| abilities.ability_ids.contains(&ability.id))
+            .ok_or_else(|| format!("Ability {} not learned", ability.id))?;
+
+        if stage != Stage::ChannelTick
+            && abilities
+                .cooldowns
+                .get(&ability.id)
+                .is_some_and(|ready_at| now < *ready_at)
+        {
+            return Err("Ability is on cooldown".to_string());
+        }
+
+        if stage != Stage::ChannelTick
+            && !ResourceSystem::can_afford(attacker, ability.resource_cost)
+        {
+            let resource = attacker
+                .resource
+                .as_ref()
+                .map_or("resource", |resource| resource.resource_type.name());
+            return Err(format!("Not enough {}", resource));
+        }
+
+        Ok(())
+    }
+
+    fn check_range(attacker: &Entity, target: &Entity, range: f32) -> Result<(), String> {
+        let distance = attacker.distance_to(target);
+        if distance > range {
+            return Err(format!("Target is out of range ({} > {})", distance, range));
+        }
+        Ok(())
+    }
+
+    /// Scale raw damage by both sides' modifiers and reduce it by the target's defense
+    fn calculate_damage(base_damage: u32, attacker: &Entity, target: &Entity) -> u32 {
+        let dealt = StatModifiers::multiplier(attacker.stat_modifiers().damage_dealt);
+        let taken = StatModifiers::multiplier(target.stat_modifiers().damage_taken);
+        let base_damage = base_damage as f32 * dealt;
+
+        let defense = target.combat.as_ref().map_or(0, |c| c.defense);
+        let damage_reduction = (defense as f32 * 0.5).min(base_damage * 0.75);
+        ((base_damage - damage_reduction) * taken).max(1.0) as u32
     }
 
     /// Apply damage to a target and return if it was killed
@@ -202,8 +655,7 @@ impl CombatSystem {
         if let Some(health) = &mut target.health {
             if health.current <= damage {
                 health.current = 0;
-                // TODO: Handle death (respawn, loot, etc.)
-                true // Target was killed
+                true // Target was killed; DeathSystem takes it from here
             } else {
                 health.current -= damage;
                 false // Target survived
@@ -213,6 +665,18 @@ impl CombatSystem {
         }
     }
 
+    /// Heal a target up to its maximum health and return the amount restored
+    fn apply_healing(target: &mut Entity, amount: u32) -> u32 {
+        match &mut target.health {
+            Some(health) => {
+                let healed = amount.min(health.maximum.saturating_sub(health.current));
+                health.current += healed;
+                healed
+            }
+            None => 0,
+        }
+    }
+
     /// Check if an entity can attack another entity
     pub fn can_attack_entity(attacker: &Entity, target: &Entity) -> bool {
         if !attacker.can_attack() || !target.is_alive() {
@@ -248,3 +712,20 @@ impl CombatSystem {
             .collect()
     }
 }
+
+fn distance(a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+    let (dx, dy, dz) = (a.0 - b.0, a.1 - b.1, a.2 - b.2);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+/// Whether `position` lies within `half_angle` radians of the yaw `facing` as seen from
+/// `origin`. Yaw 0 faces -Z, matching the client.
+fn within_cone(origin: (f32, f32, f32), facing: f32, half_angle: f32, position: &Position) -> bool {
+    let (dx, dz) = (position.x - origin.0, position.z - origin.2);
+    let length = (dx * dx + dz * dz).sqrt();
+    if length < f32::EPSILON {
+        return true;
+    }
+    let (forward_x, forward_z) = (-facing.sin(), -facing.cos());
+    (dx * forward_x + dz * forward_z) / length >= half_angle.cos() - f32::EPSILON
+}
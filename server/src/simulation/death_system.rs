@@ -0,0 +1,213 @@
+//! Death, corpses and respawning
+//!
+//! An entity whose health reaches zero becomes a corpse and raises an
+//! `EntityEffect::Death` event. Mob corpses despawn once the corpse timer runs
+//! out, and the mob comes back at its home position after its own respawn
+//! delay. Dead players stay where they fell until they release to the zone's
+//! graveyard with reduced health. Each return raises `EntityEffect::Respawn`.
+//! A mob's death also pays experience to the players who fought it and rolls
+//! its loot onto the corpse.
+
+use crate::entities::{AiState, Entity, EntityId, EntityType, Position};
+use crate::network::messages::EntityEffect;
+use crate::simulation::progression_system::Kill;
+use crate::simulation::{LootingSystem, ProgressionSystem};
+use crate::world::{PendingRespawn, WorldState, Zone};
+
+/// Death and respawn tuning
+#[derive(Debug, Clone)]
+pub struct DeathConfig {
+    /// Seconds a mob's corpse stays in the world before it despawns
+    pub corpse_duration: f64,
+    /// Fraction of maximum health a released player comes back with
+    pub release_health_fraction: f32,
+}
+
+impl Default for DeathConfig {
+    fn default() -> Self {
+        Self {
+            corpse_duration: 30.0,
+            release_health_fraction: 0.5,
+        }
+    }
+}
+
+impl DeathConfig {
+    /// Read `CORPSE_DURATION_SECS` / `RELEASE_HEALTH_FRACTION`, falling back to defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let corpse_duration: f64 = std::env::var("CORPSE_DURATION_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.corpse_duration);
+        let release_health_fraction: f32 = std::env::var("RELEASE_HEALTH_FRACTION")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.release_health_fraction);
+
+        Self {
+            corpse_duration: corpse_duration.max(0.0),
+            release_health_fraction: release_health_fraction.clamp(0.01, 1.0),
+        }
+    }
+}
+
+/// Death system, ticked once per simulation tick
+pub struct DeathSystem;
+
+impl DeathSystem {
+    /// Record new deaths, despawn expired mob corpses and respawn mobs that are due
+    pub fn update(world_state: &mut WorldState) {
+        let config = world_state.death_config().clone();
+        let now = world_state.clock().now();
+        let mut events = Vec::new();
+        let mut kills = Vec::new();
+
+        for zone in world_state.zones_mut() {
+            Self::update_zone(zone, &config, now, &mut events, &mut kills);
+        }
+
+        for (entity_id, effect) in events {
+            world_state.push_entity_effect(entity_id, effect);
+        }
+        for kill in kills {
+            ProgressionSystem::award_kill(world_state, &kill);
+            LootingSystem::drop_loot(world_state, &kill);
+        }
+    }
+
+    fn update_zone(
+        zone: &mut Zone,
+        config: &DeathConfig,
+        now: f64,
+        events: &mut Vec<(EntityId, EntityEffect)>,
+        kills: &mut Vec<Kill>,
+    ) {
+        // Corpses of players who logged out are gone already
+        let entities = &zone.entities;
+        zone.corpses
+            .retain(|entity_id, _| entities.get_entity(*entity_id).is_some());
+
+        let zone_id = zone.id;
+        for entity in zone.entities.entities_mut() {
+            if entity.is_alive() || zone.corpses.contains_key(&entity.id) {
+                continue;
+            }
+            zone.corpses.insert(entity.id, now);
+            stop(entity);
+            kills.extend(Kill::of(zone_id, entity));
+            if let Some(threat) = entity.threat.as_mut() {
+                threat.clear();
+            }
+            events.push((entity.id, EntityEffect::Death));
+        }
+
+        let expired: Vec<EntityId> = zone
+            .corpses
+            .iter()
+            .filter(|(_, died_at)| now - **died_at >= config.corpse_duration)
+            .map(|(entity_id, _)| *entity_id)
+            .collect();
+        for entity_id in expired {
+            let is_player = zone
+                .entities
+                .get_entity(entity_id)
+                .is_some_and(|entity| matches!(entity.entity_type, EntityType::Player));
+            if is_player {
+                continue;
+            }
+            zone.corpses.remove(&entity_id);
+            zone.loot.remove(&entity_id);
+            if let Some(entity) = zone.entities.remove_entity(entity_id) {
+                if let Some(respawn) = &entity.respawn {
+                    zone.pending_respawns.push(PendingRespawn {
+                        respawn_at: now + respawn.respawn_delay,
+                        entity,
+                    });
+                }
+            }
+        }
+
+        let (due, waiting) = std::mem::take(&mut zone.pending_respawns)
+            .into_iter()
+            .partition(|pending| pending.respawn_at <= now);
+        zone.pending_respawns = waiting;
+        for PendingRespawn { mut entity, .. } in due {
+            let home = entity
+                .respawn
+                .as_ref()
+                .map_or((0.0, 0.0, 0.0), |respawn| respawn.home_position);
+            revive(&mut entity, 1.0, home);
+            if let Some(combat) = entity.combat.as_mut() {
+                combat.last_attack_time = f64::NEG_INFINITY;
+            }
+            if let Some(abilities) = entity.abilities.as_mut() {
+                abilities.cooldowns.clear();
+            }
+            if let Some(ai) = entity.ai.as_mut() {
+                ai.state = AiState::Idle;
+                ai.last_state_change = now;
+                ai.has_fled = false;
+            }
+            events.push((entity.id, EntityEffect::Respawn));
+            zone.entities.add_entity(entity);
+        }
+    }
+
+    /// Bring a dead player back at its zone's graveyard with reduced health
+    pub fn release(world_state: &mut WorldState, player_id: EntityId) -> Result<(), String> {
+        let fraction = world_state.death_config().release_health_fraction;
+        let zone_id = world_state
+            .get_player_zone_id(player_id)
+            .ok_or("Player not in any zone")?;
+        let zone = world_state
+            .get_zone_mut(zone_id)
+            .ok_or_else(|| format!("Zone {} not found", zone_id))?;
+        let graveyard = zone.graveyard;
+        let entity = zone
+            .entities
+            .get_entity_mut(player_id)
+            .ok_or("Player entity not found")?;
+        if entity.is_alive() {
+            return Err("Player is not dead".to_string());
+        }
+
+        revive(entity, fraction, graveyard);
+        zone.corpses.remove(&player_id);
+        world_state.push_entity_effect(player_id, EntityEffect::Respawn);
+        Ok(())
+    }
+}
+
+/// Halt a dying entity where it stands
+fn stop(entity: &mut Entity) {
+    if let Some(movement) = entity.movement.as_mut() {
+        movement.velocity_x = 0.0;
+        movement.velocity_y = 0.0;
+        movement.velocity_z = 0.0;
+        movement.is_moving = false;
+    }
+}
+
+/// Restore health to a fraction of maximum at `position`, dropping any status effects and threat
+fn revive(entity: &mut Entity, health_fraction: f32, position: (f32, f32, f32)) {
+    if let Some(health) = entity.health.as_mut() {
+        health.current = ((health.maximum as f32 * health_fraction).round() as u32)
+            .clamp(1, health.maximum.max(1));
+    }
+    if let Some(status_effects) = entity.status_effects.as_mut() {
+        status_effects.active.clear();
+    }
+    if let Some(threat) = entity.threat.as_mut() {
+        threat.clear();
+    }
+    entity.casting = None;
+    let rotation = entity.position.as_ref().map_or(0.0, |pos| pos.rotation);
+    entity.position = Some(Position {
+        x: position.0,
+        y: position.1,
+        z: position.2,
+        rotation,
+    });
+    stop(entity);
+}
@@ -0,0 +1,198 @@
+//! Status effect processing
+//!
+//! Applies effects from abilities according to their stack rules, runs
+//! periodic damage and healing, and expires effects whose duration is up.
+//! Every apply, refresh and expiry is raised as an `EntityEffect::StatusEffect`
+//! event for replication, and every periodic tick as an
+//! `EntityEffect::DamageNumber`.
+
+use crate::abilities::{AbilityRegistry, EffectDefinition, StackRule};
+use crate::entities::{ActiveEffect, Entity, EntityId, StatModifiers};
+use crate::network::messages::{CombatEventKind, EntityEffect, StatusEffectChange};
+use crate::simulation::{ResourceSystem, ThreatSystem};
+use crate::world::WorldState;
+
+/// Status effect system, ticked once per simulation tick
+pub struct EffectsSystem;
+
+impl EffectsSystem {
+    /// Apply an effect to an entity, returning the event to replicate.
+    /// Entities without a status effect component are immune.
+    pub fn apply(
+        entity: &mut Entity,
+        definition: &EffectDefinition,
+        source_id: EntityId,
+        now: f64,
+    ) -> Option<EntityEffect> {
+        let effects = entity.status_effects.as_mut()?;
+        let expires_at = now + definition.duration as f64;
+
+        let (change, stacks) = match effects
+            .active
+            .iter_mut()
+            .find(|active| active.effect_id == definition.id)
+        {
+            Some(active) => {
+                match definition.stacking {
+                    StackRule::Ignore => return None,
+                    StackRule::Refresh => {}
+                    StackRule::Stack => {
+                        active.stacks = (active.stacks + 1).min(definition.max_stacks);
+                    }
+                }
+                active.source_id = source_id;
+                active.expires_at = expires_at;
+                (StatusEffectChange::Refreshed, active.stacks)
+            }
+            None => {
+                effects.active.push(ActiveEffect {
+                    effect_id: definition.id,
+                    source_id,
+                    stacks: 1,
+                    expires_at,
+                    next_tick_at: now + definition.tick_interval as f64,
+                    modifiers: definition.modifiers,
+                    stun: definition.stun,
+                });
+                (StatusEffectChange::Applied, 1)
+            }
+        };
+
+        Some(status_event(
+            definition,
+            stacks,
+            definition.duration,
+            change,
+        ))
+    }
+
+    /// Run periodic ticks and expire finished effects in every zone
+    pub fn update(world_state: &mut WorldState) {
+        let registry = world_state.abilities();
+        let now = world_state.clock().now();
+        let mut events = Vec::new();
+
+        for zone in world_state.zones_mut() {
+            let mut heals = Vec::new();
+            for entity in zone.entities.entities_mut() {
+                Self::update_entity(entity, &registry, now, &mut events, &mut heals);
+            }
+            for (healer_id, healed_id, amount) in heals {
+                ThreatSystem::on_healing(zone, healer_id, healed_id, amount);
+            }
+        }
+
+        for (entity_id, effect) in events {
+            world_state.push_entity_effect(entity_id, effect);
+        }
+    }
+
+    fn update_entity(
+        entity: &mut Entity,
+        registry: &AbilityRegistry,
+        now: f64,
+        events: &mut Vec<(EntityId, EntityEffect)>,
+        heals: &mut Vec<(EntityId, EntityId, u32)>,
+    ) {
+        let entity_id = entity.id;
+        let alive = entity.is_alive();
+        let damage_taken = StatModifiers::multiplier(entity.stat_modifiers().damage_taken);
+        let Some(effects) = entity.status_effects.as_mut() else {
+            return;
+        };
+        if effects.active.is_empty() {
+            return;
+        }
+
+        let mut damage = 0;
+        let mut healing = 0;
+        // Damage per source, for threat
+        let mut sources: Vec<(EntityId, u32)> = Vec::new();
+        effects.active.retain_mut(|active| {
+            let Some(definition) = registry.effect(active.effect_id) else {
+                // Definition removed by a content reload
+                return false;
+            };
+
+            // Ticks due up to now, including one landing exactly on expiry
+            if alive && definition.tick_interval > 0.0 {
+                while active.next_tick_at <= now.min(active.expires_at) {
+                    let tick_damage =
+                        (definition.tick_damage as f32 * active.stacks as f32 * damage_taken)
+                            .round() as u32;
+                    let tick_heal = definition.tick_heal * active.stacks;
+                    for (amount, kind) in [
+                        (tick_damage, CombatEventKind::Damage),
+                        (tick_heal, CombatEventKind::Heal),
+                    ] {
+                        if amount > 0 {
+                            events.push((entity_id, tick_event(active.source_id, amount, kind)));
+                        }
+                    }
+                    if tick_damage > 0 {
+                        sources.push((active.source_id, tick_damage));
+                    }
+                    if tick_heal > 0 {
+                        heals.push((active.source_id, entity_id, tick_heal));
+                    }
+                    damage += tick_damage;
+                    healing += tick_heal;
+                    active.next_tick_at += definition.tick_interval as f64;
+                }
+            }
+
+            if active.expires_at <= now {
+                events.push((
+                    entity_id,
+                    status_event(definition, 0, 0.0, StatusEffectChange::Expired),
+                ));
+                false
+            } else {
+                true
+            }
+        });
+
+        if let Some(health) = entity.health.as_mut().filter(|_| alive) {
+            health.current = if damage >= health.current && damage > 0 {
+                0
+            } else {
+                (health.current - damage)
+                    .saturating_add(healing)
+                    .min(health.maximum)
+            };
+        }
+        if alive && damage > 0 {
+            ResourceSystem::on_damage_taken(entity, damage, now);
+            for (source_id, amount) in sources {
+                ThreatSystem::on_damage(entity, source_id, amount);
+            }
+        }
+    }
+}
+
+/// A periodic tick of damage or healing, reported like a hit
+fn tick_event(source_id: EntityId, amount: u32, kind: CombatEventKind) -> EntityEffect {
+    EntityEffect::DamageNumber {
+        amount,
+        is_critical: false,
+        source_id,
+        ability_id: 0,
+        kind,
+        killing_blow: false,
+    }
+}
+
+fn status_event(
+    definition: &EffectDefinition,
+    stacks: u32,
+    duration: f32,
+    change: StatusEffectChange,
+) -> EntityEffect {
+    EntityEffect::StatusEffect {
+        effect_type: definition.name.clone(),
+        duration,
+        effect_id: definition.id,
+        stacks,
+        change,
+    }
+}
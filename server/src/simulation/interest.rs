@@ -0,0 +1,154 @@
+//! Area-of-interest management
+//!
+//! Each tick this system works out which players can see each entity and
+//! records it in the entity's `NetworkSync` component. `visible_to` lists the
+//! observing players and `viewer_intervals` how often each of them needs
+//! updates: an entity near a player replicates to them every tick, while
+//! players further off get it less often, however close anyone else stands.
+//! Replication only sends an entity to players in its `visible_to` list, so
+//! entering or leaving that list shows up as a spawn or despawn.
+
+use crate::entities::{EntityId, NetworkSync};
+use crate::world::WorldState;
+use std::collections::{HashMap, HashSet};
+
+/// Fraction beyond the radius an entity may drift before it leaves view,
+/// so entities on the boundary don't flicker in and out
+const VISIBILITY_HYSTERESIS: f32 = 1.1;
+
+/// Interest management tuning
+#[derive(Debug, Clone)]
+pub struct InterestConfig {
+    /// Entities further than this from a player are not replicated to them
+    pub radius: f32,
+    /// Entities within this distance of an observer replicate every tick
+    pub near_radius: f32,
+    /// Seconds between updates for entities beyond `near_radius`
+    pub far_sync_interval: f64,
+}
+
+impl Default for InterestConfig {
+    fn default() -> Self {
+        Self {
+            radius: 60.0,
+            near_radius: 25.0,
+            far_sync_interval: 0.25,
+        }
+    }
+}
+
+impl InterestConfig {
+    /// Read `INTEREST_RADIUS` / `INTEREST_NEAR_RADIUS` / `INTEREST_FAR_SYNC_SECS`,
+    /// falling back to defaults
+    pub fn from_env() -> Self {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    /// Build the config from `lookup`; radii that are not positive fall back to defaults
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let radius: f32 = lookup("INTEREST_RADIUS")
+            .and_then(|value| value.parse().ok())
+            .filter(|radius: &f32| *radius > 0.0)
+            .unwrap_or(defaults.radius);
+        let near_radius: f32 = lookup("INTEREST_NEAR_RADIUS")
+            .and_then(|value| value.parse().ok())
+            .filter(|radius: &f32| *radius > 0.0)
+            .unwrap_or(defaults.near_radius);
+        let far_sync_interval: f64 = lookup("INTEREST_FAR_SYNC_SECS")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.far_sync_interval);
+
+        Self {
+            radius,
+            near_radius: near_radius.min(radius),
+            far_sync_interval: far_sync_interval.max(0.0),
+        }
+    }
+}
+
+/// Computes per-entity visibility for replication
+pub struct InterestSystem;
+
+impl InterestSystem {
+    /// Refresh `visible_to` and `viewer_intervals` for every entity in every zone
+    pub fn update(world: &mut WorldState, config: &InterestConfig) {
+        let zone_ids: Vec<u32> = world.get_all_zones().iter().map(|zone| zone.id).collect();
+
+        for zone_id in zone_ids {
+            let Some(zone) = world.get_zone_mut(zone_id) else {
+                continue;
+            };
+
+            let observers: Vec<(EntityId, (f32, f32, f32))> = zone
+                .active_players
+                .iter()
+                .filter_map(|&player_id| {
+                    let position = zone.entities.get_entity(player_id)?.position.as_ref()?;
+                    Some((player_id, (position.x, position.y, position.z)))
+                })
+                .collect();
+
+            let entity_ids: Vec<EntityId> = zone
+                .entities
+                .get_all_entities()
+                .iter()
+                .map(|entity| entity.id)
+                .collect();
+
+            for entity_id in entity_ids {
+                let Some(entity) = zone.entities.get_entity_mut(entity_id) else {
+                    continue;
+                };
+                let Some(position) = entity.position.as_ref() else {
+                    continue;
+                };
+                let position = (position.x, position.y, position.z);
+
+                let sync = entity.network_sync.get_or_insert_with(|| NetworkSync {
+                    last_sync_time: 0.0,
+                    sync_interval: config.far_sync_interval,
+                    visible_to: Vec::new(),
+                    viewer_intervals: HashMap::new(),
+                });
+                let previously_visible: HashSet<EntityId> =
+                    sync.visible_to.iter().copied().collect();
+
+                let mut visible_to = Vec::new();
+                let mut viewer_intervals = HashMap::new();
+                for &(observer_id, observer_position) in &observers {
+                    let distance = if observer_id == entity_id {
+                        0.0
+                    } else {
+                        distance(position, observer_position)
+                    };
+                    let limit = if previously_visible.contains(&observer_id) {
+                        config.radius * VISIBILITY_HYSTERESIS
+                    } else {
+                        config.radius
+                    };
+
+                    if distance <= limit {
+                        visible_to.push(observer_id);
+                        let interval = if distance <= config.near_radius {
+                            0.0
+                        } else {
+                            config.far_sync_interval
+                        };
+                        viewer_intervals.insert(observer_id, interval);
+                    }
+                }
+
+                sync.visible_to = visible_to;
+                sync.viewer_intervals = viewer_intervals;
+            }
+        }
+    }
+}
+
+fn distance(a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+    let dx = a.0 - b.0;
+    let dy = a.1 - b.1;
+    let dz = a.2 - b.2;
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
@@ -0,0 +1,424 @@
+//! Corpse loot
+//!
+//! A mob with a loot table rolls it when it dies, for the player who landed
+//! the killing blow: the table's conditions see that player's level, class,
+//! quests and inventory. The drops belong to the player who tagged the mob by
+//! damaging it first, or to every member of the tagger's party in the zone,
+//! and are shared out by the party's loot rules: free for all, round robin,
+//! or need before greed, where items of the party's threshold rarity and up
+//! are rolled for. Drops and rolls come from the world's seeded RNG. Opening a
+//! corpse picks up its gold, split evenly across a party, and lists the items
+//! the player may take one loot slot at a time. Loot nobody claims goes free
+//! for anyone once `free_for_all_after` has passed; whatever is still on the
+//! corpse when it despawns is lost.
+
+use crate::entities::{Entity, EntityId, EntityType};
+use crate::items::{ItemInstance, ItemRarity};
+use crate::loot::{CorpseLoot, LootContext, LootMethod, RollChoice};
+use crate::network::messages::EntityEffect;
+use crate::simulation::progression_system::Kill;
+use crate::world::{WorldState, Zone};
+
+/// How close a player must stand to a corpse to loot it
+pub const LOOT_RANGE: f32 = 5.0;
+
+/// Loot timers
+#[derive(Debug, Clone)]
+pub struct LootConfig {
+    /// Seconds a party has to answer a need/greed/pass roll
+    pub roll_duration: f64,
+    /// Seconds after a death before anyone may loot what is left on the corpse
+    pub free_for_all_after: f64,
+}
+
+impl Default for LootConfig {
+    fn default() -> Self {
+        Self {
+            roll_duration: 15.0,
+            free_for_all_after: 20.0,
+        }
+    }
+}
+
+impl LootConfig {
+    /// Read `LOOT_ROLL_SECS` / `LOOT_FREE_FOR_ALL_SECS`, falling back to defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let roll_duration: f64 = std::env::var("LOOT_ROLL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.roll_duration);
+        let free_for_all_after: f64 = std::env::var("LOOT_FREE_FOR_ALL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.free_for_all_after);
+
+        Self {
+            roll_duration: roll_duration.max(0.0),
+            free_for_all_after: free_for_all_after.max(0.0),
+        }
+    }
+}
+
+/// A corpse's remaining loot as one player sees it
+#[derive(Debug, Clone)]
+pub struct LootWindow {
+    /// Gold the player picked up with this request
+    pub gold: u32,
+    /// Items the player may take, by loot slot
+    pub items: Vec<(u32, ItemInstance)>,
+}
+
+/// Looting system, fed by the death system and by players' loot requests
+pub struct LootingSystem;
+
+impl LootingSystem {
+    /// Settle loot rolls that everyone has answered or that ran out of time
+    pub fn update(world_state: &mut WorldState) {
+        let now = world_state.clock().now();
+        let mut events = Vec::new();
+
+        let (zones, rng) = world_state.zones_and_combat_rng_mut();
+        for zone in zones {
+            for (corpse_id, loot) in zone.loot.iter_mut() {
+                for (slot, corpse_item) in loot.items.iter_mut().enumerate() {
+                    let Some(corpse_item) = corpse_item.as_mut() else {
+                        continue;
+                    };
+                    if !corpse_item
+                        .roll
+                        .as_ref()
+                        .is_some_and(|roll| roll.is_finished(now))
+                    {
+                        continue;
+                    }
+                    let Some(roll) = corpse_item.roll.take() else {
+                        continue;
+                    };
+                    let result = roll.resolve(rng);
+                    // An item everyone passed on goes to whichever looter takes it first
+                    corpse_item.owner = result.map(|result| result.winner_id);
+                    let effect = EntityEffect::LootRollResult {
+                        corpse_id: *corpse_id,
+                        slot: slot as u32,
+                        item_id: corpse_item.item.definition_id,
+                        winner_id: result.map_or(0, |result| result.winner_id),
+                        choice: result.map_or(RollChoice::Pass, |result| result.choice),
+                        roll: result.map_or(0, |result| result.roll),
+                    };
+                    events.extend(roll.eligible.iter().map(|id| (*id, effect.clone())));
+                }
+            }
+        }
+
+        for (entity_id, effect) in events {
+            world_state.push_entity_effect(entity_id, effect);
+        }
+    }
+
+    /// Roll the victim's loot table for its killer and leave the drops on the
+    /// corpse for its tagger, shared out by the tagger's party rules
+    pub fn drop_loot(world_state: &mut WorldState, kill: &Kill) {
+        let loot_tables = world_state.loot_tables();
+        let items = world_state.items();
+        let config = world_state.loot_config().clone();
+        let now = world_state.clock().now();
+        let Some(tagger_id) = kill.tagged_by else {
+            return;
+        };
+        let party = world_state
+            .parties()
+            .party_of(tagger_id)
+            .map(|party| (party.members.clone(), party.loot_rules));
+        let Some(zone) = world_state.get_zone(kill.zone_id) else {
+            return;
+        };
+        let Some(table) = zone
+            .entities
+            .get_entity(kill.victim_id)
+            .and_then(|victim| victim.loot_table)
+            .and_then(|table_id| loot_tables.get_table(table_id))
+        else {
+            return;
+        };
+        let player = |id: EntityId| {
+            zone.entities
+                .get_entity(id)
+                .filter(|entity| is_player(entity))
+        };
+        if player(tagger_id).is_none() {
+            return;
+        }
+        // A killing blow from anything but a player leaves the tagger's context
+        let Some(killer) = kill
+            .killed_by
+            .and_then(player)
+            .or_else(|| player(tagger_id))
+        else {
+            return;
+        };
+        let context = loot_context(killer);
+        let looters = match &party {
+            Some((members, _)) => members
+                .iter()
+                .copied()
+                .filter(|id| zone.entities.get_entity(*id).is_some())
+                .collect(),
+            None => vec![tagger_id],
+        };
+        let drops = table.generate_loot(world_state.combat_rng_mut(), &context);
+        let mut loot = CorpseLoot::new(drops, looters, now + config.free_for_all_after);
+        if loot.is_empty() {
+            return;
+        }
+        if let Some((_, rules)) = party.filter(|(_, rules)| rules.method != LootMethod::FreeForAll)
+        {
+            let turn = world_state
+                .parties_mut()
+                .party_of_mut(tagger_id)
+                .and_then(|party| party.next_looter(&loot.looters))
+                .unwrap_or(tagger_id);
+            let rarity_of = |item: &ItemInstance| {
+                items
+                    .get_item(item.definition_id)
+                    .map_or(ItemRarity::Common, |definition| definition.rarity)
+            };
+            loot.share_out(&rules, turn, rarity_of, now + config.roll_duration);
+        }
+
+        let mut events = Vec::new();
+        for (slot, corpse_item) in loot.items.iter().enumerate() {
+            let Some(corpse_item) = corpse_item.as_ref() else {
+                continue;
+            };
+            let Some(roll) = corpse_item.roll.as_ref() else {
+                continue;
+            };
+            let effect = EntityEffect::LootRoll {
+                corpse_id: kill.victim_id,
+                slot: slot as u32,
+                item_id: corpse_item.item.definition_id,
+                duration: config.roll_duration as f32,
+            };
+            events.extend(roll.eligible.iter().map(|id| (*id, effect.clone())));
+        }
+        if let Some(zone) = world_state.get_zone_mut(kill.zone_id) {
+            zone.loot.insert(kill.victim_id, loot);
+        }
+        for (entity_id, effect) in events {
+            world_state.push_entity_effect(entity_id, effect);
+        }
+    }
+
+    /// Open a corpse: pick up its gold and list the items the player may take
+    pub fn open(
+        world_state: &mut WorldState,
+        player_id: EntityId,
+        corpse_id: EntityId,
+    ) -> Result<LootWindow, String> {
+        let now = world_state.clock().now();
+        let zone = reach(world_state, player_id, corpse_id, now)?;
+        let gold = pick_up_gold(zone, player_id, corpse_id);
+        Ok(window(zone, player_id, corpse_id, gold, now))
+    }
+
+    /// Take the item in one loot slot of a corpse into the player's inventory
+    pub fn take(
+        world_state: &mut WorldState,
+        player_id: EntityId,
+        corpse_id: EntityId,
+        slot: u32,
+    ) -> Result<LootWindow, String> {
+        let items = world_state.items();
+        let now = world_state.clock().now();
+        let zone = reach(world_state, player_id, corpse_id, now)?;
+        let gold = pick_up_gold(zone, player_id, corpse_id);
+
+        let loot = zone
+            .loot
+            .get(&corpse_id)
+            .ok_or("There is nothing to loot")?;
+        loot.check_take(player_id, slot, now)?;
+        let item = loot.items[slot as usize]
+            .as_ref()
+            .map(|corpse_item| corpse_item.item.clone())
+            .ok_or("That item is no longer there")?;
+        let inventory = zone
+            .entities
+            .get_entity_mut(player_id)
+            .and_then(|player| player.inventory.as_mut())
+            .ok_or("Player has no inventory")?;
+        // Add to a copy so a stack that only partly fits leaves the inventory untouched
+        let mut updated = inventory.clone();
+        updated
+            .add_item(item, &items)
+            .map_err(|error| error.to_string())?;
+        *inventory = updated;
+        if let Some(loot) = zone.loot.get_mut(&corpse_id) {
+            loot.take_item(slot);
+        }
+
+        Ok(window(zone, player_id, corpse_id, gold, now))
+    }
+
+    /// Answer the need/greed/pass roll for the item in one loot slot of a corpse
+    pub fn roll(
+        world_state: &mut WorldState,
+        player_id: EntityId,
+        corpse_id: EntityId,
+        slot: u32,
+        choice: RollChoice,
+    ) -> Result<(), String> {
+        let zone_id = world_state
+            .get_player_zone_id(player_id)
+            .ok_or("Player not in any zone")?;
+        let zone = world_state
+            .get_zone_mut(zone_id)
+            .ok_or_else(|| format!("Zone {} not found", zone_id))?;
+        zone.loot
+            .get_mut(&corpse_id)
+            .and_then(|loot| loot.items.get_mut(slot as usize))
+            .and_then(Option::as_mut)
+            .and_then(|corpse_item| corpse_item.roll.as_mut())
+            .ok_or("That item is not being rolled for")?
+            .choose(player_id, choice)
+    }
+}
+
+/// The zone of a corpse the player may loot right now
+fn reach(
+    world_state: &mut WorldState,
+    player_id: EntityId,
+    corpse_id: EntityId,
+    now: f64,
+) -> Result<&mut Zone, String> {
+    let zone_id = world_state
+        .get_player_zone_id(player_id)
+        .ok_or("Player not in any zone")?;
+    let zone = world_state
+        .get_zone_mut(zone_id)
+        .ok_or_else(|| format!("Zone {} not found", zone_id))?;
+    let player = zone
+        .entities
+        .get_entity(player_id)
+        .ok_or("Player entity not found")?;
+    if !player.is_alive() {
+        return Err("You cannot loot while dead".to_string());
+    }
+    let corpse = zone
+        .entities
+        .get_entity(corpse_id)
+        .ok_or("Corpse not found")?;
+    if corpse.is_alive() {
+        return Err("Target is not dead".to_string());
+    }
+    let loot = zone
+        .loot
+        .get(&corpse_id)
+        .ok_or("There is nothing to loot")?;
+    if !loot.can_open(player_id, now) {
+        return Err("You may not loot that corpse".to_string());
+    }
+    if player.distance_to(corpse) > LOOT_RANGE {
+        return Err("Corpse is too far away".to_string());
+    }
+    Ok(zone)
+}
+
+/// Split a corpse's gold evenly between its looters still in the zone and
+/// return the opener's share, which takes any remainder. Once the loot has
+/// gone free, a passer-by who opens the corpse takes it all.
+fn pick_up_gold(zone: &mut Zone, player_id: EntityId, corpse_id: EntityId) -> u32 {
+    let Some(loot) = zone.loot.get_mut(&corpse_id) else {
+        return 0;
+    };
+    let gold = loot.take_gold();
+    let others: Vec<EntityId> = if loot.looters.contains(&player_id) {
+        loot.looters
+            .iter()
+            .copied()
+            .filter(|id| *id != player_id && zone.entities.get_entity(*id).is_some())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let share = gold / (others.len() as u32 + 1);
+    let own_share = gold - share * others.len() as u32;
+    for id in others {
+        add_gold(zone, id, share);
+    }
+    add_gold(zone, player_id, own_share);
+    own_share
+}
+
+fn add_gold(zone: &mut Zone, player_id: EntityId, amount: u32) {
+    if let Some(inventory) = zone
+        .entities
+        .get_entity_mut(player_id)
+        .and_then(|player| player.inventory.as_mut())
+    {
+        inventory.gold += amount as u64;
+    }
+}
+
+/// What the player may take from a corpse, dropping its loot once the corpse is bare
+fn window(
+    zone: &mut Zone,
+    player_id: EntityId,
+    corpse_id: EntityId,
+    gold: u32,
+    now: f64,
+) -> LootWindow {
+    let mut items = Vec::new();
+    if let Some(loot) = zone.loot.get(&corpse_id) {
+        items = loot
+            .takeable_items(player_id, now)
+            .map(|(slot, item)| (slot, item.clone()))
+            .collect();
+        if loot.is_empty() {
+            zone.loot.remove(&corpse_id);
+        }
+    }
+    LootWindow { gold, items }
+}
+
+fn is_player(entity: &Entity) -> bool {
+    matches!(entity.entity_type, EntityType::Player)
+}
+
+/// What a loot table's conditions can see of the player it rolls for
+fn loot_context(player: &Entity) -> LootContext {
+    let level = player
+        .progression
+        .as_ref()
+        .map_or(1, |progression| progression.level);
+    let class = player
+        .stats
+        .as_ref()
+        .and_then(|stats| stats.class.clone())
+        .unwrap_or_default();
+    let (mut active, completed) = player
+        .quest_state
+        .as_ref()
+        .map(|quests| {
+            let active: Vec<u32> = quests.active_quests.keys().copied().collect();
+            (active, quests.completed_quests.clone())
+        })
+        .unwrap_or_default();
+    active.sort_unstable();
+    let owned = player
+        .inventory
+        .as_ref()
+        .map(|inventory| {
+            inventory
+                .slots
+                .values()
+                .map(|item| item.definition_id)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    LootContext::new(level, class)
+        .with_quests(active, completed)
+        .with_inventory(owned)
+}
@@ -3,10 +3,39 @@
 //! This module implements the main game simulation loop that runs
 //! at 20 Hz and updates all game systems.
 
+pub mod ai_system;
+pub mod attack_table;
+pub mod cast_system;
+pub mod clock;
 pub mod combat_system;
+pub mod death_system;
+pub mod effects_system;
+pub mod interest;
+pub mod looting_system;
 pub mod movement_system;
+pub mod party_system;
+pub mod progression_system;
+pub mod replication;
+pub mod resource_system;
+pub mod stats_system;
+pub mod threat_system;
 pub mod tick_loop;
 
+#[cfg(test)]
+mod tests;
+
+pub use ai_system::AiSystem;
+pub use attack_table::AttackTableConfig;
+pub use cast_system::CastSystem;
+pub use clock::WorldClock;
 pub use combat_system::*;
+pub use death_system::{DeathConfig, DeathSystem};
+pub use effects_system::EffectsSystem;
+pub use looting_system::{LootConfig, LootingSystem};
+pub use party_system::PartySystem;
+pub use progression_system::ProgressionSystem;
+pub use resource_system::ResourceSystem;
+pub use stats_system::StatsSystem;
+pub use threat_system::ThreatSystem;
 
 pub use tick_loop::*;
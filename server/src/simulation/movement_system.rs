@@ -4,6 +4,7 @@
 //! and updates entity positions.
 
 use crate::entities::{Entity, EntityId};
+use crate::simulation::CastSystem;
 use crate::world::WorldState;
 
 /// Movement intent from a client
@@ -24,6 +25,8 @@ pub struct MovementSystem;
 impl MovementSystem {
     /// Allow some headroom for client jitter/buffs while keeping an upper bound per tick
     const MAX_DISTANCE_FACTOR: f32 = 5.0;
+    /// Horizontal distance a caster may drift without breaking its cast
+    const CAST_MOVE_TOLERANCE: f32 = 0.05;
 
     /// Process a movement intent
     pub fn process_movement_intent(
@@ -39,6 +42,9 @@ impl MovementSystem {
             stop = intent.stop_movement,
             "processing movement intent"
         );
+        let now = world_state.clock().now();
+        let player_id = intent.player_id;
+
         // Get the player's zone
         let zone_id = world_state
             .ensure_player_zone_mapping(intent.player_id)
@@ -67,9 +73,20 @@ impl MovementSystem {
         // Validate movement
         Self::validate_movement(entity, &clamped_intent)?;
 
+        // Walking breaks a cast or channel; turning on the spot does not
+        let interrupted =
+            if Self::horizontal_distance(entity, &clamped_intent) > Self::CAST_MOVE_TOLERANCE {
+                CastSystem::cancel(entity, now, "Moved")
+            } else {
+                None
+            };
+
         // Apply movement
         Self::apply_movement(entity, clamped_intent);
 
+        if let Some(event) = interrupted {
+            world_state.push_entity_effect(player_id, event);
+        }
         Ok(())
     }
 
@@ -85,7 +102,6 @@ impl MovementSystem {
             return Err("Entity is not alive".to_string());
         }
 
-        let movement = entity.movement.as_ref().unwrap();
         let position = entity.position.as_ref().unwrap();
 
         // Check speed limits
@@ -94,7 +110,7 @@ impl MovementSystem {
         let distance = (dx * dx + dz * dz).sqrt();
 
         let max_distance_per_tick =
-            (movement.max_speed * intent.speed_modifier * Self::MAX_DISTANCE_FACTOR) / 20.0; // 20 TPS
+            (Self::max_speed(entity) * intent.speed_modifier * Self::MAX_DISTANCE_FACTOR) / 20.0; // 20 TPS
         if distance > max_distance_per_tick + f32::EPSILON {
             return Err(format!(
                 "Movement distance {} exceeds maximum {} per tick",
@@ -119,8 +135,25 @@ impl MovementSystem {
         Ok(())
     }
 
+    fn horizontal_distance(entity: &Entity, intent: &MovementIntent) -> f32 {
+        entity.position.as_ref().map_or(0.0, |position| {
+            let dx = intent.target_x - position.x;
+            let dz = intent.target_z - position.z;
+            (dx * dx + dz * dz).sqrt()
+        })
+    }
+
+    /// Maximum speed; the stat pipeline has already applied gear and status effects
+    fn max_speed(entity: &Entity) -> f32 {
+        entity
+            .movement
+            .as_ref()
+            .map_or(0.0, |movement| movement.max_speed)
+    }
+
     /// Apply validated movement to an entity
     fn apply_movement(entity: &mut Entity, intent: MovementIntent) {
+        let speed_multiplier = entity.movement_speed_multiplier();
         if let Some(position) = &mut entity.position {
             position.rotation = intent.rotation_y;
         }
@@ -136,7 +169,7 @@ impl MovementSystem {
 
             if distance > 0.0 {
                 // Normalize direction and apply speed
-                let speed = movement.speed * intent.speed_modifier;
+                let speed = movement.speed * intent.speed_modifier * speed_multiplier;
                 movement.velocity_x = (dx / distance) * speed;
                 movement.velocity_z = (dz / distance) * speed;
                 movement.is_moving = true;
@@ -147,10 +180,9 @@ impl MovementSystem {
     fn clamp_intent(entity: &Entity, intent: MovementIntent) -> MovementIntent {
         let mut adjusted = intent.clone();
 
-        let movement = match &entity.movement {
-            Some(m) => m,
-            None => return adjusted,
-        };
+        if entity.movement.is_none() {
+            return adjusted;
+        }
         let position = match &entity.position {
             Some(p) => p,
             None => return adjusted,
@@ -162,7 +194,7 @@ impl MovementSystem {
         let distance = (dx * dx + dy * dy + dz * dz).sqrt();
 
         let max_distance_per_tick =
-            (movement.max_speed * intent.speed_modifier * Self::MAX_DISTANCE_FACTOR) / 20.0; // 20 TPS
+            (Self::max_speed(entity) * intent.speed_modifier * Self::MAX_DISTANCE_FACTOR) / 20.0; // 20 TPS
         if distance > max_distance_per_tick && distance > 0.0 {
             let scale = max_distance_per_tick / distance;
             adjusted.target_x = position.x + dx * scale;
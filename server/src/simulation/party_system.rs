@@ -0,0 +1,146 @@
+//! Party requests
+//!
+//! Players invite one another, answer invitations, leave and set their
+//! party's loot rules through the `parties` registry. Every member is told
+//! how their party stands after each change, and a player who leaves, or
+//! whose party disbands under them, is told they are in none. An invitation
+//! reaches only the invited player. All of this rides the replication stream
+//! as private effects.
+
+use crate::entities::EntityId;
+use crate::loot::LootRules;
+use crate::network::messages::{EntityEffect, PartyMember};
+use crate::party::{PartyError, PartyId};
+use crate::world::WorldState;
+
+/// Party system, fed by players' party requests
+pub struct PartySystem;
+
+impl PartySystem {
+    /// Invite a player in the world into the inviter's party
+    pub fn invite(
+        world_state: &mut WorldState,
+        inviter_id: EntityId,
+        invitee_id: EntityId,
+    ) -> Result<(), PartyError> {
+        if world_state.get_player_zone_id(invitee_id).is_none() {
+            return Err(PartyError::InvalidInvitee);
+        }
+        world_state.parties_mut().invite(inviter_id, invitee_id)?;
+        let inviter_name = world_state.get_player_name(inviter_id).unwrap_or_default();
+        world_state.push_entity_effect(
+            invitee_id,
+            EntityEffect::PartyInvite {
+                inviter_id,
+                inviter_name,
+            },
+        );
+        Ok(())
+    }
+
+    /// Accept or decline the invitation a player was last sent
+    pub fn reply(
+        world_state: &mut WorldState,
+        player_id: EntityId,
+        accept: bool,
+    ) -> Result<(), PartyError> {
+        if !accept {
+            world_state
+                .parties_mut()
+                .decline_invite(player_id)
+                .ok_or(PartyError::NoInvite)?;
+            return Ok(());
+        }
+        let party_id = world_state.parties_mut().accept_invite(player_id)?;
+        Self::announce(world_state, party_id);
+        Ok(())
+    }
+
+    /// Take a player out of their party. Their invitations are dropped even
+    /// when they are in none.
+    pub fn leave(world_state: &mut WorldState, player_id: EntityId) -> Result<(), PartyError> {
+        let members = world_state
+            .parties()
+            .party_of(player_id)
+            .map(|party| party.members.clone())
+            .unwrap_or_default();
+        world_state
+            .parties_mut()
+            .leave(player_id)
+            .ok_or(PartyError::NotInParty)?;
+        for member in members {
+            Self::notify(world_state, member);
+        }
+        Ok(())
+    }
+
+    /// Change how the player's party shares loot
+    pub fn set_loot_rules(
+        world_state: &mut WorldState,
+        player_id: EntityId,
+        rules: LootRules,
+    ) -> Result<(), PartyError> {
+        world_state.parties_mut().set_loot_rules(player_id, rules)?;
+        if let Some(party_id) = world_state
+            .parties()
+            .party_of(player_id)
+            .map(|party| party.id)
+        {
+            Self::announce(world_state, party_id);
+        }
+        Ok(())
+    }
+
+    /// Tell a player why their party request was refused
+    pub fn refuse(world_state: &mut WorldState, player_id: EntityId, error: &PartyError) {
+        world_state.push_entity_effect(
+            player_id,
+            EntityEffect::PartyRefused {
+                reason: error.to_string(),
+            },
+        );
+    }
+
+    /// Tell every member of a party how it now stands
+    fn announce(world_state: &mut WorldState, party_id: PartyId) {
+        let members = world_state
+            .parties()
+            .get(party_id)
+            .map(|party| party.members.clone())
+            .unwrap_or_default();
+        for member in members {
+            Self::notify(world_state, member);
+        }
+    }
+
+    /// Tell a player how their party stands, or that they are in none
+    fn notify(world_state: &mut WorldState, player_id: EntityId) {
+        let effect = match world_state.parties().party_of(player_id) {
+            Some(party) => EntityEffect::PartyChanged {
+                party_id: party.id,
+                leader_id: party.leader,
+                members: party
+                    .members
+                    .iter()
+                    .map(|id| PartyMember {
+                        player_id: *id,
+                        name: world_state.get_player_name(*id).unwrap_or_default(),
+                    })
+                    .collect(),
+                method: party.loot_rules.method,
+                threshold: party.loot_rules.threshold,
+            },
+            None => {
+                let rules = LootRules::default();
+                EntityEffect::PartyChanged {
+                    party_id: 0,
+                    leader_id: 0,
+                    members: Vec::new(),
+                    method: rules.method,
+                    threshold: rules.threshold,
+                }
+            }
+        };
+        world_state.push_entity_effect(player_id, effect);
+    }
+}
@@ -0,0 +1,187 @@
+//! Experience and levelling
+//!
+//! When a mob dies, every player on its threat table shares the kill: each
+//! earns the experience the curve gives for the level gap between them and
+//! the mob, divided by the number of players who fought it. Crossing
+//! `experience_to_next` raises the player's level, re-derives their stats at
+//! once, teaches them the abilities their class learns at the new level and
+//! restores their health and mana or energy; rage is built in a fight, so it
+//! is left alone. The player is told what they earned, and a
+//! level-up is shown to everyone who can see them.
+
+use crate::classes::ClassRegistry;
+use crate::entities::{Entity, EntityId, ResourceType};
+use crate::network::messages::EntityEffect;
+use crate::progression::ExperienceTable;
+use crate::simulation::StatsSystem;
+use crate::world::WorldState;
+
+/// A mob that died this tick and the players who fought it
+#[derive(Debug, Clone)]
+pub struct Kill {
+    pub zone_id: u32,
+    pub victim_id: EntityId,
+    pub victim_level: u32,
+    /// First attacker to damage the victim, who its loot is rolled for
+    pub tagged_by: Option<EntityId>,
+    /// Attacker who landed the killing blow
+    pub killed_by: Option<EntityId>,
+    pub contributors: Vec<EntityId>,
+}
+
+impl Kill {
+    /// The kill `victim` leaves behind, read from its threat table before it is cleared
+    pub fn of(zone_id: u32, victim: &Entity) -> Option<Self> {
+        let threat = victim.threat.as_ref()?;
+        let mut contributors: Vec<EntityId> = threat.table.keys().copied().collect();
+        contributors.sort_unstable();
+        Some(Self {
+            zone_id,
+            victim_id: victim.id,
+            victim_level: victim.combat.as_ref().map_or(1, |combat| combat.level),
+            tagged_by: threat.tagged_by,
+            killed_by: threat.last_attacker,
+            contributors,
+        })
+    }
+}
+
+/// Progression system, fed by the death system
+pub struct ProgressionSystem;
+
+impl ProgressionSystem {
+    /// Share a kill's experience among the players who fought the victim
+    pub fn award_kill(world_state: &mut WorldState, kill: &Kill) {
+        let table = world_state.experience();
+        let Some(zone) = world_state.get_zone(kill.zone_id) else {
+            return;
+        };
+        let levels: Vec<(EntityId, u32)> = kill
+            .contributors
+            .iter()
+            .filter_map(|id| {
+                let progression = zone.entities.get_entity(*id)?.progression.as_ref()?;
+                Some((*id, progression.level))
+            })
+            .collect();
+        let share = levels.len().max(1) as u64;
+
+        for (player_id, level) in levels {
+            let amount = table.kill_experience(level, kill.victim_level) / share;
+            if amount == 0 {
+                continue;
+            }
+            for (entity_id, effect) in Self::grant(world_state, player_id, amount, kill.victim_id) {
+                world_state.push_entity_effect(entity_id, effect);
+            }
+        }
+    }
+
+    /// Give a player experience, levelling them up as many times as it pays
+    /// for, and return the events to raise
+    pub fn grant(
+        world_state: &mut WorldState,
+        player_id: EntityId,
+        amount: u64,
+        source_id: EntityId,
+    ) -> Vec<(EntityId, EntityEffect)> {
+        let table = world_state.experience();
+        let classes = world_state.classes();
+        let items = world_state.items();
+        let Some(player) = world_state
+            .get_entity_zone_id(player_id)
+            .and_then(|zone_id| world_state.get_zone_mut(zone_id))
+            .and_then(|zone| zone.entities.get_entity_mut(player_id))
+        else {
+            return Vec::new();
+        };
+
+        let levels = Self::add_experience(player, amount, &table);
+        let mut events = Vec::new();
+        if let Some(progression) = player.progression.as_ref() {
+            events.push((
+                player_id,
+                EntityEffect::Experience {
+                    amount,
+                    source_id,
+                    level: progression.level,
+                    experience: progression.experience,
+                    experience_to_next: progression.experience_to_next,
+                },
+            ));
+        }
+        if !levels.is_empty() {
+            StatsSystem::refresh(player, &classes, &items);
+            Self::learn_class_abilities(player, &classes);
+            restore(player);
+        }
+        events.extend(
+            levels
+                .into_iter()
+                .map(|level| (player_id, EntityEffect::LevelUp { level })),
+        );
+        events
+    }
+
+    /// Teach an entity every ability its class knows at its level. Cooldowns
+    /// already running are kept.
+    pub fn learn_class_abilities(entity: &mut Entity, classes: &ClassRegistry) {
+        let Some(class) = entity
+            .stats
+            .as_ref()
+            .and_then(|stats| stats.class.as_deref())
+            .and_then(|name| classes.get(name))
+        else {
+            return;
+        };
+        let level = entity
+            .progression
+            .as_ref()
+            .map_or(1, |progression| progression.level);
+        if let Some(abilities) = entity.abilities.as_mut() {
+            abilities.ability_ids = class.abilities_at(level);
+        }
+    }
+
+    /// Add experience and apply the level-ups it pays for, returning each new level
+    pub fn add_experience(entity: &mut Entity, amount: u64, table: &ExperienceTable) -> Vec<u32> {
+        let Some(progression) = entity.progression.as_mut() else {
+            return Vec::new();
+        };
+        let mut levels = Vec::new();
+        progression.experience_to_next = table.experience_to_next(progression.level);
+        if progression.experience_to_next == 0 {
+            // Experience stops counting at the level cap
+            return levels;
+        }
+
+        progression.experience += amount;
+        while progression.experience_to_next > 0
+            && progression.experience >= progression.experience_to_next
+        {
+            progression.experience -= progression.experience_to_next;
+            progression.level += 1;
+            progression.experience_to_next = table.experience_to_next(progression.level);
+            levels.push(progression.level);
+        }
+        if progression.experience_to_next == 0 {
+            progression.experience = 0;
+        }
+        levels
+    }
+}
+
+/// Refill a living entity's health and any resource that is full at rest
+fn restore(entity: &mut Entity) {
+    if !entity.is_alive() {
+        return;
+    }
+    if let Some(health) = entity.health.as_mut() {
+        health.current = health.maximum;
+    }
+    if let Some(resource) = entity.resource.as_mut() {
+        if resource.resource_type != ResourceType::Rage {
+            resource.current = resource.maximum as f32;
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Delta replication of entity state to clients
+//!
+//! Each session receives one full `WorldSnapshot` when its player enters the
+//! world or changes zone. After that each tick's changes go out as one
+//! `EntityUpdateBatch`, so bandwidth follows the amount of change rather than
+//! zone size.
+//!
+//! Clients acknowledge the snapshot IDs they hold. Every batch is a delta
+//! against the newest acknowledged snapshot (its `baseline_id`) rather than
+//! against the previous send, so a lost or reordered batch is simply
+//! superseded by the next one. A client that stops acknowledging is sent a
+//! fresh full snapshot.
+//!
+//! Only entities the interest system marked visible to the session's player are
+//! replicated, and each is refreshed no more often than the interval the
+//! interest system set for that player.
+//!
+//! Events raised during the tick (`EntityEffect`s such as status effect
+//! changes) ride along in that tick's batch as `EntityUpdate::effects` for the
+//! entities the client can see. Private events, such as a refused combat
+//! action, only go to the player controlling the entity. Events are sent once
+//! and never resent; a client that is resynchronised with a full snapshot
+//! misses them.
+
+use crate::entities::{Entity as GameEntity, EntityId, EntityType};
+use crate::network::messages::{
+    self, EntityEffect, EntityUpdate, EntityUpdateBatch, MovementState, Payload, Vector3,
+    WorldSnapshot,
+};
+use crate::network::Session;
+use crate::simulation::WorldClock;
+use crate::world::WorldState;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
+use uuid::Uuid;
+
+const POS_EPSILON: f32 = 0.05; // 5 cm
+const ROT_EPSILON: f32 = 0.01; // ~0.5 degrees
+
+/// Unacknowledged frames tolerated before falling back to a full snapshot
+const MAX_PENDING_FRAMES: usize = 64;
+
+/// Seconds before an unacknowledged state is sent again when nothing changed
+const RESEND_INTERVAL: f64 = 0.5;
+
+/// What one session's client has been told about the world
+struct SessionView {
+    /// Connection the view was built for; a resumed session starts over
+    connected_at: Instant,
+    player_id: EntityId,
+    zone_id: u32,
+    next_snapshot_id: u64,
+    /// Newest frame the client acknowledged, or the last full snapshot until it does
+    baseline: Frame,
+    /// Frames sent after the baseline, oldest first
+    pending: VecDeque<Frame>,
+}
+
+/// The entity set a client holds once it has applied one snapshot or batch
+#[derive(Clone)]
+struct Frame {
+    snapshot_id: u64,
+    sent_at: f64,
+    entities: HashMap<EntityId, KnownEntity>,
+}
+
+/// An entity's state as sent and when it was last refreshed from the world
+#[derive(Clone, PartialEq)]
+struct KnownEntity {
+    state: messages::Entity,
+    refreshed_at: f64,
+}
+
+impl SessionView {
+    fn take_snapshot_id(&mut self) -> u64 {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        id
+    }
+
+    /// What the client will hold once everything sent has arrived
+    fn latest(&self) -> &Frame {
+        self.pending.back().unwrap_or(&self.baseline)
+    }
+
+    /// Promote an acknowledged pending frame to the baseline
+    fn acknowledge(&mut self, snapshot_id: u64) {
+        let Some(index) = self
+            .pending
+            .iter()
+            .position(|frame| frame.snapshot_id == snapshot_id)
+        else {
+            return;
+        };
+        let mut acked = self.pending.drain(..=index);
+        if let Some(frame) = acked.next_back() {
+            self.baseline = frame;
+        }
+    }
+}
+
+/// Tracks per-session replication state and produces snapshots and deltas
+#[derive(Default)]
+pub struct ReplicationManager {
+    views: HashMap<Uuid, SessionView>,
+}
+
+impl ReplicationManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build this tick's replication payload for a session, if it needs one.
+    /// `now` is simulation time in seconds, used to pace distant entities, and
+    /// `effects` are the events raised this tick.
+    pub fn replicate(
+        &mut self,
+        world: &WorldState,
+        session: &Session,
+        now: f64,
+        effects: &[(EntityId, EntityEffect)],
+    ) -> Option<Payload> {
+        let Some(player_id) = session.player_id else {
+            self.views.remove(&session.id);
+            return None;
+        };
+        let zone_id = world.get_player_zone_id(player_id)?;
+        let zone = world.get_zone(zone_id)?;
+
+        let current: Vec<VisibleEntity> = zone
+            .entities
+            .get_all_entities()
+            .into_iter()
+            .filter(|entity| is_visible_to(entity, player_id))
+            .filter_map(|entity| {
+                Some(VisibleEntity {
+                    wire: entity_to_wire(entity)?,
+                    sync_interval: entity
+                        .network_sync
+                        .as_ref()
+                        .map(|sync| sync.interval_for(player_id))
+                        .unwrap_or(0.0),
+                })
+            })
+            .collect();
+
+        match self.views.get_mut(&session.id) {
+            Some(view)
+                if view.connected_at == session.connected_at
+                    && view.player_id == player_id
+                    && view.zone_id == zone_id
+                    && view.pending.len() < MAX_PENDING_FRAMES =>
+            {
+                if let Some(acked) = session.acked_snapshot_id {
+                    view.acknowledge(acked);
+                }
+                diff_view(view, current, world.clock(), now, effects, player_id)
+                    .map(Payload::EntityUpdateBatch)
+            }
+            previous => {
+                // Joining, resuming, changing zone or not acknowledging: start
+                // over from a full snapshot
+                let next_snapshot_id = previous.map(|view| view.next_snapshot_id).unwrap_or(1);
+                let entities = current
+                    .iter()
+                    .map(|entity| {
+                        let known = KnownEntity {
+                            state: entity.wire.clone(),
+                            refreshed_at: now,
+                        };
+                        (entity.wire.id, known)
+                    })
+                    .collect();
+                let mut view = SessionView {
+                    connected_at: session.connected_at,
+                    player_id,
+                    zone_id,
+                    next_snapshot_id,
+                    baseline: Frame {
+                        snapshot_id: 0,
+                        sent_at: now,
+                        entities,
+                    },
+                    pending: VecDeque::new(),
+                };
+                view.baseline.snapshot_id = view.take_snapshot_id();
+                let snapshot = WorldSnapshot {
+                    snapshot_id: view.baseline.snapshot_id,
+                    entities: current.into_iter().map(|entity| entity.wire).collect(),
+                    player_entity_id: player_id,
+                    zone_name: zone.name.clone(),
+                    server_tick: world.clock().tick(),
+                };
+                self.views.insert(session.id, view);
+                Some(Payload::WorldSnapshot(snapshot))
+            }
+        }
+    }
+
+    /// Drop state for sessions that are no longer connected
+    pub fn retain_sessions(&mut self, active: &HashSet<Uuid>) {
+        self.views
+            .retain(|session_id, _| active.contains(session_id));
+    }
+}
+
+/// An entity in a player's area of interest this tick
+struct VisibleEntity {
+    wire: messages::Entity,
+    sync_interval: f64,
+}
+
+/// Players always see themselves; everything else follows the interest system
+fn is_visible_to(entity: &GameEntity, player_id: EntityId) -> bool {
+    entity.id == player_id
+        || entity
+            .network_sync
+            .as_ref()
+            .is_some_and(|sync| sync.visible_to.contains(&player_id))
+}
+
+/// Work out this tick's frame and, if the client needs it, the delta that
+/// takes the acknowledged baseline to it
+fn diff_view(
+    view: &mut SessionView,
+    current: Vec<VisibleEntity>,
+    clock: WorldClock,
+    now: f64,
+    effects: &[(EntityId, EntityEffect)],
+    player_id: EntityId,
+) -> Option<EntityUpdateBatch> {
+    let latest = view.latest();
+    let mut entities = HashMap::with_capacity(current.len());
+
+    for entity in current {
+        let id = entity.wire.id;
+        let known = match latest.entities.get(&id) {
+            // Not due for a refresh, or not moved enough to matter
+            Some(previous)
+                if now - previous.refreshed_at < entity.sync_interval
+                    || !has_changed(&previous.state, &entity.wire) =>
+            {
+                previous.clone()
+            }
+            _ => KnownEntity {
+                state: entity.wire,
+                refreshed_at: now,
+            },
+        };
+        entities.insert(id, known);
+    }
+
+    let visible_effects: Vec<&(EntityId, EntityEffect)> = effects
+        .iter()
+        .filter(|(id, effect)| {
+            entities.contains_key(id) && (!effect.is_private() || *id == player_id)
+        })
+        .collect();
+
+    let changed = !same_states(&entities, &latest.entities);
+    let unacknowledged = !view.pending.is_empty() && now - latest.sent_at >= RESEND_INTERVAL;
+    if !changed && !unacknowledged && visible_effects.is_empty() {
+        return None;
+    }
+
+    let baseline = &view.baseline;
+    let mut spawned = Vec::new();
+    let mut updated = Vec::new();
+    for (id, known) in &entities {
+        match baseline.entities.get(id) {
+            Some(base) => updated.extend(diff_entity(&base.state, &known.state)),
+            None => spawned.push(known.state.clone()),
+        }
+    }
+    let despawned: Vec<EntityId> = baseline
+        .entities
+        .keys()
+        .filter(|id| !entities.contains_key(id))
+        .copied()
+        .collect();
+    let baseline_id = baseline.snapshot_id;
+
+    for (id, effect) in visible_effects {
+        match updated.iter_mut().find(|update| update.entity_id == *id) {
+            Some(update) => update.effects.push(effect.clone()),
+            None => updated.push(EntityUpdate {
+                entity_id: *id,
+                position: None,
+                rotation: None,
+                state: None,
+                effects: vec![effect.clone()],
+            }),
+        }
+    }
+
+    let snapshot_id = view.take_snapshot_id();
+    view.pending.push_back(Frame {
+        snapshot_id,
+        sent_at: now,
+        entities,
+    });
+
+    Some(EntityUpdateBatch {
+        snapshot_id,
+        baseline_id,
+        spawned,
+        updated,
+        despawned,
+        server_tick: clock.tick(),
+    })
+}
+
+fn same_states(a: &HashMap<EntityId, KnownEntity>, b: &HashMap<EntityId, KnownEntity>) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(id, known)| b.get(id).is_some_and(|other| other.state == known.state))
+}
+
+/// Whether the world state has drifted far enough from what was sent to resend it
+fn has_changed(sent: &messages::Entity, current: &messages::Entity) -> bool {
+    exceeds(&sent.position, &current.position, POS_EPSILON)
+        || exceeds(&sent.rotation, &current.rotation, ROT_EPSILON)
+        || sent.state != current.state
+}
+
+/// An update carrying only the fields that differ from the baseline
+fn diff_entity(base: &messages::Entity, current: &messages::Entity) -> Option<EntityUpdate> {
+    let update = EntityUpdate {
+        entity_id: current.id,
+        position: (base.position != current.position).then(|| current.position.clone()),
+        rotation: (base.rotation != current.rotation).then(|| current.rotation.clone()),
+        state: (base.state != current.state).then(|| current.state.clone()),
+        effects: Vec::new(),
+    };
+
+    if update.position.is_none() && update.rotation.is_none() && update.state.is_none() {
+        None
+    } else {
+        Some(update)
+    }
+}
+
+fn exceeds(a: &Vector3, b: &Vector3, epsilon: f32) -> bool {
+    (a.x - b.x).abs() > epsilon || (a.y - b.y).abs() > epsilon || (a.z - b.z).abs() > epsilon
+}
+
+pub(crate) fn entity_to_wire(entity: &GameEntity) -> Option<messages::Entity> {
+    let position = entity.position.as_ref()?;
+
+    let movement_state = determine_movement_state(entity);
+    let health_percent = entity
+        .health
+        .as_ref()
+        .map(|health| {
+            if health.maximum == 0 {
+                1.0
+            } else {
+                (health.current as f32 / health.maximum as f32).clamp(0.0, 1.0)
+            }
+        })
+        .unwrap_or(1.0);
+
+    Some(messages::Entity {
+        id: entity.id,
+        entity_type: entity_type_name(&entity.entity_type).to_string(),
+        position: Vector3 {
+            x: position.x,
+            y: position.y,
+            z: position.z,
+        },
+        rotation: Vector3 {
+            x: 0.0,
+            y: position.rotation,
+            z: 0.0,
+        },
+        state: messages::EntityState {
+            movement_state,
+            health_percent,
+            display_name: entity.name.clone(),
+            resource: entity
+                .resource
+                .as_ref()
+                .map(|resource| messages::ResourceState {
+                    resource_type: resource.resource_type.name().to_string(),
+                    current: resource.value(),
+                    maximum: resource.maximum,
+                }),
+        },
+    })
+}
+
+fn determine_movement_state(entity: &GameEntity) -> MovementState {
+    if entity
+        .health
+        .as_ref()
+        .map(|health| health.current == 0)
+        .unwrap_or(false)
+    {
+        MovementState::Dead
+    } else if entity
+        .movement
+        .as_ref()
+        .map(|movement| movement.is_moving)
+        .unwrap_or(false)
+    {
+        MovementState::Running
+    } else {
+        MovementState::Idle
+    }
+}
+
+fn entity_type_name(entity_type: &EntityType) -> &'static str {
+    match entity_type {
+        EntityType::Player => "player",
+        EntityType::Mob => "mob",
+        EntityType::Npc => "npc",
+        EntityType::WorldObject => "object",
+    }
+}
@@ -0,0 +1,93 @@
+//! Class resource regeneration and decay
+//!
+//! Each resource type follows its own rules:
+//! - Mana regenerates slowly, and only once the entity has been out of combat
+//!   for a few seconds.
+//! - Rage is generated by dealing and taking damage and decays out of combat.
+//! - Energy regenerates quickly at all times.
+
+use crate::entities::{Entity, ResourceType};
+use crate::world::WorldState;
+
+/// Seconds without dealing or taking damage before an entity leaves combat
+pub const COMBAT_TIMEOUT: f64 = 5.0;
+
+/// Fraction of maximum mana regenerated per second out of combat
+const MANA_REGEN_FRACTION: f32 = 0.02;
+/// Energy regenerated per second
+const ENERGY_REGEN_PER_SECOND: f32 = 10.0;
+/// Rage lost per second out of combat
+const RAGE_DECAY_PER_SECOND: f32 = 2.0;
+/// Rage generated per point of damage dealt
+const RAGE_PER_DAMAGE_DEALT: f32 = 1.0;
+/// Rage generated per point of damage taken
+const RAGE_PER_DAMAGE_TAKEN: f32 = 0.5;
+
+/// Resource system, ticked once per simulation tick
+pub struct ResourceSystem;
+
+impl ResourceSystem {
+    /// Regenerate or decay every living entity's resource
+    pub fn update(world_state: &mut WorldState, delta_time: f64) {
+        let now = world_state.clock().now();
+
+        for zone in world_state.zones_mut() {
+            for entity in zone.entities.entities_mut() {
+                Self::update_entity(entity, now, delta_time as f32);
+            }
+        }
+    }
+
+    fn update_entity(entity: &mut Entity, now: f64, delta_time: f32) {
+        let alive = entity.is_alive();
+        let Some(resource) = entity.resource.as_mut().filter(|_| alive) else {
+            return;
+        };
+        let in_combat = now - resource.last_combat_time < COMBAT_TIMEOUT;
+
+        match resource.resource_type {
+            ResourceType::Mana if !in_combat => {
+                resource.adjust(resource.maximum as f32 * MANA_REGEN_FRACTION * delta_time)
+            }
+            ResourceType::Rage if !in_combat => {
+                resource.adjust(-RAGE_DECAY_PER_SECOND * delta_time)
+            }
+            ResourceType::Energy => resource.adjust(ENERGY_REGEN_PER_SECOND * delta_time),
+            _ => {}
+        }
+    }
+
+    /// Put the attacker in combat and generate rage for the damage it dealt
+    pub fn on_damage_dealt(entity: &mut Entity, damage: u32, now: f64) {
+        Self::on_damage(entity, damage as f32 * RAGE_PER_DAMAGE_DEALT, now);
+    }
+
+    /// Put the target in combat and generate rage for the damage it took
+    pub fn on_damage_taken(entity: &mut Entity, damage: u32, now: f64) {
+        Self::on_damage(entity, damage as f32 * RAGE_PER_DAMAGE_TAKEN, now);
+    }
+
+    fn on_damage(entity: &mut Entity, rage: f32, now: f64) {
+        if let Some(resource) = entity.resource.as_mut() {
+            resource.last_combat_time = now;
+            if resource.resource_type == ResourceType::Rage {
+                resource.adjust(rage);
+            }
+        }
+    }
+
+    /// Whether the entity can pay `cost`; entities without a resource pay nothing
+    pub fn can_afford(entity: &Entity, cost: u32) -> bool {
+        entity
+            .resource
+            .as_ref()
+            .map_or(true, |resource| resource.value() >= cost)
+    }
+
+    /// Deduct `cost` from the entity's resource
+    pub fn spend(entity: &mut Entity, cost: u32) {
+        if let Some(resource) = entity.resource.as_mut() {
+            resource.adjust(-(cost as f32));
+        }
+    }
+}
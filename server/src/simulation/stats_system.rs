@@ -0,0 +1,130 @@
+//! Derived stat pipeline
+//!
+//! An entity's final attack power, defense, maximum health, critical strike
+//! chance, haste and movement speed are built in layers: base stats from its
+//! class at its current level (or its spawn stats when it has no class), then
+//! the `ItemStats`, weapon and armour of everything it has equipped, then the
+//! modifiers of its active status effects. The pipeline runs every tick and
+//! writes to `Health`, `Combat` and `Movement` only when the result changes,
+//! so new gear, a level-up or a buff takes hold within a tick.
+
+use crate::classes::ClassRegistry;
+use crate::entities::{Entity, StatBlock, StatModifiers};
+use crate::items::ItemRegistry;
+use crate::world::WorldState;
+
+/// Attack power per point of strength
+const ATTACK_POWER_PER_STRENGTH: f32 = 2.0;
+
+/// Stat system, ticked once per simulation tick
+pub struct StatsSystem;
+
+impl StatsSystem {
+    /// Recompute every entity's stats and apply the ones that changed
+    pub fn update(world_state: &mut WorldState) {
+        let classes = world_state.classes();
+        let items = world_state.items();
+        for zone in world_state.zones_mut() {
+            for entity in zone.entities.entities_mut() {
+                Self::refresh(entity, &classes, &items);
+            }
+        }
+    }
+
+    /// Recompute one entity's stats and apply them if they changed
+    pub fn refresh(entity: &mut Entity, classes: &ClassRegistry, items: &ItemRegistry) {
+        let Some(derived) = Self::derive(entity, classes, items) else {
+            return;
+        };
+        let level = level(entity);
+        let Some(stats) = entity.stats.as_mut() else {
+            return;
+        };
+        let base_max_speed = stats.base_max_speed;
+        let previous = stats.derived.replace(derived);
+        if previous == Some(derived) {
+            return;
+        }
+
+        if let Some(health) = entity.health.as_mut() {
+            // A higher maximum comes with the extra health; a lower one only caps it
+            if let Some(previous) = previous.filter(|_| health.current > 0) {
+                health.current += derived.max_health.saturating_sub(previous.max_health);
+            }
+            health.maximum = derived.max_health;
+            health.current = health.current.min(health.maximum);
+        }
+        if let Some(combat) = entity.combat.as_mut() {
+            combat.level = level;
+            combat.attack_power = derived.attack_power;
+            combat.defense = derived.defense;
+            combat.agility = derived.agility;
+            combat.critical_chance = derived.critical_chance;
+            combat.attack_speed = derived.attack_speed;
+        }
+        if let Some(movement) = entity.movement.as_mut() {
+            movement.max_speed = base_max_speed * derived.movement_speed;
+        }
+    }
+
+    /// Class and level, then gear, then status effects
+    pub fn derive(
+        entity: &Entity,
+        classes: &ClassRegistry,
+        items: &ItemRegistry,
+    ) -> Option<StatBlock> {
+        let stats = entity.stats.as_ref()?;
+        let mut block = match stats.class.as_deref().and_then(|name| classes.get(name)) {
+            Some(class) => class.stats_at(level(entity)),
+            None => stats.base,
+        };
+
+        if let Some(equipment) = &entity.equipment {
+            let gear = equipment.calculate_total_stats(items);
+            let strength_power = (gear.strength as f32 * ATTACK_POWER_PER_STRENGTH) as i32;
+            let armor = equipment.get_armor_value(items) as i32;
+            block.attack_power = add(block.attack_power, gear.attack_power + strength_power);
+            block.defense = add(block.defense, gear.defense + armor);
+            block.max_health = add(block.max_health, gear.health);
+            block.agility = add(block.agility, gear.agility);
+            block.critical_chance += gear.critical_chance;
+            block.haste += gear.haste;
+            block.movement_speed += gear.movement_speed;
+
+            // A weapon sets the swing speed and adds its damage per second
+            if let Some((damage, speed)) = equipment
+                .get_weapon_damage(items)
+                .filter(|(_, speed)| *speed > 0.0)
+            {
+                block.attack_power += (damage as f32 / speed).round() as u32;
+                block.attack_speed = 1.0 / speed;
+            }
+        }
+
+        let modifiers = entity.stat_modifiers();
+        let scale =
+            |value: u32, change: f32| (value as f32 * StatModifiers::multiplier(change)).round();
+        block.attack_power = scale(block.attack_power, modifiers.attack_power) as u32;
+        block.defense = scale(block.defense, modifiers.defense) as u32;
+        block.max_health = (scale(block.max_health, modifiers.max_health) as u32).max(1);
+        block.critical_chance = (block.critical_chance + modifiers.critical_chance).max(0.0);
+        block.haste = (block.haste + modifiers.haste).max(0.0);
+        block.movement_speed = (block.movement_speed + modifiers.movement_speed).max(0.0);
+        block.attack_speed *= 1.0 + block.haste;
+        Some(block)
+    }
+}
+
+/// Players level through `Progression`; everything else keeps its combat level
+fn level(entity: &Entity) -> u32 {
+    entity
+        .progression
+        .as_ref()
+        .map(|progression| progression.level)
+        .or_else(|| entity.combat.as_ref().map(|combat| combat.level))
+        .unwrap_or(1)
+}
+
+fn add(value: u32, bonus: i32) -> u32 {
+    (value as i64 + bonus as i64).max(0) as u32
+}
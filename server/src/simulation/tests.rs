@@ -0,0 +1,2621 @@
+use crate::entities::{AiState, Entity, EntityId, Resource, ResourceType};
+use crate::items::{EquipmentSlot, ItemInstance, ItemRarity};
+use crate::loot::{
+    LootCondition, LootEntry, LootMethod, LootRules, LootSystem, LootTable, RollChoice,
+};
+use crate::network::messages::{
+    CastPhase, CombatEventKind, EntityEffect, Payload, ResourceState, StatusEffectChange,
+};
+use crate::network::{Session, SessionStore};
+use crate::party::PartyError;
+use crate::simulation::attack_table::AttackTable;
+use crate::simulation::interest::{InterestConfig, InterestSystem};
+use crate::simulation::movement_system::{MovementIntent, MovementSystem};
+use crate::simulation::replication::ReplicationManager;
+use crate::simulation::resource_system::COMBAT_TIMEOUT;
+use crate::simulation::{
+    AiSystem, AttackTableConfig, CastSystem, CombatAction, CombatSystem, DeathConfig, DeathSystem,
+    EffectsSystem, LootingSystem, PartySystem, ProgressionSystem, ResourceSystem, StatsSystem,
+    ThreatSystem, WorldClock,
+};
+use crate::world::WorldState;
+use uuid::Uuid;
+
+async fn session_with_player(world: &mut WorldState) -> (SessionStore, Session, u64) {
+    // Every attack lands as an ordinary hit unless a test opts back into the attack table
+    world.set_attack_table(AttackTableConfig {
+        miss_chance: 0.0,
+        dodge_chance: 0.0,
+        parry_chance: 0.0,
+        critical_chance: 0.0,
+        level_step: 0.0,
+        agility_step: 0.0,
+        ..AttackTableConfig::default()
+    });
+    let store = SessionStore::new();
+    let session_id = store.create_session().await;
+    let player_id = world
+        .spawn_player_entity(
+            "Tester",
+            "1",
+            (0.0, 0.0, 0.0),
+            0.0,
+            (100, 100),
+            ("mana", 150, 150),
+        )
+        .unwrap();
+    // Classless, so it knows nothing yet; tests reach for any ability
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(player_id)
+        .unwrap()
+        .abilities
+        .as_mut()
+        .unwrap()
+        .ability_ids = (1..=10).collect();
+    store
+        .authenticate_session(&session_id, Uuid::new_v4(), player_id, None)
+        .await;
+    let session = store.get_session(&session_id).await.unwrap();
+    (store, session, player_id)
+}
+
+fn replicate(
+    replication: &mut ReplicationManager,
+    world: &mut WorldState,
+    session: &Session,
+    now: f64,
+) -> Option<Payload> {
+    InterestSystem::update(world, &InterestConfig::default());
+    let effects = world.drain_entity_effects();
+    replication.replicate(world, session, now, &effects)
+}
+
+/// Place a mob on the x axis so its distance from the player at the origin is exactly `x`
+fn set_mob_x(world: &mut WorldState, mob_id: u64, x: f32) {
+    let position = world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .position
+        .as_mut()
+        .unwrap();
+    position.x = x;
+    position.y = 0.0;
+    position.z = 0.0;
+}
+
+/// Ask the player to walk a short way along x and return how far one tick carried it
+fn walk_one_tick(world: &mut WorldState, player_id: EntityId) -> f32 {
+    let start = world.get_player_pose(player_id).unwrap().0;
+    MovementSystem::process_movement_intent(
+        world,
+        MovementIntent {
+            player_id,
+            target_x: start + 0.2,
+            target_y: 0.0,
+            target_z: 0.0,
+            speed_modifier: 1.0,
+            stop_movement: false,
+            rotation_y: 0.0,
+        },
+    )
+    .unwrap();
+    world.update(0.05);
+    world.get_player_pose(player_id).unwrap().0 - start
+}
+
+#[tokio::test]
+async fn test_replication_sends_snapshot_then_deltas() {
+    let mut world = WorldState::new();
+    let (_store, session, player_id) = session_with_player(&mut world).await;
+    let mut replication = ReplicationManager::new();
+    let mut clock = WorldClock::new();
+    clock.advance(0.05);
+    world.set_clock(clock);
+
+    let Some(Payload::WorldSnapshot(snapshot)) =
+        replicate(&mut replication, &mut world, &session, 0.0)
+    else {
+        panic!("expected a full snapshot on join");
+    };
+    assert_eq!(snapshot.player_entity_id, player_id);
+    assert_eq!(snapshot.server_tick, 1);
+    assert!(snapshot.entities.iter().any(|e| e.id == player_id));
+
+    // Nothing changed, so nothing is sent
+    assert!(replicate(&mut replication, &mut world, &session, 1.0).is_none());
+
+    let mob_id = {
+        let zone = world.get_zone_mut(1).unwrap();
+        let mob_id = zone.entities.get_mobs()[0].id;
+        zone.entities
+            .get_entity_mut(mob_id)
+            .unwrap()
+            .position
+            .as_mut()
+            .unwrap()
+            .x += 1.0;
+        mob_id
+    };
+    clock.advance(0.05);
+    world.set_clock(clock);
+    let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, 2.0)
+    else {
+        panic!("expected a delta batch");
+    };
+    assert!(batch.snapshot_id > snapshot.snapshot_id);
+    assert_eq!(batch.server_tick, 2);
+    assert_eq!(batch.updated.len(), 1);
+    assert_eq!(batch.updated[0].entity_id, mob_id);
+    assert!(batch.updated[0].position.is_some());
+    assert!(batch.updated[0].state.is_none());
+
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .remove_entity(mob_id);
+    let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, 3.0)
+    else {
+        panic!("expected a despawn batch");
+    };
+    assert_eq!(batch.despawned, vec![mob_id]);
+}
+
+#[tokio::test]
+async fn test_zone_change_resends_full_snapshot() {
+    let mut world = WorldState::new();
+    let (_store, session, player_id) = session_with_player(&mut world).await;
+    let mut replication = ReplicationManager::new();
+    replicate(&mut replication, &mut world, &session, 0.0);
+
+    world
+        .move_player_to_zone_with_position(player_id, 2, (-95.0, 0.0, 0.0))
+        .unwrap();
+
+    let Some(Payload::WorldSnapshot(snapshot)) =
+        replicate(&mut replication, &mut world, &session, 1.0)
+    else {
+        panic!("expected a full snapshot after the zone change");
+    };
+    assert_eq!(snapshot.zone_name, "Forest Zone");
+    let player = snapshot
+        .entities
+        .iter()
+        .find(|e| e.id == player_id)
+        .expect("player entity moves with the player");
+    assert_eq!(player.position.x, -95.0);
+}
+
+#[tokio::test]
+async fn test_interest_culls_distant_entities() {
+    let mut world = WorldState::new();
+    let (_store, mut session, player_id) = session_with_player(&mut world).await;
+    let mut replication = ReplicationManager::new();
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+
+    // Well outside the default 60 unit radius
+    set_mob_x(&mut world, mob_id, 90.0);
+    let Some(Payload::WorldSnapshot(snapshot)) =
+        replicate(&mut replication, &mut world, &session, 0.0)
+    else {
+        panic!("expected a full snapshot on join");
+    };
+    assert!(snapshot.entities.iter().any(|e| e.id == player_id));
+    assert!(!snapshot.entities.iter().any(|e| e.id == mob_id));
+
+    set_mob_x(&mut world, mob_id, 40.0);
+    let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, 1.0)
+    else {
+        panic!("expected a spawn batch");
+    };
+    assert!(batch.spawned.iter().any(|e| e.id == mob_id));
+    session.acked_snapshot_id = Some(batch.snapshot_id);
+    let sync = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(mob_id)
+        .unwrap()
+        .network_sync
+        .clone()
+        .unwrap();
+    assert_eq!(sync.visible_to, vec![player_id]);
+    assert_eq!(
+        sync.interval_for(player_id),
+        InterestConfig::default().far_sync_interval
+    );
+
+    // Inside the hysteresis band the mob stays visible
+    set_mob_x(&mut world, mob_id, 63.0);
+    if let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, 2.0)
+    {
+        assert!(batch.despawned.is_empty());
+    }
+
+    set_mob_x(&mut world, mob_id, 90.0);
+    let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, 3.0)
+    else {
+        panic!("expected a despawn batch");
+    };
+    assert_eq!(batch.despawned, vec![mob_id]);
+}
+
+#[tokio::test]
+async fn test_far_viewers_stay_throttled_when_someone_else_is_near() {
+    let mut world = WorldState::new();
+    let (_store, mut session, player_id) = session_with_player(&mut world).await;
+    let mut replication = ReplicationManager::new();
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 40.0);
+    let neighbour_id = world
+        .spawn_player_entity(
+            "Neighbour",
+            "1",
+            (41.0, 0.0, 0.0),
+            0.0,
+            (100, 100),
+            ("mana", 150, 150),
+        )
+        .unwrap();
+
+    let Some(Payload::WorldSnapshot(snapshot)) =
+        replicate(&mut replication, &mut world, &session, 0.0)
+    else {
+        panic!("expected a full snapshot on join");
+    };
+    session.acked_snapshot_id = Some(snapshot.snapshot_id);
+    let sync = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(mob_id)
+        .unwrap()
+        .network_sync
+        .clone()
+        .unwrap();
+    let far_sync_interval = InterestConfig::default().far_sync_interval;
+    assert_eq!(sync.interval_for(neighbour_id), 0.0);
+    assert_eq!(sync.interval_for(player_id), far_sync_interval);
+
+    // The mob moves within the far interval: the distant player is not sent it yet
+    set_mob_x(&mut world, mob_id, 42.0);
+    let now = far_sync_interval / 2.0;
+    if let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, now)
+    {
+        assert!(!batch
+            .updated
+            .iter()
+            .any(|update| update.entity_id == mob_id));
+    }
+    let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, far_sync_interval)
+    else {
+        panic!("expected the mob once the far interval has passed");
+    };
+    assert!(batch
+        .updated
+        .iter()
+        .any(|update| update.entity_id == mob_id));
+}
+
+#[test]
+fn test_interest_radii_fall_back_unless_positive() {
+    let config_with = |radius: &str, near_radius: &str| {
+        InterestConfig::from_vars(|key| match key {
+            "INTEREST_RADIUS" => Some(radius.to_string()),
+            "INTEREST_NEAR_RADIUS" => Some(near_radius.to_string()),
+            _ => None,
+        })
+    };
+    let defaults = InterestConfig::default();
+
+    let config = config_with("80", "30");
+    assert_eq!((config.radius, config.near_radius), (80.0, 30.0));
+    for bad in ["0", "-5", "NaN", "wide"] {
+        let config = config_with(bad, bad);
+        assert_eq!(
+            (config.radius, config.near_radius),
+            (defaults.radius, defaults.near_radius),
+            "{}",
+            bad
+        );
+    }
+    // The near radius never reaches past the interest radius
+    let config = config_with("10", "30");
+    assert_eq!((config.radius, config.near_radius), (10.0, 10.0));
+}
+
+#[tokio::test]
+async fn test_deltas_are_relative_to_acknowledged_baseline() {
+    let mut world = WorldState::new();
+    let (_store, mut session, _player_id) = session_with_player(&mut world).await;
+    let mut replication = ReplicationManager::new();
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 10.0);
+
+    let Some(Payload::WorldSnapshot(snapshot)) =
+        replicate(&mut replication, &mut world, &session, 0.0)
+    else {
+        panic!("expected a full snapshot on join");
+    };
+
+    // Two unacknowledged batches: the second still carries everything since
+    // the snapshot, so losing the first costs nothing
+    set_mob_x(&mut world, mob_id, 11.0);
+    let Some(Payload::EntityUpdateBatch(first)) =
+        replicate(&mut replication, &mut world, &session, 0.05)
+    else {
+        panic!("expected a delta batch");
+    };
+    assert_eq!(first.baseline_id, snapshot.snapshot_id);
+
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .position
+        .as_mut()
+        .unwrap()
+        .rotation = 1.0;
+    let Some(Payload::EntityUpdateBatch(second)) =
+        replicate(&mut replication, &mut world, &session, 0.1)
+    else {
+        panic!("expected a delta batch");
+    };
+    assert_eq!(second.baseline_id, snapshot.snapshot_id);
+    let update = second
+        .updated
+        .iter()
+        .find(|update| update.entity_id == mob_id)
+        .unwrap();
+    assert_eq!(update.position.as_ref().unwrap().x, 11.0);
+    assert!(update.rotation.is_some());
+
+    // Once acknowledged, the next delta only carries what changed since
+    session.acked_snapshot_id = Some(second.snapshot_id);
+    set_mob_x(&mut world, mob_id, 12.0);
+    let Some(Payload::EntityUpdateBatch(third)) =
+        replicate(&mut replication, &mut world, &session, 0.15)
+    else {
+        panic!("expected a delta batch");
+    };
+    assert_eq!(third.baseline_id, second.snapshot_id);
+    let update = third
+        .updated
+        .iter()
+        .find(|update| update.entity_id == mob_id)
+        .unwrap();
+    assert!(update.position.is_some());
+    assert!(update.rotation.is_none());
+
+    // Unacknowledged state is resent even when nothing changes
+    assert!(replicate(&mut replication, &mut world, &session, 0.2).is_none());
+    let Some(Payload::EntityUpdateBatch(resend)) =
+        replicate(&mut replication, &mut world, &session, 1.0)
+    else {
+        panic!("expected the unacknowledged state to be resent");
+    };
+    assert_eq!(resend.baseline_id, second.snapshot_id);
+
+    // A client that never acknowledges is eventually resynced in full
+    let mut now = 1.0;
+    let resynced = (0..100).any(|step| {
+        now += 0.05;
+        set_mob_x(&mut world, mob_id, 13.0 + step as f32 * 0.1);
+        matches!(
+            replicate(&mut replication, &mut world, &session, now),
+            Some(Payload::WorldSnapshot(_))
+        )
+    });
+    assert!(resynced);
+}
+
+#[tokio::test]
+async fn test_resumed_player_can_move_again() {
+    use crate::network::session_token::ResumeClaims;
+
+    let mut world = WorldState::new();
+    let (store, session, player_id) = session_with_player(&mut world).await;
+
+    // The connection drops mid-stride and the character is halted
+    assert!(walk_one_tick(&mut world, player_id) > 0.0);
+    store.mark_link_dead(&session.id).await;
+    world.halt_player(player_id);
+    let halted = world.get_player_pose(player_id).unwrap();
+    world.update(0.05);
+    assert_eq!(world.get_player_pose(player_id).unwrap(), halted);
+
+    let reconnect = store.create_session().await;
+    let claims = ResumeClaims {
+        session_id: session.id,
+        account_id: session.account_id.unwrap(),
+        expires_at: u64::MAX,
+    };
+    store.resume_session(&claims, &reconnect).await.unwrap();
+    assert!(walk_one_tick(&mut world, player_id) > 0.0);
+}
+
+#[tokio::test]
+async fn test_auto_attack_respects_attack_speed() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    let mob = world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap();
+    mob.health.as_mut().unwrap().current = 10_000;
+
+    // Players swing once per second
+    let mut clock = WorldClock::new();
+    let mut attack_at = |world: &mut WorldState, seconds: f64| {
+        while clock.now() < seconds {
+            clock.advance(0.05);
+        }
+        world.set_clock(clock);
+        CombatSystem::process_combat_action(
+            world,
+            player_id,
+            CombatAction::AutoAttack { target_id: mob_id },
+        )
+    };
+
+    assert!(attack_at(&mut world, 0.05).success);
+    let last_attack_time = world
+        .get_player_zone(player_id)
+        .unwrap()
+        .entities
+        .get_entity(player_id)
+        .unwrap()
+        .combat
+        .as_ref()
+        .unwrap()
+        .last_attack_time;
+    assert!(last_attack_time > 0.0);
+
+    let early = attack_at(&mut world, 0.5);
+    assert!(!early.success);
+    assert_eq!(
+        early.error_message.as_deref(),
+        Some("Attack is on cooldown")
+    );
+
+    assert!(attack_at(&mut world, last_attack_time + 1.0).success);
+    assert!(!attack_at(&mut world, last_attack_time + 1.5).success);
+}
+
+#[tokio::test]
+async fn test_abilities_resolve_from_definitions() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 10_000;
+    let use_ability = |world: &mut WorldState, ability_id: u32| {
+        CombatSystem::process_combat_action(
+            world,
+            player_id,
+            CombatAction::Ability {
+                ability_id,
+                target_id: mob_id,
+                target_position: None,
+            },
+        )
+    };
+
+    let unknown = use_ability(&mut world, 9999);
+    assert!(!unknown.success);
+    assert_eq!(
+        unknown.error_message.as_deref(),
+        Some("Unknown ability 9999")
+    );
+
+    // The mob's bite exists but players never learn it
+    let unlearned = use_ability(&mut world, 100);
+    assert!(!unlearned.success);
+    assert_eq!(
+        unlearned.error_message.as_deref(),
+        Some("Ability 100 not learned")
+    );
+
+    let strike = use_ability(&mut world, 1);
+    assert!(strike.success);
+    assert!(strike.damage_dealt > 0);
+    let cooling_down = use_ability(&mut world, 1);
+    assert_eq!(
+        cooling_down.error_message.as_deref(),
+        Some("Ability is on cooldown")
+    );
+
+    // Healing an enemy is refused; the caster can heal themself
+    let mend_enemy = use_ability(&mut world, 3);
+    assert_eq!(
+        mend_enemy.error_message.as_deref(),
+        Some("Target is not friendly")
+    );
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(player_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 90;
+    let mend_self = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::Ability {
+            ability_id: 3,
+            target_id: player_id,
+            target_position: None,
+        },
+    );
+    assert!(mend_self.success);
+    let healed = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(player_id)
+        .unwrap()
+        .health
+        .as_ref()
+        .unwrap()
+        .current;
+    assert_eq!(healed, 100);
+}
+
+#[tokio::test]
+async fn test_status_effects_stack_tick_and_expire() {
+    let mut world = WorldState::new();
+    let (_store, mut session, player_id) = session_with_player(&mut world).await;
+    let mut replication = ReplicationManager::new();
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 5.0);
+    let mob_health = |world: &WorldState| {
+        let mob = world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(mob_id)
+            .unwrap();
+        mob.health.as_ref().unwrap().current
+    };
+    let health = world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap();
+    health.current = 1_000;
+    health.maximum = 1_000;
+
+    let mut clock = WorldClock::new();
+    let mut advance_to = |world: &mut WorldState, seconds: f64| {
+        while clock.now() < seconds {
+            clock.advance(0.05);
+            world.set_clock(clock);
+            CastSystem::update(world);
+            EffectsSystem::update(world);
+        }
+    };
+    let firebolt = |world: &mut WorldState| {
+        CombatSystem::process_combat_action(
+            world,
+            player_id,
+            CombatAction::Ability {
+                ability_id: 2,
+                target_id: mob_id,
+                target_position: None,
+            },
+        )
+    };
+
+    let Some(Payload::WorldSnapshot(snapshot)) =
+        replicate(&mut replication, &mut world, &session, 0.0)
+    else {
+        panic!("expected a full snapshot on join");
+    };
+    session.acked_snapshot_id = Some(snapshot.snapshot_id);
+
+    // Firebolt sets the mob Burning once its cast completes; the event reaches
+    // the client with the batch
+    advance_to(&mut world, 0.05);
+    assert!(firebolt(&mut world).success);
+    advance_to(&mut world, 1.6);
+    let Some(Payload::EntityUpdateBatch(batch)) =
+        replicate(&mut replication, &mut world, &session, 1.6)
+    else {
+        panic!("expected the effect to be sent");
+    };
+    let update = batch
+        .updated
+        .iter()
+        .find(|update| update.entity_id == mob_id)
+        .unwrap();
+    assert!(matches!(
+        update.effects[..],
+        [
+            EntityEffect::DamageNumber {
+                ability_id: 2,
+                kind: CombatEventKind::Damage,
+                ..
+            },
+            EntityEffect::StatusEffect {
+                effect_id: 1,
+                stacks: 1,
+                change: StatusEffectChange::Applied,
+                ..
+            }
+        ]
+    ));
+
+    // One 3 damage tick after two seconds
+    let before = mob_health(&world);
+    advance_to(&mut world, 3.7);
+    assert_eq!(mob_health(&world), before - 3);
+
+    // Recasting adds a stack and restarts the duration
+    advance_to(&mut world, 4.1);
+    assert!(firebolt(&mut world).success);
+    advance_to(&mut world, 5.7);
+    let events = world.drain_entity_effects();
+    assert!(events.iter().any(|(id, effect)| *id == mob_id
+        && matches!(
+            effect,
+            EntityEffect::StatusEffect {
+                stacks: 2,
+                change: StatusEffectChange::Refreshed,
+                ..
+            }
+        )));
+
+    // Six seconds after the recast lands it wears off
+    advance_to(&mut world, 11.7);
+    let events = world.drain_entity_effects();
+    assert!(events.iter().any(|(id, effect)| *id == mob_id
+        && matches!(
+            effect,
+            EntityEffect::StatusEffect {
+                change: StatusEffectChange::Expired,
+                ..
+            }
+        )));
+    let mob = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(mob_id)
+        .unwrap();
+    assert!(mob.status_effects.as_ref().unwrap().active.is_empty());
+}
+
+#[tokio::test]
+async fn test_health_regenerates_a_fraction_of_a_point_per_tick() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    fn player(world: &mut WorldState, player_id: u64) -> &mut Entity {
+        world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(player_id)
+            .unwrap()
+    }
+    let health = |world: &mut WorldState| player(world, player_id).health.as_ref().unwrap().current;
+    // Four seconds of ticks, none of which is worth a whole point on its own
+    let regenerate = |world: &mut WorldState| {
+        let before = health(world);
+        for _ in 0..80 {
+            world.update(0.05);
+        }
+        health(world) - before
+    };
+    player(&mut world, player_id)
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 50;
+
+    let base = regenerate(&mut world);
+    assert!((3..=4).contains(&base), "1 HP per second, got {}", base);
+
+    // Renewal speeds regeneration up by half
+    let renewal = world.abilities().effect(3).unwrap().clone();
+    EffectsSystem::apply(player(&mut world, player_id), &renewal, player_id, 0.0).unwrap();
+    let buffed = regenerate(&mut world);
+    assert!(
+        (5..=6).contains(&buffed),
+        "1.5 HP per second, got {}",
+        buffed
+    );
+
+    // Nothing banks up while at full health
+    let full = player(&mut world, player_id).health.as_mut().unwrap();
+    full.current = full.maximum;
+    regenerate(&mut world);
+    let health = player(&mut world, player_id).health.as_ref().unwrap();
+    assert_eq!(health.regen_accumulator, 0.0);
+}
+
+#[tokio::test]
+async fn test_class_resources_gate_abilities_and_regenerate() {
+    let mut world = WorldState::new();
+    let (_store, session, player_id) = session_with_player(&mut world).await;
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 10_000;
+    let set_resource = |world: &mut WorldState, resource: Resource| {
+        world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(player_id)
+            .unwrap()
+            .resource = Some(resource);
+    };
+    let resource = |world: &WorldState| world.get_player_resource(player_id).unwrap();
+    let mut clock = WorldClock::new();
+    let mut tick = |world: &mut WorldState, seconds: f64| {
+        clock.advance(seconds);
+        world.set_clock(clock);
+        ResourceSystem::update(world, seconds);
+    };
+
+    // A warrior with no rage cannot Strike
+    set_resource(&mut world, Resource::new(ResourceType::Rage, 0, 100));
+    let strike = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::Ability {
+            ability_id: 1,
+            target_id: mob_id,
+            target_position: None,
+        },
+    );
+    assert_eq!(strike.error_message.as_deref(), Some("Not enough rage"));
+
+    // Dealing damage generates rage, which the next Strike spends
+    let swing = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::AutoAttack { target_id: mob_id },
+    );
+    assert!(swing.success);
+    assert_eq!(resource(&world), swing.damage_dealt);
+    set_resource(&mut world, Resource::new(ResourceType::Rage, 15, 100));
+    let strike = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::Ability {
+            ability_id: 1,
+            target_id: mob_id,
+            target_position: None,
+        },
+    );
+    assert!(strike.success);
+    assert_eq!(resource(&world), 15 - 10 + strike.damage_dealt);
+
+    // Rage holds while in combat and decays once out of it
+    let rage = resource(&world);
+    tick(&mut world, 1.0);
+    assert_eq!(resource(&world), rage);
+    tick(&mut world, COMBAT_TIMEOUT);
+    assert_eq!(resource(&world), rage - 10);
+
+    // Energy regenerates regardless of combat
+    set_resource(&mut world, Resource::new(ResourceType::Energy, 0, 120));
+    tick(&mut world, 1.0);
+    assert_eq!(resource(&world), 10);
+
+    // Mana waits for the player to leave combat
+    let mut mana = Resource::new(ResourceType::Mana, 0, 150);
+    mana.last_combat_time = world.clock().now();
+    set_resource(&mut world, mana);
+    tick(&mut world, 1.0);
+    assert_eq!(resource(&world), 0);
+    tick(&mut world, COMBAT_TIMEOUT);
+    assert_eq!(resource(&world), 15);
+
+    // The resource is replicated with the entity's state
+    let mut replication = ReplicationManager::new();
+    let Some(Payload::WorldSnapshot(snapshot)) =
+        replicate(&mut replication, &mut world, &session, 0.0)
+    else {
+        panic!("expected a full snapshot on join");
+    };
+    let player = snapshot
+        .entities
+        .iter()
+        .find(|entity| entity.id == player_id)
+        .unwrap();
+    assert_eq!(
+        player.state.resource,
+        Some(ResourceState {
+            resource_type: "mana".to_string(),
+            current: 15,
+            maximum: 150,
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_death_despawns_and_respawns_mobs_and_releases_players() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    world.set_death_config(DeathConfig {
+        corpse_duration: 5.0,
+        release_health_fraction: 0.5,
+    });
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    let (home, respawn_delay) = {
+        let respawn = world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(mob_id)
+            .unwrap()
+            .respawn
+            .clone()
+            .unwrap();
+        (respawn.home_position, respawn.respawn_delay)
+    };
+    set_mob_x(&mut world, mob_id, 1.0);
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 1;
+    let mut clock = WorldClock::new();
+    let mut advance = |world: &mut WorldState, seconds: f64| {
+        clock.advance(seconds);
+        world.set_clock(clock);
+        DeathSystem::update(world);
+        world.drain_entity_effects()
+    };
+
+    // The killing blow leaves a corpse and raises a death event
+    let kill = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::AutoAttack { target_id: mob_id },
+    );
+    assert!(kill.target_killed);
+    let events = advance(&mut world, 0.05);
+    assert!(events
+        .iter()
+        .any(|(id, effect)| *id == mob_id && matches!(effect, EntityEffect::Death)));
+    assert!(world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(mob_id)
+        .is_some());
+
+    // The corpse despawns once its timer runs out
+    advance(&mut world, 5.0);
+    assert!(world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(mob_id)
+        .is_none());
+
+    // After its respawn delay the mob is back home at full health
+    advance(&mut world, respawn_delay - 1.0);
+    assert!(world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(mob_id)
+        .is_none());
+    let events = advance(&mut world, 1.0);
+    assert!(events
+        .iter()
+        .any(|(id, effect)| *id == mob_id && matches!(effect, EntityEffect::Respawn)));
+    let mob = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(mob_id)
+        .unwrap();
+    let position = mob.position.as_ref().unwrap();
+    assert_eq!((position.x, position.y, position.z), home);
+    let health = mob.health.as_ref().unwrap();
+    assert_eq!(health.current, health.maximum);
+
+    // A living player cannot release
+    assert!(DeathSystem::release(&mut world, player_id).is_err());
+
+    // A dead player's corpse stays until they release to the graveyard
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(player_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 0;
+    let events = advance(&mut world, 0.05);
+    assert!(events
+        .iter()
+        .any(|(id, effect)| *id == player_id && matches!(effect, EntityEffect::Death)));
+    advance(&mut world, 10.0);
+    assert!(world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(player_id)
+        .is_some());
+
+    DeathSystem::release(&mut world, player_id).unwrap();
+    let events = world.drain_entity_effects();
+    assert!(events
+        .iter()
+        .any(|(id, effect)| *id == player_id && matches!(effect, EntityEffect::Respawn)));
+    let player = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(player_id)
+        .unwrap();
+    assert_eq!(player.health.as_ref().unwrap().current, 50);
+    let position = player.position.as_ref().unwrap();
+    assert_eq!(
+        (position.x, position.y, position.z),
+        world.get_zone(1).unwrap().graveyard
+    );
+
+    // Back on their feet, they can walk away from the graveyard
+    assert!(walk_one_tick(&mut world, player_id) > 0.0);
+}
+
+#[tokio::test]
+async fn test_combat_events_reach_observers_and_refusals_stay_private() {
+    let mut world = WorldState::new();
+    let (store, mut attacker_session, attacker_id) = session_with_player(&mut world).await;
+    let observer_id = world
+        .spawn_player_entity(
+            "Observer",
+            "1",
+            (2.0, 0.0, 0.0),
+            0.0,
+            (100, 100),
+            ("mana", 150, 150),
+        )
+        .unwrap();
+    let observer_session_id = store.create_session().await;
+    store
+        .authenticate_session(&observer_session_id, Uuid::new_v4(), observer_id, None)
+        .await;
+    let mut observer_session = store.get_session(&observer_session_id).await.unwrap();
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 10_000;
+
+    let mut attacker_replication = ReplicationManager::new();
+    let mut observer_replication = ReplicationManager::new();
+    for (replication, session) in [
+        (&mut attacker_replication, &mut attacker_session),
+        (&mut observer_replication, &mut observer_session),
+    ] {
+        let Some(Payload::WorldSnapshot(snapshot)) =
+            replication.replicate(&world, session, 0.0, &[])
+        else {
+            panic!("expected a full snapshot on join");
+        };
+        session.acked_snapshot_id = Some(snapshot.snapshot_id);
+    }
+
+    // A hit and a refused follow-up swing in the same tick
+    let hit = CombatSystem::process_combat_action(
+        &mut world,
+        attacker_id,
+        CombatAction::AutoAttack { target_id: mob_id },
+    );
+    assert!(hit.success);
+    let refused = CombatSystem::process_combat_action(
+        &mut world,
+        attacker_id,
+        CombatAction::AutoAttack { target_id: mob_id },
+    );
+    assert!(!refused.success);
+    InterestSystem::update(&mut world, &InterestConfig::default());
+    let effects = world.drain_entity_effects();
+
+    let events_for = |replication: &mut ReplicationManager, session: &Session| {
+        let Some(Payload::EntityUpdateBatch(batch)) =
+            replication.replicate(&world, session, 0.05, &effects)
+        else {
+            panic!("expected a batch carrying the combat events");
+        };
+        batch
+            .updated
+            .into_iter()
+            .flat_map(|update| {
+                let entity_id = update.entity_id;
+                update
+                    .effects
+                    .into_iter()
+                    .map(move |effect| (entity_id, effect))
+            })
+            .collect::<Vec<_>>()
+    };
+    let attacker_events = events_for(&mut attacker_replication, &attacker_session);
+    let observer_events = events_for(&mut observer_replication, &observer_session);
+
+    for events in [&attacker_events, &observer_events] {
+        assert!(events.iter().any(|(id, effect)| *id == mob_id
+            && matches!(
+                effect,
+                EntityEffect::DamageNumber {
+                    amount,
+                    source_id,
+                    kind: CombatEventKind::Damage,
+                    killing_blow: false,
+                    ..
+                } if *amount == hit.damage_dealt && *source_id == attacker_id
+            )));
+    }
+    assert!(attacker_events.iter().any(|(id, effect)| *id == attacker_id
+        && matches!(
+            effect,
+            EntityEffect::ActionRejected { target_id, reason, .. }
+                if *target_id == mob_id && reason == "Attack is on cooldown"
+        )));
+    assert!(!observer_events
+        .iter()
+        .any(|(_, effect)| matches!(effect, EntityEffect::ActionRejected { .. })));
+}
+
+#[tokio::test]
+async fn test_threat_tables_pick_targets_and_taunt_pulls_aggro() {
+    let mut world = WorldState::new();
+    let (_store, _session, tank_id) = session_with_player(&mut world).await;
+    let healer_id = world
+        .spawn_player_entity(
+            "Healer",
+            "1",
+            (1.0, 0.0, 1.0),
+            0.0,
+            (100, 100),
+            ("mana", 150, 150),
+        )
+        .unwrap();
+    world.set_player_class(healer_id, "mage", 1);
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    {
+        let mob = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(mob_id)
+            .unwrap();
+        mob.health.as_mut().unwrap().current = 10_000;
+        mob.ai.as_mut().unwrap().home_position = (1.0, 0.0, 0.0);
+    }
+    let act = |world: &mut WorldState, attacker_id: u64, action: CombatAction| {
+        let result = CombatSystem::process_combat_action(world, attacker_id, action);
+        assert!(result.success, "{:?}", result.error_message);
+        result
+    };
+    let threat_of = |world: &WorldState, source_id: u64| {
+        let mob = world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(mob_id)
+            .unwrap();
+        mob.threat.as_ref().unwrap().get(source_id)
+    };
+    let set_threat = |world: &mut WorldState, source_id: u64, amount: f32| {
+        let mob = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(mob_id)
+            .unwrap();
+        mob.threat.as_mut().unwrap().table.insert(source_id, amount);
+    };
+    let ai_state = |world: &WorldState| {
+        let mob = world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(mob_id)
+            .unwrap();
+        mob.ai.as_ref().unwrap().state.clone()
+    };
+    let set_health = |world: &mut WorldState, entity_id: u64, current: u32| {
+        world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(entity_id)
+            .unwrap()
+            .health
+            .as_mut()
+            .unwrap()
+            .current = current;
+    };
+
+    // Damage builds threat and the mob goes after the attacker
+    let hit = act(
+        &mut world,
+        tank_id,
+        CombatAction::AutoAttack { target_id: mob_id },
+    );
+    assert_eq!(threat_of(&world, tank_id), hit.damage_dealt as f32);
+    ThreatSystem::update(&mut world, 0.0);
+    assert!(matches!(ai_state(&world), AiState::Chasing { target_id } if target_id == tank_id));
+
+    // Healing the tank puts the healer on the table
+    set_health(&mut world, tank_id, 50);
+    let mend = act(
+        &mut world,
+        healer_id,
+        CombatAction::Ability {
+            ability_id: 3,
+            target_id: tank_id,
+            target_position: None,
+        },
+    );
+    assert!(mend.success);
+    let healed = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_entity(tank_id)
+        .unwrap()
+        .health
+        .as_ref()
+        .unwrap()
+        .current
+        - 50;
+    assert!(healed > 0);
+    assert_eq!(threat_of(&world, healer_id), healed as f32 * 0.5);
+
+    // Edging past the target is not enough to pull; clearly overtaking it is
+    let tank_threat = threat_of(&world, tank_id);
+    set_threat(&mut world, healer_id, tank_threat * 1.05);
+    ThreatSystem::update(&mut world, 0.0);
+    assert!(matches!(ai_state(&world), AiState::Chasing { target_id } if target_id == tank_id));
+    set_threat(&mut world, healer_id, tank_threat * 1.5);
+    ThreatSystem::update(&mut world, 0.0);
+    assert!(matches!(ai_state(&world), AiState::Chasing { target_id } if target_id == healer_id));
+
+    // Taunt pulls the mob straight back and matches the top threat
+    act(
+        &mut world,
+        tank_id,
+        CombatAction::Ability {
+            ability_id: 4,
+            target_id: mob_id,
+            target_position: None,
+        },
+    );
+    assert_eq!(threat_of(&world, tank_id), threat_of(&world, healer_id));
+    ThreatSystem::update(&mut world, 0.0);
+    assert!(matches!(ai_state(&world), AiState::Chasing { target_id } if target_id == tank_id));
+
+    // Threat decays over time
+    let before = threat_of(&world, tank_id);
+    ThreatSystem::update(&mut world, 1.0);
+    assert!(threat_of(&world, tank_id) < before);
+
+    // A dead attacker drops off the table; with nobody left the mob evades home
+    set_health(&mut world, tank_id, 0);
+    ThreatSystem::update(&mut world, 0.0);
+    assert_eq!(threat_of(&world, tank_id), 0.0);
+    assert!(matches!(ai_state(&world), AiState::Chasing { target_id } if target_id == healer_id));
+    set_health(&mut world, healer_id, 0);
+    ThreatSystem::update(&mut world, 0.0);
+    assert!(matches!(ai_state(&world), AiState::Returning { .. }));
+    AiSystem::update(&mut world);
+    assert!(matches!(ai_state(&world), AiState::Idle));
+
+    // A mob pulled past its leash range drops its table and evades
+    set_health(&mut world, healer_id, 100);
+    set_mob_x(&mut world, mob_id, 90.0);
+    set_threat(&mut world, healer_id, 10.0);
+    ThreatSystem::update(&mut world, 0.0);
+    assert!(matches!(ai_state(&world), AiState::Returning { .. }));
+    assert_eq!(threat_of(&world, healer_id), 0.0);
+}
+
+#[tokio::test]
+async fn test_mob_ai_aggroes_chases_attacks_flees_and_leashes() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let mob_named = |world: &WorldState, name: &str| {
+        world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_mobs()
+            .into_iter()
+            .find(|mob| mob.name == name)
+            .unwrap()
+            .id
+    };
+    let (orc_id, goblin_id, wolf_id) = (
+        mob_named(&world, "Orc"),
+        mob_named(&world, "Goblin"),
+        mob_named(&world, "Wolf"),
+    );
+    {
+        let zone = world.get_zone_mut(1).unwrap();
+        // The wolf's beat passes close to the goblin's; keep it out of the way
+        zone.entities.remove_entity(wolf_id);
+        let health = zone
+            .entities
+            .get_entity_mut(player_id)
+            .unwrap()
+            .health
+            .as_mut()
+            .unwrap();
+        health.current = 10_000;
+        health.maximum = 10_000;
+    }
+    let mut clock = WorldClock::new();
+    let mut tick = |world: &mut WorldState| {
+        clock.advance(0.05);
+        world.set_clock(clock);
+        world.update(0.05);
+        EffectsSystem::update(world);
+        DeathSystem::update(world);
+        ThreatSystem::update(world, 0.05);
+        AiSystem::update(world);
+        world.drain_entity_effects();
+    };
+    let entity = |world: &WorldState, entity_id: u64| {
+        world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(entity_id)
+            .unwrap()
+            .clone()
+    };
+    let place_player = |world: &mut WorldState, x: f32, z: f32| {
+        let position = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(player_id)
+            .unwrap()
+            .position
+            .as_mut()
+            .unwrap();
+        position.x = x;
+        position.y = 0.0;
+        position.z = z;
+    };
+    let player_health = |world: &WorldState| entity(world, player_id).health.unwrap().current;
+
+    // A player walking into the orc's aggro range is noticed, chased and bitten
+    place_player(&mut world, -15.0, 22.0);
+    tick(&mut world);
+    assert!(matches!(
+        entity(&world, orc_id).ai.unwrap().state,
+        AiState::Chasing { target_id } if target_id == player_id
+    ));
+    let mut attacked = false;
+    for _ in 0..100 {
+        tick(&mut world);
+        if player_health(&world) < 10_000 {
+            attacked = true;
+            break;
+        }
+    }
+    assert!(attacked, "the orc never landed a hit");
+    assert!(matches!(
+        entity(&world, orc_id).ai.unwrap().state,
+        AiState::Attacking { .. }
+    ));
+
+    // Kiting it past its leash range sends it home, where it heals up
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(orc_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 20;
+    place_player(&mut world, -15.0, 80.0);
+    let returning = (0..300).any(|_| {
+        tick(&mut world);
+        matches!(
+            entity(&world, orc_id).ai.unwrap().state,
+            AiState::Returning { .. }
+        )
+    });
+    assert!(returning);
+    assert!(entity(&world, orc_id).threat.unwrap().table.is_empty());
+    let home = (0..300).any(|_| {
+        tick(&mut world);
+        matches!(entity(&world, orc_id).ai.unwrap().state, AiState::Idle)
+    });
+    assert!(home);
+    let orc_health = entity(&world, orc_id).health.unwrap();
+    assert_eq!(orc_health.current, orc_health.maximum);
+
+    // A badly hurt skirmisher runs from its attacker once, then turns to fight
+    {
+        let goblin = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(goblin_id)
+            .unwrap();
+        let health = goblin.health.as_mut().unwrap();
+        health.current = health.maximum / 10;
+    }
+    let goblin_position = entity(&world, goblin_id).position.unwrap();
+    place_player(&mut world, goblin_position.x, goblin_position.z + 1.0);
+    let fled = (0..5).any(|_| {
+        tick(&mut world);
+        matches!(
+            entity(&world, goblin_id).ai.unwrap().state,
+            AiState::Fleeing { .. }
+        )
+    });
+    assert!(fled);
+    let before = entity(&world, goblin_id).distance_to(&entity(&world, player_id));
+    for _ in 0..10 {
+        tick(&mut world);
+    }
+    assert!(entity(&world, goblin_id).distance_to(&entity(&world, player_id)) > before);
+    for _ in 0..80 {
+        tick(&mut world);
+    }
+    let goblin = entity(&world, goblin_id).ai.unwrap();
+    assert!(goblin.has_fled);
+    assert!(matches!(
+        goblin.state,
+        AiState::Chasing { .. } | AiState::Attacking { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_casts_channels_and_interrupts() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    {
+        let mob = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(mob_id)
+            .unwrap();
+        mob.health.as_mut().unwrap().current = 10_000;
+        mob.abilities.as_mut().unwrap().ability_ids.push(2);
+    }
+    let mut clock = WorldClock::new();
+    let mut advance_to = |world: &mut WorldState, seconds: f64| {
+        let mut events = Vec::new();
+        while clock.now() < seconds {
+            clock.advance(0.05);
+            world.set_clock(clock);
+            CastSystem::update(world);
+            events.extend(world.drain_entity_effects());
+        }
+        events
+    };
+    let use_ability = |world: &mut WorldState, caster_id: u64, ability_id: u32, target_id: u64| {
+        CombatSystem::process_combat_action(
+            world,
+            caster_id,
+            CombatAction::Ability {
+                ability_id,
+                target_id,
+                target_position: None,
+            },
+        )
+    };
+    let entity = |world: &WorldState, entity_id: u64| {
+        world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(entity_id)
+            .unwrap()
+            .clone()
+    };
+    let mob_health = |world: &WorldState| entity(world, mob_id).health.unwrap().current;
+    let mana = |world: &WorldState| entity(world, player_id).resource.unwrap().value();
+    let cast_phases = |events: &[(u64, EntityEffect)], caster_id: u64| {
+        events
+            .iter()
+            .filter(|(id, _)| *id == caster_id)
+            .filter_map(|(_, effect)| match effect {
+                EntityEffect::Cast { phase, reason, .. } => Some((*phase, reason.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Starting a cast does no damage yet and blocks other actions
+    advance_to(&mut world, 0.05);
+    let started = use_ability(&mut world, player_id, 2, mob_id);
+    assert!(started.success);
+    assert_eq!(started.damage_dealt, 0);
+    let busy = use_ability(&mut world, player_id, 1, mob_id);
+    assert_eq!(busy.error_message.as_deref(), Some("Already casting"));
+    let events = advance_to(&mut world, 0.6);
+    assert_eq!(
+        cast_phases(&events, player_id),
+        vec![
+            (CastPhase::Started, String::new()),
+            (CastPhase::Progress, String::new())
+        ]
+    );
+
+    // Walking away breaks the cast before anything is paid or dealt
+    let (health_before, mana_before) = (mob_health(&world), mana(&world));
+    MovementSystem::process_movement_intent(
+        &mut world,
+        MovementIntent {
+            player_id,
+            target_x: 0.0,
+            target_y: 0.0,
+            target_z: 0.2,
+            speed_modifier: 1.0,
+            stop_movement: false,
+            rotation_y: 0.0,
+        },
+    )
+    .unwrap();
+    MovementSystem::stop_movement(&mut world, player_id).unwrap();
+    let events = advance_to(&mut world, 2.5);
+    assert_eq!(
+        cast_phases(&events, player_id),
+        vec![(CastPhase::Interrupted, "Moved".to_string())]
+    );
+    assert!(entity(&world, player_id).casting.is_none());
+    assert_eq!(
+        (mob_health(&world), mana(&world)),
+        (health_before, mana_before)
+    );
+
+    // Standing still, the cast lands once its time is up
+    assert!(use_ability(&mut world, player_id, 2, mob_id).success);
+    let events = advance_to(&mut world, 4.1);
+    assert_eq!(
+        cast_phases(&events, player_id).last(),
+        Some(&(CastPhase::Finished, String::new()))
+    );
+    assert!(mob_health(&world) < health_before);
+    assert_eq!(mana(&world), mana_before - 20);
+
+    // A channel pays up front, then hits once per tick until it ends
+    let (health_before, mana_before) = (mob_health(&world), mana(&world));
+    assert!(use_ability(&mut world, player_id, 6, mob_id).success);
+    assert_eq!(mana(&world), mana_before - 30);
+    let events = advance_to(&mut world, 7.2);
+    let hits = events
+        .iter()
+        .filter(|(id, effect)| {
+            *id == mob_id && matches!(effect, EntityEffect::DamageNumber { ability_id: 6, .. })
+        })
+        .count();
+    assert_eq!(hits, 3);
+    assert!(mob_health(&world) < health_before);
+    assert_eq!(
+        cast_phases(&events, player_id).last(),
+        Some(&(CastPhase::Finished, String::new()))
+    );
+
+    // An interrupting ability cancels the target's cast
+    assert!(use_ability(&mut world, mob_id, 2, player_id).success);
+    let player_health = entity(&world, player_id).health.unwrap().current;
+    assert!(use_ability(&mut world, player_id, 5, mob_id).success);
+    let events = advance_to(&mut world, 9.0);
+    assert_eq!(
+        cast_phases(&events, mob_id).last(),
+        Some(&(CastPhase::Interrupted, "Interrupted".to_string()))
+    );
+    assert_eq!(
+        entity(&world, player_id).health.unwrap().current,
+        player_health
+    );
+
+    // So does a stun, which also stops the mob acting until it wears off
+    assert!(use_ability(&mut world, mob_id, 2, player_id).success);
+    assert!(use_ability(&mut world, player_id, 7, mob_id).success);
+    let events = advance_to(&mut world, 9.2);
+    assert_eq!(
+        cast_phases(&events, mob_id).last(),
+        Some(&(CastPhase::Interrupted, "Stunned".to_string()))
+    );
+    let stunned = use_ability(&mut world, mob_id, 2, player_id);
+    assert_eq!(stunned.error_message.as_deref(), Some("Stunned"));
+}
+
+#[tokio::test]
+async fn test_area_abilities_hit_hostiles_in_circles_and_cones() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let bystander_id = world
+        .spawn_player_entity(
+            "Bystander",
+            "1",
+            (0.0, 0.0, -1.0),
+            0.0,
+            (100, 100),
+            ("mana", 100, 100),
+        )
+        .unwrap();
+    let mob_ids: Vec<u64> = (0..7)
+        .map(|n| {
+            world.get_zone_mut(1).unwrap().entities.create_test_mob(
+                format!("Target {}", n),
+                500.0,
+                500.0,
+                1,
+                "brute",
+                None,
+            )
+        })
+        .collect();
+    let place = |world: &mut WorldState, entity_id: u64, x: f32, z: f32| {
+        let entity = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(entity_id)
+            .unwrap();
+        entity.health.as_mut().unwrap().current = 10_000;
+        entity.health.as_mut().unwrap().maximum = 10_000;
+        let position = entity.position.as_mut().unwrap();
+        (position.x, position.y, position.z) = (x, 0.0, z);
+    };
+    for mob_id in &mob_ids {
+        place(&mut world, *mob_id, 500.0, 500.0);
+    }
+    let health = |world: &WorldState, entity_id: u64| {
+        let zone = world.get_zone(1).unwrap();
+        zone.entities
+            .get_entity(entity_id)
+            .unwrap()
+            .health
+            .as_ref()
+            .unwrap()
+            .current
+    };
+    let use_ability =
+        |world: &mut WorldState, ability_id: u32, target_position: Option<(f32, f32, f32)>| {
+            CombatSystem::process_combat_action(
+                world,
+                player_id,
+                CombatAction::Ability {
+                    ability_id,
+                    target_id: 0,
+                    target_position,
+                },
+            )
+        };
+
+    // Cleave reaches 45 degrees either side of the caster's facing (-Z) and spares friends
+    let (front, flank, behind, wide) = (mob_ids[0], mob_ids[1], mob_ids[2], mob_ids[3]);
+    place(&mut world, front, 0.0, -2.0);
+    place(&mut world, flank, 1.5, -2.0);
+    place(&mut world, behind, 0.0, 2.0);
+    place(&mut world, wide, 3.0, -1.0);
+    let cleave = use_ability(&mut world, 9, None);
+    assert!(cleave.success, "{:?}", cleave.error_message);
+    assert!(health(&world, front) < 10_000);
+    assert!(health(&world, flank) < 10_000);
+    assert_eq!(health(&world, behind), 10_000);
+    assert_eq!(health(&world, wide), 10_000);
+    assert_eq!(health(&world, bystander_id), 100);
+    assert_eq!(
+        cleave.damage_dealt,
+        2 * 10_000 - health(&world, front) - health(&world, flank)
+    );
+    let hit_events = world
+        .drain_entity_effects()
+        .into_iter()
+        .filter(|(_, effect)| {
+            matches!(
+                effect,
+                EntityEffect::DamageNumber {
+                    kind: CombatEventKind::Damage,
+                    ability_id: 9,
+                    ..
+                }
+            )
+        })
+        .count();
+    assert_eq!(hit_events, 2);
+
+    // A ground circle must be aimed within range of the caster
+    let too_far = use_ability(&mut world, 8, Some((40.0, 0.0, 0.0)));
+    assert!(!too_far.success);
+    assert!(too_far
+        .error_message
+        .unwrap()
+        .starts_with("Target position is out of range"));
+
+    // Rain of Fire hits the five hostiles nearest the aimed point
+    for (offset, mob_id) in mob_ids.iter().take(6).enumerate() {
+        place(&mut world, *mob_id, 20.0 + offset as f32 * 0.9, 0.0);
+    }
+    place(&mut world, mob_ids[6], 26.0, 0.0);
+    let rain = use_ability(&mut world, 8, Some((20.0, 0.0, 0.0)));
+    assert!(rain.success, "{:?}", rain.error_message);
+    for mob_id in &mob_ids[..5] {
+        assert!(health(&world, *mob_id) < 10_000);
+    }
+    assert_eq!(health(&world, mob_ids[5]), 10_000, "target cap exceeded");
+    assert_eq!(health(&world, mob_ids[6]), 10_000, "outside the circle");
+}
+
+#[tokio::test]
+async fn test_attack_table_rolls_are_seeded_and_flag_crits() {
+    let fight =
+        |seed: u64| async move {
+            let mut world = WorldState::new();
+            let (_store, _session, player_id) = session_with_player(&mut world).await;
+            world.set_attack_table(AttackTableConfig::default());
+            world.seed_combat_rng(seed);
+            let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+            set_mob_x(&mut world, mob_id, 1.0);
+            world
+                .get_zone_mut(1)
+                .unwrap()
+                .entities
+                .get_entity_mut(mob_id)
+                .unwrap()
+                .health
+                .as_mut()
+                .unwrap()
+                .current = 100_000;
+
+            let mut clock = WorldClock::new();
+            let mut outcomes = Vec::new();
+            for _ in 0..300 {
+                clock.advance(1.0);
+                world.set_clock(clock);
+                let swing = CombatSystem::process_combat_action(
+                    &mut world,
+                    player_id,
+                    CombatAction::AutoAttack { target_id: mob_id },
+                );
+                assert!(swing.success);
+                outcomes.extend(world.drain_entity_effects().into_iter().filter_map(
+                    |(_, effect)| match effect {
+                        EntityEffect::DamageNumber {
+                            amount,
+                            is_critical,
+                            kind,
+                            ..
+                        } => Some((kind, amount, is_critical)),
+                        _ => None,
+                    },
+                ));
+            }
+            outcomes
+        };
+
+    let outcomes = fight(7).await;
+    assert_eq!(outcomes.len(), 300, "one event per swing");
+    assert_eq!(outcomes, fight(7).await, "same seed, same fight");
+    assert_ne!(outcomes, fight(8).await);
+
+    for kind in [
+        CombatEventKind::Miss,
+        CombatEventKind::Dodge,
+        CombatEventKind::Parry,
+    ] {
+        assert!(outcomes
+            .iter()
+            .any(|(k, amount, _)| *k == kind && *amount == 0));
+    }
+    let damage = |critical: bool| {
+        outcomes
+            .iter()
+            .find(|(kind, _, is_critical)| {
+                *kind == CombatEventKind::Damage && *is_critical == critical
+            })
+            .map(|(_, amount, _)| *amount)
+            .unwrap()
+    };
+    assert!(damage(true) > damage(false), "crits hit harder");
+}
+
+#[tokio::test]
+async fn test_stats_derive_from_class_level_gear_and_buffs() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let classes = world.classes();
+    let warrior = classes.get("warrior").unwrap();
+    let player = |world: &WorldState| {
+        world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(player_id)
+            .unwrap()
+            .clone()
+    };
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    let mob_attack_power = |world: &WorldState| {
+        let mob = world.get_zone(1).unwrap().entities.get_entity(mob_id);
+        mob.unwrap().combat.as_ref().unwrap().attack_power
+    };
+    let spawned_mob_attack_power = mob_attack_power(&world);
+
+    let mut clock = WorldClock::new();
+    let mut advance_to = |world: &mut WorldState, seconds: f64| {
+        while clock.now() < seconds {
+            clock.advance(0.05);
+            world.set_clock(clock);
+            CastSystem::update(world);
+            EffectsSystem::update(world);
+            StatsSystem::update(world);
+        }
+    };
+
+    // A level 1 warrior takes the class's base stats; spawn health is kept
+    world.set_player_class(player_id, "warrior", 1);
+    advance_to(&mut world, 0.05);
+    let novice = player(&world);
+    let base = warrior.stats_at(1);
+    assert_eq!(novice.health.as_ref().unwrap().maximum, base.max_health);
+    assert_eq!(novice.health.as_ref().unwrap().current, 100);
+    let combat = novice.combat.as_ref().unwrap();
+    assert_eq!(combat.attack_power, base.attack_power);
+    assert_eq!(combat.defense, base.defense);
+    assert_eq!(combat.attack_speed, base.attack_speed);
+    assert_eq!(mob_attack_power(&world), spawned_mob_attack_power);
+
+    // Levelling up raises stats and grants the extra health
+    world.set_player_class(player_id, "warrior", 2);
+    advance_to(&mut world, 0.1);
+    let veteran = player(&world);
+    let grown = warrior.stats_at(2);
+    let health = veteran.health.as_ref().unwrap();
+    assert_eq!(health.maximum, grown.max_health);
+    assert_eq!(health.current, 100 + grown.max_health - base.max_health);
+    assert_eq!(veteran.combat.as_ref().unwrap().level, 2);
+    assert_eq!(
+        veteran.combat.as_ref().unwrap().attack_power,
+        grown.attack_power
+    );
+
+    // The Rusty Sword adds its attack power and damage per second and sets the swing speed
+    world
+        .equip_item(player_id, ItemInstance::new(1, 1), EquipmentSlot::MainHand)
+        .unwrap();
+    advance_to(&mut world, 0.15);
+    let armed = player(&world).combat.unwrap();
+    assert_eq!(armed.attack_power, grown.attack_power + 5 + 8);
+    assert_eq!(armed.attack_speed, 0.5);
+
+    // The Iron Axe needs level 5
+    assert!(world
+        .equip_item(player_id, ItemInstance::new(2, 1), EquipmentSlot::MainHand)
+        .is_err());
+
+    // Battle Cry raises attack power and haste until it wears off
+    let battle_cry = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::Ability {
+            ability_id: 10,
+            target_id: 0,
+            target_position: None,
+        },
+    );
+    assert!(battle_cry.success, "{:?}", battle_cry.error_message);
+    advance_to(&mut world, 0.2);
+    let rallied = player(&world).combat.unwrap();
+    assert_eq!(
+        rallied.attack_power,
+        (armed.attack_power as f32 * 1.1).round() as u32
+    );
+    assert!(rallied.attack_speed > armed.attack_speed);
+
+    advance_to(&mut world, 21.0);
+    let faded = player(&world).combat.unwrap();
+    assert_eq!(faded.attack_power, armed.attack_power);
+    assert_eq!(faded.attack_speed, armed.attack_speed);
+
+    // Taking the sword off goes back to the class's unarmed stats
+    assert!(world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(player_id)
+        .unwrap()
+        .equipment
+        .as_mut()
+        .unwrap()
+        .unequip_item(EquipmentSlot::MainHand)
+        .is_some());
+    advance_to(&mut world, 21.05);
+    let unarmed = player(&world).combat.unwrap();
+    assert_eq!(unarmed.attack_power, grown.attack_power);
+    assert_eq!(unarmed.attack_speed, grown.attack_speed);
+}
+
+#[tokio::test]
+async fn test_kills_award_experience_and_level_players_up() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    world.set_player_class(player_id, "warrior", 1);
+    world.set_player_experience(player_id, 380);
+    StatsSystem::update(&mut world);
+    let mobs: Vec<u64> = world
+        .get_zone(1)
+        .unwrap()
+        .entities
+        .get_mobs()
+        .iter()
+        .map(|mob| mob.id)
+        .collect();
+    let prepare_mob = |world: &mut WorldState, mob_id: u64| {
+        set_mob_x(world, mob_id, 1.0);
+        let mob = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(mob_id)
+            .unwrap();
+        mob.health.as_mut().unwrap().current = 1;
+        mob.combat.as_ref().unwrap().level
+    };
+    let player = |world: &WorldState| {
+        world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(player_id)
+            .unwrap()
+            .clone()
+    };
+    {
+        let zone = world.get_zone_mut(1).unwrap();
+        let wounded = zone.entities.get_entity_mut(player_id).unwrap();
+        wounded.health.as_mut().unwrap().current = 50;
+        wounded.resource.as_mut().unwrap().current = 10.0;
+    }
+    let mut clock = WorldClock::new();
+    let mut kill = |world: &mut WorldState, mob_id: u64| {
+        clock.advance(5.0);
+        world.set_clock(clock);
+        let result = CombatSystem::process_combat_action(
+            world,
+            player_id,
+            CombatAction::AutoAttack { target_id: mob_id },
+        );
+        assert!(result.target_killed);
+        world.drain_entity_effects();
+        DeathSystem::update(world);
+        world.drain_entity_effects()
+    };
+
+    // An even-level kill pays the full reward and tips the player into level 2
+    let mob_level = prepare_mob(&mut world, mobs[0]);
+    let reward = world.experience().kill_experience(1, mob_level);
+    let events = kill(&mut world, mobs[0]);
+    let earned = events.iter().find_map(|(id, effect)| match effect {
+        EntityEffect::Experience {
+            amount,
+            source_id,
+            level,
+            experience,
+            experience_to_next,
+        } if *id == player_id => Some((
+            *amount,
+            *source_id,
+            *level,
+            *experience,
+            *experience_to_next,
+        )),
+        _ => None,
+    });
+    assert_eq!(
+        earned,
+        Some((
+            reward,
+            mobs[0],
+            2,
+            380 + reward - 400,
+            world.experience().experience_to_next(2)
+        ))
+    );
+    assert!(events.iter().any(
+        |(id, effect)| *id == player_id && matches!(effect, EntityEffect::LevelUp { level: 2 })
+    ));
+
+    // Levelling raises stats at once and restores health and mana
+    let levelled = player(&world);
+    let stats = world.classes().get("warrior").unwrap().stats_at(2);
+    let health = levelled.health.as_ref().unwrap();
+    assert_eq!(
+        (health.current, health.maximum),
+        (stats.max_health, stats.max_health)
+    );
+    assert_eq!(levelled.combat.as_ref().unwrap().level, 2);
+    assert_eq!(levelled.resource.as_ref().unwrap().value(), 150);
+    assert_eq!(world.get_player_progression(player_id).unwrap().level, 2);
+
+    // Mobs far below the player are worth nothing
+    world.set_player_class(player_id, "warrior", 8);
+    world.set_player_experience(player_id, 0);
+    prepare_mob(&mut world, mobs[1]);
+    let events = kill(&mut world, mobs[1]);
+    assert!(!events
+        .iter()
+        .any(|(_, effect)| matches!(effect, EntityEffect::Experience { .. })));
+    assert_eq!(
+        world.get_player_progression(player_id).unwrap().experience,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_class_abilities_are_learned_as_players_level() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    let known = |world: &WorldState| {
+        let player = world.get_zone(1).unwrap().entities.get_entity(player_id);
+        let mut ids = player
+            .unwrap()
+            .abilities
+            .as_ref()
+            .unwrap()
+            .ability_ids
+            .clone();
+        ids.sort_unstable();
+        ids
+    };
+    let cleave = |world: &mut WorldState| {
+        CombatSystem::process_combat_action(
+            world,
+            player_id,
+            CombatAction::Ability {
+                ability_id: 9,
+                target_id: mob_id,
+                target_position: None,
+            },
+        )
+    };
+
+    // A new warrior knows Strike and Battle Cry, and nothing a mage casts
+    world.set_player_class(player_id, "warrior", 1);
+    assert_eq!(known(&world), vec![1, 10]);
+    let refused = cleave(&mut world);
+    assert_eq!(
+        refused.error_message.as_deref(),
+        Some("Ability 9 not learned")
+    );
+
+    // Levelling to 5 teaches Taunt on the way and Cleave on arrival
+    let table = world.experience();
+    let needed: u64 = (1..5).map(|level| table.experience_to_next(level)).sum();
+    ProgressionSystem::grant(&mut world, player_id, needed, mob_id);
+    assert_eq!(known(&world), vec![1, 4, 9, 10]);
+    let learned = cleave(&mut world);
+    assert_ne!(
+        learned.error_message.as_deref(),
+        Some("Ability 9 not learned")
+    );
+}
+
+#[tokio::test]
+async fn test_mobs_drop_loot_that_their_attackers_can_take() {
+    let mut world = WorldState::new();
+    let (store, _session, player_id) = session_with_player(&mut world).await;
+    world.set_player_class(player_id, "warrior", 1);
+    let bystander_id = world
+        .spawn_player_entity(
+            "Bystander",
+            "1",
+            (2.0, 0.0, 0.0),
+            0.0,
+            (100, 100),
+            ("mana", 150, 150),
+        )
+        .unwrap();
+    let bystander_session = store.create_session().await;
+    store
+        .authenticate_session(&bystander_session, Uuid::new_v4(), bystander_id, None)
+        .await;
+
+    let mut loot_tables = LootSystem::new();
+    loot_tables.register_table(
+        LootTable::new(1, "Test Loot")
+            .add_guaranteed_drop(1)
+            .add_guaranteed_drop(200)
+            .add_entry(
+                LootEntry::new(201, 1.0).with_condition(LootCondition::Class("mage".to_string())),
+            )
+            .add_entry(
+                LootEntry::new(100, 1.0)
+                    .with_condition(LootCondition::Class("warrior".to_string())),
+            )
+            .with_gold(7, 7),
+    );
+    world.set_loot_tables(loot_tables);
+
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    {
+        let mob = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(mob_id)
+            .unwrap();
+        mob.loot_table = Some(1);
+        mob.health.as_mut().unwrap().current = 1;
+    }
+    let mut clock = WorldClock::new();
+    clock.advance(5.0);
+    world.set_clock(clock);
+    let result = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::AutoAttack { target_id: mob_id },
+    );
+    assert!(result.target_killed);
+    DeathSystem::update(&mut world);
+    let inventory = |world: &WorldState| {
+        world
+            .get_zone(1)
+            .unwrap()
+            .entities
+            .get_entity(player_id)
+            .unwrap()
+            .inventory
+            .clone()
+            .unwrap()
+    };
+
+    // Only the player who tagged the mob may loot it
+    assert!(LootingSystem::open(&mut world, bystander_id, mob_id).is_err());
+
+    // Opening picks up the gold and lists the items the killer qualified for
+    let window = LootingSystem::open(&mut world, player_id, mob_id).unwrap();
+    assert_eq!(window.gold, 7);
+    let mut dropped: Vec<u32> = window
+        .items
+        .iter()
+        .map(|(_, item)| item.definition_id)
+        .collect();
+    dropped.sort_unstable();
+    assert_eq!(dropped, vec![1, 100, 200]);
+    assert_eq!(inventory(&world).gold, 7);
+    assert_eq!(
+        LootingSystem::open(&mut world, player_id, mob_id)
+            .unwrap()
+            .gold,
+        0
+    );
+
+    // Taking an item moves it into the inventory and empties its loot slot
+    let (sword_slot, _) = window
+        .items
+        .iter()
+        .find(|(_, item)| item.definition_id == 1)
+        .unwrap();
+    let window = LootingSystem::take(&mut world, player_id, mob_id, *sword_slot).unwrap();
+    assert_eq!(window.items.len(), 2);
+    assert!(inventory(&world).has_item(1, 1));
+    assert!(LootingSystem::take(&mut world, player_id, mob_id, *sword_slot).is_err());
+
+    // A full inventory leaves the item on the corpse
+    let (shirt_slot, _) = window
+        .items
+        .iter()
+        .find(|(_, item)| item.definition_id == 100)
+        .unwrap();
+    {
+        let player = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(player_id)
+            .unwrap();
+        let inventory = player.inventory.as_mut().unwrap();
+        inventory.max_slots = inventory.used_slots() as u32;
+    }
+    assert_eq!(
+        LootingSystem::take(&mut world, player_id, mob_id, *shirt_slot).err(),
+        Some("Inventory is full".to_string())
+    );
+    assert_eq!(world.get_zone(1).unwrap().loot[&mob_id].items.len(), 3);
+
+    // The corpse must be within reach
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(player_id)
+        .unwrap()
+        .position
+        .as_mut()
+        .unwrap()
+        .x = 20.0;
+    assert_eq!(
+        LootingSystem::open(&mut world, player_id, mob_id).err(),
+        Some("Corpse is too far away".to_string())
+    );
+
+    // Loot left behind goes with the corpse
+    clock.advance(world.death_config().corpse_duration);
+    world.set_clock(clock);
+    DeathSystem::update(&mut world);
+    assert!(world.get_zone(1).unwrap().loot.is_empty());
+}
+
+/// Tag a mob as a warrior, let a mage finish it off and return what the
+/// tagger finds on the corpse: its gold and item IDs
+async fn loot_of_a_finished_off_mob(seed: u64) -> (u32, Vec<u32>) {
+    let mut world = WorldState::new();
+    let (_store, _session, tagger_id) = session_with_player(&mut world).await;
+    world.set_player_class(tagger_id, "warrior", 1);
+    let killer_id = world
+        .spawn_player_entity(
+            "Finisher",
+            "1",
+            (1.5, 0.0, 0.0),
+            0.0,
+            (100, 100),
+            ("mana", 150, 150),
+        )
+        .unwrap();
+    world.set_player_class(killer_id, "mage", 1);
+
+    let mut loot_tables = LootSystem::new();
+    let mut table = LootTable::new(1, "Test Loot")
+        .add_entry(
+            LootEntry::new(201, 1.0).with_condition(LootCondition::Class("mage".to_string())),
+        )
+        .add_entry(
+            LootEntry::new(100, 1.0).with_condition(LootCondition::Class("warrior".to_string())),
+        )
+        .with_gold(1, 1_000);
+    for item_id in [1, 2, 200, 202] {
+        table = table.add_entry(LootEntry::new(item_id, 0.5));
+    }
+    loot_tables.register_table(table);
+    world.set_loot_tables(loot_tables);
+
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .loot_table = Some(1);
+    world.seed_combat_rng(seed);
+    let mut clock = WorldClock::new();
+    clock.advance(5.0);
+    world.set_clock(clock);
+    let hit = |world: &mut WorldState, attacker_id: u64| {
+        CombatSystem::process_combat_action(
+            world,
+            attacker_id,
+            CombatAction::AutoAttack { target_id: mob_id },
+        )
+    };
+
+    assert!(!hit(&mut world, tagger_id).target_killed);
+    world
+        .get_zone_mut(1)
+        .unwrap()
+        .entities
+        .get_entity_mut(mob_id)
+        .unwrap()
+        .health
+        .as_mut()
+        .unwrap()
+        .current = 1;
+    assert!(hit(&mut world, killer_id).target_killed);
+    DeathSystem::update(&mut world);
+
+    assert!(
+        LootingSystem::open(&mut world, killer_id, mob_id).is_err(),
+        "the loot still belongs to the tagger"
+    );
+    let window = LootingSystem::open(&mut world, tagger_id, mob_id).unwrap();
+    let mut items: Vec<u32> = window
+        .items
+        .iter()
+        .map(|(_, item)| item.definition_id)
+        .collect();
+    items.sort_unstable();
+    (window.gold, items)
+}
+
+#[tokio::test]
+async fn test_loot_is_rolled_for_the_killer_from_the_seeded_rng() {
+    let (gold, items) = loot_of_a_finished_off_mob(7).await;
+    // The killer's class decides the conditional drops
+    assert!(items.contains(&201));
+    assert!(!items.contains(&100));
+
+    // The same seed finds the same loot; some other seed does not
+    assert_eq!(loot_of_a_finished_off_mob(7).await, (gold, items.clone()));
+    let mut differs = false;
+    for seed in 8..16 {
+        differs |= loot_of_a_finished_off_mob(seed).await != (gold, items.clone());
+    }
+    assert!(differs, "loot ignored the seed");
+}
+
+#[tokio::test]
+async fn test_party_loot_goes_to_the_tagging_party_by_its_rules() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let spawn = |world: &mut WorldState, name: &str, x: f32| {
+        world
+            .spawn_player_entity(
+                name,
+                "1",
+                (x, 0.0, 0.0),
+                0.0,
+                (100, 100),
+                ("mana", 150, 150),
+            )
+            .unwrap()
+    };
+    let partner_id = spawn(&mut world, "Partner", 2.0);
+    let outsider_id = spawn(&mut world, "Outsider", 3.0);
+    let party_id = world.parties_mut().create(player_id).unwrap();
+    world.parties_mut().join(party_id, partner_id).unwrap();
+
+    // The iron axe is uncommon, so the party rolls for it; the potion is handed out
+    let mut loot_tables = LootSystem::new();
+    loot_tables.register_table(
+        LootTable::new(1, "Test Loot")
+            .add_guaranteed_drop(2)
+            .add_guaranteed_drop(200)
+            .with_gold(10, 10),
+    );
+    world.set_loot_tables(loot_tables);
+
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    {
+        let mob = world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .get_entity_mut(mob_id)
+            .unwrap();
+        mob.loot_table = Some(1);
+        mob.health.as_mut().unwrap().current = 1;
+        // The partner tags the mob before the player lands the killing blow
+        ThreatSystem::on_damage(mob, partner_id, 1);
+        ThreatSystem::on_damage(mob, outsider_id, 1);
+    }
+    let mut clock = WorldClock::new();
+    clock.advance(5.0);
+    world.set_clock(clock);
+    let result = CombatSystem::process_combat_action(
+        &mut world,
+        player_id,
+        CombatAction::AutoAttack { target_id: mob_id },
+    );
+    assert!(result.target_killed);
+    world.drain_entity_effects();
+    DeathSystem::update(&mut world);
+
+    let loot = &world.get_zone(1).unwrap().loot[&mob_id];
+    assert_eq!(loot.looters, vec![player_id, partner_id]);
+    let axe_slot = loot
+        .items
+        .iter()
+        .position(|corpse_item| corpse_item.as_ref().unwrap().item.definition_id == 2)
+        .unwrap() as u32;
+    let mut rolls: Vec<EntityId> = world
+        .drain_entity_effects()
+        .into_iter()
+        .filter(|(_, effect)| matches!(effect, EntityEffect::LootRoll { slot, .. } if *slot == axe_slot))
+        .map(|(entity_id, _)| entity_id)
+        .collect();
+    rolls.sort_unstable();
+    assert_eq!(rolls, vec![player_id, partner_id]);
+
+    // A later attacker outside the party may not loot the corpse
+    assert_eq!(
+        LootingSystem::open(&mut world, outsider_id, mob_id).err(),
+        Some("You may not loot that corpse".to_string())
+    );
+
+    // The gold is split across the party; the potion went to the leader's turn
+    let window = LootingSystem::open(&mut world, player_id, mob_id).unwrap();
+    assert_eq!(window.gold, 5);
+    let gold = |world: &WorldState, id: EntityId| {
+        let entity = world.get_zone(1).unwrap().entities.get_entity(id).unwrap();
+        entity.inventory.as_ref().unwrap().gold
+    };
+    assert_eq!(gold(&world, partner_id), 5);
+    let visible: Vec<u32> = window
+        .items
+        .iter()
+        .map(|(_, item)| item.definition_id)
+        .collect();
+    assert_eq!(visible, vec![200]);
+    assert_eq!(
+        LootingSystem::take(&mut world, partner_id, mob_id, axe_slot).err(),
+        Some("That item is still being rolled for".to_string())
+    );
+
+    // Need beats greed once everyone has answered
+    LootingSystem::roll(&mut world, player_id, mob_id, axe_slot, RollChoice::Greed).unwrap();
+    assert!(
+        LootingSystem::roll(&mut world, outsider_id, mob_id, axe_slot, RollChoice::Need).is_err()
+    );
+    LootingSystem::roll(&mut world, partner_id, mob_id, axe_slot, RollChoice::Need).unwrap();
+    LootingSystem::update(&mut world);
+    let results: Vec<(EntityId, EntityEffect)> = world.drain_entity_effects();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|(_, effect)| matches!(
+        effect,
+        EntityEffect::LootRollResult { winner_id, choice: RollChoice::Need, .. }
+            if *winner_id == partner_id
+    )));
+    assert_eq!(
+        LootingSystem::take(&mut world, player_id, mob_id, axe_slot).err(),
+        Some("That item belongs to someone else".to_string())
+    );
+    LootingSystem::take(&mut world, partner_id, mob_id, axe_slot).unwrap();
+
+    // Whatever the party leaves unclaimed goes free for anyone in time
+    clock.advance(world.loot_config().free_for_all_after);
+    world.set_clock(clock);
+    let window = LootingSystem::open(&mut world, outsider_id, mob_id).unwrap();
+    let (potion_slot, _) = window.items[0];
+    LootingSystem::take(&mut world, outsider_id, mob_id, potion_slot).unwrap();
+    assert!(world.get_zone(1).unwrap().loot.is_empty());
+}
+
+#[test]
+fn test_attack_table_scales_with_level_and_range() {
+    let config = AttackTableConfig::default();
+    let player = Entity::new_player(1, "Tester".to_string());
+    let peer = Entity::new_mob(2, "Peer".to_string(), 1);
+    let elite = Entity::new_mob(3, "Elite".to_string(), 6);
+
+    let even = AttackTable::attack(&config, &player, &peer, true);
+    let uphill = AttackTable::attack(&config, &player, &elite, true);
+    assert!(uphill.miss > even.miss);
+    assert!(uphill.dodge > even.dodge);
+    assert!(uphill.critical < even.critical);
+
+    let spell = AttackTable::attack(&config, &player, &peer, false);
+    assert_eq!((spell.dodge, spell.parry), (0.0, 0.0));
+    assert_eq!(spell.miss, even.miss);
+
+    let heal = AttackTable::heal(&config, &player);
+    assert_eq!(heal.miss, 0.0);
+    assert!(heal.critical > 0.0);
+}
+
+#[test]
+fn test_party_requests_reach_the_players_they_concern() {
+    let mut world = WorldState::new();
+    let [aria, bram, cole] = ["Aria", "Bram", "Cole"].map(|name| {
+        world
+            .spawn_player_entity(
+                name,
+                "1",
+                (0.0, 0.0, 0.0),
+                0.0,
+                (100, 100),
+                ("mana", 150, 150),
+            )
+            .unwrap()
+    });
+    // Who is in each recipient's party, or None for an invitation
+    let delivered = |world: &mut WorldState| {
+        world
+            .drain_entity_effects()
+            .into_iter()
+            .map(|(player_id, effect)| match effect {
+                EntityEffect::PartyChanged { members, .. } => (
+                    player_id,
+                    Some(members.iter().map(|member| member.name.clone()).collect()),
+                ),
+                EntityEffect::PartyInvite { inviter_id, .. } => {
+                    assert_eq!(inviter_id, aria);
+                    (player_id, None)
+                }
+                other => panic!("unexpected effect {:?}", other),
+            })
+            .collect::<Vec<(EntityId, Option<Vec<String>>)>>()
+    };
+    let names = |names: &[&str]| Some(names.iter().map(|name| name.to_string()).collect());
+
+    assert_eq!(
+        PartySystem::invite(&mut world, aria, 999),
+        Err(PartyError::InvalidInvitee)
+    );
+    assert_eq!(
+        PartySystem::reply(&mut world, bram, true),
+        Err(PartyError::NoInvite)
+    );
+
+    // Only the invitee hears of the invitation; both members hear of the party
+    PartySystem::invite(&mut world, aria, bram).unwrap();
+    assert_eq!(delivered(&mut world), vec![(bram, None)]);
+    PartySystem::reply(&mut world, bram, true).unwrap();
+    assert_eq!(
+        delivered(&mut world),
+        vec![
+            (aria, names(&["Aria", "Bram"])),
+            (bram, names(&["Aria", "Bram"]))
+        ]
+    );
+
+    let rules = LootRules {
+        method: LootMethod::RoundRobin,
+        threshold: ItemRarity::Rare,
+    };
+    assert_eq!(
+        PartySystem::set_loot_rules(&mut world, bram, rules),
+        Err(PartyError::NotLeader)
+    );
+    PartySystem::refuse(&mut world, bram, &PartyError::NotLeader);
+    assert!(matches!(
+        world.drain_entity_effects().as_slice(),
+        [(id, EntityEffect::PartyRefused { reason })]
+            if *id == bram && *reason == PartyError::NotLeader.to_string()
+    ));
+    PartySystem::set_loot_rules(&mut world, aria, rules).unwrap();
+    let changes = world.drain_entity_effects();
+    assert_eq!(changes.len(), 2);
+    assert!(changes.iter().all(|(_, effect)| matches!(
+        effect,
+        EntityEffect::PartyChanged {
+            method: LootMethod::RoundRobin,
+            threshold: ItemRarity::Rare,
+            ..
+        }
+    )));
+
+    PartySystem::invite(&mut world, aria, cole).unwrap();
+    PartySystem::reply(&mut world, cole, true).unwrap();
+    delivered(&mut world);
+
+    // A leaver hears they are in no party, the rest who is left
+    PartySystem::leave(&mut world, bram).unwrap();
+    assert_eq!(
+        delivered(&mut world),
+        vec![
+            (aria, names(&["Aria", "Cole"])),
+            (bram, names(&[])),
+            (cole, names(&["Aria", "Cole"]))
+        ]
+    );
+    assert_eq!(
+        PartySystem::leave(&mut world, bram),
+        Err(PartyError::NotInParty)
+    );
+
+    // Logging out leaves the party too, and disbanding it tells the one left
+    world.remove_player(cole);
+    assert_eq!(
+        delivered(&mut world),
+        vec![(aria, names(&[])), (cole, names(&[]))]
+    );
+}
+
+#[tokio::test]
+async fn test_harmful_abilities_only_land_on_hostile_targets() {
+    let mut world = WorldState::new();
+    let (_store, _session, player_id) = session_with_player(&mut world).await;
+    let other_player_id = world
+        .spawn_player_entity(
+            "Bystander",
+            "1",
+            (1.0, 0.0, 0.0),
+            0.0,
+            (100, 100),
+            ("mana", 150, 150),
+        )
+        .unwrap();
+    let vendor_id =
+        world
+            .get_zone_mut(1)
+            .unwrap()
+            .entities
+            .create_test_vendor("Vendor".to_string(), 0.0, 1.0);
+    let strike = |world: &mut WorldState, target_id: EntityId| {
+        CombatSystem::process_combat_action(
+            world,
+            player_id,
+            CombatAction::Ability {
+                ability_id: 1,
+                target_id,
+                target_position: None,
+            },
+        )
+    };
+
+    for target_id in [other_player_id, vendor_id] {
+        let result = strike(&mut world, target_id);
+        assert!(!result.success);
+        assert_eq!(
+            result.error_message.as_deref(),
+            Some("Target is not hostile")
+        );
+        let target = world.get_zone(1).unwrap().entities.get_entity(target_id);
+        assert!(target.unwrap().is_alive());
+    }
+
+    let mob_id = world.get_zone(1).unwrap().entities.get_mobs()[0].id;
+    set_mob_x(&mut world, mob_id, 1.0);
+    assert!(strike(&mut world, mob_id).success);
+}
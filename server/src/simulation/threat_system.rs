@@ -0,0 +1,207 @@
+//! Threat tables and mob target selection
+//!
+//! Damage done to a mob adds threat for the attacker. Healing an entity that
+//! is on a mob's table adds threat for the healer, split across every mob
+//! fighting the healed entity. Threat decays over time and the mob attacks
+//! whoever holds the most of it, switching only once another attacker clearly
+//! overtakes its current target. Taunting puts the caster level with the top
+//! of the table and makes it the target outright. The first attacker to
+//! damage a mob tags it, claiming its loot. A mob that leashes or dies
+//! forgets its table and its tag.
+
+use crate::entities::{AiState, Entity, EntityId};
+use crate::world::{WorldState, Zone};
+use std::collections::HashMap;
+
+/// Threat per point of damage dealt
+const THREAT_PER_DAMAGE: f32 = 1.0;
+/// Threat per point of healing done, before it is split between mobs
+const THREAT_PER_HEALING: f32 = 0.5;
+/// Fraction of threat lost per second
+const THREAT_DECAY_PER_SECOND: f32 = 0.05;
+/// Entries that decay below this are dropped from the table
+const MIN_THREAT: f32 = 0.5;
+/// Threat a mob holds against a player it noticed before anyone attacked
+const AGGRO_THREAT: f32 = 1.0;
+/// A new attacker must exceed the current target's threat by this factor to pull
+const TARGET_SWITCH_MARGIN: f32 = 1.1;
+
+/// Threat system, ticked once per simulation tick
+pub struct ThreatSystem;
+
+impl ThreatSystem {
+    /// Decay threat, drop stale entries and point every mob at its top attacker
+    pub fn update(world_state: &mut WorldState, delta_time: f64) {
+        for zone in world_state.zones_mut() {
+            Self::update_zone(zone, delta_time as f32);
+        }
+    }
+
+    fn update_zone(zone: &mut Zone, delta_time: f32) {
+        // Attackers that died or left the zone no longer hold threat
+        let living: HashMap<EntityId, bool> = zone
+            .entities
+            .get_all_entities()
+            .into_iter()
+            .map(|entity| (entity.id, entity.is_alive()))
+            .collect();
+        let decay = (1.0 - THREAT_DECAY_PER_SECOND * delta_time).max(0.0);
+
+        for entity in zone.entities.entities_mut() {
+            let alive = entity.is_alive();
+            let Some(threat) = entity.threat.as_mut() else {
+                continue;
+            };
+            if !alive {
+                threat.clear();
+                continue;
+            }
+
+            threat.table.retain(|source_id, amount| {
+                *amount *= decay;
+                *amount >= MIN_THREAT && living.get(source_id).copied().unwrap_or(false)
+            });
+
+            let leash_range = entity
+                .ai
+                .as_ref()
+                .map_or(f32::INFINITY, |ai| ai.leash_range);
+            if distance_from_home(entity).is_some_and(|distance| distance > leash_range) {
+                Self::evade(entity);
+                continue;
+            }
+            Self::select_target(entity);
+        }
+    }
+
+    /// Follow the top of the table, or evade once nobody is left on it
+    fn select_target(entity: &mut Entity) {
+        let Some(threat) = entity.threat.as_mut() else {
+            return;
+        };
+        let current = threat.target_id.filter(|id| threat.table.contains_key(id));
+        let target_id = match (threat.top(), current) {
+            (None, _) => None,
+            (Some((_, top)), Some(current_id))
+                if top < threat.get(current_id) * TARGET_SWITCH_MARGIN =>
+            {
+                Some(current_id)
+            }
+            (Some((top_id, _)), _) => Some(top_id),
+        };
+        let had_target = threat.target_id.is_some();
+        threat.target_id = target_id;
+
+        let Some(ai) = entity.ai.as_mut() else {
+            return;
+        };
+        match target_id {
+            Some(target_id) => match ai.state {
+                AiState::Chasing { target_id: current }
+                | AiState::Attacking { target_id: current }
+                    if current == target_id => {}
+                // Fleeing mobs turn back to the top of the table once the flight is over
+                AiState::Returning { .. } | AiState::Fleeing { .. } => {}
+                _ => ai.state = AiState::Chasing { target_id },
+            },
+            None if had_target => Self::evade(entity),
+            None => {}
+        }
+    }
+
+    /// Drop every threat entry and head home
+    pub fn evade(entity: &mut Entity) {
+        if let Some(threat) = entity.threat.as_mut() {
+            threat.clear();
+        }
+        if let Some(ai) = entity.ai.as_mut() {
+            ai.state = AiState::Returning {
+                home_position: ai.home_position,
+            };
+        }
+    }
+
+    /// Whether a mob is walking home and ignoring attackers
+    fn is_evading(entity: &Entity) -> bool {
+        entity
+            .ai
+            .as_ref()
+            .is_some_and(|ai| matches!(ai.state, AiState::Returning { .. }))
+    }
+
+    /// Add threat on `target` for damage dealt by `attacker_id`
+    pub fn on_damage(target: &mut Entity, attacker_id: EntityId, damage: u32) {
+        if target.id == attacker_id || Self::is_evading(target) {
+            return;
+        }
+        if let Some(threat) = target.threat.as_mut() {
+            threat.add(attacker_id, damage as f32 * THREAT_PER_DAMAGE);
+            threat.tagged_by.get_or_insert(attacker_id);
+            threat.last_attacker = Some(attacker_id);
+        }
+    }
+
+    /// Add threat for `healer_id` on every mob that has `healed_id` on its table
+    pub fn on_healing(zone: &mut Zone, healer_id: EntityId, healed_id: EntityId, healing: u32) {
+        let mut mobs: Vec<&mut Entity> = zone
+            .entities
+            .entities_mut()
+            .filter(|entity| entity.id != healer_id && !Self::is_evading(entity))
+            .filter(|entity| {
+                entity
+                    .threat
+                    .as_ref()
+                    .is_some_and(|threat| threat.table.contains_key(&healed_id))
+            })
+            .collect();
+        if mobs.is_empty() {
+            return;
+        }
+
+        let share = healing as f32 * THREAT_PER_HEALING / mobs.len() as f32;
+        for mob in mobs.iter_mut() {
+            if let Some(threat) = mob.threat.as_mut() {
+                threat.add(healer_id, share);
+            }
+        }
+    }
+
+    /// Put a player the mob noticed on its table, ready to be chased
+    pub fn aggro(entity: &mut Entity, target_id: EntityId) {
+        if let Some(threat) = entity.threat.as_mut() {
+            threat.add(target_id, AGGRO_THREAT);
+            threat.target_id.get_or_insert(target_id);
+        }
+    }
+
+    /// Match the top threat on `target` and make `taunter_id` its target
+    pub fn taunt(target: &mut Entity, taunter_id: EntityId) {
+        if target.id == taunter_id || Self::is_evading(target) {
+            return;
+        }
+        let Some(threat) = target.threat.as_mut() else {
+            return;
+        };
+        let top = threat.top().map_or(0.0, |(_, amount)| amount);
+        let own = threat.get(taunter_id);
+        threat.add(taunter_id, (top - own).max(MIN_THREAT));
+        threat.target_id = Some(taunter_id);
+        if let Some(ai) = target.ai.as_mut() {
+            ai.state = AiState::Chasing {
+                target_id: taunter_id,
+            };
+        }
+    }
+}
+
+/// How far a mob has been pulled from its home position
+pub(crate) fn distance_from_home(entity: &Entity) -> Option<f32> {
+    let ai = entity.ai.as_ref()?;
+    let position = entity.position.as_ref()?;
+    let (dx, dy, dz) = (
+        position.x - ai.home_position.0,
+        position.y - ai.home_position.1,
+        position.z - ai.home_position.2,
+    );
+    Some((dx * dx + dy * dy + dz * dz).sqrt())
+}
@@ -3,19 +3,24 @@
 //! This module implements the 20 Hz game simulation loop that
 //! updates all game systems each tick.
 
-use crate::entities::{Entity as GameEntity, EntityType};
-use crate::network::messages::{self, Envelope, MovementState, Payload, Vector3, WorldSnapshot};
+use crate::entities::EntityId;
+use crate::network::messages::EntityEffect;
+use crate::network::messages::{Envelope, Payload};
 use crate::network::SessionStore;
+use crate::simulation::interest::{InterestConfig, InterestSystem};
 use crate::simulation::movement_system::{MovementIntent as SimMovementIntent, MovementSystem};
-use crate::simulation::CombatSystem;
+use crate::simulation::replication::ReplicationManager;
+use crate::simulation::{
+    AiSystem, CastSystem, CombatSystem, DeathSystem, EffectsSystem, LootingSystem, ResourceSystem,
+    StatsSystem, ThreatSystem, WorldClock,
+};
 use crate::world::WorldState;
 use chrono::Utc;
-use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock};
+use std::collections::HashSet;
 use std::time::Duration;
+use tokio::sync::watch;
 use tokio::time::interval;
-use tracing::{info, warn};
-use uuid::Uuid;
+use tracing::{debug, info, warn};
 
 /// Target ticks per second for the simulation
 const TARGET_TPS: f64 = 20.0;
@@ -25,21 +30,38 @@ const TICK_DURATION: Duration = Duration::from_micros((1_000_000.0 / TARGET_TPS)
 pub struct SimulationLoop {
     world_state: std::sync::Arc<tokio::sync::RwLock<WorldState>>,
     session_store: SessionStore,
+    replication: ReplicationManager,
+    interest: InterestConfig,
+    /// Authoritative simulation time, published to the world every tick
+    clock: WorldClock,
     running: bool,
+    /// Stops the loop between ticks once it reads true
+    shutdown: Option<watch::Receiver<bool>>,
 }
 
 impl SimulationLoop {
     pub fn new(
         world_state: std::sync::Arc<tokio::sync::RwLock<WorldState>>,
         session_store: SessionStore,
+        interest: InterestConfig,
     ) -> Self {
         Self {
             world_state,
             session_store,
+            replication: ReplicationManager::new(),
+            interest,
+            clock: WorldClock::new(),
             running: false,
+            shutdown: None,
         }
     }
 
+    /// Stop the loop when `shutdown` becomes true
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
     /// Start the simulation loop
     pub async fn run(&mut self) {
         self.running = true;
@@ -50,7 +72,20 @@ impl SimulationLoop {
             if !self.running {
                 break;
             }
-            timer.tick().await;
+            let stop_requested = match self.shutdown.as_mut() {
+                Some(shutdown) => tokio::select! {
+                    _ = timer.tick() => false,
+                    _ = shutdown.wait_for(|stopping| *stopping) => true,
+                },
+                None => {
+                    timer.tick().await;
+                    false
+                }
+            };
+            if stop_requested {
+                self.stop();
+                continue;
+            }
             self.process_tick().await;
         }
 
@@ -62,9 +97,11 @@ impl SimulationLoop {
         self.running = false;
     }
 
-    async fn process_tick(&self) {
-        {
+    async fn process_tick(&mut self) {
+        self.clock.advance(TICK_DURATION.as_secs_f64());
+        let effects = {
             let mut world = self.world_state.write().await;
+            world.set_clock(self.clock);
             world.update(TICK_DURATION.as_secs_f64());
 
             for intent in world.drain_movement_intents() {
@@ -85,41 +122,66 @@ impl SimulationLoop {
             }
 
             for (attacker_id, action) in world.drain_combat_actions() {
+                // Refusals reach the attacker as events; they are routine, e.g. cooldowns
                 let result = CombatSystem::process_combat_action(&mut world, attacker_id, action);
                 if !result.success {
-                    warn!(
+                    debug!(
                         attacker = attacker_id,
                         error = ?result.error_message,
-                        "Combat action failed"
+                        "Combat action refused"
                     );
                 }
             }
-        }
 
-        self.broadcast_world_snapshots().await;
+            CastSystem::update(&mut world);
+            EffectsSystem::update(&mut world);
+            StatsSystem::update(&mut world);
+            DeathSystem::update(&mut world);
+            LootingSystem::update(&mut world);
+            ThreatSystem::update(&mut world, TICK_DURATION.as_secs_f64());
+            AiSystem::update(&mut world);
+            ResourceSystem::update(&mut world, TICK_DURATION.as_secs_f64());
+
+            InterestSystem::update(&mut world, &self.interest);
+            world.drain_entity_effects()
+        };
+
+        self.replicate_world_state(&effects).await;
     }
 
-    async fn broadcast_world_snapshots(&self) {
+    /// Send each session a full snapshot or this tick's delta batch, carrying
+    /// the events raised this tick
+    async fn replicate_world_state(&mut self, effects: &[(EntityId, EntityEffect)]) {
         let sessions = self.session_store.get_active_sessions().await;
+        let active: HashSet<_> = sessions.iter().map(|session| session.id).collect();
+        self.replication.retain_sessions(&active);
         if sessions.is_empty() {
             return;
         }
 
-        let mut snapshots = Vec::with_capacity(sessions.len());
+        let mut payloads = Vec::with_capacity(sessions.len());
         {
             let world = self.world_state.read().await;
             for session in &sessions {
-                if let Some(snapshot) = build_world_snapshot(&world, session) {
-                    snapshots.push((session.id, snapshot));
+                if let Some(payload) =
+                    self.replication
+                        .replicate(&world, session, self.clock.now(), effects)
+                {
+                    payloads.push((session.id, payload));
                 }
             }
         }
 
-        for (session_id, snapshot) in snapshots {
+        for (session_id, payload) in payloads {
+            let snapshot_id = match &payload {
+                Payload::WorldSnapshot(snapshot) => snapshot.snapshot_id,
+                Payload::EntityUpdateBatch(batch) => batch.snapshot_id,
+                _ => 0,
+            };
             let envelope = Envelope {
-                sequence_id: snapshot.snapshot_id as u32,
+                sequence_id: snapshot_id as u32,
                 timestamp: Utc::now().timestamp_millis() as u64,
-                payload: Payload::WorldSnapshot(snapshot),
+                payload,
             };
 
             if self
@@ -128,7 +190,10 @@ impl SimulationLoop {
                 .await
                 .is_err()
             {
-                warn!("Failed to send world snapshot to session {}", session_id);
+                warn!(
+                    "Failed to send replication update to session {}",
+                    session_id
+                );
             }
         }
     }
@@ -143,136 +208,3 @@ impl SimulationLoop {
         self.world_state.write().await
     }
 }
-
-pub(crate) fn build_world_snapshot(
-    world: &WorldState,
-    session: &crate::network::Session,
-) -> Option<WorldSnapshot> {
-    const POS_EPSILON: f32 = 0.05; // 5 cm
-    const ROT_EPSILON: f32 = 0.01; // ~0.5 degrees
-    let player_id = session.player_id?;
-    let zone_id = world.get_player_zone_id(player_id)?;
-    let zone = world.get_zone(zone_id)?;
-
-    let entities = zone
-        .entities
-        .get_all_entities()
-        .into_iter()
-        .filter_map(|e| {
-            static LAST_SENT: OnceLock<Mutex<HashMap<Uuid, HashMap<u64, (f32, f32, f32, f32)>>>> =
-                OnceLock::new();
-            let cache = LAST_SENT.get_or_init(|| Mutex::new(HashMap::new()));
-            let mut last = cache.lock().ok()?;
-
-            let session_entry = last.entry(session.id).or_insert_with(HashMap::new);
-            let id = e.id;
-            let pos = e.position.as_ref()?;
-            let entry = session_entry
-                .entry(id)
-                .or_insert((pos.x, pos.y, pos.z, pos.rotation));
-
-            let dx = (pos.x - entry.0).abs();
-            let dy = (pos.y - entry.1).abs();
-            let dz = (pos.z - entry.2).abs();
-            let drot = (pos.rotation - entry.3).abs();
-
-            let mut pose_to_send = *entry;
-            if dx > POS_EPSILON || dy > POS_EPSILON || dz > POS_EPSILON || drot > ROT_EPSILON {
-                pose_to_send = (pos.x, pos.y, pos.z, pos.rotation);
-                *entry = pose_to_send;
-            }
-
-            // Release lock before serialization to avoid holding across allocations.
-            drop(last);
-            entity_to_wire(e, Some(pose_to_send))
-        })
-        .collect::<Vec<_>>();
-
-    let snapshot_id_i64 = Utc::now().timestamp_millis();
-    let snapshot_id = if snapshot_id_i64.is_negative() {
-        0
-    } else {
-        snapshot_id_i64 as u64
-    };
-
-    Some(WorldSnapshot {
-        snapshot_id,
-        entities,
-        player_entity_id: player_id,
-        zone_name: zone.name.clone(),
-    })
-}
-
-pub(crate) fn entity_to_wire(
-    entity: &GameEntity,
-    override_pose: Option<(f32, f32, f32, f32)>,
-) -> Option<messages::Entity> {
-    let (px, py, pz, prot) = if let Some(p) = override_pose {
-        p
-    } else {
-        let position = entity.position.as_ref()?;
-        (position.x, position.y, position.z, position.rotation)
-    };
-
-    let movement_state = determine_movement_state(entity);
-    let health_percent = entity
-        .health
-        .as_ref()
-        .map(|health| {
-            if health.maximum == 0 {
-                1.0
-            } else {
-                (health.current as f32 / health.maximum as f32).clamp(0.0, 1.0)
-            }
-        })
-        .unwrap_or(1.0);
-
-    Some(messages::Entity {
-        id: entity.id,
-        entity_type: entity_type_name(&entity.entity_type).to_string(),
-        position: Vector3 {
-            x: px,
-            y: py,
-            z: pz,
-        },
-        rotation: Vector3 {
-            x: 0.0,
-            y: prot,
-            z: 0.0,
-        },
-        state: messages::EntityState {
-            movement_state,
-            health_percent,
-            display_name: entity.name.clone(),
-        },
-    })
-}
-
-fn determine_movement_state(entity: &GameEntity) -> MovementState {
-    if entity
-        .health
-        .as_ref()
-        .map(|health| health.current == 0)
-        .unwrap_or(false)
-    {
-        MovementState::Dead
-    } else if entity
-        .movement
-        .as_ref()
-        .map(|movement| movement.is_moving)
-        .unwrap_or(false)
-    {
-        MovementState::Running
-    } else {
-        MovementState::Idle
-    }
-}
-
-fn entity_type_name(entity_type: &EntityType) -> &'static str {
-    match entity_type {
-        EntityType::Player => "player",
-        EntityType::Mob => "mob",
-        EntityType::Npc => "npc",
-        EntityType::WorldObject => "object",
-    }
-}
@@ -3,11 +3,27 @@
 //! This module manages the overall game world state, including
 //! all zones and cross-zone operations.
 
-use crate::entities::EntityId;
+use crate::abilities::AbilityRegistry;
+use crate::ai::AiProfileRegistry;
+use crate::classes::ClassRegistry;
+use crate::entities::{Entity, EntityId, Progression, Resource, ResourceType};
+use crate::items::ItemRegistry;
+#[cfg(test)]
+use crate::items::{EquipmentSlot, ItemInstance, ItemStats};
+use crate::loot::LootSystem;
+use crate::network::messages::EntityEffect;
 use crate::network::MovementIntent;
-use crate::simulation::CombatAction;
+use crate::party::Parties;
+use crate::progression::ExperienceTable;
+use crate::simulation::{
+    AttackTableConfig, CombatAction, DeathConfig, LootConfig, PartySystem, ProgressionSystem,
+    WorldClock,
+};
 use crate::world::Zone;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 use tracing::warn;
 
 /// Manages the entire game world
@@ -16,6 +32,19 @@ pub struct WorldState {
     player_zone_map: HashMap<EntityId, u32>, // Player ID -> Zone ID
     movement_intents: VecDeque<MovementIntent>, // Queue of movement intents to process
     combat_actions: VecDeque<(EntityId, CombatAction)>, // Queue of (attacker_id, action) to process
+    clock: WorldClock,                       // Simulation time as of the current tick
+    abilities: Arc<AbilityRegistry>,         // Ability and effect definitions from content
+    ai_profiles: Arc<AiProfileRegistry>,     // Mob behaviour profiles from content
+    classes: Arc<ClassRegistry>,             // Class base stats and growth from content
+    items: Arc<ItemRegistry>,                // Item definitions
+    experience: Arc<ExperienceTable>,        // Experience curve and kill rewards from content
+    loot_tables: Arc<LootSystem>,            // Loot tables mobs roll on death
+    parties: Parties,                        // Player parties and their loot rules
+    entity_effects: Vec<(EntityId, EntityEffect)>, // Events raised this tick, for replication
+    death_config: DeathConfig,               // Corpse timer and release rules
+    loot_config: LootConfig,                 // Loot roll and free-for-all timers
+    attack_table: AttackTableConfig,         // Hit, avoidance and critical strike tuning
+    combat_rng: StdRng,                      // Source of attack table and loot rolls
 }
 
 impl WorldState {
@@ -25,6 +54,19 @@ impl WorldState {
             player_zone_map: HashMap::new(),
             movement_intents: VecDeque::new(),
             combat_actions: VecDeque::new(),
+            clock: WorldClock::new(),
+            abilities: Arc::new(AbilityRegistry::bundled()),
+            ai_profiles: Arc::new(AiProfileRegistry::bundled()),
+            classes: Arc::new(ClassRegistry::bundled()),
+            items: Arc::new(Self::default_items()),
+            experience: Arc::new(ExperienceTable::bundled()),
+            loot_tables: Arc::new(Self::default_loot_tables()),
+            parties: Parties::default(),
+            entity_effects: Vec::new(),
+            death_config: DeathConfig::default(),
+            loot_config: LootConfig::default(),
+            attack_table: AttackTableConfig::default(),
+            combat_rng: StdRng::from_entropy(),
         };
 
         // Create starter zone
@@ -62,6 +104,16 @@ impl WorldState {
         self.player_zone_map.get(&player_id).cloned()
     }
 
+    /// Get the zone any entity is in: players from the zone map, everything else by lookup
+    pub fn get_entity_zone_id(&self, entity_id: EntityId) -> Option<u32> {
+        self.get_player_zone_id(entity_id).or_else(|| {
+            self.zones
+                .iter()
+                .find(|(_, zone)| zone.entities.get_entity(entity_id).is_some())
+                .map(|(zone_id, _)| *zone_id)
+        })
+    }
+
     /// Ensure a player has a zone mapping; if missing, try to infer it from zones.
     pub fn ensure_player_zone_mapping(&mut self, player_id: EntityId) -> Option<u32> {
         if let Some(zone_id) = self.get_player_zone_id(player_id) {
@@ -86,6 +138,7 @@ impl WorldState {
         position: (f32, f32, f32),
         rotation: f32,
         health: (i32, i32),
+        resource: (&str, i32, i32),
     ) -> Result<EntityId, String> {
         let zone_id = self.resolve_zone_id(zone_label);
         let zone = self
@@ -105,6 +158,19 @@ impl WorldState {
             h.current = health.0.max(0) as u32;
             h.maximum = health.1.max(1) as u32;
         }
+        match ResourceType::from_name(resource.0) {
+            Some(resource_type) => {
+                player.resource = Some(Resource::new(
+                    resource_type,
+                    resource.1.max(0) as u32,
+                    resource.2.max(0) as u32,
+                ));
+            }
+            None => warn!(
+                "Unknown resource type '{}' for player {}; spawning without one",
+                resource.0, name
+            ),
+        }
 
         zone.entities.add_entity(player);
         zone.add_player(entity_id);
@@ -137,23 +203,7 @@ impl WorldState {
         player_id: EntityId,
         new_zone_id: u32,
     ) -> Result<(), String> {
-        // Remove from current zone
-        if let Some(current_zone_id) = self.player_zone_map.get(&player_id).cloned() {
-            if let Some(current_zone) = self.zones.get_mut(&current_zone_id) {
-                current_zone.remove_player(player_id);
-                // Note: Entity stays in zone's entity manager for now
-                // In a full implementation, we'd move the entity data too
-            }
-        }
-
-        // Add to new zone
-        if let Some(new_zone) = self.zones.get_mut(&new_zone_id) {
-            new_zone.add_player(player_id);
-            self.player_zone_map.insert(player_id, new_zone_id);
-            Ok(())
-        } else {
-            Err(format!("Zone {} does not exist", new_zone_id))
-        }
+        self.transfer_player(player_id, new_zone_id, None)
     }
 
     /// Add a player to the starter zone
@@ -164,6 +214,154 @@ impl WorldState {
         }
     }
 
+    /// Simulation time as of the current tick
+    pub fn clock(&self) -> WorldClock {
+        self.clock
+    }
+
+    /// Publish the simulation loop's clock for this tick
+    pub fn set_clock(&mut self, clock: WorldClock) {
+        self.clock = clock;
+    }
+
+    /// Ability and effect definitions; a shared handle so systems can hold it
+    /// while mutating zones
+    pub fn abilities(&self) -> Arc<AbilityRegistry> {
+        self.abilities.clone()
+    }
+
+    /// Replace the ability definitions, e.g. with ones loaded from `ABILITIES_PATH`
+    pub fn set_abilities(&mut self, abilities: AbilityRegistry) {
+        self.abilities = Arc::new(abilities);
+    }
+
+    /// Mob AI profiles; a shared handle like `abilities`
+    pub fn ai_profiles(&self) -> Arc<AiProfileRegistry> {
+        self.ai_profiles.clone()
+    }
+
+    /// Replace the AI profiles, e.g. with ones loaded from `AI_PROFILES_PATH`
+    pub fn set_ai_profiles(&mut self, profiles: AiProfileRegistry) {
+        self.ai_profiles = Arc::new(profiles);
+    }
+
+    /// Class definitions; a shared handle like `abilities`
+    pub fn classes(&self) -> Arc<ClassRegistry> {
+        self.classes.clone()
+    }
+
+    /// Replace the class definitions, e.g. with ones loaded from `CLASSES_PATH`
+    pub fn set_classes(&mut self, classes: ClassRegistry) {
+        self.classes = Arc::new(classes);
+    }
+
+    /// Item definitions; a shared handle like `abilities`
+    pub fn items(&self) -> Arc<ItemRegistry> {
+        self.items.clone()
+    }
+
+    /// Experience curve and kill rewards; a shared handle like `abilities`
+    pub fn experience(&self) -> Arc<ExperienceTable> {
+        self.experience.clone()
+    }
+
+    /// Replace the experience curve, e.g. with one loaded from `EXPERIENCE_PATH`
+    pub fn set_experience(&mut self, experience: ExperienceTable) {
+        self.experience = Arc::new(experience);
+    }
+
+    /// Loot tables; a shared handle like `abilities`
+    pub fn loot_tables(&self) -> Arc<LootSystem> {
+        self.loot_tables.clone()
+    }
+
+    /// Replace the loot tables
+    #[cfg(test)]
+    pub fn set_loot_tables(&mut self, loot_tables: LootSystem) {
+        self.loot_tables = Arc::new(loot_tables);
+    }
+
+    pub fn parties(&self) -> &Parties {
+        &self.parties
+    }
+
+    pub fn parties_mut(&mut self) -> &mut Parties {
+        &mut self.parties
+    }
+
+    fn default_items() -> ItemRegistry {
+        let mut items = ItemRegistry::new();
+        items.load_defaults();
+        items
+    }
+
+    fn default_loot_tables() -> LootSystem {
+        let mut loot_tables = LootSystem::new();
+        loot_tables.load_defaults();
+        loot_tables
+    }
+
+    /// Corpse timer and release rules
+    pub fn death_config(&self) -> &DeathConfig {
+        &self.death_config
+    }
+
+    /// Replace the death rules, e.g. with ones read from the environment
+    pub fn set_death_config(&mut self, config: DeathConfig) {
+        self.death_config = config;
+    }
+
+    /// Loot roll and free-for-all timers
+    pub fn loot_config(&self) -> &LootConfig {
+        &self.loot_config
+    }
+
+    /// Replace the loot timers, e.g. with ones read from the environment
+    pub fn set_loot_config(&mut self, config: LootConfig) {
+        self.loot_config = config;
+    }
+
+    /// Attack table tuning
+    pub fn attack_table(&self) -> &AttackTableConfig {
+        &self.attack_table
+    }
+
+    /// Replace the attack table tuning
+    pub fn set_attack_table(&mut self, config: AttackTableConfig) {
+        self.attack_table = config;
+    }
+
+    /// Restart combat and loot rolls from a fixed seed so fights can be reproduced
+    pub fn seed_combat_rng(&mut self, seed: u64) {
+        self.combat_rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// A zone together with the combat RNG, for resolving attacks in it
+    pub fn zone_and_combat_rng_mut(&mut self, zone_id: u32) -> Option<(&mut Zone, &mut StdRng)> {
+        let zone = self.zones.get_mut(&zone_id)?;
+        Some((zone, &mut self.combat_rng))
+    }
+
+    /// Every zone together with the combat RNG, for settling loot rolls
+    pub fn zones_and_combat_rng_mut(&mut self) -> (impl Iterator<Item = &mut Zone>, &mut StdRng) {
+        (self.zones.values_mut(), &mut self.combat_rng)
+    }
+
+    /// The combat RNG on its own, for rolling loot tables
+    pub fn combat_rng_mut(&mut self) -> &mut StdRng {
+        &mut self.combat_rng
+    }
+
+    /// Record a visual event on an entity for clients that can see it
+    pub fn push_entity_effect(&mut self, entity_id: EntityId, effect: EntityEffect) {
+        self.entity_effects.push((entity_id, effect));
+    }
+
+    /// Take the events raised since the last drain
+    pub fn drain_entity_effects(&mut self) -> Vec<(EntityId, EntityEffect)> {
+        std::mem::take(&mut self.entity_effects)
+    }
+
     /// Update all zones
     pub fn update(&mut self, delta_time: f64) {
         for zone in self.zones.values_mut() {
@@ -215,29 +413,48 @@ impl WorldState {
         new_zone_id: u32,
         position: (f32, f32, f32),
     ) -> Result<(), String> {
-        // Remove from current zone
+        self.transfer_player(player_id, new_zone_id, Some(position))
+    }
+
+    /// Move a player's entity and zone membership, optionally repositioning it
+    fn transfer_player(
+        &mut self,
+        player_id: EntityId,
+        new_zone_id: u32,
+        position: Option<(f32, f32, f32)>,
+    ) -> Result<(), String> {
+        if !self.zones.contains_key(&new_zone_id) {
+            return Err(format!("Zone {} does not exist", new_zone_id));
+        }
+
+        // Remove from current zone, taking the entity data along
+        let mut entity = None;
         if let Some(current_zone_id) = self.player_zone_map.get(&player_id).cloned() {
             if let Some(current_zone) = self.zones.get_mut(&current_zone_id) {
                 current_zone.remove_player(player_id);
-                // Move the entity to new position before moving zones
-                if let Some(entity) = current_zone.entities.get_entity_mut(player_id) {
-                    if let Some(pos) = &mut entity.position {
-                        pos.x = position.0;
-                        pos.y = position.1;
-                        pos.z = position.2;
-                    }
-                }
+                entity = current_zone.entities.remove_entity(player_id);
+            }
+        }
+
+        if let (Some(entity), Some(position)) = (&mut entity, position) {
+            if let Some(pos) = &mut entity.position {
+                pos.x = position.0;
+                pos.y = position.1;
+                pos.z = position.2;
             }
         }
 
         // Add to new zone
-        if let Some(new_zone) = self.zones.get_mut(&new_zone_id) {
-            new_zone.add_player(player_id);
-            self.player_zone_map.insert(player_id, new_zone_id);
-            Ok(())
-        } else {
-            Err(format!("Zone {} does not exist", new_zone_id))
+        let new_zone = self
+            .zones
+            .get_mut(&new_zone_id)
+            .ok_or_else(|| format!("Zone {} does not exist", new_zone_id))?;
+        if let Some(entity) = entity {
+            new_zone.entities.add_entity(entity);
         }
+        new_zone.add_player(player_id);
+        self.player_zone_map.insert(player_id, new_zone_id);
+        Ok(())
     }
 
     /// Get all zones
@@ -245,6 +462,11 @@ impl WorldState {
         self.zones.values().collect()
     }
 
+    /// Get mutable references to all zones
+    pub fn zones_mut(&mut self) -> impl Iterator<Item = &mut Zone> {
+        self.zones.values_mut()
+    }
+
     /// Get zone count
     pub fn zone_count(&self) -> usize {
         self.zones.len()
@@ -272,6 +494,8 @@ impl WorldState {
 
     /// Remove a player from the world and clean up its entity
     pub fn remove_player(&mut self, player_id: EntityId) {
+        // The rest of the party hears that they left; not being in one is fine
+        let _ = PartySystem::leave(self, player_id);
         if let Some(zone_id) = self.player_zone_map.remove(&player_id) {
             if let Some(zone) = self.zones.get_mut(&zone_id) {
                 zone.remove_player(player_id);
@@ -296,6 +520,7 @@ impl WorldState {
         }
 
         for id in to_remove {
+            let _ = PartySystem::leave(self, id);
             if let Some(zone_id) = self.player_zone_map.remove(&id) {
                 if let Some(zone) = self.zones.get_mut(&zone_id) {
                     zone.remove_player(id);
@@ -311,6 +536,97 @@ impl WorldState {
         }
     }
 
+    /// Give a spawned player its class and level and the abilities they come
+    /// with; the stat pipeline derives its stats from them on the next tick
+    pub fn set_player_class(&mut self, player_id: EntityId, class: &str, level: u32) {
+        let classes = self.classes();
+        let Some(player) = self.get_player_entity_mut(player_id) else {
+            return;
+        };
+        if let Some(stats) = player.stats.as_mut() {
+            stats.class = Some(class.to_string());
+        }
+        if let Some(progression) = player.progression.as_mut() {
+            progression.level = level;
+        }
+        if let Some(combat) = player.combat.as_mut() {
+            combat.level = level;
+        }
+        ProgressionSystem::learn_class_abilities(player, &classes);
+    }
+
+    /// Restore a spawned player's experience into their current level
+    pub fn set_player_experience(&mut self, player_id: EntityId, experience: u64) {
+        let table = self.experience();
+        let Some(progression) = self
+            .get_player_entity_mut(player_id)
+            .and_then(|player| player.progression.as_mut())
+        else {
+            return;
+        };
+        progression.experience_to_next = table.experience_to_next(progression.level);
+        progression.experience = experience.min(progression.experience_to_next.saturating_sub(1));
+    }
+
+    /// A player's level and experience, for saving
+    pub fn get_player_progression(&self, player_id: EntityId) -> Option<Progression> {
+        let zone_id = self.player_zone_map.get(&player_id)?;
+        let zone = self.zones.get(zone_id)?;
+        zone.entities.get_entity(player_id)?.progression.clone()
+    }
+
+    /// Equip an item on a player if they meet its requirements; stats follow
+    /// on the next tick. Whatever was in the slot is replaced.
+    #[cfg(test)]
+    pub fn equip_item(
+        &mut self,
+        player_id: EntityId,
+        item: ItemInstance,
+        slot: EquipmentSlot,
+    ) -> Result<(), String> {
+        let items = self.items();
+        let definition = items
+            .get_item(item.definition_id)
+            .ok_or_else(|| format!("Unknown item {}", item.definition_id))?;
+        let player = self
+            .get_player_entity_mut(player_id)
+            .ok_or_else(|| format!("Player {} not found", player_id))?;
+
+        let level = player.progression.as_ref().map_or(1, |p| p.level);
+        let class = player
+            .stats
+            .as_ref()
+            .and_then(|stats| stats.class.clone())
+            .unwrap_or_default();
+        let equipment = player
+            .equipment
+            .as_mut()
+            .ok_or_else(|| format!("Player {} cannot equip items", player_id))?;
+        let gear = equipment.calculate_total_stats(&items);
+        let attributes = ItemStats {
+            agility: gear.agility
+                + player
+                    .combat
+                    .as_ref()
+                    .map_or(0, |combat| combat.agility as i32),
+            ..gear
+        };
+        if !definition.can_equip(level, &class, &attributes) {
+            return Err(format!("Requirements not met for {}", definition.name));
+        }
+        equipment
+            .equip_item(item, slot, &items)
+            .map_err(|e| e.to_string())
+    }
+
+    fn get_player_entity_mut(&mut self, player_id: EntityId) -> Option<&mut Entity> {
+        let zone_id = self.player_zone_map.get(&player_id)?;
+        self.zones
+            .get_mut(zone_id)?
+            .entities
+            .get_entity_mut(player_id)
+    }
+
     /// Get a player's current position and rotation if present
     pub fn get_player_pose(&self, player_id: EntityId) -> Option<(f32, f32, f32, f32)> {
         let zone_id = self.player_zone_map.get(&player_id)?;
@@ -320,6 +636,32 @@ impl WorldState {
         Some((pos.x, pos.y, pos.z, pos.rotation))
     }
 
+    /// Get a player's current class resource value if it has one
+    pub fn get_player_resource(&self, player_id: EntityId) -> Option<u32> {
+        let zone_id = self.player_zone_map.get(&player_id)?;
+        let zone = self.zones.get(zone_id)?;
+        let entity = zone.entities.get_entity(player_id)?;
+        entity.resource.as_ref().map(Resource::value)
+    }
+
+    /// Bring a player's entity to a stop, e.g. when its connection drops
+    pub fn halt_player(&mut self, player_id: EntityId) {
+        let Some(zone_id) = self.player_zone_map.get(&player_id).copied() else {
+            return;
+        };
+        let movement = self
+            .zones
+            .get_mut(&zone_id)
+            .and_then(|zone| zone.entities.get_entity_mut(player_id))
+            .and_then(|entity| entity.movement.as_mut());
+        if let Some(movement) = movement {
+            movement.velocity_x = 0.0;
+            movement.velocity_y = 0.0;
+            movement.velocity_z = 0.0;
+            movement.is_moving = false;
+        }
+    }
+
     pub fn get_player_name(&self, player_id: EntityId) -> Option<String> {
         let zone_id = self.player_zone_map.get(&player_id)?;
         let zone = self.zones.get(zone_id)?;
@@ -3,8 +3,13 @@
 //! Zones represent distinct areas of the game world with their own
 //! entities, boundaries, and rules.
 
-use crate::entities::{EntityId, EntityManager};
-use std::collections::HashSet;
+use crate::entities::{Entity, EntityId, EntityManager};
+use crate::loot::CorpseLoot;
+use std::collections::{HashMap, HashSet};
+
+/// Size of the entity ID block reserved for each zone, so IDs stay unique
+/// when a player entity moves between zones
+pub const ZONE_ENTITY_ID_STRIDE: EntityId = 1_000_000;
 
 /// Represents a game zone/area
 pub struct Zone {
@@ -13,6 +18,21 @@ pub struct Zone {
     pub bounds: ZoneBounds,
     pub entities: EntityManager,
     pub active_players: HashSet<EntityId>,
+    /// Where released players come back to life
+    pub graveyard: (f32, f32, f32),
+    /// Dead entities still in the world -> time of death
+    pub corpses: HashMap<EntityId, f64>,
+    /// Loot left on corpses until it is taken or the corpse despawns
+    pub loot: HashMap<EntityId, CorpseLoot>,
+    /// Despawned mobs waiting to come back
+    pub pending_respawns: Vec<PendingRespawn>,
+}
+
+/// A despawned mob and when it returns
+#[derive(Debug, Clone)]
+pub struct PendingRespawn {
+    pub entity: Entity,
+    pub respawn_at: f64,
 }
 
 #[derive(Debug, Clone)]
@@ -31,8 +51,12 @@ impl Zone {
             id,
             name,
             bounds,
-            entities: EntityManager::new(),
+            entities: EntityManager::with_first_id(id as EntityId * ZONE_ENTITY_ID_STRIDE + 1),
             active_players: HashSet::new(),
+            graveyard: (0.0, 0.0, 0.0),
+            corpses: HashMap::new(),
+            loot: HashMap::new(),
+            pending_respawns: Vec::new(),
         }
     }
 
@@ -80,14 +104,15 @@ impl Zone {
                 max_z: 100.0,
             },
         );
+        zone.graveyard = (0.0, 2.0, 12.0);
 
         // Create some test mobs
         zone.entities
-            .create_test_mob("Goblin".to_string(), 15.0, 15.0, 1);
+            .create_test_mob("Goblin".to_string(), 15.0, 15.0, 1, "skirmisher", Some(1));
         zone.entities
-            .create_test_mob("Orc".to_string(), -15.0, 15.0, 1);
+            .create_test_mob("Orc".to_string(), -15.0, 15.0, 1, "brute", Some(2));
         zone.entities
-            .create_test_mob("Wolf".to_string(), 0.0, 25.0, 1);
+            .create_test_mob("Wolf".to_string(), 0.0, 25.0, 1, "prowler", Some(3));
 
         zone
     }
@@ -106,16 +131,23 @@ impl Zone {
                 max_z: 150.0,
             },
         );
+        zone.graveyard = (-90.0, 0.0, 0.0);
 
         // Create higher level mobs
+        zone.entities.create_test_mob(
+            "Elite Goblin".to_string(),
+            30.0,
+            30.0,
+            3,
+            "skirmisher",
+            Some(1),
+        );
         zone.entities
-            .create_test_mob("Elite Goblin".to_string(), 30.0, 30.0, 3);
-        zone.entities
-            .create_test_mob("Troll".to_string(), -30.0, 30.0, 4);
+            .create_test_mob("Troll".to_string(), -30.0, 30.0, 4, "brute", Some(2));
         zone.entities
-            .create_test_mob("Dire Wolf".to_string(), 0.0, 40.0, 3);
+            .create_test_mob("Dire Wolf".to_string(), 0.0, 40.0, 3, "prowler", Some(3));
         zone.entities
-            .create_test_mob("Bandit".to_string(), 50.0, 0.0, 2);
+            .create_test_mob("Bandit".to_string(), 50.0, 0.0, 2, "skirmisher", Some(1));
 
         // Create a vendor NPC
         zone.entities